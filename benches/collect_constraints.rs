@@ -0,0 +1,40 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::hint::black_box;
+use type_checker::annotator::annotate;
+use type_checker::constraint::collect_constraints;
+use type_checker::desugar::desugar;
+use type_checker::parser::parse;
+use type_checker::tokenizer::tokenize_with_spans;
+
+/// Builds `let val xaa = 0 in let val xab = xaa in ... end end`, a chain of
+/// `n` `let` expressions each nested in the previous one's body, so the
+/// traversal in `collect_constraints` has depth proportional to `n`. Names
+/// spell the index in two letters, since identifiers hold no digits, which
+/// caps `n` at 676; the nesting limit of 256 caps it lower still.
+fn nested_let_chain(n: usize) -> String {
+    let name = |i: usize| format!("x{}{}", (b'a' + (i / 26) as u8) as char, (b'a' + (i % 26) as u8) as char);
+    let mut source = name(n - 1);
+    for i in (0..n).rev() {
+        let value = if i == 0 { String::from("0") } else { name(i - 1) };
+        source = format!("let val {} = {} in {} end", name(i), value, source);
+    }
+    source
+}
+
+fn bench_collect_constraints(c: &mut Criterion) {
+    let mut group = c.benchmark_group("collect_constraints");
+    for depth in [10, 100, 200] {
+        let source = nested_let_chain(depth);
+        let tokens = tokenize_with_spans(&source).expect("source should tokenize");
+        let term = desugar(&parse(&tokens).expect("source should parse"));
+        let typed_term = annotate(&term).expect("source should annotate");
+
+        group.bench_with_input(BenchmarkId::from_parameter(depth), &typed_term, |b, typed_term| {
+            b.iter(|| collect_constraints(black_box(typed_term)));
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_collect_constraints);
+criterion_main!(benches);
@@ -0,0 +1,306 @@
+use crate::annotator::{Type, TypeVarGen, TypedTerm, TypedTermKind};
+use crate::constraint::{ConstraintReason, TypeEnv, TypeError};
+use crate::parser::{Term, TermKind};
+use crate::tokenizer::Span;
+use crate::unifier::Substitution;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+/// Infers a fully-solved [`TypedTerm`] for `term` using Algorithm M: unlike
+/// [`crate::algorithm_w::infer`], which synthesizes each subterm's type
+/// bottom-up and unifies siblings against each other afterward, this pushes
+/// an *expected* type down into every subterm and unifies against that as
+/// soon as the subterm's own type is known. A mismatch is then caught at
+/// the specific leaf that violates the expectation — the boolean literal
+/// used where a function was expected, say — rather than surfacing later
+/// as a conflict between two inferred types whose original source has
+/// already been lost.
+///
+/// Unification still goes through [`Substitution::add_constraints`], so
+/// this shares the same union-find solver and occurs check as the other
+/// two engines. On well-typed programs all three agree; this one exists so
+/// error-message quality can be compared against the others, not to
+/// replace either.
+pub fn infer(term: &Term) -> Result<TypedTerm, TypeError> {
+    infer_with_env(term, &TypeEnv::default_prelude())
+}
+
+/// Like [`infer`], but resolves identifiers not bound by an enclosing `fn`
+/// or `let` against `prelude` instead of the default builtin operators.
+pub fn infer_with_env(term: &Term, prelude: &TypeEnv) -> Result<TypedTerm, TypeError> {
+    let mut gen = TypeVarGen::new();
+    let mut substitution = Substitution::default();
+    let expected = Type::Placeholder(gen.fresh());
+    let typed_term = check_term(term, &expected, &mut gen, &BTreeMap::new(), prelude, &mut substitution)?;
+    Ok(substitution.apply_term(&typed_term))
+}
+
+fn unify_types(
+    substitution: &mut Substitution,
+    expected: &Type,
+    found: &Type,
+    reason: ConstraintReason,
+    span: Span,
+) -> Result<(), TypeError> {
+    let constraint = crate::constraint::Constraint::equal(Rc::new(expected.clone()), Rc::new(found.clone()), reason, span, span);
+    substitution
+        .add_constraints(std::slice::from_ref(&constraint))
+        .map_err(|mut errors| errors.remove(0))
+}
+
+/// Splits `expected` into a parameter and return type for a `fn`, binding
+/// it to a fresh `parameter -> return` function type first if it isn't
+/// already known to be one.
+fn decompose_function_type(
+    substitution: &mut Substitution,
+    expected: &Type,
+    gen: &mut TypeVarGen,
+    span: Span,
+) -> Result<(Type, Type), TypeError> {
+    match substitution.apply(expected) {
+        Type::Function {
+            parameter_type,
+            return_type,
+            ..
+        } => Ok((*parameter_type, *return_type)),
+        other => {
+            let parameter_type = Type::Placeholder(gen.fresh());
+            let return_type = Type::Placeholder(gen.fresh());
+            unify_types(
+                substitution,
+                &other,
+                &Type::Function {
+                    parameter_type: Box::new(parameter_type.clone()),
+                    return_type: Box::new(return_type.clone()),
+                    effects: Vec::new(),
+                },
+                ConstraintReason::FunctionSignature,
+                span,
+            )?;
+            Ok((parameter_type, return_type))
+        }
+    }
+}
+
+fn check_term(
+    term: &Term,
+    expected: &Type,
+    gen: &mut TypeVarGen,
+    env: &BTreeMap<String, Type>,
+    prelude: &TypeEnv,
+    substitution: &mut Substitution,
+) -> Result<TypedTerm, TypeError> {
+    match &term.kind {
+        TermKind::Boolean(value) => {
+            unify_types(substitution, expected, &Type::Boolean, ConstraintReason::BooleanLiteral, term.span)?;
+            Ok(TypedTerm {
+                ty: Rc::new(Type::Boolean),
+                kind: TypedTermKind::Boolean(*value),
+                span: term.span,
+            })
+        }
+        TermKind::Error => Ok(TypedTerm {
+            ty: Rc::new(Type::Placeholder(gen.fresh())),
+            kind: TypedTermKind::Error,
+            span: term.span,
+        }),
+        TermKind::FunctionApplication { function, argument } => {
+            let parameter_type = Type::Placeholder(gen.fresh());
+            let function_expected = Type::Function {
+                parameter_type: Box::new(parameter_type.clone()),
+                return_type: Box::new(expected.clone()),
+                effects: Vec::new(),
+            };
+            let typed_function = check_term(function, &function_expected, gen, env, prelude, substitution)?;
+            let resolved_parameter_type = substitution.apply(&parameter_type);
+            let typed_argument = check_term(argument, &resolved_parameter_type, gen, env, prelude, substitution)?;
+            Ok(TypedTerm {
+                ty: Rc::new(substitution.apply(expected)),
+                kind: TypedTermKind::FunctionApplication {
+                    function: Box::from(typed_function),
+                    argument: Box::from(typed_argument),
+                },
+                span: term.span,
+            })
+        }
+        TermKind::FunctionDefinition { parameter, body } => {
+            let (parameter_type, return_type) = decompose_function_type(substitution, expected, gen, term.span)?;
+            let mut extended_env = env.clone();
+            if let TermKind::Identifier(name) = &parameter.kind {
+                extended_env.insert(name.clone(), parameter_type.clone());
+            }
+            let typed_parameter = check_term(parameter, &parameter_type, gen, &extended_env, prelude, substitution)?;
+            let typed_body = check_term(body, &return_type, gen, &extended_env, prelude, substitution)?;
+            let ty = Type::Function {
+                parameter_type: Box::new((*typed_parameter.ty).clone()),
+                return_type: Box::new((*typed_body.ty).clone()),
+                effects: Vec::new(),
+            };
+            Ok(TypedTerm {
+                ty: Rc::new(ty),
+                kind: TypedTermKind::FunctionDefinition {
+                    parameter: Box::from(typed_parameter),
+                    body: Box::from(typed_body),
+                },
+                span: term.span,
+            })
+        }
+        TermKind::Identifier(name) => {
+            let ty = if let Some(existing_ty) = env.get(name) {
+                existing_ty.clone()
+            } else if let Some(signature) = prelude.get(name) {
+                signature.clone()
+            } else {
+                return Err(TypeError::UnboundIdentifier {
+                    name: name.clone(),
+                    span: term.span,
+                    suggestion: crate::constraint::suggest_identifier(
+                        name,
+                        env.keys().map(String::as_str).chain(prelude.names()),
+                    ),
+                });
+            };
+            unify_types(substitution, expected, &ty, ConstraintReason::BuiltinSignature, term.span)?;
+            Ok(TypedTerm {
+                ty: Rc::new(ty),
+                kind: TypedTermKind::Identifier(name.clone()),
+                span: term.span,
+            })
+        }
+        TermKind::IfExpression {
+            condition,
+            true_branch,
+            false_branch,
+        } => {
+            let typed_condition = check_term(condition, &Type::Boolean, gen, env, prelude, substitution)?;
+            let typed_true_branch = check_term(true_branch, expected, gen, env, prelude, substitution)?;
+            let typed_false_branch = check_term(false_branch, expected, gen, env, prelude, substitution)?;
+            Ok(TypedTerm {
+                ty: Rc::new(substitution.apply(expected)),
+                kind: TypedTermKind::IfExpression {
+                    condition: Box::from(typed_condition),
+                    true_branch: Box::from(typed_true_branch),
+                    false_branch: Box::from(typed_false_branch),
+                },
+                span: term.span,
+            })
+        }
+        TermKind::Integer(value) => {
+            let ty = Type::Numeric(gen.fresh());
+            unify_types(substitution, expected, &ty, ConstraintReason::IntegerLiteral, term.span)?;
+            Ok(TypedTerm {
+                ty: Rc::new(ty),
+                kind: TypedTermKind::Integer(*value),
+                span: term.span,
+            })
+        }
+        TermKind::LetExpression {
+            declaration_name,
+            declaration_value,
+            expression,
+        } => {
+            let declaration_type = Type::Placeholder(gen.fresh());
+            let mut extended_env = env.clone();
+            if let TermKind::Identifier(name) = &declaration_name.kind {
+                extended_env.insert(name.clone(), declaration_type.clone());
+            }
+            let typed_declaration_name = check_term(declaration_name, &declaration_type, gen, &extended_env, prelude, substitution)?;
+            gen.enter_level();
+            let typed_declaration_value = check_term(declaration_value, &declaration_type, gen, env, prelude, substitution)?;
+            gen.exit_level();
+            let typed_expression = check_term(expression, expected, gen, &extended_env, prelude, substitution)?;
+            Ok(TypedTerm {
+                ty: Rc::new(substitution.apply(expected)),
+                kind: TypedTermKind::LetExpression {
+                    declaration_name: Box::from(typed_declaration_name),
+                    declaration_value: Box::from(typed_declaration_value),
+                    expression: Box::from(typed_expression),
+                },
+                span: term.span,
+            })
+        }
+        TermKind::RaiseExpression { exception } => {
+            let exception_type = Type::Placeholder(gen.fresh());
+            let typed_exception = check_term(exception, &exception_type, gen, env, prelude, substitution)?;
+            Ok(TypedTerm {
+                ty: Rc::new(Type::Bottom),
+                kind: TypedTermKind::RaiseExpression {
+                    exception: Box::from(typed_exception),
+                },
+                span: term.span,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::desugar::desugar;
+    use crate::parser::parse;
+    use crate::tokenizer::tokenize_with_spans;
+
+    fn typecheck(source: &str) -> Result<TypedTerm, TypeError> {
+        let tokens = tokenize_with_spans(source).expect("tokenizing should succeed");
+        let term = desugar(&parse(&tokens).expect("parsing should succeed"));
+        infer(&term)
+    }
+
+    #[test]
+    fn test_infer_solves_a_boolean_literal() {
+        let typed_term = typecheck("true").expect("inference should succeed");
+        assert_eq!(*typed_term.ty, Type::Boolean);
+    }
+
+    #[test]
+    fn test_infer_solves_an_integer_literal_to_a_default_numeric_type() {
+        let typed_term = typecheck("1").expect("inference should succeed");
+        assert_eq!(crate::annotator::default_numeric_types(&typed_term.ty), Type::Integer);
+    }
+
+    #[test]
+    fn test_infer_solves_an_identity_function() {
+        let typed_term = typecheck("fn x => x").expect("inference should succeed");
+        match &*typed_term.ty {
+            Type::Function { parameter_type, return_type, .. } => assert_eq!(parameter_type, return_type),
+            other => panic!("expected a function type, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_infer_solves_an_if_expression_by_pushing_the_expected_type_into_both_branches() {
+        let typed_term = typecheck("if true then false else true").expect("inference should succeed");
+        assert_eq!(*typed_term.ty, Type::Boolean);
+    }
+
+    #[test]
+    fn test_infer_solves_a_let_expression() {
+        let typed_term = typecheck("let val x = true in x end").expect("inference should succeed");
+        assert_eq!(*typed_term.ty, Type::Boolean);
+    }
+
+    #[test]
+    fn test_infer_reports_a_type_mismatch_at_the_offending_branch() {
+        let error = typecheck("if true then true else fn x => x").unwrap_err();
+        assert!(matches!(error, TypeError::TypeMismatch { .. }));
+    }
+
+    #[test]
+    fn test_infer_reports_an_unbound_identifier() {
+        let error = typecheck("foo").unwrap_err();
+        assert!(matches!(error, TypeError::UnboundIdentifier { .. }));
+    }
+
+    #[test]
+    fn test_check_agrees_with_the_constraint_based_pipeline() {
+        let algorithm_m_result = typecheck("fn x => if x then true else false").expect("algorithm m inference should succeed");
+        let tokens = tokenize_with_spans("fn x => if x then true else false").expect("tokenizing should succeed");
+        let term = desugar(&parse(&tokens).expect("parsing should succeed"));
+        let constraint_based_result = crate::algorithm_w::check(&term, &crate::annotator::CheckerOptions::default())
+            .expect("constraint-based inference should succeed");
+        assert_eq!(
+            crate::annotator::default_numeric_types(&algorithm_m_result.ty),
+            crate::annotator::default_numeric_types(&constraint_based_result.ty)
+        );
+    }
+}
@@ -0,0 +1,347 @@
+use crate::annotator::{CheckerOptions, InferenceEngine, Type, TypeVarGen, TypedTerm, TypedTermKind};
+use crate::constraint::{Constraint, ConstraintReason, TypeEnv, TypeError};
+use crate::parser::{Term, TermKind};
+use crate::tokenizer::Span;
+use crate::unifier::Substitution;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+/// Infers a fully-solved [`TypedTerm`] for `term` using classic Algorithm
+/// W: each subterm is annotated and immediately unified against its
+/// neighbors as the tree is walked, rather than [`crate::annotator::annotate`]
+/// handing out placeholders first and [`crate::constraint::collect_constraints`]
+/// plus [`crate::unifier::unify`] solving them afterward as a batch. The two
+/// pipelines agree on every well-typed program — this one is here so a
+/// caller can cross-check the constraint-based default, or compare their
+/// performance, rather than to replace it; the constraint-based pipeline
+/// stays the one `crate::graphviz` and `crate::smtlib` render and
+/// `unifier::explain` traces failures through, none of which this engine
+/// produces.
+///
+/// Unification here still goes through [`Substitution::add_constraints`],
+/// one constraint at a time, so both engines share the exact same
+/// union-find solver and occurs check; only how the constraints are
+/// produced — one at a time during the walk, instead of collected into a
+/// `Vec` up front — differs. Because the underlying [`Substitution`]
+/// resolves a variable through whatever it's bound to at the moment it's
+/// asked, unifying eagerly like this reaches the same fixed point solving
+/// the same constraints as a batch would; classic Algorithm W's separate
+/// step of applying the substitution to the environment before each
+/// recursive call isn't needed on top of that.
+///
+/// Unlike the constraint-based pipeline, which accumulates every error it
+/// finds, this fails on the first one — the traversal has already
+/// committed to a substitution by the time a later subterm is reached, so
+/// there is no independent later work left to keep checking.
+pub fn infer(term: &Term) -> Result<TypedTerm, TypeError> {
+    infer_with_env(term, &TypeEnv::default_prelude())
+}
+
+/// Like [`infer`], but resolves identifiers not bound by an enclosing `fn`
+/// or `let` against `prelude` instead of the default builtin operators.
+pub fn infer_with_env(term: &Term, prelude: &TypeEnv) -> Result<TypedTerm, TypeError> {
+    let mut gen = TypeVarGen::new();
+    let mut substitution = Substitution::default();
+    let typed_term = infer_term(term, &mut gen, &BTreeMap::new(), prelude, &mut substitution)?;
+    Ok(substitution.apply_term(&typed_term))
+}
+
+/// Dispatches to [`infer`], `crate::algorithm_m::infer`, or the
+/// constraint-based pipeline according to `options.inference_engine`, so a
+/// caller can pick the engine the same way it picks any other
+/// [`CheckerOptions`] setting. Returns every error found when running the
+/// constraint-based engine, but at most one when running either
+/// substitution-based engine, per their own fail-fast behavior.
+pub fn check(term: &Term, options: &CheckerOptions) -> Result<TypedTerm, Vec<TypeError>> {
+    match options.inference_engine {
+        InferenceEngine::AlgorithmW => infer(term).map_err(|error| vec![error]),
+        InferenceEngine::AlgorithmM => crate::algorithm_m::infer(term).map_err(|error| vec![error]),
+        InferenceEngine::ConstraintBased => {
+            let typed_term = crate::annotator::annotate(term).map_err(|name| {
+                vec![TypeError::UnboundIdentifier {
+                    name,
+                    span: term.span,
+                    suggestion: None,
+                }]
+            })?;
+            let constraints = crate::constraint::collect_constraints(&typed_term)?;
+            let substitution = crate::unifier::unify(&constraints)?;
+            Ok(substitution.apply_term(&typed_term))
+        }
+    }
+}
+
+fn unify_types(
+    substitution: &mut Substitution,
+    expected: &Type,
+    found: &Type,
+    reason: ConstraintReason,
+    span: Span,
+) -> Result<(), TypeError> {
+    let constraint = Constraint::equal(Rc::new(expected.clone()), Rc::new(found.clone()), reason, span, span);
+    substitution
+        .add_constraints(std::slice::from_ref(&constraint))
+        .map_err(|mut errors| errors.remove(0))
+}
+
+fn infer_term(
+    term: &Term,
+    gen: &mut TypeVarGen,
+    env: &BTreeMap<String, Type>,
+    prelude: &TypeEnv,
+    substitution: &mut Substitution,
+) -> Result<TypedTerm, TypeError> {
+    match &term.kind {
+        TermKind::Boolean(value) => {
+            let ty = Type::Placeholder(gen.fresh());
+            unify_types(substitution, &ty, &Type::Boolean, ConstraintReason::BooleanLiteral, term.span)?;
+            Ok(TypedTerm {
+                ty: Rc::new(ty),
+                kind: TypedTermKind::Boolean(*value),
+                span: term.span,
+            })
+        }
+        TermKind::Error => Ok(TypedTerm {
+            ty: Rc::new(Type::Placeholder(gen.fresh())),
+            kind: TypedTermKind::Error,
+            span: term.span,
+        }),
+        TermKind::FunctionApplication { function, argument } => {
+            let ty = Type::Placeholder(gen.fresh());
+            let typed_function = infer_term(function, gen, env, prelude, substitution)?;
+            let typed_argument = infer_term(argument, gen, env, prelude, substitution)?;
+            unify_types(
+                substitution,
+                &typed_function.ty,
+                &Type::Function {
+                    parameter_type: Box::new((*typed_argument.ty).clone()),
+                    return_type: Box::new(ty.clone()),
+                    effects: Vec::new(),
+                },
+                ConstraintReason::ApplicationArgument,
+                term.span,
+            )?;
+            Ok(TypedTerm {
+                ty: Rc::new(ty),
+                kind: TypedTermKind::FunctionApplication {
+                    function: Box::from(typed_function),
+                    argument: Box::from(typed_argument),
+                },
+                span: term.span,
+            })
+        }
+        TermKind::FunctionDefinition { parameter, body } => {
+            let mut extended_env = env.clone();
+            if let TermKind::Identifier(name) = &parameter.kind {
+                extended_env.insert(name.clone(), Type::Placeholder(gen.fresh()));
+            }
+            let typed_parameter = infer_term(parameter, gen, &extended_env, prelude, substitution)?;
+            let typed_body = infer_term(body, gen, &extended_env, prelude, substitution)?;
+            let ty = Type::Function {
+                parameter_type: Box::new((*typed_parameter.ty).clone()),
+                return_type: Box::new((*typed_body.ty).clone()),
+                effects: Vec::new(),
+            };
+            Ok(TypedTerm {
+                ty: Rc::new(ty),
+                kind: TypedTermKind::FunctionDefinition {
+                    parameter: Box::from(typed_parameter),
+                    body: Box::from(typed_body),
+                },
+                span: term.span,
+            })
+        }
+        TermKind::Identifier(name) => {
+            if let Some(existing_ty) = env.get(name) {
+                return Ok(TypedTerm {
+                    ty: Rc::new(existing_ty.clone()),
+                    kind: TypedTermKind::Identifier(name.clone()),
+                    span: term.span,
+                });
+            }
+            match prelude.get(name) {
+                Some(signature) => {
+                    let ty = Type::Placeholder(gen.fresh());
+                    unify_types(substitution, &ty, signature, ConstraintReason::BuiltinSignature, term.span)?;
+                    Ok(TypedTerm {
+                        ty: Rc::new(ty),
+                        kind: TypedTermKind::Identifier(name.clone()),
+                        span: term.span,
+                    })
+                }
+                None => Err(TypeError::UnboundIdentifier {
+                    name: name.clone(),
+                    span: term.span,
+                    suggestion: crate::constraint::suggest_identifier(
+                        name,
+                        env.keys().map(String::as_str).chain(prelude.names()),
+                    ),
+                }),
+            }
+        }
+        TermKind::IfExpression {
+            condition,
+            true_branch,
+            false_branch,
+        } => {
+            let ty = Type::Placeholder(gen.fresh());
+            let typed_condition = infer_term(condition, gen, env, prelude, substitution)?;
+            let typed_true_branch = infer_term(true_branch, gen, env, prelude, substitution)?;
+            let typed_false_branch = infer_term(false_branch, gen, env, prelude, substitution)?;
+            unify_types(
+                substitution,
+                &typed_condition.ty,
+                &Type::Boolean,
+                ConstraintReason::IfConditionBool,
+                typed_condition.span,
+            )?;
+            unify_types(
+                substitution,
+                &ty,
+                &typed_true_branch.ty,
+                ConstraintReason::BranchesMustMatch,
+                typed_true_branch.span,
+            )?;
+            unify_types(
+                substitution,
+                &ty,
+                &typed_false_branch.ty,
+                ConstraintReason::BranchesMustMatch,
+                typed_false_branch.span,
+            )?;
+            Ok(TypedTerm {
+                ty: Rc::new(ty),
+                kind: TypedTermKind::IfExpression {
+                    condition: Box::from(typed_condition),
+                    true_branch: Box::from(typed_true_branch),
+                    false_branch: Box::from(typed_false_branch),
+                },
+                span: term.span,
+            })
+        }
+        TermKind::Integer(value) => Ok(TypedTerm {
+            ty: Rc::new(Type::Numeric(gen.fresh())),
+            kind: TypedTermKind::Integer(*value),
+            span: term.span,
+        }),
+        TermKind::LetExpression {
+            declaration_name,
+            declaration_value,
+            expression,
+        } => {
+            let mut extended_env = env.clone();
+            if let TermKind::Identifier(name) = &declaration_name.kind {
+                extended_env.insert(name.clone(), Type::Placeholder(gen.fresh()));
+            }
+            let typed_declaration_name = infer_term(declaration_name, gen, &extended_env, prelude, substitution)?;
+            gen.enter_level();
+            let typed_declaration_value = infer_term(declaration_value, gen, env, prelude, substitution)?;
+            gen.exit_level();
+            unify_types(
+                substitution,
+                &typed_declaration_name.ty,
+                &typed_declaration_value.ty,
+                ConstraintReason::LetBinding,
+                typed_declaration_value.span,
+            )?;
+            let typed_expression = infer_term(expression, gen, &extended_env, prelude, substitution)?;
+            let ty = (*typed_expression.ty).clone();
+            Ok(TypedTerm {
+                ty: Rc::new(ty),
+                kind: TypedTermKind::LetExpression {
+                    declaration_name: Box::from(typed_declaration_name),
+                    declaration_value: Box::from(typed_declaration_value),
+                    expression: Box::from(typed_expression),
+                },
+                span: term.span,
+            })
+        }
+        TermKind::RaiseExpression { exception } => {
+            // Mirrors `annotator::annotate_term`: the id this node would
+            // have claimed is still burned here rather than handed to
+            // `exception`, the same way `annotate_term` burns one for
+            // `raise`.
+            gen.fresh();
+            let typed_exception = infer_term(exception, gen, env, prelude, substitution)?;
+            Ok(TypedTerm {
+                ty: Rc::new(Type::Bottom),
+                kind: TypedTermKind::RaiseExpression {
+                    exception: Box::from(typed_exception),
+                },
+                span: term.span,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::desugar::desugar;
+    use crate::parser::parse;
+    use crate::tokenizer::tokenize_with_spans;
+
+    fn typecheck(source: &str) -> Result<TypedTerm, TypeError> {
+        let tokens = tokenize_with_spans(source).expect("tokenizing should succeed");
+        let term = desugar(&parse(&tokens).expect("parsing should succeed"));
+        infer(&term)
+    }
+
+    #[test]
+    fn test_infer_solves_a_boolean_literal() {
+        let typed_term = typecheck("true").expect("inference should succeed");
+        assert_eq!(*typed_term.ty, Type::Boolean);
+    }
+
+    #[test]
+    fn test_infer_solves_an_integer_literal_to_a_default_numeric_type() {
+        let typed_term = typecheck("1").expect("inference should succeed");
+        assert_eq!(crate::annotator::default_numeric_types(&typed_term.ty), Type::Integer);
+    }
+
+    #[test]
+    fn test_infer_solves_an_identity_function() {
+        let typed_term = typecheck("fn x => x").expect("inference should succeed");
+        match &*typed_term.ty {
+            Type::Function { parameter_type, return_type, .. } => assert_eq!(parameter_type, return_type),
+            other => panic!("expected a function type, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_infer_solves_an_if_expression_by_unifying_both_branches() {
+        let typed_term = typecheck("if true then false else true").expect("inference should succeed");
+        assert_eq!(*typed_term.ty, Type::Boolean);
+    }
+
+    #[test]
+    fn test_infer_solves_a_let_expression() {
+        let typed_term = typecheck("let val x = true in x end").expect("inference should succeed");
+        assert_eq!(*typed_term.ty, Type::Boolean);
+    }
+
+    #[test]
+    fn test_infer_reports_a_type_mismatch() {
+        let error = typecheck("if true then true else fn x => x").unwrap_err();
+        assert!(matches!(error, TypeError::TypeMismatch { .. }));
+    }
+
+    #[test]
+    fn test_infer_reports_an_unbound_identifier() {
+        let error = typecheck("foo").unwrap_err();
+        assert!(matches!(error, TypeError::UnboundIdentifier { .. }));
+    }
+
+    #[test]
+    fn test_check_agrees_with_the_constraint_based_pipeline() {
+        let tokens = tokenize_with_spans("fn x => if x then true else false").expect("tokenizing should succeed");
+        let term = desugar(&parse(&tokens).expect("parsing should succeed"));
+        let algorithm_w_result = check(&term, &CheckerOptions { inference_engine: InferenceEngine::AlgorithmW, ..Default::default() })
+            .expect("algorithm w inference should succeed");
+        let constraint_based_result = check(&term, &CheckerOptions::default()).expect("constraint-based inference should succeed");
+        assert_eq!(
+            crate::annotator::default_numeric_types(&algorithm_w_result.ty),
+            crate::annotator::default_numeric_types(&constraint_based_result.ty)
+        );
+    }
+}
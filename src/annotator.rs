@@ -1,27 +1,357 @@
-use crate::parser::Term;
-use std::collections::HashMap;
+use crate::parser::{Term, TermKind};
+use crate::tokenizer::Span;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::{Display, Error, Formatter};
+use std::ops::Deref;
+use std::rc::Rc;
 
-#[derive(Debug, Clone, Eq, Hash, PartialEq)]
+// `Ord`/`PartialOrd` give `Type` an arbitrary but deterministic total
+// order (derived in field/variant declaration order), used by
+// `constraint::Constraint::canonical` to pick a stable side for a
+// symmetric `Equal` constraint regardless of the order the two types were
+// inferred in.
+#[derive(Debug, Clone, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Type {
     Boolean,
+    /// The type of `raise e`: it unifies with anything, so a raising
+    /// branch never pollutes inference of the other branch of an `if`.
+    Bottom,
+    /// An application of a user-defined datatype constructor to type
+    /// arguments, e.g. `Tagged int t1`. Arguments are kept in full even
+    /// when they are phantom (not used by any constructor argument), so
+    /// they are preserved through inference and shown in the final type.
+    Constructor {
+        name: String,
+        arguments: Vec<Type>,
+    },
     Function {
         parameter_type: Box<Type>,
         return_type: Box<Type>,
+        /// Exceptions this function may raise, inferred from its body.
+        /// Filled in from the body's `raise` sites by
+        /// `constraint::collect_constraints`; the annotator and the
+        /// `algorithm_w`/`algorithm_m` engines leave it empty.
+        effects: Vec<String>,
     },
+    /// An overloaded builtin type formed from several function signatures
+    /// (e.g. `int -> int -> int & real -> real -> real`), one of which the
+    /// solver picks based on the constraints on its arguments.
+    Intersection(Vec<Type>),
     Integer,
+    /// A constrained numeric variable (`Num 'a`) assigned to integer
+    /// literals, which [`default_numeric_types`] resolves to [`Type::Integer`]
+    /// if nothing else pins it down (e.g. to a future `real` type).
+    Numeric(u32),
     Placeholder(u32),
+    /// A record type, keyed by field name. Kept in a [`BTreeMap`] so two
+    /// records with the same fields compare equal (and hash the same)
+    /// regardless of the order their fields were written in.
+    Record(BTreeMap<String, Type>),
 }
 
-#[derive(Debug, PartialEq)]
+/// Replaces every remaining [`Type::Numeric`] variable in `ty` with
+/// [`Type::Integer`], the default for a numeric literal that was never
+/// constrained to another numeric type.
+pub fn default_numeric_types(ty: &Type) -> Type {
+    match ty {
+        Type::Numeric(_) => Type::Integer,
+        Type::Intersection(members) => {
+            Type::Intersection(members.iter().map(default_numeric_types).collect())
+        }
+        Type::Constructor { name, arguments } => Type::Constructor {
+            name: name.clone(),
+            arguments: arguments.iter().map(default_numeric_types).collect(),
+        },
+        Type::Record(fields) => Type::Record(
+            fields
+                .iter()
+                .map(|(name, field_type)| (name.clone(), default_numeric_types(field_type)))
+                .collect(),
+        ),
+        Type::Function {
+            parameter_type,
+            return_type,
+            effects,
+        } => Type::Function {
+            parameter_type: Box::from(default_numeric_types(parameter_type)),
+            return_type: Box::from(default_numeric_types(return_type)),
+            effects: effects.clone(),
+        },
+        other => other.clone(),
+    }
+}
+
+/// A [`TypedTerm`] whose every node's type has already been run through
+/// [`default_numeric_types`], produced by [`resolve`]. Evaluators, code
+/// generators, and hover tooling want this rather than a bare
+/// [`TypedTerm`], so they never have to re-default a leftover
+/// [`Type::Numeric`] literal themselves; a [`Type::Placeholder`] can still
+/// remain if nothing in the source ever constrained it (an unused function
+/// parameter, say), since there's no concrete type to default that to.
+#[derive(Debug)]
+pub struct ResolvedTerm(pub TypedTerm);
+
+impl Deref for ResolvedTerm {
+    type Target = TypedTerm;
+
+    fn deref(&self) -> &TypedTerm {
+        &self.0
+    }
+}
+
+/// Builds a [`ResolvedTerm`] from `term`, applying [`default_numeric_types`]
+/// to every node's type rather than just the root's.
+pub fn resolve(term: &TypedTerm) -> ResolvedTerm {
+    ResolvedTerm(resolve_term(term))
+}
+
+fn resolve_term(term: &TypedTerm) -> TypedTerm {
+    TypedTerm {
+        ty: Rc::new(default_numeric_types(&term.ty)),
+        kind: resolve_term_kind(&term.kind),
+        span: term.span,
+    }
+}
+
+fn resolve_term_kind(kind: &TypedTermKind) -> TypedTermKind {
+    match kind {
+        TypedTermKind::Boolean(value) => TypedTermKind::Boolean(*value),
+        TypedTermKind::Error => TypedTermKind::Error,
+        TypedTermKind::FunctionApplication { function, argument } => TypedTermKind::FunctionApplication {
+            function: Box::new(resolve_term(function)),
+            argument: Box::new(resolve_term(argument)),
+        },
+        TypedTermKind::FunctionDefinition { parameter, body } => TypedTermKind::FunctionDefinition {
+            parameter: Box::new(resolve_term(parameter)),
+            body: Box::new(resolve_term(body)),
+        },
+        TypedTermKind::Identifier(name) => TypedTermKind::Identifier(name.clone()),
+        TypedTermKind::IfExpression {
+            condition,
+            true_branch,
+            false_branch,
+        } => TypedTermKind::IfExpression {
+            condition: Box::new(resolve_term(condition)),
+            true_branch: Box::new(resolve_term(true_branch)),
+            false_branch: Box::new(resolve_term(false_branch)),
+        },
+        TypedTermKind::Integer(value) => TypedTermKind::Integer(*value),
+        TypedTermKind::LetExpression {
+            declaration_name,
+            declaration_value,
+            expression,
+        } => TypedTermKind::LetExpression {
+            declaration_name: Box::new(resolve_term(declaration_name)),
+            declaration_value: Box::new(resolve_term(declaration_value)),
+            expression: Box::new(resolve_term(expression)),
+        },
+        TypedTermKind::RaiseExpression { exception } => TypedTermKind::RaiseExpression {
+            exception: Box::new(resolve_term(exception)),
+        },
+    }
+}
+
+/// Collects the ids of every [`Type::Numeric`] and [`Type::Placeholder`]
+/// variable occurring anywhere in `ty`, the metavariables a solver would
+/// need to either resolve or generalize over.
+pub fn type_variables(ty: &Type) -> HashSet<u32> {
+    let mut variables = HashSet::new();
+    collect_type_variables(ty, &mut variables);
+    variables
+}
+
+fn collect_type_variables(ty: &Type, variables: &mut HashSet<u32>) {
+    match ty {
+        Type::Numeric(id) | Type::Placeholder(id) => {
+            variables.insert(*id);
+        }
+        Type::Function {
+            parameter_type,
+            return_type,
+            ..
+        } => {
+            collect_type_variables(parameter_type, variables);
+            collect_type_variables(return_type, variables);
+        }
+        Type::Intersection(members) => {
+            for member in members {
+                collect_type_variables(member, variables);
+            }
+        }
+        Type::Constructor { arguments, .. } => {
+            for argument in arguments {
+                collect_type_variables(argument, variables);
+            }
+        }
+        Type::Record(fields) => {
+            for field_type in fields.values() {
+                collect_type_variables(field_type, variables);
+            }
+        }
+        Type::Boolean | Type::Bottom | Type::Integer => {}
+    }
+}
+
+/// Options controlling how a [`Type`] is rendered and, for [`Type::Record`],
+/// how it is checked.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CheckerOptions {
+    /// When set, function types with a non-empty effect set are displayed
+    /// as `int -[Div]-> int` instead of `int => int`.
+    pub show_effects: bool,
+    /// Which of `crate::algorithm_w::check`'s inference engines to run.
+    pub inference_engine: InferenceEngine,
+    /// Governs whether a [`Type::Record`] is checked structurally or
+    /// nominally against an expected type; see
+    /// `crate::constraint::record_constraint`.
+    pub record_mode: RecordCheckingMode,
+}
+
+/// How a [`Type::Record`] is compared against an expected type.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RecordCheckingMode {
+    /// A record is compatible with an expected record if it has at least
+    /// the expected fields with compatible types (width subtyping); extra
+    /// fields are allowed. Suited to teaching scenarios that emphasize
+    /// structural typing.
+    #[default]
+    Structural,
+    /// A record is only compatible with an expected record of the exact
+    /// same fields; no extra or missing fields are tolerated.
+    Nominal,
+}
+
+/// Selects between the ways this crate can infer a [`TypedTerm`]'s types,
+/// so a caller can cross-check one against another on the same AST. See
+/// `crate::algorithm_w` and `crate::algorithm_m` for the tradeoffs between
+/// them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InferenceEngine {
+    /// [`annotate`] to fill in placeholders, then
+    /// `constraint::collect_constraints` and `unifier::unify` to solve them
+    /// as a batch. The default: its flat constraint list is what
+    /// `crate::graphviz` and `crate::smtlib` render, and what
+    /// `unifier::explain` traces failures through.
+    #[default]
+    ConstraintBased,
+    /// `algorithm_w::infer`, which unifies each subterm against its
+    /// neighbors as it visits it, never building a constraint list at all.
+    AlgorithmW,
+    /// `algorithm_m::infer`, which pushes an expected type down into each
+    /// subterm instead of synthesizing types bottom-up, so a mismatch is
+    /// caught at the subterm that actually violates the expectation.
+    AlgorithmM,
+}
+
+impl Type {
+    /// Renders the type using the given [`CheckerOptions`], e.g. to show
+    /// exception effects on function types.
+    pub fn display_with_options(&self, options: &CheckerOptions) -> String {
+        match self {
+            Type::Function {
+                parameter_type,
+                return_type,
+                effects,
+            } if options.show_effects && !effects.is_empty() => format!(
+                "{} -[{}]-> {}",
+                parenthesize_operand_with_options(parameter_type, options),
+                effects.join(", "),
+                return_type.display_with_options(options),
+            ),
+            Type::Function {
+                parameter_type,
+                return_type,
+                ..
+            } => format!(
+                "{} => {}",
+                parenthesize_operand_with_options(parameter_type, options),
+                return_type.display_with_options(options),
+            ),
+            other => format!("{}", other),
+        }
+    }
+
+    /// Renumbers every [`Type::Placeholder`]/[`Type::Numeric`] id in `self`
+    /// to a small id starting from 1, assigned in the order the original
+    /// ids first appear. A principal type's shape doesn't depend on which
+    /// arbitrary ids the [`TypeVarGen`] handed out to reach it, so this
+    /// makes `fn x => x` report the same type string every time it's
+    /// checked, instead of one that drifts with how many variables an
+    /// earlier part of the session already allocated.
+    pub fn canonicalize(&self) -> Type {
+        canonicalize_with(self, &mut HashMap::new())
+    }
+}
+
+fn canonicalize_with(ty: &Type, ids: &mut HashMap<u32, u32>) -> Type {
+    match ty {
+        Type::Numeric(id) => Type::Numeric(canonical_id(*id, ids)),
+        Type::Placeholder(id) => Type::Placeholder(canonical_id(*id, ids)),
+        Type::Intersection(members) => {
+            Type::Intersection(members.iter().map(|m| canonicalize_with(m, ids)).collect())
+        }
+        Type::Function {
+            parameter_type,
+            return_type,
+            effects,
+        } => Type::Function {
+            parameter_type: Box::from(canonicalize_with(parameter_type, ids)),
+            return_type: Box::from(canonicalize_with(return_type, ids)),
+            effects: effects.clone(),
+        },
+        Type::Constructor { name, arguments } => Type::Constructor {
+            name: name.clone(),
+            arguments: arguments.iter().map(|argument| canonicalize_with(argument, ids)).collect(),
+        },
+        Type::Record(fields) => Type::Record(
+            fields
+                .iter()
+                .map(|(name, field_type)| (name.clone(), canonicalize_with(field_type, ids)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// Looks up the canonical id already assigned to `id`, or assigns it the
+/// next one in sequence (starting from 1) if this is the first time it's
+/// been seen.
+fn canonical_id(id: u32, ids: &mut HashMap<u32, u32>) -> u32 {
+    let next = ids.len() as u32 + 1;
+    *ids.entry(id).or_insert(next)
+}
+
+/// An annotated term together with the [`Span`] of the [`Term`] it was
+/// produced from, so type errors reported later in the pipeline can still
+/// point back at the original source.
+///
+/// `ty` is an [`Rc`] rather than an owned [`Type`] so that copying it out
+/// to build a [`crate::constraint::Constraint`] — done once or twice per
+/// node during constraint generation — is a refcount bump instead of a
+/// walk of the whole (potentially deeply nested) type tree.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct TypedTerm {
-    pub ty: Type,
+    pub ty: Rc<Type>,
     pub kind: TypedTermKind,
+    pub span: Span,
+}
+
+impl PartialEq for TypedTerm {
+    fn eq(&self, other: &TypedTerm) -> bool {
+        self.ty == other.ty && self.kind == other.kind
+    }
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum TypedTermKind {
     Boolean(bool),
+    /// Carries forward a [`Term::Error`], so a broken subtree still gets a
+    /// fresh type variable (letting inference proceed around it) instead of
+    /// aborting the whole annotation pass.
+    Error,
     FunctionApplication {
         function: Box<TypedTerm>,
         argument: Box<TypedTerm>,
@@ -42,32 +372,369 @@ pub enum TypedTermKind {
         declaration_value: Box<TypedTerm>,
         expression: Box<TypedTerm>,
     },
+    RaiseExpression {
+        exception: Box<TypedTerm>,
+    },
 }
 
 impl Display for Type {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
         match self {
             Type::Boolean => write!(f, "bool"),
+            Type::Bottom => write!(f, "never"),
+            Type::Constructor { name, arguments } => {
+                write!(f, "{}", name)?;
+                for argument in arguments {
+                    write!(f, " {}", argument)?;
+                }
+                Ok(())
+            }
+            Type::Intersection(members) => {
+                let rendered: Vec<String> = members.iter().map(|m| m.to_string()).collect();
+                write!(f, "{}", rendered.join(" & "))
+            }
             Type::Function {
                 parameter_type,
                 return_type,
-            } => write!(f, "{} => {}", parameter_type, return_type),
+                ..
+            } => write!(f, "{} => {}", parenthesize_operand(parameter_type), return_type),
             Type::Integer => write!(f, "int"),
+            Type::Numeric(counter) => write!(f, "t{}", counter),
             Type::Placeholder(counter) => write!(f, "t{}", counter),
+            Type::Record(fields) => {
+                write!(f, "{{")?;
+                for (index, (name, field_type)) in fields.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", name, field_type)?;
+                }
+                write!(f, "}}")
+            }
         }
     }
 }
 
+/// The first point at which `expected` and `found` diverge, so a diagnostic
+/// can point at exactly the differing sub-component instead of printing
+/// two long, mostly-identical types and leaving the reader to spot the
+/// difference themselves.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeDiff {
+    /// The nested components walked to reach the differing sub-type,
+    /// outermost first, e.g. `["parameter type", "return type"]` for a
+    /// mismatch inside `(a => b) => c`'s `b`. Empty if `expected` and
+    /// `found` disagree at the top level.
+    pub path: Vec<String>,
+    pub expected: Type,
+    pub found: Type,
+}
+
+/// Compares `expected` against `found`, descending into matching
+/// [`Type::Function`]/[`Type::Constructor`]/[`Type::Record`] shapes for as
+/// long as they agree, and returning the innermost pair of sub-types that
+/// actually differ, tagged with the path taken to reach them.
+pub fn diff_types(expected: &Type, found: &Type) -> TypeDiff {
+    match (expected, found) {
+        (
+            Type::Function { parameter_type: expected_parameter, return_type: expected_return, .. },
+            Type::Function { parameter_type: found_parameter, return_type: found_return, .. },
+        ) => {
+            let mut diff = if expected_parameter != found_parameter {
+                diff_types(expected_parameter, found_parameter)
+            } else {
+                diff_types(expected_return, found_return)
+            };
+            diff.path.insert(
+                0,
+                String::from(if expected_parameter != found_parameter { "parameter type" } else { "return type" }),
+            );
+            diff
+        }
+        (
+            Type::Constructor { name: expected_name, arguments: expected_arguments },
+            Type::Constructor { name: found_name, arguments: found_arguments },
+        ) if expected_name == found_name && expected_arguments.len() == found_arguments.len() => expected_arguments
+            .iter()
+            .zip(found_arguments)
+            .enumerate()
+            .find(|(_, (expected_argument, found_argument))| expected_argument != found_argument)
+            .map(|(index, (expected_argument, found_argument))| {
+                let mut diff = diff_types(expected_argument, found_argument);
+                diff.path.insert(0, format!("argument {} of `{}`", index + 1, expected_name));
+                diff
+            })
+            .unwrap_or_else(|| TypeDiff { path: Vec::new(), expected: expected.clone(), found: found.clone() }),
+        (Type::Record(expected_fields), Type::Record(found_fields))
+            if expected_fields.keys().eq(found_fields.keys()) =>
+        {
+            expected_fields
+                .iter()
+                .find(|(name, expected_type)| found_fields.get(*name) != Some(expected_type))
+                .map(|(name, expected_type)| {
+                    let mut diff = diff_types(expected_type, &found_fields[name]);
+                    diff.path.insert(0, format!("field `{}`", name));
+                    diff
+                })
+                .unwrap_or_else(|| TypeDiff { path: Vec::new(), expected: expected.clone(), found: found.clone() })
+        }
+        _ => TypeDiff { path: Vec::new(), expected: expected.clone(), found: found.clone() },
+    }
+}
+
+/// Wraps `ty` in parentheses if rendering it bare would be ambiguous as the
+/// left-hand side of `=>`: `(int => int) => int` needs them since `=>` is
+/// right-associative and would otherwise read as `int => int => int`, a
+/// different, three-argument-curried type. Anything else (including a
+/// function type as the *right*-hand side, which already parses
+/// unambiguously) renders as-is.
+fn parenthesize_operand(ty: &Type) -> String {
+    match ty {
+        Type::Function { .. } => format!("({})", ty),
+        _ => ty.to_string(),
+    }
+}
+
+/// Like [`parenthesize_operand`], but rendering through
+/// [`Type::display_with_options`] so effect annotations inside a
+/// parenthesized parameter type are still shown.
+fn parenthesize_operand_with_options(ty: &Type, options: &CheckerOptions) -> String {
+    match ty {
+        Type::Function { .. } => format!("({})", ty.display_with_options(options)),
+        _ => ty.display_with_options(options),
+    }
+}
+
+/// Renders `ty` the way [`Display`] does, except every
+/// [`Type::Placeholder`]/[`Type::Numeric`] id is replaced with an ML-style
+/// letter (`'a`, `'b`, ..., `'z`, `'a1`, ...) assigned in the order the ids
+/// first appear, so a final inferred type reads `'a => 'a` instead of
+/// exposing the arbitrary internal `t7`-style numbering `Display` uses.
+pub fn display_with_variable_letters(ty: &Type) -> String {
+    render_with_letters(ty, &mut HashMap::new())
+}
+
+fn render_with_letters(ty: &Type, letters: &mut HashMap<u32, String>) -> String {
+    match ty {
+        Type::Boolean => String::from("bool"),
+        Type::Bottom => String::from("never"),
+        Type::Constructor { name, arguments } => {
+            let mut rendered = name.clone();
+            for argument in arguments {
+                rendered.push(' ');
+                rendered.push_str(&render_with_letters(argument, letters));
+            }
+            rendered
+        }
+        Type::Intersection(members) => members
+            .iter()
+            .map(|member| render_with_letters(member, letters))
+            .collect::<Vec<String>>()
+            .join(" & "),
+        Type::Function {
+            parameter_type,
+            return_type,
+            ..
+        } => {
+            let parameter = render_with_letters(parameter_type, letters);
+            let parameter = match **parameter_type {
+                Type::Function { .. } => format!("({})", parameter),
+                _ => parameter,
+            };
+            format!("{} => {}", parameter, render_with_letters(return_type, letters))
+        }
+        Type::Integer => String::from("int"),
+        Type::Numeric(id) | Type::Placeholder(id) => variable_letter(*id, letters),
+        Type::Record(fields) => {
+            let rendered: Vec<String> = fields
+                .iter()
+                .map(|(name, field_type)| format!("{}: {}", name, render_with_letters(field_type, letters)))
+                .collect();
+            format!("{{{}}}", rendered.join(", "))
+        }
+    }
+}
+
+/// Looks up the letter already assigned to `id`, or assigns it the next one
+/// in the sequence if this is the first time it's been seen.
+fn variable_letter(id: u32, letters: &mut HashMap<u32, String>) -> String {
+    if let Some(existing) = letters.get(&id) {
+        return existing.clone();
+    }
+    let letter = letter_for_index(letters.len());
+    letters.insert(id, letter.clone());
+    letter
+}
+
+/// Maps `0, 1, 2, ..., 25, 26, 27, ...` to `'a, 'b, 'c, ..., 'z, 'a1, 'b1,
+/// ...`, the same overflow scheme ML implementations typically use once a
+/// type has more than 26 distinct variables.
+fn letter_for_index(index: usize) -> String {
+    let letter = (b'a' + (index % 26) as u8) as char;
+    let generation = index / 26;
+    if generation == 0 {
+        format!("'{}", letter)
+    } else {
+        format!("'{}{}", letter, generation)
+    }
+}
+
 impl Display for TypedTerm {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
         write!(f, "<{}>{}</{}>", self.ty, self.kind, self.ty)
     }
 }
 
+/// Visits every node of a [`TypedTerm`] tree, so an analysis (a linter, a
+/// metric, a free-variable collector) can hook the variants it cares about
+/// without reimplementing the recursion over the rest of [`TypedTermKind`].
+/// The default method bodies just recurse via [`walk_typed_term`], so
+/// overriding none of them visits every node without doing anything.
+pub trait TypedTermVisitor {
+    fn visit_typed_term(&mut self, term: &TypedTerm) {
+        walk_typed_term(self, term);
+    }
+    fn visit_boolean(&mut self, _ty: &Type, _value: bool) {}
+    fn visit_error(&mut self, _ty: &Type) {}
+    fn visit_identifier(&mut self, _ty: &Type, _name: &str) {}
+    fn visit_integer(&mut self, _ty: &Type, _value: i32) {}
+}
+
+/// The default recursion for [`TypedTermVisitor::visit_typed_term`]:
+/// dispatches leaf variants to their dedicated `visit_*` method and recurses
+/// into the children of every compound variant.
+pub fn walk_typed_term<V: TypedTermVisitor + ?Sized>(visitor: &mut V, term: &TypedTerm) {
+    match &term.kind {
+        TypedTermKind::Boolean(value) => visitor.visit_boolean(&term.ty, *value),
+        TypedTermKind::Error => visitor.visit_error(&term.ty),
+        TypedTermKind::FunctionApplication { function, argument } => {
+            visitor.visit_typed_term(function);
+            visitor.visit_typed_term(argument);
+        }
+        TypedTermKind::FunctionDefinition { parameter, body } => {
+            visitor.visit_typed_term(parameter);
+            visitor.visit_typed_term(body);
+        }
+        TypedTermKind::Identifier(name) => visitor.visit_identifier(&term.ty, name),
+        TypedTermKind::IfExpression {
+            condition,
+            true_branch,
+            false_branch,
+        } => {
+            visitor.visit_typed_term(condition);
+            visitor.visit_typed_term(true_branch);
+            visitor.visit_typed_term(false_branch);
+        }
+        TypedTermKind::Integer(value) => visitor.visit_integer(&term.ty, *value),
+        TypedTermKind::LetExpression {
+            declaration_name,
+            declaration_value,
+            expression,
+        } => {
+            visitor.visit_typed_term(declaration_name);
+            visitor.visit_typed_term(declaration_value);
+            visitor.visit_typed_term(expression);
+        }
+        TypedTermKind::RaiseExpression { exception } => {
+            visitor.visit_typed_term(exception);
+        }
+    }
+}
+
+/// Collects every [`Type::Numeric`] and [`Type::Placeholder`] variable
+/// appearing in `term`'s type or any of its subterms' types, needed by
+/// generalization (does this variable also occur in the surrounding
+/// environment?) and other passes that would otherwise have to walk the
+/// whole tree by hand.
+pub fn free_type_variables(term: &TypedTerm) -> HashSet<u32> {
+    struct Collector {
+        variables: HashSet<u32>,
+    }
+
+    impl TypedTermVisitor for Collector {
+        fn visit_typed_term(&mut self, term: &TypedTerm) {
+            self.variables.extend(type_variables(&term.ty));
+            walk_typed_term(self, term);
+        }
+    }
+
+    let mut collector = Collector {
+        variables: HashSet::new(),
+    };
+    collector.visit_typed_term(term);
+    collector.variables
+}
+
+/// Collects the name and inferred [`Type`] of every identifier occurrence
+/// in `term`, in the order [`walk_typed_term`] visits them, so a caller
+/// (a hover tooltip, a "find all references" query) doesn't have to
+/// implement its own [`TypedTermVisitor`] just to see identifiers.
+pub fn identifier_occurrences(term: &TypedTerm) -> Vec<(String, Type)> {
+    struct Collector {
+        occurrences: Vec<(String, Type)>,
+    }
+
+    impl TypedTermVisitor for Collector {
+        fn visit_identifier(&mut self, ty: &Type, name: &str) {
+            self.occurrences.push((name.to_string(), ty.clone()));
+        }
+    }
+
+    let mut collector = Collector {
+        occurrences: Vec::new(),
+    };
+    collector.visit_typed_term(term);
+    collector.occurrences
+}
+
+/// Finds the innermost node of `term` whose span contains `span`, or
+/// `None` if `span` falls outside `term` entirely. Ties are broken toward
+/// the most specific match, since a node's span always contains every one
+/// of its children's spans — exactly what a hover or go-to-definition
+/// request positioned at a cursor offset needs.
+pub fn find_node_at_span(term: &TypedTerm, span: Span) -> Option<&TypedTerm> {
+    if !span_contains(&term.span, &span) {
+        return None;
+    }
+    for child in typed_term_children(term) {
+        if let Some(found) = find_node_at_span(child, span) {
+            return Some(found);
+        }
+    }
+    Some(term)
+}
+
+fn span_contains(outer: &Span, inner: &Span) -> bool {
+    outer.start <= inner.start && inner.end <= outer.end
+}
+
+fn typed_term_children(term: &TypedTerm) -> Vec<&TypedTerm> {
+    match &term.kind {
+        TypedTermKind::Boolean(_)
+        | TypedTermKind::Error
+        | TypedTermKind::Identifier(_)
+        | TypedTermKind::Integer(_) => vec![],
+        TypedTermKind::FunctionApplication { function, argument } => vec![function, argument],
+        TypedTermKind::FunctionDefinition { parameter, body } => vec![parameter, body],
+        TypedTermKind::IfExpression {
+            condition,
+            true_branch,
+            false_branch,
+        } => vec![condition, true_branch, false_branch],
+        TypedTermKind::LetExpression {
+            declaration_name,
+            declaration_value,
+            expression,
+        } => vec![declaration_name, declaration_value, expression],
+        TypedTermKind::RaiseExpression { exception } => vec![exception],
+    }
+}
+
 impl Display for TypedTermKind {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
         match self {
             TypedTermKind::Boolean(value) => write!(f, "{}", value),
+            TypedTermKind::Error => write!(f, "<error>"),
             TypedTermKind::FunctionApplication { function, argument } => {
                 write!(f, "{}({})", function, argument)
             }
@@ -94,161 +761,807 @@ impl Display for TypedTermKind {
                 "let val {} = {} in {} end",
                 declaration_name, declaration_value, expression
             ),
+            TypedTermKind::RaiseExpression { exception } => write!(f, "raise {}", exception),
         }
     }
 }
 
+/// Hands out fresh [`Type::Placeholder`]/[`Type::Numeric`] ids in sequence,
+/// starting from 1. [`annotate_term`] used to thread a plain `u32` counter
+/// through its recursion by hand, which made every call site responsible
+/// for computing the next call's starting value; encapsulating that state
+/// here instead means each [`annotate`] call gets its own generator, so
+/// repeated or concurrent checks can't see each other's counters the way a
+/// shared global would.
+///
+/// Each id also remembers the OCaml-style *level* it was minted at, i.e.
+/// how many enclosing `let`-bindings were open at the time — see
+/// [`TypeVarGen::enter_level`]. A future generalization pass can then
+/// decide which variables in a `let`'s inferred type are safe to
+/// quantify over by comparing their level against the level of the
+/// binding being generalized, rather than recomputing the free variables
+/// of the whole surrounding environment from scratch.
+#[derive(Debug)]
+pub struct TypeVarGen {
+    next: u32,
+    current_level: u32,
+    levels: HashMap<u32, u32>,
+}
+
+impl TypeVarGen {
+    pub fn new() -> TypeVarGen {
+        TypeVarGen {
+            next: 1,
+            current_level: 1,
+            levels: HashMap::new(),
+        }
+    }
+
+    /// Allocates and returns the next id, advancing the generator so the
+    /// same id is never handed out twice, and records the current level
+    /// as the level it was minted at.
+    pub fn fresh(&mut self) -> u32 {
+        let id = self.next;
+        self.next += 1;
+        self.levels.insert(id, self.current_level);
+        id
+    }
+
+    /// The level ids minted by [`TypeVarGen::fresh`] are currently
+    /// stamped with.
+    pub fn current_level(&self) -> u32 {
+        self.current_level
+    }
+
+    /// Opens a new, deeper level, for annotating the body of a binding
+    /// (e.g. a `let`'s declaration value) that may later be generalized.
+    /// Must be paired with [`TypeVarGen::exit_level`] once that body has
+    /// been fully annotated.
+    pub fn enter_level(&mut self) {
+        self.current_level += 1;
+    }
+
+    /// Closes the level most recently opened by [`TypeVarGen::enter_level`].
+    pub fn exit_level(&mut self) {
+        self.current_level -= 1;
+    }
+
+    /// The level `id` was minted at, or `None` if `id` was never handed
+    /// out by this generator.
+    pub fn level_of(&self, id: u32) -> Option<u32> {
+        self.levels.get(&id).copied()
+    }
+}
+
+impl Default for TypeVarGen {
+    fn default() -> Self {
+        TypeVarGen::new()
+    }
+}
+
 pub fn annotate(term: &Term) -> Result<TypedTerm, String> {
-    let (annotated_term, _) = annotate_term(term, 1, &HashMap::new())?;
-    Ok(annotated_term)
+    annotate_with_env(term, &BTreeMap::new())
+}
+
+/// Like [`annotate`], but resolves identifiers against `env` before falling
+/// back to a fresh variable, so a prelude of builtins or the accumulated
+/// bindings from earlier REPL inputs can be checked against without being
+/// re-annotated as part of the same term.
+///
+/// `env` is a [`BTreeMap`] rather than a `HashMap` so that any future code
+/// walking it in order (not just looking entries up by name, as this
+/// function itself does) gets the same iteration order every run,
+/// regardless of hashing, keeping annotation output reproducible across
+/// runs and platforms.
+pub fn annotate_with_env(term: &Term, env: &BTreeMap<String, Type>) -> Result<TypedTerm, String> {
+    annotate_term(term, &mut TypeVarGen::new(), env, 0)
 }
 
+/// The recursion limit `annotate_term` enforces before giving up with an
+/// error instead of overflowing the stack on a pathologically nested term,
+/// mirroring [`crate::parser::DEFAULT_MAX_DEPTH`]'s role for parsing.
+const MAX_ANNOTATE_DEPTH: usize = 256;
+
 fn annotate_term(
     term: &Term,
-    counter: u32,
-    env: &HashMap<String, Type>,
-) -> Result<(TypedTerm, u32), String> {
-    match term {
-        Term::Boolean(value) => Ok((
-            TypedTerm {
-                ty: Type::Placeholder(counter),
-                kind: TypedTermKind::Boolean(*value),
-            },
-            counter,
-        )),
-        Term::FunctionApplication { function, argument } => {
-            let (typed_function, typed_function_counter) =
-                annotate_term(function, counter + 1, env)?;
-            let (typed_argument, typed_argument_counter) =
-                annotate_term(argument, typed_function_counter + 1, env)?;
-            Ok((
-                TypedTerm {
-                    ty: Type::Placeholder(counter),
-                    kind: TypedTermKind::FunctionApplication {
-                        function: Box::from(typed_function),
-                        argument: Box::from(typed_argument),
-                    },
+    gen: &mut TypeVarGen,
+    env: &BTreeMap<String, Type>,
+    depth: usize,
+) -> Result<TypedTerm, String> {
+    if depth > MAX_ANNOTATE_DEPTH {
+        return Err(format!("term nested too deeply (max depth {})", MAX_ANNOTATE_DEPTH));
+    }
+    let depth = depth + 1;
+    match &term.kind {
+        TermKind::Boolean(value) => Ok(TypedTerm {
+            ty: Rc::new(Type::Placeholder(gen.fresh())),
+            kind: TypedTermKind::Boolean(*value),
+            span: term.span,
+        }),
+        TermKind::Error => Ok(TypedTerm {
+            ty: Rc::new(Type::Placeholder(gen.fresh())),
+            kind: TypedTermKind::Error,
+            span: term.span,
+        }),
+        TermKind::FunctionApplication { function, argument } => {
+            let ty = Rc::new(Type::Placeholder(gen.fresh()));
+            let typed_function = annotate_term(function, gen, env, depth)?;
+            let typed_argument = annotate_term(argument, gen, env, depth)?;
+            Ok(TypedTerm {
+                ty,
+                kind: TypedTermKind::FunctionApplication {
+                    function: Box::from(typed_function),
+                    argument: Box::from(typed_argument),
                 },
-                typed_argument_counter,
-            ))
+                span: term.span,
+            })
         }
-        Term::FunctionDefinition { parameter, body } => {
+        TermKind::FunctionDefinition { parameter, body } => {
+            let ty = Rc::new(Type::Placeholder(gen.fresh()));
             let mut extended_env = env.clone();
-            if let Term::Identifier(name) = *parameter.clone() {
-                extended_env.insert(name.clone(), Type::Placeholder(counter + 1));
+            if let TermKind::Identifier(name) = &parameter.kind {
+                extended_env.insert(name.clone(), Type::Placeholder(gen.fresh()));
             }
-            let (typed_parameter, typed_parameter_counter) =
-                annotate_term(parameter, counter + 2, &extended_env)?;
-            let (typed_body, typed_body_counter) =
-                annotate_term(body, typed_parameter_counter + 1, &extended_env)?;
-            Ok((
-                TypedTerm {
-                    ty: Type::Placeholder(counter),
-                    kind: TypedTermKind::FunctionDefinition {
-                        parameter: Box::from(typed_parameter),
-                        body: Box::from(typed_body),
-                    },
+            let typed_parameter = annotate_term(parameter, gen, &extended_env, depth)?;
+            let typed_body = annotate_term(body, gen, &extended_env, depth)?;
+            Ok(TypedTerm {
+                ty,
+                kind: TypedTermKind::FunctionDefinition {
+                    parameter: Box::from(typed_parameter),
+                    body: Box::from(typed_body),
                 },
-                typed_body_counter,
-            ))
+                span: term.span,
+            })
         }
-        Term::Identifier(name) => match env.get(name) {
-            Some(existing_ty) => Ok((
-                TypedTerm {
-                    ty: existing_ty.clone(),
-                    kind: TypedTermKind::Identifier(name.clone()),
-                },
-                counter - 1,
-            )),
+        TermKind::Identifier(name) => match env.get(name) {
+            Some(existing_ty) => Ok(TypedTerm {
+                ty: Rc::new(existing_ty.clone()),
+                kind: TypedTermKind::Identifier(name.clone()),
+                span: term.span,
+            }),
             None => match name.as_ref() {
-                "+" | "-" | "*" | "/" => Ok((
-                    TypedTerm {
-                        ty: Type::Placeholder(counter),
-                        kind: TypedTermKind::Identifier(name.clone()),
-                    },
-                    counter,
-                )),
+                "+" | "-" | "*" | "/" => Ok(TypedTerm {
+                    ty: Rc::new(Type::Placeholder(gen.fresh())),
+                    kind: TypedTermKind::Identifier(name.clone()),
+                    span: term.span,
+                }),
                 _ => Err(format!("unbound identifier: {}", name)),
             },
         },
-        Term::IfExpression {
+        TermKind::IfExpression {
             condition,
             true_branch,
             false_branch,
         } => {
-            let (typed_condition, typed_condition_counter) =
-                annotate_term(condition, counter + 1, env)?;
-            let (typed_true_branch, typed_true_branch_counter) =
-                annotate_term(true_branch, typed_condition_counter + 1, env)?;
-            let (typed_false_branch, typed_false_branch_counter) =
-                annotate_term(false_branch, typed_true_branch_counter + 1, env)?;
-            Ok((
-                TypedTerm {
-                    ty: Type::Placeholder(counter),
-                    kind: TypedTermKind::IfExpression {
-                        condition: Box::from(typed_condition),
-                        true_branch: Box::from(typed_true_branch),
-                        false_branch: Box::from(typed_false_branch),
-                    },
+            let ty = Rc::new(Type::Placeholder(gen.fresh()));
+            let typed_condition = annotate_term(condition, gen, env, depth)?;
+            let typed_true_branch = annotate_term(true_branch, gen, env, depth)?;
+            let typed_false_branch = annotate_term(false_branch, gen, env, depth)?;
+            Ok(TypedTerm {
+                ty,
+                kind: TypedTermKind::IfExpression {
+                    condition: Box::from(typed_condition),
+                    true_branch: Box::from(typed_true_branch),
+                    false_branch: Box::from(typed_false_branch),
                 },
-                typed_false_branch_counter,
-            ))
+                span: term.span,
+            })
         }
-        Term::Integer(value) => Ok((
-            TypedTerm {
-                ty: Type::Placeholder(counter),
-                kind: TypedTermKind::Integer(*value),
-            },
-            counter,
-        )),
-        Term::LetExpression {
+        TermKind::Integer(value) => Ok(TypedTerm {
+            ty: Rc::new(Type::Numeric(gen.fresh())),
+            kind: TypedTermKind::Integer(*value),
+            span: term.span,
+        }),
+        TermKind::LetExpression {
             declaration_name,
             declaration_value,
             expression,
         } => {
+            let ty = Rc::new(Type::Placeholder(gen.fresh()));
             let mut extended_env = env.clone();
-            if let Term::Identifier(name) = *declaration_name.clone() {
-                extended_env.insert(name.clone(), Type::Placeholder(counter + 1));
+            if let TermKind::Identifier(name) = &declaration_name.kind {
+                extended_env.insert(name.clone(), Type::Placeholder(gen.fresh()));
             }
-            let (typed_declaration_name, typed_declaration_name_counter) =
-                annotate_term(declaration_name, counter + 2, &extended_env)?;
-            let (typed_declaration_value, typed_declaration_value_counter) =
-                annotate_term(declaration_value, typed_declaration_name_counter + 1, env)?;
-            let (typed_expression, typed_expression_counter) = annotate_term(
-                expression,
-                typed_declaration_value_counter + 1,
-                &extended_env,
-            )?;
-            Ok((
-                TypedTerm {
-                    ty: Type::Placeholder(counter),
-                    kind: TypedTermKind::LetExpression {
-                        declaration_name: Box::from(typed_declaration_name),
-                        declaration_value: Box::from(typed_declaration_value),
-                        expression: Box::from(typed_expression),
-                    },
+            let typed_declaration_name = annotate_term(declaration_name, gen, &extended_env, depth)?;
+            gen.enter_level();
+            let typed_declaration_value = annotate_term(declaration_value, gen, env, depth)?;
+            gen.exit_level();
+            let typed_expression = annotate_term(expression, gen, &extended_env, depth)?;
+            Ok(TypedTerm {
+                ty,
+                kind: TypedTermKind::LetExpression {
+                    declaration_name: Box::from(typed_declaration_name),
+                    declaration_value: Box::from(typed_declaration_value),
+                    expression: Box::from(typed_expression),
                 },
-                typed_expression_counter,
-            ))
+                span: term.span,
+            })
+        }
+        TermKind::RaiseExpression { exception } => {
+            // `raise` itself has no placeholder of its own (its type is
+            // always `Type::Bottom`), but the id it would have used is
+            // still burned here rather than handed to `exception`, keeping
+            // numbering identical to what a straight-line traversal would
+            // have produced if every node claimed an id on the way in.
+            gen.fresh();
+            let typed_exception = annotate_term(exception, gen, env, depth)?;
+            Ok(TypedTerm {
+                ty: Rc::new(Type::Bottom),
+                kind: TypedTermKind::RaiseExpression {
+                    exception: Box::from(typed_exception),
+                },
+                span: term.span,
+            })
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::annotator::{annotate, Type, TypedTerm, TypedTermKind};
-    use crate::parser::parse;
-    use crate::tokenizer::tokenize;
+    use crate::annotator::{
+        annotate, annotate_with_env, default_numeric_types, diff_types, display_with_variable_letters,
+        find_node_at_span, free_type_variables, identifier_occurrences, resolve, type_variables,
+        CheckerOptions, ResolvedTerm, Type, TypeVarGen, TypedTerm, TypedTermKind, TypedTermVisitor,
+    };
+    use crate::parser::{parse, Term, TermKind};
+    use crate::tokenizer::{tokenize_with_spans, Span};
+    use std::collections::{BTreeMap, HashSet};
+    use std::rc::Rc;
+
+    #[test]
+    fn test_annotate_rejects_a_pathologically_nested_term_instead_of_overflowing_the_stack() {
+        let mut term = Term::identifier("x");
+        for _ in 0..200_000 {
+            term = Term::lambda("x", term);
+        }
+        assert!(annotate(&term).is_err());
+        // `term`'s nested `Box<Term>` chain would itself overflow the stack
+        // via recursive drop glue on the way out of this test; that's a
+        // property of the tree this test intentionally builds, not of
+        // `annotate`, which is what's actually under test here.
+        std::mem::forget(term);
+    }
+
+    #[test]
+    fn test_type_var_gen_starts_at_one_and_counts_up() {
+        let mut gen = TypeVarGen::new();
+        assert_eq!(gen.fresh(), 1);
+        assert_eq!(gen.fresh(), 2);
+        assert_eq!(gen.fresh(), 3);
+    }
+
+    #[test]
+    fn test_type_var_gen_instances_are_independent() {
+        // Two separate generators (standing in for two separate `annotate`
+        // calls) don't share state, so repeated or concurrent checks always
+        // start counting from 1 regardless of what any other check has done.
+        let mut first = TypeVarGen::new();
+        let mut second = TypeVarGen::new();
+        first.fresh();
+        first.fresh();
+        assert_eq!(second.fresh(), 1);
+    }
+
+    #[test]
+    fn test_type_var_gen_stamps_fresh_ids_with_the_current_level() {
+        let mut gen = TypeVarGen::new();
+        let outer = gen.fresh();
+        gen.enter_level();
+        let inner = gen.fresh();
+        gen.exit_level();
+        assert_eq!(gen.level_of(outer), Some(1));
+        assert_eq!(gen.level_of(inner), Some(2));
+    }
+
+    #[test]
+    fn test_type_var_gen_exit_level_restores_the_enclosing_level() {
+        let mut gen = TypeVarGen::new();
+        assert_eq!(gen.current_level(), 1);
+        gen.enter_level();
+        assert_eq!(gen.current_level(), 2);
+        gen.exit_level();
+        assert_eq!(gen.current_level(), 1);
+    }
+
+    #[test]
+    fn test_type_var_gen_level_of_unknown_id_is_none() {
+        let gen = TypeVarGen::new();
+        assert_eq!(gen.level_of(1), None);
+    }
+
+    #[test]
+    fn test_annotate_numbers_placeholders_identically_across_repeated_calls() -> Result<(), String> {
+        let tokens = tokenize_with_spans("fn x => x")?;
+        let term = parse(&tokens)?;
+        assert_eq!(annotate(&term)?, annotate(&term)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_annotate_with_env_resolves_an_identifier_to_a_prelude_binding() -> Result<(), String> {
+        let tokens = tokenize_with_spans("double")?;
+        let term = parse(&tokens)?;
+        let mut env = BTreeMap::new();
+        env.insert(
+            String::from("double"),
+            Type::Function {
+                parameter_type: Box::from(Type::Integer),
+                return_type: Box::from(Type::Integer),
+                effects: Vec::new(),
+            },
+        );
+        let typed_term = annotate_with_env(&term, &env)?;
+        assert_eq!(
+            typed_term.ty,
+            Rc::new(Type::Function {
+                parameter_type: Box::from(Type::Integer),
+                return_type: Box::from(Type::Integer),
+                effects: Vec::new(),
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_annotate_with_env_still_errors_on_a_name_not_in_the_environment() -> Result<(), String> {
+        let tokens = tokenize_with_spans("undefined")?;
+        let term = parse(&tokens)?;
+        assert_eq!(
+            annotate_with_env(&term, &BTreeMap::new()),
+            Err(String::from("unbound identifier: undefined"))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_annotate_with_an_empty_env_matches_annotate() -> Result<(), String> {
+        let tokens = tokenize_with_spans("fn x => x")?;
+        let term = parse(&tokens)?;
+        assert_eq!(annotate(&term)?, annotate_with_env(&term, &BTreeMap::new())?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_annotate_with_env_is_independent_of_binding_insertion_order() -> Result<(), String> {
+        // `env` is a `BTreeMap`, so its iteration order is fixed by key,
+        // regardless of the order bindings were inserted in; this checks
+        // that guarantee holds for the resulting annotation.
+        let tokens = tokenize_with_spans("a + b")?;
+        let term = parse(&tokens)?;
+        let mut ascending = BTreeMap::new();
+        ascending.insert(String::from("a"), Type::Integer);
+        ascending.insert(String::from("b"), Type::Integer);
+        let mut descending = BTreeMap::new();
+        descending.insert(String::from("b"), Type::Integer);
+        descending.insert(String::from("a"), Type::Integer);
+        assert_eq!(
+            annotate_with_env(&term, &ascending)?,
+            annotate_with_env(&term, &descending)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_canonicalize_renumbers_from_one_in_first_appearance_order() {
+        let ty = Type::Function {
+            parameter_type: Box::from(Type::Placeholder(9)),
+            return_type: Box::from(Type::Placeholder(9)),
+            effects: Vec::new(),
+        };
+        assert_eq!(
+            ty.canonicalize(),
+            Type::Function {
+                parameter_type: Box::from(Type::Placeholder(1)),
+                return_type: Box::from(Type::Placeholder(1)),
+                effects: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_canonicalize_is_independent_of_the_original_ids() {
+        // Two principal types that only differ in which arbitrary ids the
+        // generator happened to allocate should canonicalize identically.
+        let first = Type::Function {
+            parameter_type: Box::from(Type::Placeholder(3)),
+            return_type: Box::from(Type::Placeholder(3)),
+            effects: Vec::new(),
+        };
+        let second = Type::Function {
+            parameter_type: Box::from(Type::Placeholder(41)),
+            return_type: Box::from(Type::Placeholder(41)),
+            effects: Vec::new(),
+        };
+        assert_eq!(first.canonicalize(), second.canonicalize());
+    }
+
+    #[test]
+    fn test_canonicalize_preserves_the_numeric_vs_placeholder_distinction() {
+        let ty = Type::Function {
+            parameter_type: Box::from(Type::Numeric(5)),
+            return_type: Box::from(Type::Placeholder(8)),
+            effects: Vec::new(),
+        };
+        assert_eq!(
+            ty.canonicalize(),
+            Type::Function {
+                parameter_type: Box::from(Type::Numeric(1)),
+                return_type: Box::from(Type::Placeholder(2)),
+                effects: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_canonicalize_leaves_a_concrete_type_alone() {
+        assert_eq!(Type::Integer.canonicalize(), Type::Integer);
+    }
+
+    #[test]
+    fn test_display_with_variable_letters_assigns_letters_in_first_appearance_order() {
+        let ty = Type::Function {
+            parameter_type: Box::from(Type::Placeholder(7)),
+            return_type: Box::from(Type::Placeholder(7)),
+            effects: Vec::new(),
+        };
+        assert_eq!(display_with_variable_letters(&ty), "'a => 'a");
+    }
+
+    #[test]
+    fn test_display_with_variable_letters_gives_distinct_ids_distinct_letters() {
+        let ty = Type::Function {
+            parameter_type: Box::from(Type::Placeholder(9)),
+            return_type: Box::from(Type::Numeric(3)),
+            effects: Vec::new(),
+        };
+        assert_eq!(display_with_variable_letters(&ty), "'a => 'b");
+    }
+
+    #[test]
+    fn test_display_with_variable_letters_leaves_concrete_types_alone() {
+        assert_eq!(display_with_variable_letters(&Type::Integer), "int");
+    }
+
+    #[test]
+    fn test_display_with_variable_letters_overflows_past_z() {
+        let members: Vec<Type> = (0..27).map(Type::Placeholder).collect();
+        let ty = Type::Intersection(members);
+        let rendered = display_with_variable_letters(&ty);
+        assert!(rendered.starts_with("'a & 'b"));
+        assert!(rendered.ends_with("'a1"));
+    }
+
+    #[test]
+    fn test_display_phantom_type_parameter() {
+        // `Tagged int t1` — the second argument is a phantom parameter that
+        // does not correspond to any constructor field.
+        let ty = Type::Constructor {
+            name: String::from("Tagged"),
+            arguments: vec![Type::Integer, Type::Placeholder(1)],
+        };
+        assert_eq!(ty.to_string(), "Tagged int t1");
+    }
+
+    #[test]
+    fn test_default_numeric_types_preserves_phantom_arguments() {
+        let ty = Type::Constructor {
+            name: String::from("Tagged"),
+            arguments: vec![Type::Numeric(1), Type::Placeholder(2)],
+        };
+        assert_eq!(
+            default_numeric_types(&ty),
+            Type::Constructor {
+                name: String::from("Tagged"),
+                arguments: vec![Type::Integer, Type::Placeholder(2)],
+            }
+        );
+    }
+
+    #[test]
+    fn test_default_numeric_types_resolves_to_integer() {
+        assert_eq!(default_numeric_types(&Type::Numeric(1)), Type::Integer);
+    }
+
+    #[test]
+    fn test_resolve_defaults_numeric_types_at_every_node_not_just_the_root() {
+        let parameter = TypedTerm {
+            ty: Rc::new(Type::Numeric(1)),
+            kind: TypedTermKind::Identifier(String::from("x")),
+            span: Span::default(),
+        };
+        let body = TypedTerm {
+            ty: Rc::new(Type::Numeric(2)),
+            kind: TypedTermKind::Integer(1),
+            span: Span::default(),
+        };
+        let term = TypedTerm {
+            ty: Rc::new(Type::Function {
+                parameter_type: Box::from(Type::Numeric(1)),
+                return_type: Box::from(Type::Numeric(2)),
+                effects: Vec::new(),
+            }),
+            kind: TypedTermKind::FunctionDefinition {
+                parameter: Box::from(parameter),
+                body: Box::from(body),
+            },
+            span: Span::default(),
+        };
+        let ResolvedTerm(resolved) = resolve(&term);
+        assert_eq!(
+            *resolved.ty,
+            Type::Function {
+                parameter_type: Box::from(Type::Integer),
+                return_type: Box::from(Type::Integer),
+                effects: Vec::new(),
+            }
+        );
+        match resolved.kind {
+            TypedTermKind::FunctionDefinition { parameter, body } => {
+                assert_eq!(*parameter.ty, Type::Integer);
+                assert_eq!(*body.ty, Type::Integer);
+            }
+            other => panic!("expected a function definition, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_default_numeric_types_recurses_into_function_type() {
+        let ty = Type::Function {
+            parameter_type: Box::from(Type::Numeric(1)),
+            return_type: Box::from(Type::Boolean),
+            effects: Vec::new(),
+        };
+        assert_eq!(
+            default_numeric_types(&ty),
+            Type::Function {
+                parameter_type: Box::from(Type::Integer),
+                return_type: Box::from(Type::Boolean),
+                effects: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_annotate_integer_uses_numeric_type() -> Result<(), String> {
+        let tokens = tokenize_with_spans("42")?;
+        let term = parse(&tokens)?;
+        let typed_term = annotate(&term)?;
+        assert_eq!(typed_term.ty, Rc::new(Type::Numeric(1)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_display_parenthesizes_a_function_typed_parameter() {
+        // `(int => int) => int` is unambiguous; without parentheses
+        // `int => int => int` would read as a different, curried type.
+        let ty = Type::Function {
+            parameter_type: Box::from(Type::Function {
+                parameter_type: Box::from(Type::Integer),
+                return_type: Box::from(Type::Integer),
+                effects: Vec::new(),
+            }),
+            return_type: Box::from(Type::Integer),
+            effects: Vec::new(),
+        };
+        assert_eq!(ty.to_string(), "(int => int) => int");
+    }
+
+    #[test]
+    fn test_display_does_not_parenthesize_a_function_typed_return() {
+        // `=>` is right-associative, so a function-typed return needs no
+        // parentheses: `int => int => int` already means `int => (int =>
+        // int)` unambiguously.
+        let ty = Type::Function {
+            parameter_type: Box::from(Type::Integer),
+            return_type: Box::from(Type::Function {
+                parameter_type: Box::from(Type::Integer),
+                return_type: Box::from(Type::Integer),
+                effects: Vec::new(),
+            }),
+            effects: Vec::new(),
+        };
+        assert_eq!(ty.to_string(), "int => int => int");
+    }
+
+    #[test]
+    fn test_display_with_options_also_parenthesizes_a_function_typed_parameter() {
+        let ty = Type::Function {
+            parameter_type: Box::from(Type::Function {
+                parameter_type: Box::from(Type::Integer),
+                return_type: Box::from(Type::Integer),
+                effects: vec![String::from("Div")],
+            }),
+            return_type: Box::from(Type::Integer),
+            effects: Vec::new(),
+        };
+        assert_eq!(
+            ty.display_with_options(&CheckerOptions {
+                show_effects: true,
+                ..Default::default()
+            }),
+            "(int -[Div]-> int) => int"
+        );
+    }
+
+    #[test]
+    fn test_display_function_type_without_effects() {
+        let ty = Type::Function {
+            parameter_type: Box::from(Type::Integer),
+            return_type: Box::from(Type::Integer),
+            effects: Vec::new(),
+        };
+        assert_eq!(ty.to_string(), "int => int");
+        assert_eq!(
+            ty.display_with_options(&CheckerOptions { show_effects: true, ..Default::default() }),
+            "int => int"
+        );
+    }
+
+    #[test]
+    fn test_display_function_type_with_effects() {
+        let ty = Type::Function {
+            parameter_type: Box::from(Type::Integer),
+            return_type: Box::from(Type::Integer),
+            effects: vec![String::from("Div")],
+        };
+        assert_eq!(ty.to_string(), "int => int");
+        assert_eq!(
+            ty.display_with_options(&CheckerOptions { show_effects: true, ..Default::default() }),
+            "int -[Div]-> int"
+        );
+        assert_eq!(
+            ty.display_with_options(&CheckerOptions { show_effects: false, ..Default::default() }),
+            "int => int"
+        );
+    }
+
+    #[test]
+    fn test_typed_term_visitor_default_walk_collects_every_identifier() -> Result<(), String> {
+        struct IdentifierCollector {
+            names: Vec<String>,
+        }
+
+        impl TypedTermVisitor for IdentifierCollector {
+            fn visit_identifier(&mut self, _ty: &Type, name: &str) {
+                self.names.push(String::from(name));
+            }
+        }
+
+        let tokens = tokenize_with_spans("fn x => x + 1")?;
+        let term = parse(&tokens)?;
+        let typed_term = annotate(&term)?;
+        let mut collector = IdentifierCollector { names: Vec::new() };
+        collector.visit_typed_term(&typed_term);
+        assert_eq!(collector.names, vec!["x", "+", "x"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_type_variables_collects_placeholder_and_numeric_ids() {
+        let ty = Type::Function {
+            parameter_type: Box::from(Type::Placeholder(1)),
+            return_type: Box::from(Type::Numeric(2)),
+            effects: Vec::new(),
+        };
+        assert_eq!(type_variables(&ty), HashSet::from([1, 2]));
+    }
+
+    #[test]
+    fn test_type_variables_of_a_concrete_type_is_empty() {
+        assert_eq!(type_variables(&Type::Boolean), HashSet::new());
+    }
+
+    #[test]
+    fn test_free_type_variables_collects_every_subterms_type_variables() -> Result<(), String> {
+        let tokens = tokenize_with_spans("fn x => x")?;
+        let term = parse(&tokens)?;
+        let typed_term = annotate(&term)?;
+        // Every fresh placeholder `annotate` hands out while typing `fn x
+        // => x` shows up: one for the parameter/body (`x` occurs in both),
+        // and one for the function itself.
+        assert_eq!(free_type_variables(&typed_term), HashSet::from([1, 2]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_identifier_occurrences_collects_name_and_type() -> Result<(), String> {
+        let tokens = tokenize_with_spans("fn x => x")?;
+        let term = parse(&tokens)?;
+        let typed_term = annotate(&term)?;
+        assert_eq!(
+            identifier_occurrences(&typed_term),
+            vec![
+                (String::from("x"), Type::Placeholder(2)),
+                (String::from("x"), Type::Placeholder(2)),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_identifier_occurrences_is_empty_for_a_term_without_identifiers() -> Result<(), String> {
+        let tokens = tokenize_with_spans("1")?;
+        let term = parse(&tokens)?;
+        let typed_term = annotate(&term)?;
+        assert_eq!(identifier_occurrences(&typed_term), vec![]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_node_at_span_finds_the_innermost_matching_node() -> Result<(), String> {
+        let tokens = tokenize_with_spans("fn x => x")?;
+        let term = parse(&tokens)?;
+        let typed_term = annotate(&term)?;
+        let body_span = match &typed_term.kind {
+            TypedTermKind::FunctionDefinition { body, .. } => body.span,
+            _ => panic!("expected FunctionDefinition"),
+        };
+        let found = find_node_at_span(&typed_term, body_span).expect("body span is inside the term");
+        assert_eq!(found.kind, TypedTermKind::Identifier(String::from("x")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_node_at_span_returns_none_outside_the_root_span() -> Result<(), String> {
+        let tokens = tokenize_with_spans("fn x => x")?;
+        let term = parse(&tokens)?;
+        let typed_term = annotate(&term)?;
+        let out_of_range = Span {
+            start: typed_term.span.end + 10,
+            end: typed_term.span.end + 11,
+            line: 1,
+            column: 1,
+        };
+        assert_eq!(find_node_at_span(&typed_term, out_of_range), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_annotate_a_term_built_without_the_string_front_end() {
+        let term = Term::app(Term::lambda("x", Term::identifier("x")), Term::integer(1));
+        let typed_term = annotate(&term);
+        assert_eq!(
+            typed_term,
+            Ok(TypedTerm {
+                span: Span::default(),
+                ty: Rc::new(Type::Placeholder(1)),
+                kind: TypedTermKind::FunctionApplication {
+                    function: Box::from(TypedTerm {
+                        span: Span::default(),
+                        ty: Rc::new(Type::Placeholder(2)),
+                        kind: TypedTermKind::FunctionDefinition {
+                            parameter: Box::from(TypedTerm {
+                                span: Span::default(),
+                                ty: Rc::new(Type::Placeholder(3)),
+                                kind: TypedTermKind::Identifier(String::from("x"))
+                            }),
+                            body: Box::from(TypedTerm {
+                                span: Span::default(),
+                                ty: Rc::new(Type::Placeholder(3)),
+                                kind: TypedTermKind::Identifier(String::from("x"))
+                            }),
+                        }
+                    }),
+                    argument: Box::from(TypedTerm {
+                        span: Span::default(),
+                        ty: Rc::new(Type::Numeric(4)),
+                        kind: TypedTermKind::Integer(1)
+                    })
+                }
+            })
+        );
+    }
 
     #[test]
     fn test_annotate_integer() -> Result<(), String> {
-        let tokens = tokenize("42")?;
+        let tokens = tokenize_with_spans("42")?;
         let term = parse(&tokens)?;
         let typed_term = annotate(&term);
         assert_eq!(
             typed_term,
             Ok(TypedTerm {
-                ty: Type::Placeholder(1),
+                span: Span::default(),
+                ty: Rc::new(Type::Numeric(1)),
                 kind: TypedTermKind::Integer(42)
             })
         );
@@ -257,13 +1570,14 @@ mod tests {
 
     #[test]
     fn test_annotate_boolean_true() -> Result<(), String> {
-        let tokens = tokenize("true")?;
+        let tokens = tokenize_with_spans("true")?;
         let term = parse(&tokens)?;
         let typed_term = annotate(&term);
         assert_eq!(
             typed_term,
             Ok(TypedTerm {
-                ty: Type::Placeholder(1),
+                span: Span::default(),
+                ty: Rc::new(Type::Placeholder(1)),
                 kind: TypedTermKind::Boolean(true)
             })
         );
@@ -272,13 +1586,14 @@ mod tests {
 
     #[test]
     fn test_annotate_boolean_false() -> Result<(), String> {
-        let tokens = tokenize("false")?;
+        let tokens = tokenize_with_spans("false")?;
         let term = parse(&tokens)?;
         let typed_term = annotate(&term);
         assert_eq!(
             typed_term,
             Ok(TypedTerm {
-                ty: Type::Placeholder(1),
+                span: Span::default(),
+                ty: Rc::new(Type::Placeholder(1)),
                 kind: TypedTermKind::Boolean(false)
             })
         );
@@ -287,7 +1602,7 @@ mod tests {
 
     #[test]
     fn test_annotate_unbound_identifier() -> Result<(), String> {
-        let tokens = tokenize("x")?;
+        let tokens = tokenize_with_spans("x")?;
         let term = parse(&tokens)?;
         let typed_term = annotate(&term);
         assert!(typed_term.is_err());
@@ -296,20 +1611,23 @@ mod tests {
 
     #[test]
     fn test_annotate_function_definition() -> Result<(), String> {
-        let tokens = tokenize("fn x => x")?;
+        let tokens = tokenize_with_spans("fn x => x")?;
         let term = parse(&tokens)?;
         let typed_term = annotate(&term);
         assert_eq!(
             typed_term,
             Ok(TypedTerm {
-                ty: Type::Placeholder(1),
+                span: Span::default(),
+                ty: Rc::new(Type::Placeholder(1)),
                 kind: TypedTermKind::FunctionDefinition {
                     parameter: Box::from(TypedTerm {
-                        ty: Type::Placeholder(2),
+                        span: Span::default(),
+                        ty: Rc::new(Type::Placeholder(2)),
                         kind: TypedTermKind::Identifier(String::from("x"))
                     }),
                     body: Box::from(TypedTerm {
-                        ty: Type::Placeholder(2),
+                        span: Span::default(),
+                        ty: Rc::new(Type::Placeholder(2)),
                         kind: TypedTermKind::Identifier(String::from("x"))
                     }),
                 }
@@ -320,24 +1638,28 @@ mod tests {
 
     #[test]
     fn test_annotate_if_expression() -> Result<(), String> {
-        let tokens = tokenize("if true then 0 else 1")?;
+        let tokens = tokenize_with_spans("if true then 0 else 1")?;
         let term = parse(&tokens)?;
         let typed_term = annotate(&term);
         assert_eq!(
             typed_term,
             Ok(TypedTerm {
-                ty: Type::Placeholder(1),
+                span: Span::default(),
+                ty: Rc::new(Type::Placeholder(1)),
                 kind: TypedTermKind::IfExpression {
                     condition: Box::from(TypedTerm {
-                        ty: Type::Placeholder(2),
+                        span: Span::default(),
+                        ty: Rc::new(Type::Placeholder(2)),
                         kind: TypedTermKind::Boolean(true)
                     }),
                     true_branch: Box::from(TypedTerm {
-                        ty: Type::Placeholder(3),
+                        span: Span::default(),
+                        ty: Rc::new(Type::Numeric(3)),
                         kind: TypedTermKind::Integer(0)
                     }),
                     false_branch: Box::from(TypedTerm {
-                        ty: Type::Placeholder(4),
+                        span: Span::default(),
+                        ty: Rc::new(Type::Numeric(4)),
                         kind: TypedTermKind::Integer(1)
                     }),
                 }
@@ -348,43 +1670,52 @@ mod tests {
 
     #[test]
     fn test_annotate_let_expression() -> Result<(), String> {
-        let tokens = tokenize("let val inc = fn x => x + 1 in inc(42) end")?;
+        let tokens = tokenize_with_spans("let val inc = fn x => x + 1 in inc(42) end")?;
         let term = parse(&tokens)?;
         let typed_term = annotate(&term);
         assert_eq!(
             typed_term,
             Ok(TypedTerm {
-                ty: Type::Placeholder(1),
+                span: Span::default(),
+                ty: Rc::new(Type::Placeholder(1)),
                 kind: TypedTermKind::LetExpression {
                     declaration_name: Box::from(TypedTerm {
-                        ty: Type::Placeholder(2),
+                        span: Span::default(),
+                        ty: Rc::new(Type::Placeholder(2)),
                         kind: TypedTermKind::Identifier(String::from("inc"))
                     }),
                     declaration_value: Box::from(TypedTerm {
-                        ty: Type::Placeholder(3),
+                        span: Span::default(),
+                        ty: Rc::new(Type::Placeholder(3)),
                         kind: TypedTermKind::FunctionDefinition {
                             parameter: Box::from(TypedTerm {
-                                ty: Type::Placeholder(4),
+                                span: Span::default(),
+                                ty: Rc::new(Type::Placeholder(4)),
                                 kind: TypedTermKind::Identifier(String::from("x"))
                             }),
                             body: Box::from(TypedTerm {
-                                ty: Type::Placeholder(5),
+                                span: Span::default(),
+                                ty: Rc::new(Type::Placeholder(5)),
                                 kind: TypedTermKind::FunctionApplication {
                                     function: Box::from(TypedTerm {
-                                        ty: Type::Placeholder(6),
+                                        span: Span::default(),
+                                        ty: Rc::new(Type::Placeholder(6)),
                                         kind: TypedTermKind::FunctionApplication {
                                             function: Box::from(TypedTerm {
-                                                ty: Type::Placeholder(7),
+                                                span: Span::default(),
+                                                ty: Rc::new(Type::Placeholder(7)),
                                                 kind: TypedTermKind::Identifier(String::from("+"))
                                             }),
                                             argument: Box::from(TypedTerm {
-                                                ty: Type::Placeholder(4),
+                                                span: Span::default(),
+                                                ty: Rc::new(Type::Placeholder(4)),
                                                 kind: TypedTermKind::Identifier(String::from("x"))
                                             })
                                         }
                                     }),
                                     argument: Box::from(TypedTerm {
-                                        ty: Type::Placeholder(8),
+                                        span: Span::default(),
+                                        ty: Rc::new(Type::Numeric(8)),
                                         kind: TypedTermKind::Integer(1)
                                     })
                                 }
@@ -392,14 +1723,17 @@ mod tests {
                         }
                     }),
                     expression: Box::from(TypedTerm {
-                        ty: Type::Placeholder(9),
+                        span: Span::default(),
+                        ty: Rc::new(Type::Placeholder(9)),
                         kind: TypedTermKind::FunctionApplication {
                             function: Box::from(TypedTerm {
-                                ty: Type::Placeholder(2),
+                                span: Span::default(),
+                                ty: Rc::new(Type::Placeholder(2)),
                                 kind: TypedTermKind::Identifier(String::from("inc"))
                             }),
                             argument: Box::from(TypedTerm {
-                                ty: Type::Placeholder(10),
+                                span: Span::default(),
+                                ty: Rc::new(Type::Numeric(10)),
                                 kind: TypedTermKind::Integer(42)
                             })
                         }
@@ -409,4 +1743,148 @@ mod tests {
         );
         Ok(())
     }
+
+    #[test]
+    fn test_annotate_error_term_gets_a_fresh_placeholder() -> Result<(), String> {
+        let typed_term = annotate(&Term::new(TermKind::Error, Span::default()));
+        assert_eq!(
+            typed_term,
+            Ok(TypedTerm {
+                span: Span::default(),
+                ty: Rc::new(Type::Placeholder(1)),
+                kind: TypedTermKind::Error
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_annotate_raise_expression() -> Result<(), String> {
+        let tokens = tokenize_with_spans("raise 0")?;
+        let term = parse(&tokens)?;
+        let typed_term = annotate(&term);
+        assert_eq!(
+            typed_term,
+            Ok(TypedTerm {
+                span: Span::default(),
+                ty: Rc::new(Type::Bottom),
+                kind: TypedTermKind::RaiseExpression {
+                    exception: Box::from(TypedTerm {
+                        span: Span::default(),
+                        ty: Rc::new(Type::Numeric(2)),
+                        kind: TypedTermKind::Integer(0)
+                    }),
+                }
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_types_reports_the_top_level_mismatch_when_the_shapes_disagree() {
+        let diff = diff_types(&Type::Boolean, &Type::Integer);
+        assert_eq!(diff.path, Vec::<String>::new());
+        assert_eq!(diff.expected, Type::Boolean);
+        assert_eq!(diff.found, Type::Integer);
+    }
+
+    #[test]
+    fn test_diff_types_descends_into_a_matching_functions_return_type() {
+        let expected = Type::Function {
+            parameter_type: Box::new(Type::Integer),
+            return_type: Box::new(Type::Boolean),
+            effects: Vec::new(),
+        };
+        let found = Type::Function {
+            parameter_type: Box::new(Type::Integer),
+            return_type: Box::new(Type::Integer),
+            effects: Vec::new(),
+        };
+        let diff = diff_types(&expected, &found);
+        assert_eq!(diff.path, vec![String::from("return type")]);
+        assert_eq!(diff.expected, Type::Boolean);
+        assert_eq!(diff.found, Type::Integer);
+    }
+
+    #[test]
+    fn test_diff_types_descends_through_nested_function_types() {
+        let inner_expected = Type::Function {
+            parameter_type: Box::new(Type::Boolean),
+            return_type: Box::new(Type::Boolean),
+            effects: Vec::new(),
+        };
+        let inner_found = Type::Function {
+            parameter_type: Box::new(Type::Boolean),
+            return_type: Box::new(Type::Integer),
+            effects: Vec::new(),
+        };
+        let expected = Type::Function {
+            parameter_type: Box::new(inner_expected),
+            return_type: Box::new(Type::Boolean),
+            effects: Vec::new(),
+        };
+        let found = Type::Function {
+            parameter_type: Box::new(inner_found),
+            return_type: Box::new(Type::Boolean),
+            effects: Vec::new(),
+        };
+        let diff = diff_types(&expected, &found);
+        assert_eq!(diff.path, vec![String::from("parameter type"), String::from("return type")]);
+        assert_eq!(diff.expected, Type::Boolean);
+        assert_eq!(diff.found, Type::Integer);
+    }
+
+    #[test]
+    fn test_diff_types_descends_into_a_mismatched_constructor_argument() {
+        let expected = Type::Constructor {
+            name: String::from("Pair"),
+            arguments: vec![Type::Integer, Type::Boolean],
+        };
+        let found = Type::Constructor {
+            name: String::from("Pair"),
+            arguments: vec![Type::Integer, Type::Integer],
+        };
+        let diff = diff_types(&expected, &found);
+        assert_eq!(diff.path, vec![String::from("argument 2 of `Pair`")]);
+        assert_eq!(diff.expected, Type::Boolean);
+        assert_eq!(diff.found, Type::Integer);
+    }
+
+    #[test]
+    fn test_display_record() {
+        let ty = Type::Record(BTreeMap::from([
+            (String::from("age"), Type::Integer),
+            (String::from("name"), Type::Boolean),
+        ]));
+        assert_eq!(ty.to_string(), "{age: int, name: bool}");
+    }
+
+    #[test]
+    fn test_diff_types_descends_into_a_mismatched_record_field() {
+        let expected = Type::Record(BTreeMap::from([
+            (String::from("id"), Type::Integer),
+            (String::from("ok"), Type::Boolean),
+        ]));
+        let found = Type::Record(BTreeMap::from([
+            (String::from("id"), Type::Integer),
+            (String::from("ok"), Type::Integer),
+        ]));
+        let diff = diff_types(&expected, &found);
+        assert_eq!(diff.path, vec![String::from("field `ok`")]);
+        assert_eq!(diff.expected, Type::Boolean);
+        assert_eq!(diff.found, Type::Integer);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_type_round_trips_through_json() {
+        let ty = Type::Function {
+            parameter_type: Box::new(Type::Integer),
+            return_type: Box::new(Type::Boolean),
+            effects: Vec::new(),
+        };
+        let json = serde_json::to_string(&ty).unwrap();
+        let round_tripped: Type = serde_json::from_str(&json).unwrap();
+        assert_eq!(round_tripped, ty);
+    }
 }
@@ -0,0 +1,833 @@
+//! `tc`: a small command-line front end that runs the checker's pipeline
+//! against one or more `.sml`-like source files, printing each file's
+//! inferred top-level type or its diagnostics rendered against the source,
+//! and exiting nonzero if any file failed to check.
+//!
+//! `--emit=STAGE` dumps an intermediate representation instead of the
+//! final type, and `--format=json` switches that dump from human-readable
+//! text to JSON (diagnostics follow `--error-format` instead), which is handy for
+//! feeding the pipeline's output into another tool or teaching how a
+//! source term moves through it stage by stage.
+//!
+//! `--watch` keeps re-checking the given files or directories as they
+//! change instead of exiting after one pass, clearing the screen and
+//! re-rendering diagnostics each time — handy for keeping a checker
+//! running alongside an editor.
+//!
+//! `tc repl` instead reads expressions from standard input one at a time,
+//! printing `val it : <inferred type>` for each, the way an interpreter's
+//! toplevel does. `val <name> = <expr>` and `fun <name> <params> = <body>`
+//! extend the session's environment instead, so a later input can refer
+//! back to `<name>`; `:env` lists everything bound so far, and `:reset`
+//! clears it back to the prelude.
+//!
+//! The process exit code distinguishes [`Outcome::Clean`] (0),
+//! [`Outcome::WarningsOnly`] (1), [`Outcome::TypeErrors`] (2), and
+//! [`Outcome::InternalError`] (3, for a problem with the tool itself —
+//! today, only a file that couldn't be read) rather than collapsing every
+//! failure to a single nonzero code, so a CI script or autograder can
+//! tell "your program has a type error" apart from "the checker itself
+//! choked on this input" without scraping output. `--quiet` drops
+//! everything but error-level diagnostics, and `--error-format=json`
+//! prints each diagnostic as a single-line JSON object instead of the
+//! human-rendered form, for either of those consumers to parse.
+//!
+//! `--show-effects` renders a function type's inferred exception effects
+//! as part of its final type, e.g. `int -[Div]-> int` instead of plain
+//! `int => int`, for the final type (`--emit=type`, the default) and the
+//! REPL's `val it : <type>`.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt::Debug;
+use std::fmt::Write as _;
+use std::io::{self, BufRead, IsTerminal, Read, Write};
+use std::path::Path;
+use std::process::ExitCode;
+use type_checker::annotator::{annotate, annotate_with_env, default_numeric_types, type_variables, CheckerOptions, Type};
+use type_checker::constraint::{collect_constraints, collect_constraints_with_env, instantiate, TypeEnv, TypeScheme};
+use type_checker::desugar::desugar;
+use type_checker::diagnostics::render;
+use type_checker::formatter::format as format_term;
+use type_checker::lint::{self, Level, WarningsConfig};
+use type_checker::parser::parse;
+use type_checker::tokenizer::{tokenize_with_spans, Token};
+use type_checker::unifier::unify;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("repl") {
+        return run_repl(args.iter().any(|arg| arg == "--show-effects"));
+    }
+    let emit = parse_emit_flag(&args);
+    let format = parse_format_flag(&args);
+    let options = CheckOptions {
+        quiet: args.iter().any(|arg| arg == "--quiet"),
+        error_format: parse_error_format_flag(&args),
+        show_effects: args.iter().any(|arg| arg == "--show-effects"),
+    };
+    let watch = args.iter().any(|arg| arg == "--watch");
+    let mut paths: Vec<&String> = args.iter().filter(|arg| !arg.starts_with("--")).collect();
+    let stdin_path = STDIN_PATH.to_string();
+    if paths.is_empty() && !io::stdin().is_terminal() {
+        paths.push(&stdin_path);
+    }
+    if paths.is_empty() {
+        eprintln!(
+            "usage: tc [--watch] [--quiet] [--error-format=human|json] [--show-effects] \
+             [--emit=tokens|ast|typed-ast|constraints|subst|type] [--format=human|json] <file>..."
+        );
+        eprintln!("       tc -");
+        eprintln!("       tc repl");
+        return ExitCode::FAILURE;
+    }
+    if watch {
+        return run_watch(&paths, emit, format, &options);
+    }
+    let mut expanded = Vec::new();
+    let mut any_directory = false;
+    for path in &paths {
+        if Path::new(path.as_str()).is_dir() {
+            any_directory = true;
+            expanded.extend(discover_source_files(Path::new(path.as_str())));
+        } else {
+            expanded.push((*path).clone());
+        }
+    }
+    let outcome = if any_directory {
+        check_directory(expanded, emit, format, &options)
+    } else {
+        // Every path is checked regardless of earlier failures: `fold`
+        // consumes the whole iterator, so a failing file never stops the
+        // ones after it from being checked.
+        expanded
+            .iter()
+            .map(|path| check_file_and_print(path, emit, format, &options))
+            .fold(Outcome::Clean, Outcome::max)
+    };
+    ExitCode::from(outcome.exit_code())
+}
+
+/// The overall result of checking one or more files, ordered from best to
+/// worst so aggregating many files' outcomes is just [`Outcome::max`].
+/// Maps to a distinct process exit code so a CI script or autograder can
+/// tell these cases apart without parsing output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Outcome {
+    /// Every file checked with no lint findings above [`Level::Allow`].
+    Clean,
+    /// Every file checked, but at least one had a lint finding — still
+    /// non-fatal, per [`type_checker::lint`]'s own contract.
+    WarningsOnly,
+    /// At least one file failed to check: a lexical, parse, or type error.
+    TypeErrors,
+    /// At least one file couldn't even be read, which is a problem with
+    /// the invocation rather than with anything the checker found.
+    InternalError,
+}
+
+impl Outcome {
+    fn exit_code(self) -> u8 {
+        match self {
+            Outcome::Clean => 0,
+            Outcome::WarningsOnly => 1,
+            Outcome::TypeErrors => 2,
+            Outcome::InternalError => 3,
+        }
+    }
+}
+
+/// `--quiet`, `--error-format`, and `--show-effects` together, bundled the
+/// way [`type_checker::diagnostics::RenderOptions`] bundles `--color`,
+/// since all three are read together at every call site that reports a
+/// diagnostic or prints a final type.
+struct CheckOptions {
+    quiet: bool,
+    error_format: ErrorFormat,
+    /// Whether the final type (`--emit=type`, the default) is rendered
+    /// with [`Type::display_with_options`] to show its inferred exception
+    /// effects, e.g. `int -[Div]-> int` instead of `int => int`.
+    show_effects: bool,
+}
+
+/// Whether a diagnostic is printed as a `path: severity: message` line or
+/// as a single-line JSON object, for a caller that wants to parse it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ErrorFormat {
+    Human,
+    Json,
+}
+
+/// Parses the value of the last `--prefix<value>` argument in `args` that
+/// `parse` accepts, defaulting to `default` when the flag is absent or
+/// every occurrence's value is rejected. A repeated flag's later
+/// occurrence wins over an earlier one, matching how most CLI parsers
+/// treat one; `.next_back()` reads that occurrence directly off the back
+/// of the (double-ended) filter iterator instead of `.last()` walking the
+/// whole thing just to throw away everything but the final item.
+fn parse_last_flag<T>(args: &[String], prefix: &str, parse: impl Fn(&str) -> Option<T>, default: T) -> T {
+    args.iter().filter_map(|arg| arg.strip_prefix(prefix)).filter_map(parse).next_back().unwrap_or(default)
+}
+
+/// Parses an `--error-format=human|json` flag out of `args`, defaulting
+/// to [`ErrorFormat::Human`] when it's absent or its value doesn't parse.
+fn parse_error_format_flag(args: &[String]) -> ErrorFormat {
+    parse_last_flag(
+        args,
+        "--error-format=",
+        |value| match value {
+            "human" => Some(ErrorFormat::Human),
+            "json" => Some(ErrorFormat::Json),
+            _ => None,
+        },
+        ErrorFormat::Human,
+    )
+}
+
+/// The extension a directory-discovered file must have to be treated as a
+/// source file worth checking, matching the `.sml`-like files this crate's
+/// pipeline actually understands.
+const SOURCE_EXTENSION: &str = "sml";
+
+/// Recursively lists every `.sml` file under `root`, sorted, so a
+/// directory argument expands to a deterministic, stably-ordered work
+/// list regardless of the order [`std::fs::read_dir`] happens to yield.
+/// A dotdir (`.git`, `.direnv`, an editor's `.vscode`, ...) is skipped
+/// entirely rather than walked into, the way most source tools treat a
+/// leading `.` as "not part of the project"; a dotfile is skipped for the
+/// same reason. A file without [`SOURCE_EXTENSION`] is skipped too, so
+/// pointing the checker at a project root doesn't also try to parse its
+/// `README.md` or `Cargo.toml` as source.
+fn discover_source_files(root: &Path) -> Vec<String> {
+    fn is_dotfile(path: &Path) -> bool {
+        path.file_name().and_then(|name| name.to_str()).is_some_and(|name| name.starts_with('.'))
+    }
+    fn walk(dir: &Path, files: &mut Vec<String>) {
+        let Ok(entries) = std::fs::read_dir(dir) else { return };
+        for entry in entries.filter_map(Result::ok) {
+            let path = entry.path();
+            if is_dotfile(&path) {
+                continue;
+            }
+            if path.is_dir() {
+                walk(&path, files);
+            } else if path.is_file() && path.extension().is_some_and(|extension| extension == SOURCE_EXTENSION) {
+                files.push(path.to_string_lossy().into_owned());
+            }
+        }
+    }
+    let mut files = Vec::new();
+    walk(root, &mut files);
+    files.sort();
+    files
+}
+
+/// Checks `paths` on a rayon thread pool rather than one at a time, since
+/// nothing in the pipeline shares mutable state across files — each
+/// `check_file` call builds its own [`type_checker::annotator::TypeVarGen`]
+/// and [`type_checker::unifier::Substitution`] from scratch, so there is
+/// nothing to synchronize between threads. Every file's diagnostics are
+/// still printed together and in `paths`'s order, never interleaved with
+/// another file's, even though the checks themselves may finish out of
+/// order: `rayon`'s `collect` preserves the source order regardless of
+/// which thread produced which result.
+fn check_directory(paths: Vec<String>, stage: EmitStage, format: OutputFormat, options: &CheckOptions) -> Outcome {
+    use rayon::prelude::*;
+    let reports: Vec<(Outcome, Report)> =
+        paths.into_par_iter().map(|path| check_file(&path, stage, format, options)).collect();
+    let mut worst = Outcome::Clean;
+    for (outcome, report) in reports {
+        report.flush();
+        worst = worst.max(outcome);
+    }
+    worst
+}
+
+/// Re-checks `roots` (files or directories) every time one of the files
+/// they name changes, clearing the screen and re-rendering diagnostics
+/// each time, until the process is killed.
+///
+/// Each round re-runs the full pipeline on every watched file from scratch
+/// rather than reusing [`type_checker::unifier::Substitution::checkpoint`]
+/// and [`type_checker::unifier::Substitution::undo`]: those roll a single
+/// solve back to an earlier point in the *same* run, which fits a REPL
+/// trying one more line against bindings it already solved, but a changed
+/// file is new source text top to bottom, so there is nothing of the old
+/// solve worth keeping.
+fn run_watch(roots: &[&String], emit: EmitStage, format: OutputFormat, options: &CheckOptions) -> ExitCode {
+    let mut last_snapshot = Vec::new();
+    loop {
+        let paths = watched_paths(roots);
+        let snapshot: Vec<(String, Option<std::time::SystemTime>)> =
+            paths.iter().map(|path| (path.clone(), std::fs::metadata(path).and_then(|meta| meta.modified()).ok())).collect();
+        if snapshot != last_snapshot {
+            print!("\x1B[2J\x1B[H");
+            let outcome = paths
+                .iter()
+                .map(|path| check_file_and_print(path, emit, format, options))
+                .fold(Outcome::Clean, Outcome::max);
+            println!();
+            println!(
+                "{}",
+                match outcome {
+                    Outcome::Clean => "no problems found",
+                    Outcome::WarningsOnly => "no problems found (warnings only)",
+                    Outcome::TypeErrors => "type errors found",
+                    Outcome::InternalError => "internal error",
+                }
+            );
+            println!("watching for changes... (ctrl-c to stop)");
+            io::stdout().flush().ok();
+            last_snapshot = snapshot;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(250));
+    }
+}
+
+/// Expands `roots` into the flat list of files to watch, re-walking any
+/// directory among them fresh on every call so a file added to it
+/// mid-watch is picked up on the next poll.
+fn watched_paths(roots: &[&String]) -> Vec<String> {
+    let mut paths = Vec::new();
+    for root in roots {
+        if Path::new(root.as_str()).is_dir() {
+            paths.extend(discover_source_files(Path::new(root.as_str())));
+        } else {
+            paths.push((*root).clone());
+        }
+    }
+    paths
+}
+
+/// A REPL session's accumulated `val`/`fun` bindings, kept as a
+/// [`TypeEnv`] like any other prelude this crate's pipeline can check
+/// against, plus the order they were declared in (for `:env`) and a
+/// counter for minting type variables that instantiate a binding's scheme
+/// without colliding with the ones a later input's own [`annotate_with_env`]
+/// call mints for itself — which always starts back at 1, since each
+/// input is annotated independently.
+struct ReplSession {
+    env: TypeEnv,
+    declared: Vec<String>,
+    next_var: u32,
+}
+
+impl ReplSession {
+    fn new() -> ReplSession {
+        ReplSession {
+            env: TypeEnv::default_prelude(),
+            declared: Vec::new(),
+            next_var: FIRST_SESSION_VAR,
+        }
+    }
+
+    fn fresh_var(&mut self) -> Type {
+        let id = self.next_var;
+        self.next_var += 1;
+        Type::Placeholder(id)
+    }
+}
+
+/// Where a REPL session's own type variables start counting from, well
+/// past anything a single input's `annotate_with_env` call (which always
+/// starts its own [`type_checker::annotator::TypeVarGen`] back at 1) could
+/// mint for itself, so a variable instantiated from an earlier binding's
+/// scheme is never mistaken for one of the current input's own.
+const FIRST_SESSION_VAR: u32 = 100_000;
+
+/// Reads expressions from standard input one at a time — accumulating
+/// further lines while `buffer` has an unmatched `let` still waiting on
+/// its `end` — and either evaluates them against the session, or extends
+/// it, until standard input is closed.
+fn run_repl(show_effects: bool) -> ExitCode {
+    let stdin = io::stdin();
+    let mut buffer = String::new();
+    let mut session = ReplSession::new();
+    loop {
+        print!("{}", if buffer.is_empty() { "- " } else { "= " });
+        io::stdout().flush().ok();
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).unwrap_or(0) == 0 {
+            break;
+        }
+        buffer.push_str(&line);
+        if needs_more_input(&buffer) {
+            continue;
+        }
+        let source = std::mem::take(&mut buffer);
+        if !source.trim().is_empty() {
+            run_repl_input(&source, &mut session, show_effects);
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+/// Whether `source` still has an unclosed `let ... end` block and the REPL
+/// should keep collecting lines rather than trying to parse it yet. A
+/// lexical error is left for the input to report immediately instead of
+/// waiting for more input that would never resolve it.
+fn needs_more_input(source: &str) -> bool {
+    match tokenize_with_spans(source) {
+        Ok(tokens) => {
+            let depth = tokens.iter().fold(0i32, |depth, spanned| match spanned.token {
+                Token::KeywordLet => depth + 1,
+                Token::KeywordEnd => depth - 1,
+                _ => depth,
+            });
+            depth > 0
+        }
+        Err(_) => false,
+    }
+}
+
+/// One line of REPL input, classified before it's run.
+enum ReplInput<'a> {
+    /// `:env`, listing every name the session has bound so far.
+    Env,
+    /// `:reset`, dropping every session binding back to the prelude.
+    Reset,
+    /// `val <name> = <expr>` (or `fun <name> <param>... = <body>`,
+    /// desugared to this form), binding `<name>` to `<expr>`'s
+    /// generalized type in the session once it checks.
+    Declaration { name: String, expr_source: String },
+    /// A plain expression, checked against the session but not bound to
+    /// anything.
+    Expression(&'a str),
+}
+
+/// Classifies one REPL input, recognizing `:env`, `:reset`, `val`, and
+/// `fun` by inspecting the source text directly rather than through the
+/// grammar, since this crate's `let val ... in ... end` is the only place
+/// `val` otherwise appears, and there is no top-level declaration form
+/// (or `fun` keyword) at all — both are conveniences specific to the REPL.
+fn classify_repl_input(source: &str) -> ReplInput<'_> {
+    let trimmed = source.trim();
+    if trimmed == ":env" {
+        return ReplInput::Env;
+    }
+    if trimmed == ":reset" {
+        return ReplInput::Reset;
+    }
+    if let Some(rest) = trimmed.strip_prefix("fun ") {
+        if let Some((head, body)) = rest.split_once('=') {
+            let mut names = head.split_whitespace();
+            if let Some(name) = names.next() {
+                let expr_source =
+                    names.rev().fold(body.trim().to_string(), |body, param| format!("fn {} => {}", param, body));
+                return ReplInput::Declaration { name: name.to_string(), expr_source };
+            }
+        }
+    }
+    if let Some(rest) = trimmed.strip_prefix("val ") {
+        if let Some((name, expr)) = rest.split_once('=') {
+            return ReplInput::Declaration { name: name.trim().to_string(), expr_source: expr.trim().to_string() };
+        }
+    }
+    ReplInput::Expression(source)
+}
+
+/// Classifies and runs one REPL input against `session`, printing its
+/// result and, for a `val`/`fun` declaration that checks, extending
+/// `session` with the new binding. `show_effects` controls whether a
+/// printed function type shows its inferred exception effects, the same
+/// as `--show-effects` does for a checked file's final type.
+fn run_repl_input(source: &str, session: &mut ReplSession, show_effects: bool) {
+    let options = CheckerOptions { show_effects, ..Default::default() };
+    match classify_repl_input(source) {
+        ReplInput::Env => {
+            for name in &session.declared {
+                let scheme = session.env.get_scheme(name).expect("declared names are always bound to a scheme");
+                println!("val {} : {}", name, scheme);
+            }
+        }
+        ReplInput::Reset => {
+            *session = ReplSession::new();
+            println!("environment reset");
+        }
+        ReplInput::Declaration { name, expr_source } => {
+            if let Some(ty) = check_expression(&expr_source, session) {
+                let scheme = generalize(&ty);
+                println!("val {} : {}", name, scheme);
+                session.env.insert_scheme(name.clone(), scheme);
+                session.declared.retain(|declared| declared != &name);
+                session.declared.push(name);
+            }
+        }
+        ReplInput::Expression(expr_source) => {
+            if let Some(ty) = check_expression(expr_source, session) {
+                println!("val it : {}", ty.display_with_options(&options));
+            }
+        }
+    }
+}
+
+/// Generalizes `ty` into a [`TypeScheme`] that binds every type variable
+/// still free in it, so a later [`instantiate`] of the scheme gets its own
+/// fresh copies rather than reusing (and so accidentally unifying with)
+/// whichever type this particular definition happened to need.
+fn generalize(ty: &Type) -> TypeScheme {
+    let mut bound_vars: Vec<u32> = type_variables(ty).into_iter().collect();
+    bound_vars.sort_unstable();
+    TypeScheme {
+        bound_vars: bound_vars.into_iter().map(|var| (var, None)).collect(),
+        ty: ty.clone(),
+    }
+}
+
+/// Runs the full pipeline against `source`, resolving identifiers bound
+/// earlier in `session` the same way a builtin operator is resolved:
+/// [`annotate_with_env`] gives the reference its own fresh placeholder,
+/// and a constraint equating that placeholder with a fresh instantiation
+/// of the binding's scheme is what actually pins its type down, so two
+/// separate REPL inputs referencing the same polymorphic binding each get
+/// their own instantiation. Returns the inferred type on success, having
+/// already printed diagnostics (rendered against `source` for a
+/// [`TypeError`]) on failure.
+///
+/// [`TypeError`]: type_checker::constraint::TypeError
+fn check_expression(source: &str, session: &mut ReplSession) -> Option<Type> {
+    let tokens = match tokenize_with_spans(source) {
+        Ok(tokens) => tokens,
+        Err(error) => {
+            eprintln!("{}", error);
+            return None;
+        }
+    };
+    let term = match parse(&tokens) {
+        Ok(term) => term,
+        Err(errors) => {
+            for error in errors {
+                eprintln!("{}", error);
+            }
+            return None;
+        }
+    };
+    let term = desugar(&term);
+
+    let mut annotate_env = BTreeMap::new();
+    let mut constraint_env = TypeEnv::default_prelude();
+    for name in session.declared.clone() {
+        let scheme = session.env.get_scheme(&name).expect("declared names are always bound to a scheme").clone();
+        annotate_env.insert(name.clone(), session.fresh_var());
+        let arguments: Vec<Type> = scheme.bound_vars.iter().map(|_| session.fresh_var()).collect();
+        let instantiated =
+            instantiate(&scheme, &arguments).expect("arguments were built to match scheme.bound_vars one for one");
+        constraint_env.insert(name, instantiated);
+    }
+
+    let typed_term = match annotate_with_env(&term, &annotate_env) {
+        Ok(typed_term) => typed_term,
+        Err(message) => {
+            eprintln!("{}", message);
+            return None;
+        }
+    };
+    let constraints = match collect_constraints_with_env(&typed_term, &constraint_env) {
+        Ok(constraints) => constraints,
+        Err(errors) => {
+            for error in &errors {
+                print!("{}", render(source, error, &[]));
+            }
+            return None;
+        }
+    };
+    match unify(&constraints) {
+        Ok(substitution) => Some(default_numeric_types(&substitution.apply(&typed_term.ty))),
+        Err(errors) => {
+            for error in &errors {
+                print!("{}", render(source, error, &constraints));
+            }
+            None
+        }
+    }
+}
+
+/// Which stage's intermediate representation `--emit` should dump instead
+/// of the final inferred type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EmitStage {
+    Tokens,
+    Ast,
+    TypedAst,
+    Constraints,
+    Subst,
+    Type,
+}
+
+impl EmitStage {
+    fn parse(name: &str) -> Option<EmitStage> {
+        match name {
+            "tokens" => Some(EmitStage::Tokens),
+            "ast" => Some(EmitStage::Ast),
+            "typed-ast" => Some(EmitStage::TypedAst),
+            "constraints" => Some(EmitStage::Constraints),
+            "subst" => Some(EmitStage::Subst),
+            "type" => Some(EmitStage::Type),
+            _ => None,
+        }
+    }
+}
+
+/// Whether `--emit`'s dump is printed as plain text or as JSON; a
+/// diagnostic's format is chosen separately, by [`ErrorFormat`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Human,
+    Json,
+}
+
+/// Parses an `--emit=STAGE` flag out of `args`, defaulting to
+/// [`EmitStage::Type`] (today's behavior — printing just the inferred type)
+/// when it's absent or its value doesn't parse, since a mistyped flag
+/// shouldn't stop the checker from running.
+fn parse_emit_flag(args: &[String]) -> EmitStage {
+    parse_last_flag(args, "--emit=", EmitStage::parse, EmitStage::Type)
+}
+
+/// Parses a `--format=human|json` flag out of `args`, defaulting to
+/// [`OutputFormat::Human`] when it's absent or its value doesn't parse.
+fn parse_format_flag(args: &[String]) -> OutputFormat {
+    parse_last_flag(
+        args,
+        "--format=",
+        |value| match value {
+            "human" => Some(OutputFormat::Human),
+            "json" => Some(OutputFormat::Json),
+            _ => None,
+        },
+        OutputFormat::Human,
+    )
+}
+
+/// The stdout and stderr text a single [`check_file`] call produced, kept
+/// apart (rather than printed as they're produced) so a caller checking
+/// many files at once — [`check_directory`], on a rayon thread pool — can
+/// still flush each file's output as one unbroken chunk on the right
+/// stream, in the file's own turn, instead of letting concurrent checks'
+/// lines interleave.
+#[derive(Default)]
+struct Report {
+    stdout: String,
+    stderr: String,
+}
+
+impl Report {
+    fn flush(self) {
+        print!("{}", self.stdout);
+        eprint!("{}", self.stderr);
+    }
+}
+
+/// Appends `path` and either `value`'s [`std::fmt::Display`] rendering or
+/// a single-key JSON object holding it to `report`'s stdout, depending on
+/// `format`.
+fn emit(report: &mut Report, path: &str, key: &str, value: impl std::fmt::Display, format: OutputFormat) {
+    match format {
+        OutputFormat::Human => writeln!(report.stdout, "{}: {}", path, value).unwrap(),
+        OutputFormat::Json => {
+            writeln!(report.stdout, "{}", serde_json::json!({ "path": path, key: value.to_string() })).unwrap()
+        }
+    }
+}
+
+/// Like [`emit`], but for a slice of values that don't have a single
+/// combined [`std::fmt::Display`] rendering — each is appended on its own
+/// line in human form, or collected into a JSON array under `key`.
+fn emit_all(report: &mut Report, path: &str, key: &str, values: &[impl std::fmt::Display], format: OutputFormat) {
+    match format {
+        OutputFormat::Human => {
+            for value in values {
+                writeln!(report.stdout, "{}: {}", path, value).unwrap();
+            }
+        }
+        OutputFormat::Json => {
+            let rendered: Vec<String> = values.iter().map(ToString::to_string).collect();
+            writeln!(report.stdout, "{}", serde_json::json!({ "path": path, key: rendered })).unwrap();
+        }
+    }
+}
+
+/// Like [`emit`], but for a value that only implements [`Debug`] (tokens
+/// and substitutions don't have a rustc-style [`std::fmt::Display`]
+/// rendering of their own), rendered with `{:?}` in both output formats.
+fn emit_debug(report: &mut Report, path: &str, key: &str, value: impl Debug, format: OutputFormat) {
+    emit(report, path, key, format_args!("{:?}", value), format)
+}
+
+/// Appends one diagnostic to `report`, as a `path: severity: message` line
+/// or a single-line JSON object depending on `options.error_format` — the
+/// JSON form always goes to stdout, so a machine consumer gets every
+/// diagnostic on one stream instead of split across stdout and stderr.
+/// Under `--quiet`, a `"warning"` is dropped entirely, since quiet mode is
+/// for scripts that only want to hear about failures.
+fn report_diagnostic(report: &mut Report, path: &str, severity: &str, message: impl std::fmt::Display, options: &CheckOptions) {
+    if severity == "warning" && options.quiet {
+        return;
+    }
+    match options.error_format {
+        ErrorFormat::Human => writeln!(report.stderr, "{}: {}: {}", path, severity, message).unwrap(),
+        ErrorFormat::Json => writeln!(
+            report.stdout,
+            "{}",
+            serde_json::json!({ "path": path, "severity": severity, "message": message.to_string() })
+        )
+        .unwrap(),
+    }
+}
+
+/// Runs [`check_file`] and immediately flushes its report, for the common
+/// case of checking one file (or a plain list of files) sequentially,
+/// where there's no concurrent output to interleave with.
+fn check_file_and_print(path: &str, stage: EmitStage, format: OutputFormat, options: &CheckOptions) -> Outcome {
+    let (outcome, report) = check_file(path, stage, format, options);
+    report.flush();
+    outcome
+}
+
+/// Runs the full pipeline against the source at `path`, stopping to
+/// record `emit`'s stage if it isn't [`EmitStage::Type`], or otherwise
+/// recording the inferred type on success, or diagnostics rendered
+/// against the source (with a snippet and caret underline, for a
+/// [`TypeError`]) on failure. Returns the file's [`Outcome`], along with
+/// the [`Report`] the caller should flush once it's ready to.
+///
+/// [`TypeError`]: type_checker::constraint::TypeError
+///
+/// Reads `path` from disk, or — for [`STDIN_PATH`] — reads the source
+/// from standard input instead, so a shell pipeline or editor integration
+/// that only has a buffer in memory doesn't need to write it to a
+/// temporary file first. Either failing is an [`Outcome::InternalError`]:
+/// a missing or unreadable file is a problem with the invocation, not
+/// something the checker found wrong with a program.
+fn check_file(path: &str, stage: EmitStage, format: OutputFormat, options: &CheckOptions) -> (Outcome, Report) {
+    let mut report = Report::default();
+    let source = if path == STDIN_PATH {
+        let mut source = String::new();
+        match io::stdin().lock().read_to_string(&mut source) {
+            Ok(_) => source,
+            Err(error) => {
+                report_diagnostic(&mut report, path, "error", error, options);
+                return (Outcome::InternalError, report);
+            }
+        }
+    } else {
+        match std::fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(error) => {
+                report_diagnostic(&mut report, path, "error", error, options);
+                return (Outcome::InternalError, report);
+            }
+        }
+    };
+    let outcome = check_source(&mut report, path, &source, stage, format, options);
+    (outcome, report)
+}
+
+/// The path name a caller passes to check `-` (standalone) or run with no
+/// file arguments at all against piped input, in place of a real path.
+const STDIN_PATH: &str = "-";
+
+fn check_source(
+    report: &mut Report,
+    path: &str,
+    source: &str,
+    stage: EmitStage,
+    format: OutputFormat,
+    options: &CheckOptions,
+) -> Outcome {
+    let tokens = match tokenize_with_spans(source) {
+        Ok(tokens) => tokens,
+        Err(error) => {
+            report_diagnostic(report, path, "error", error, options);
+            return Outcome::TypeErrors;
+        }
+    };
+    if stage == EmitStage::Tokens {
+        if !options.quiet {
+            emit_debug(report, path, "tokens", &tokens, format);
+        }
+        return Outcome::Clean;
+    }
+    let term = match parse(&tokens) {
+        Ok(term) => term,
+        Err(errors) => {
+            for error in errors {
+                report_diagnostic(report, path, "error", error, options);
+            }
+            return Outcome::TypeErrors;
+        }
+    };
+    let mut outcome = Outcome::Clean;
+    for finding in lint::check(&term, &WarningsConfig::new()) {
+        let severity = match finding.level {
+            Level::Allow => continue,
+            Level::Warn => "warning",
+            Level::Deny => "error",
+        };
+        report_diagnostic(report, path, severity, &finding.message, options);
+        outcome = outcome.max(Outcome::WarningsOnly);
+    }
+    if stage == EmitStage::Ast {
+        if !options.quiet {
+            emit(report, path, "ast", format_term(&term), format);
+        }
+        return outcome;
+    }
+    let term = desugar(&term);
+    let typed_term = match annotate(&term) {
+        Ok(typed_term) => typed_term,
+        Err(message) => {
+            report_diagnostic(report, path, "error", message, options);
+            return Outcome::TypeErrors;
+        }
+    };
+    if stage == EmitStage::TypedAst {
+        if !options.quiet {
+            emit(report, path, "typed_ast", &typed_term, format);
+        }
+        return outcome;
+    }
+    let constraints = match collect_constraints(&typed_term) {
+        Ok(constraints) => constraints,
+        Err(errors) => {
+            for error in &errors {
+                match options.error_format {
+                    ErrorFormat::Human => write!(report.stdout, "{}", render(source, error, &[])).unwrap(),
+                    ErrorFormat::Json => report_diagnostic(report, path, "error", error, options),
+                }
+            }
+            return Outcome::TypeErrors;
+        }
+    };
+    if stage == EmitStage::Constraints {
+        if !options.quiet {
+            emit_all(report, path, "constraints", &constraints, format);
+        }
+        return outcome;
+    }
+    match unify(&constraints) {
+        Ok(substitution) => {
+            if stage == EmitStage::Subst {
+                if !options.quiet {
+                    emit_debug(report, path, "subst", &substitution, format);
+                }
+                return outcome;
+            }
+            if !options.quiet {
+                let ty = default_numeric_types(&substitution.apply(&typed_term.ty));
+                let checker_options = CheckerOptions { show_effects: options.show_effects, ..Default::default() };
+                emit(report, path, "type", ty.display_with_options(&checker_options), format);
+            }
+            outcome
+        }
+        Err(errors) => {
+            for error in &errors {
+                match options.error_format {
+                    ErrorFormat::Human => write!(report.stdout, "{}", render(source, error, &constraints)).unwrap(),
+                    ErrorFormat::Json => report_diagnostic(report, path, "error", error, options),
+                }
+            }
+            Outcome::TypeErrors
+        }
+    }
+}
@@ -1,163 +1,1685 @@
-use crate::annotator::{Type, TypedTerm, TypedTermKind};
-use std::collections::HashMap;
+use crate::annotator::{RecordCheckingMode, Type, TypedTerm, TypedTermKind};
+use crate::tokenizer::Span;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Display, Error, Formatter};
+use std::rc::Rc;
 
-#[derive(Debug, Eq, Hash, PartialEq)]
-pub struct Constraint {
-    type1: Type,
-    type2: Type,
+/// Why a [`Constraint`] was generated, i.e. which syntactic construct in
+/// the source is responsible for requiring the two types to relate the way
+/// the constraint says. Kept separate from `Type` and `Constraint`
+/// themselves so a solver can still compare or hash constraints
+/// structurally while an error reporter uses the reason to explain a
+/// failed unification in terms the programmer wrote, e.g. "the branches of
+/// this `if` must have the same type" instead of "int != bool".
+#[derive(Debug, Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ConstraintReason {
+    /// A boolean literal's type must be [`Type::Boolean`].
+    BooleanLiteral,
+    /// An integer literal's type must be a [`Type::Numeric`] variable.
+    IntegerLiteral,
+    /// An `if` condition must be [`Type::Boolean`].
+    IfConditionBool,
+    /// Both branches of an `if`, and the `if` expression itself, must all
+    /// agree on one type.
+    BranchesMustMatch,
+    /// A function's parameter type is unified with the type of the
+    /// argument it was applied to.
+    ApplicationArgument,
+    /// A `fn`'s own type is the function type from its parameter to its
+    /// body.
+    FunctionSignature,
+    /// An identifier resolved to a builtin (`+`, `-`, `*`, `/`, `=`) must
+    /// match that builtin's fixed signature.
+    BuiltinSignature,
+    /// A `let`'s declared name and its declaration value must have the
+    /// same type.
+    LetBinding,
+    /// A `let ... in e end` expression has the same type as `e`.
+    LetResult,
+    /// A use of a `let`-bound identifier is checked against a fresh
+    /// instantiation of its generalized [`TypeScheme`], rather than the one
+    /// concrete type its binder was given.
+    LetInstantiation,
+}
+
+impl ConstraintReason {
+    /// A short, human-readable phrase describing the syntactic construct
+    /// that produced this reason, for use in explanations like the one
+    /// [`crate::unifier::explain`] renders around a failed unification
+    /// (e.g. "is used as the condition of `if`, so it must be `bool`").
+    pub fn description(&self) -> &'static str {
+        match self {
+            ConstraintReason::BooleanLiteral => "is a boolean literal",
+            ConstraintReason::IntegerLiteral => "is an integer literal",
+            ConstraintReason::IfConditionBool => "is used as the condition of `if`",
+            ConstraintReason::BranchesMustMatch => "is used as a branch of `if`",
+            ConstraintReason::ApplicationArgument => "is passed as a function argument",
+            ConstraintReason::FunctionSignature => "is the body of a function definition",
+            ConstraintReason::BuiltinSignature => "is used with a builtin operator",
+            ConstraintReason::LetBinding => "is bound by `let`",
+            ConstraintReason::LetResult => "is the result of a `let ... in ... end`",
+            ConstraintReason::LetInstantiation => "is used as a `let`-bound identifier",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, Hash, PartialEq)]
+pub enum Constraint {
+    /// The two types must be exactly the same.
+    Equal {
+        // `Rc<Type>` rather than an owned `Type`: constraint generation
+        // hands over the same `Type` a `TypedTerm` already carries (itself
+        // an `Rc`), so building a constraint is a refcount bump instead of
+        // a clone of the whole type tree.
+        type1: Rc<Type>,
+        type2: Rc<Type>,
+        reason: ConstraintReason,
+        /// Source span of the expression `type1` was inferred for.
+        lhs_span: Span,
+        /// Source span of the expression `type2` was inferred for.
+        rhs_span: Span,
+    },
+    /// `sub` must be a subtype of `sup` under [`is_subtype`]'s lattice.
+    Subtype {
+        sub: Rc<Type>,
+        sup: Rc<Type>,
+        reason: ConstraintReason,
+        /// Source span of the expression `sub` was inferred for.
+        lhs_span: Span,
+        /// Source span of the expression `sup` was inferred for.
+        rhs_span: Span,
+    },
+    /// `ty` must be *some* instantiation of `scheme`, without committing to
+    /// which type arguments during constraint generation. This is what
+    /// lets let-generalization be expressed declaratively and solved in
+    /// dependency order — the scheme's binder is generalized once, and
+    /// each use site gets its own [`Constraint::Instance`] to be resolved
+    /// (with its own, possibly different, choice of type arguments) once
+    /// the solver gets to it, rather than forcing every use to agree on
+    /// one instantiation picked up front.
+    Instance {
+        scheme: TypeScheme,
+        ty: Rc<Type>,
+        reason: ConstraintReason,
+        /// Source span of the identifier `ty` was inferred for.
+        span: Span,
+    },
+}
+
+impl Constraint {
+    /// Builds an [`Constraint::Equal`] constraint, so a caller assembling
+    /// its own constraints (e.g. an external solver's test suite) doesn't
+    /// need to name the variant's field names to construct one.
+    pub fn equal(
+        type1: Rc<Type>,
+        type2: Rc<Type>,
+        reason: ConstraintReason,
+        lhs_span: Span,
+        rhs_span: Span,
+    ) -> Constraint {
+        Constraint::Equal {
+            type1,
+            type2,
+            reason,
+            lhs_span,
+            rhs_span,
+        }
+    }
+
+    /// Builds a [`Constraint::Subtype`] constraint.
+    pub fn subtype(
+        sub: Rc<Type>,
+        sup: Rc<Type>,
+        reason: ConstraintReason,
+        lhs_span: Span,
+        rhs_span: Span,
+    ) -> Constraint {
+        Constraint::Subtype {
+            sub,
+            sup,
+            reason,
+            lhs_span,
+            rhs_span,
+        }
+    }
+
+    /// Builds a [`Constraint::Instance`] constraint.
+    pub fn instance(scheme: TypeScheme, ty: Rc<Type>, reason: ConstraintReason, span: Span) -> Constraint {
+        Constraint::Instance {
+            scheme,
+            ty,
+            reason,
+            span,
+        }
+    }
+
+    /// The left-hand type of the constraint (`type1` for [`Constraint::Equal`],
+    /// `sub` for [`Constraint::Subtype`]), so a solver can iterate over a
+    /// mixed list of constraints without matching on the variant first.
+    pub fn lhs(&self) -> &Type {
+        match self {
+            Constraint::Equal { type1, .. } => type1,
+            Constraint::Subtype { sub, .. } => sub,
+            Constraint::Instance { scheme, .. } => &scheme.ty,
+        }
+    }
+
+    /// The right-hand type of the constraint (`type2` for [`Constraint::Equal`],
+    /// `sup` for [`Constraint::Subtype`], `ty` for [`Constraint::Instance`]).
+    pub fn rhs(&self) -> &Type {
+        match self {
+            Constraint::Equal { type2, .. } => type2,
+            Constraint::Subtype { sup, .. } => sup,
+            Constraint::Instance { ty, .. } => ty,
+        }
+    }
+
+    /// Why this constraint was generated.
+    pub fn reason(&self) -> ConstraintReason {
+        match self {
+            Constraint::Equal { reason, .. } => *reason,
+            Constraint::Subtype { reason, .. } => *reason,
+            Constraint::Instance { reason, .. } => *reason,
+        }
+    }
+
+    /// The source span of the expression [`Constraint::lhs`] was inferred
+    /// for, so a unification failure can point at the conflicting site on
+    /// this side of the constraint. [`Constraint::Instance`] has only one
+    /// meaningful span (the use site being instantiated), which it returns
+    /// for both sides.
+    pub fn lhs_span(&self) -> Span {
+        match self {
+            Constraint::Equal { lhs_span, .. } => *lhs_span,
+            Constraint::Subtype { lhs_span, .. } => *lhs_span,
+            Constraint::Instance { span, .. } => *span,
+        }
+    }
+
+    /// The source span of the expression [`Constraint::rhs`] was inferred
+    /// for.
+    pub fn rhs_span(&self) -> Span {
+        match self {
+            Constraint::Equal { rhs_span, .. } => *rhs_span,
+            Constraint::Subtype { rhs_span, .. } => *rhs_span,
+            Constraint::Instance { span, .. } => *span,
+        }
+    }
+
+    /// Puts an [`Constraint::Equal`] constraint into a deterministic
+    /// canonical form by ordering its two sides (using `Type`'s derived
+    /// [`Ord`]) rather than the order they happened to be generated in, so
+    /// `x = y` and `y = x` compare equal and hash the same. Left as-is for
+    /// [`Constraint::Subtype`], whose sides are not interchangeable: `sub
+    /// <: sup` is not the same constraint as `sup <: sub`.
+    pub fn canonical(self) -> Constraint {
+        match self {
+            Constraint::Equal {
+                type1,
+                type2,
+                reason,
+                lhs_span,
+                rhs_span,
+            } if type2 < type1 => Constraint::Equal {
+                type1: type2,
+                type2: type1,
+                reason,
+                lhs_span: rhs_span,
+                rhs_span: lhs_span,
+            },
+            other => other,
+        }
+    }
 }
 
 impl Display for Constraint {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
-        write!(f, "{} = {}", self.type1, self.type2)
+        match self {
+            Constraint::Equal { type1, type2, .. } => write!(f, "{} = {}", type1, type2),
+            Constraint::Subtype { sub, sup, .. } => write!(f, "{} <: {}", sub, sup),
+            Constraint::Instance { scheme, ty, .. } => write!(f, "{} :: {}", ty, scheme),
+        }
     }
 }
 
-pub fn collect_constraints(term: &TypedTerm) -> Vec<Constraint> {
-    let mut bindings = HashMap::new();
-    bindings.insert(
-        String::from("+"),
-        Type::Function {
-            parameter_type: Box::from(Type::Integer),
-            return_type: Box::from(Type::Integer),
-        },
-    );
-    bindings.insert(
-        String::from("-"),
-        Type::Function {
-            parameter_type: Box::from(Type::Integer),
-            return_type: Box::from(Type::Integer),
-        },
-    );
-    bindings.insert(
-        String::from("*"),
-        Type::Function {
-            parameter_type: Box::from(Type::Integer),
-            return_type: Box::from(Type::Integer),
-        },
-    );
-    bindings.insert(
-        String::from("/"),
+/// Canonicalizes every constraint and removes exact duplicates, preserving
+/// the order the first occurrence of each distinct constraint appeared in.
+/// Constraint generation walks the AST and can easily re-derive the same
+/// fact more than once (e.g. an identifier used twice constrains its type
+/// against the same builtin signature twice), which wastes solver work and
+/// makes golden-test output sensitive to incidental reordering of the
+/// generation pass.
+pub fn dedup_constraints(constraints: Vec<Constraint>) -> Vec<Constraint> {
+    let mut seen = HashSet::new();
+    let mut deduped = Vec::new();
+    for constraint in constraints {
+        let constraint = constraint.canonical();
+        if seen.insert(constraint.clone()) {
+            deduped.push(constraint);
+        }
+    }
+    deduped
+}
+
+/// Renders `constraints` as a deterministic, human-readable text dump: one
+/// canonicalized constraint per line, sorted so that the same underlying
+/// constraint set always produces the same text regardless of the order
+/// constraint generation happened to visit the AST in, with every type
+/// variable alpha-renamed (`t7`, `t12`, ... become `a`, `b`, ...) in the
+/// order they first appear so the dump doesn't depend on `annotate`'s exact
+/// placeholder/numeric counter values either. Intended for snapshot tests
+/// and for handing a constraint set to external tooling that shouldn't
+/// need to know this crate's internal numbering.
+///
+/// Each line has one of the following forms:
+/// ```text
+/// <lhs> = <rhs>      (from a Constraint::Equal)
+/// <lhs> <: <rhs>     (from a Constraint::Subtype)
+/// <ty> :: <scheme>   (from a Constraint::Instance)
+/// ```
+/// where `<scheme>` is the scheme's type alone, without its `forall`
+/// binders.
+pub fn dump_constraints(constraints: &[Constraint]) -> String {
+    let mut constraints = dedup_constraints(constraints.to_vec());
+    constraints.sort_by_key(dump_sort_key);
+    let mut renames = VarRenames::default();
+    let mut lines: Vec<String> = constraints
+        .iter()
+        .map(|constraint| dump_line(constraint, &mut renames))
+        .collect();
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+/// A total order over constraints that only depends on their content, not
+/// on the order constraint generation happened to produce them in.
+fn dump_sort_key(constraint: &Constraint) -> (u8, Type, Type, ConstraintReason) {
+    let tag = match constraint {
+        Constraint::Equal { .. } => 0,
+        Constraint::Subtype { .. } => 1,
+        Constraint::Instance { .. } => 2,
+    };
+    (tag, constraint.lhs().clone(), constraint.rhs().clone(), constraint.reason())
+}
+
+fn dump_line(constraint: &Constraint, renames: &mut VarRenames) -> String {
+    match constraint {
+        Constraint::Equal { type1, type2, .. } => {
+            format!("{} = {}", dump_type(type1, renames), dump_type(type2, renames))
+        }
+        Constraint::Subtype { sub, sup, .. } => {
+            format!("{} <: {}", dump_type(sub, renames), dump_type(sup, renames))
+        }
+        Constraint::Instance { scheme, ty, .. } => {
+            format!("{} :: {}", dump_type(ty, renames), dump_type(&scheme.ty, renames))
+        }
+    }
+}
+
+/// Assigns short, stable names to type variables the first time
+/// [`dump_type`] encounters them, so the same variable always renders the
+/// same way within one call to [`dump_constraints`] regardless of the
+/// numeric id `annotate` happened to give it. Placeholders and numeric
+/// literals are renamed from separate letter/number sequences, since they
+/// are conceptually different kinds of variable even though `Type`'s own
+/// [`Display`] renders both as `t{id}`.
+#[derive(Default)]
+struct VarRenames {
+    placeholders: HashMap<u32, String>,
+    numerics: HashMap<u32, String>,
+}
+
+impl VarRenames {
+    fn placeholder(&mut self, id: u32) -> String {
+        let next = self.placeholders.len();
+        self.placeholders
+            .entry(id)
+            .or_insert_with(|| alpha_name(next))
+            .clone()
+    }
+
+    fn numeric(&mut self, id: u32) -> String {
+        let next = self.numerics.len();
+        self.numerics.entry(id).or_insert_with(|| format!("n{}", next)).clone()
+    }
+}
+
+/// Renders the `n`th variable name in the sequence `a, b, ..., z, aa, ab, ...`.
+fn alpha_name(n: usize) -> String {
+    let mut n = n;
+    let mut name = Vec::new();
+    loop {
+        name.push(b'a' + (n % 26) as u8);
+        n /= 26;
+        if n == 0 {
+            break;
+        }
+        n -= 1;
+    }
+    name.reverse();
+    String::from_utf8(name).expect("alpha_name only ever produces ASCII bytes")
+}
+
+fn dump_type(ty: &Type, renames: &mut VarRenames) -> String {
+    match ty {
+        Type::Boolean => String::from("bool"),
+        Type::Bottom => String::from("never"),
+        Type::Integer => String::from("int"),
+        Type::Numeric(id) => renames.numeric(*id),
+        Type::Placeholder(id) => renames.placeholder(*id),
+        Type::Constructor { name, arguments } => {
+            let mut rendered = name.clone();
+            for argument in arguments {
+                rendered.push(' ');
+                rendered.push_str(&dump_type(argument, renames));
+            }
+            rendered
+        }
         Type::Function {
-            parameter_type: Box::from(Type::Integer),
-            return_type: Box::from(Type::Integer),
+            parameter_type,
+            return_type,
+            ..
+        } => {
+            let parameter = dump_type(parameter_type, renames);
+            let parameter = match **parameter_type {
+                Type::Function { .. } => format!("({})", parameter),
+                _ => parameter,
+            };
+            format!("{} => {}", parameter, dump_type(return_type, renames))
+        }
+        Type::Intersection(members) => members
+            .iter()
+            .map(|member| dump_type(member, renames))
+            .collect::<Vec<_>>()
+            .join(" & "),
+        Type::Record(fields) => {
+            let rendered: Vec<String> = fields
+                .iter()
+                .map(|(name, field_type)| format!("{}: {}", name, dump_type(field_type, renames)))
+                .collect();
+            format!("{{{}}}", rendered.join(", "))
+        }
+    }
+}
+
+/// A subtyping lattice used to decide [`Constraint::Subtype`] constraints:
+/// every type is a subtype of itself; [`Type::Bottom`] is a subtype of
+/// everything, matching its "unifies with anything" unification behavior;
+/// functions are contravariant in their parameter and covariant in their
+/// return type; a matching [`Type::Constructor`] is a subtype pointwise in
+/// its arguments; a [`Type::Record`] is a subtype of another if it has at
+/// least the supertype's fields, each pointwise a subtype of the
+/// supertype's (width-and-depth record subtyping); and a
+/// [`Type::Intersection`] on either side is treated as a set of
+/// alternatives, so it participates in the relation through whichever
+/// member makes it hold. This is the foundation coercions, gradual typing,
+/// and record width subtyping build on.
+pub fn is_subtype(sub: &Type, sup: &Type) -> bool {
+    if sub == sup {
+        return true;
+    }
+    match (sub, sup) {
+        (Type::Bottom, _) => true,
+        (
+            Type::Function { parameter_type: sub_parameter, return_type: sub_return, .. },
+            Type::Function { parameter_type: sup_parameter, return_type: sup_return, .. },
+        ) => is_subtype(sup_parameter, sub_parameter) && is_subtype(sub_return, sup_return),
+        (
+            Type::Constructor { name: sub_name, arguments: sub_arguments },
+            Type::Constructor { name: sup_name, arguments: sup_arguments },
+        ) if sub_name == sup_name && sub_arguments.len() == sup_arguments.len() => sub_arguments
+            .iter()
+            .zip(sup_arguments)
+            .all(|(sub_argument, sup_argument)| is_subtype(sub_argument, sup_argument)),
+        (Type::Record(sub_fields), Type::Record(sup_fields)) => {
+            sup_fields.iter().all(|(name, sup_field_type)| {
+                sub_fields
+                    .get(name)
+                    .is_some_and(|sub_field_type| is_subtype(sub_field_type, sup_field_type))
+            })
+        }
+        (Type::Intersection(members), _) => members.iter().any(|member| is_subtype(member, sup)),
+        (_, Type::Intersection(members)) => members.iter().any(|member| is_subtype(sub, member)),
+        _ => false,
+    }
+}
+
+/// Builds the [`Constraint`] checking a record-typed value's `actual` type
+/// against its `expected` type, dispatching on `mode`: [`RecordCheckingMode::Structural`]
+/// emits a [`Constraint::Subtype`] (so extra fields on `actual` are
+/// tolerated, per [`is_subtype`]'s width-and-depth record rule), while
+/// [`RecordCheckingMode::Nominal`] emits a [`Constraint::Equal`] (so
+/// `actual` must have exactly `expected`'s fields, no more and no fewer).
+/// This is the one place the two modes actually diverge; everything
+/// upstream of it — parsing and annotating a record literal — is
+/// mode-agnostic.
+pub fn record_constraint(
+    mode: RecordCheckingMode,
+    actual: Rc<Type>,
+    expected: Rc<Type>,
+    reason: ConstraintReason,
+    lhs_span: Span,
+    rhs_span: Span,
+) -> Constraint {
+    match mode {
+        RecordCheckingMode::Structural => Constraint::subtype(actual, expected, reason, lhs_span, rhs_span),
+        RecordCheckingMode::Nominal => Constraint::equal(actual, expected, reason, lhs_span, rhs_span),
+    }
+}
+
+/// Picks the member of an overloaded builtin's [`Type::Intersection`] that
+/// is equal to `expected`, the branch consistent with the constraints
+/// collected on the other side of the application. Returns `None` if
+/// `ty` is not an intersection or no member matches.
+pub fn select_branch(ty: &Type, expected: &Type) -> Option<Type> {
+    match ty {
+        Type::Intersection(members) => members.iter().find(|member| *member == expected).cloned(),
+        _ => None,
+    }
+}
+
+/// A polymorphic type annotation of the form `forall 'a <: bound. ty`,
+/// where each bound variable may carry an upper bound checked (via
+/// [`is_subtype`]) at instantiation time, e.g. `forall 'a <: printable. 'a
+/// -> string`.
+#[derive(Debug, Clone, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TypeScheme {
+    pub bound_vars: Vec<(u32, Option<Type>)>,
+    pub ty: Type,
+}
+
+impl Display for TypeScheme {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "forall")?;
+        for (var, bound) in &self.bound_vars {
+            match bound {
+                Some(bound) => write!(f, " 't{} <: {}", var, bound)?,
+                None => write!(f, " 't{}", var)?,
+            }
+        }
+        write!(f, ". {}", self.ty)
+    }
+}
+
+/// Instantiates `scheme` by substituting each bound variable with the
+/// corresponding entry in `arguments`, failing if an argument does not
+/// satisfy that variable's bound.
+pub fn instantiate(scheme: &TypeScheme, arguments: &[Type]) -> Result<Type, String> {
+    if arguments.len() != scheme.bound_vars.len() {
+        return Err(format!(
+            "expected {} type argument(s) but got {}",
+            scheme.bound_vars.len(),
+            arguments.len()
+        ));
+    }
+    let mut ty = scheme.ty.clone();
+    for ((var, bound), argument) in scheme.bound_vars.iter().zip(arguments) {
+        if let Some(bound) = bound {
+            if !is_subtype(argument, bound) {
+                return Err(format!(
+                    "type argument {} does not satisfy bound {}",
+                    argument, bound
+                ));
+            }
+        }
+        ty = substitute_type_var(&ty, *var, argument);
+    }
+    Ok(ty)
+}
+
+fn substitute_type_var(ty: &Type, var: u32, replacement: &Type) -> Type {
+    match ty {
+        Type::Placeholder(id) if *id == var => replacement.clone(),
+        Type::Constructor { name, arguments } => Type::Constructor {
+            name: name.clone(),
+            arguments: arguments
+                .iter()
+                .map(|argument| substitute_type_var(argument, var, replacement))
+                .collect(),
         },
-    );
-    bindings.insert(
-        String::from("="),
         Type::Function {
-            parameter_type: Box::from(Type::Integer),
-            return_type: Box::from(Type::Integer),
+            parameter_type,
+            return_type,
+            effects,
+        } => Type::Function {
+            parameter_type: Box::from(substitute_type_var(parameter_type, var, replacement)),
+            return_type: Box::from(substitute_type_var(return_type, var, replacement)),
+            effects: effects.clone(),
         },
-    );
-    collect_constraints_with_bindings(term, &bindings)
+        Type::Record(fields) => Type::Record(
+            fields
+                .iter()
+                .map(|(name, field_type)| (name.clone(), substitute_type_var(field_type, var, replacement)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
 }
 
-fn collect_constraints_with_bindings(
-    term: &TypedTerm,
-    bindings: &HashMap<String, Type>,
-) -> Vec<Constraint> {
-    match &term.kind {
-        TypedTermKind::Boolean(_) => vec![Constraint {
-            type1: term.ty.clone(),
-            type2: Type::Boolean,
-        }],
-        TypedTermKind::FunctionApplication { function, argument } => {
-            let mut constraints = vec![Constraint {
-                type1: function.ty.clone(),
-                type2: Type::Function {
-                    parameter_type: Box::from(argument.ty.clone()),
-                    return_type: Box::from(term.ty.clone()),
+/// A table of builtin identifier bindings available to
+/// [`collect_constraints_with_env`], kept as its own type (rather than a
+/// bare `HashMap`) so an embedder can add, remove, or re-type builtins
+/// without forking the crate. Most bindings are monomorphic — inserted
+/// with [`insert`](TypeEnv::insert) and looked up with
+/// [`get`](TypeEnv::get) — but a name can also be bound to a
+/// [`TypeScheme`] via [`insert_scheme`](TypeEnv::insert_scheme) for a
+/// builtin that's polymorphic over its instantiation, the way `id` or a
+/// datatype constructor would be.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TypeEnv {
+    bindings: HashMap<String, Type>,
+    schemes: HashMap<String, TypeScheme>,
+}
+
+impl TypeEnv {
+    pub fn new() -> TypeEnv {
+        TypeEnv::default()
+    }
+
+    /// The prelude `collect_constraints` used to hardcode: `+`, `-`, `*`,
+    /// `/`, and `=`, each typed `int -> int -> int`, curried to match how
+    /// the parser desugars `a op b` into `App(App(op, a), b)`.
+    pub fn default_prelude() -> TypeEnv {
+        let mut env = TypeEnv::new();
+        for name in ["+", "-", "*", "/", "="] {
+            env.insert(
+                name,
+                Type::Function {
+                    parameter_type: Box::from(Type::Integer),
+                    return_type: Box::from(Type::Function {
+                        parameter_type: Box::from(Type::Integer),
+                        return_type: Box::from(Type::Integer),
+                        effects: Vec::new(),
+                    }),
+                    effects: Vec::new(),
                 },
-            }];
-            constraints.extend(collect_constraints_with_bindings(function, bindings));
-            constraints.extend(collect_constraints_with_bindings(argument, bindings));
-            constraints
+            );
         }
-        TypedTermKind::FunctionDefinition { parameter, body } => {
-            let mut constraints = vec![Constraint {
-                type1: term.ty.clone(),
-                type2: Type::Function {
-                    parameter_type: Box::from(parameter.ty.clone()),
-                    return_type: Box::from(body.ty.clone()),
-                },
-            }];
-            constraints.extend(collect_constraints_with_bindings(body, bindings));
-            constraints
+        env
+    }
+
+    pub fn insert(&mut self, name: impl Into<String>, ty: Type) {
+        self.bindings.insert(name.into(), ty);
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Type> {
+        self.bindings.remove(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Type> {
+        self.bindings.get(name)
+    }
+
+    /// Binds `name` to a polymorphic [`TypeScheme`] instead of a single
+    /// [`Type`], so each use can be instantiated (via [`instantiate`])
+    /// separately, e.g. giving a builtin identity function the type
+    /// `forall 't. t -> t` rather than pinning it to one concrete type.
+    pub fn insert_scheme(&mut self, name: impl Into<String>, scheme: TypeScheme) {
+        self.schemes.insert(name.into(), scheme);
+    }
+
+    pub fn remove_scheme(&mut self, name: &str) -> Option<TypeScheme> {
+        self.schemes.remove(name)
+    }
+
+    pub fn get_scheme(&self, name: &str) -> Option<&TypeScheme> {
+        self.schemes.get(name)
+    }
+
+    /// Every bound name, in no particular order — used to look for a
+    /// did-you-mean candidate when an identifier turns out unbound.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.bindings.keys().map(String::as_str).chain(self.schemes.keys().map(String::as_str))
+    }
+
+    /// A scoped copy of this environment for a nested scope (e.g. a `let`
+    /// or `fn` body) to extend without mutating the original — the same
+    /// clone-then-insert pattern `algorithm_w`/`algorithm_m` already use
+    /// for their own local environments, but on a [`TypeEnv`] itself.
+    pub fn child(&self) -> TypeEnv {
+        self.clone()
+    }
+
+    /// Merges every binding and scheme from `other` into this environment,
+    /// so an embedder can bring in a whole module or prelude of
+    /// domain-specific builtins in one call instead of inserting them one
+    /// at a time. A name present in both takes `other`'s binding.
+    pub fn import(&mut self, other: &TypeEnv) {
+        self.bindings.extend(other.bindings.iter().map(|(name, ty)| (name.clone(), ty.clone())));
+        self.schemes.extend(other.schemes.iter().map(|(name, scheme)| (name.clone(), scheme.clone())));
+    }
+}
+
+/// The closest name to `name` among `candidates`, if one is within a
+/// small edit distance — close enough that it's more likely a typo than a
+/// coincidence, e.g. `lenght` against `length` but not against `map`.
+pub fn suggest_identifier<'a>(
+    name: &str,
+    candidates: impl Iterator<Item = &'a str>,
+) -> Option<String> {
+    candidates
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (candidate, edit_distance(name, candidate)))
+        .filter(|(_, distance)| *distance <= 2)
+        .min_by_key(|(_, distance)| *distance)
+        .map(|(candidate, _)| candidate.to_string())
+}
+
+/// The Levenshtein edit distance between `a` and `b`: the fewest
+/// single-character insertions, deletions, or substitutions needed to
+/// turn one into the other.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.iter().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let above_left = previous;
+            previous = row[j + 1];
+            row[j + 1] = if a_char == b_char {
+                above_left
+            } else {
+                1 + above_left.min(row[j]).min(previous)
+            };
         }
-        TypedTermKind::Identifier(name) => match bindings.get(name) {
-            Some(ty) => vec![Constraint {
-                type1: term.ty.clone(),
-                type2: ty.clone(),
-            }],
-            None => vec![],
-        },
-        TypedTermKind::IfExpression {
-            condition,
-            true_branch,
-            false_branch,
-        } => {
-            let mut constraints = vec![
-                Constraint {
-                    type1: term.ty.clone(),
-                    type2: true_branch.ty.clone(),
-                },
-                Constraint {
-                    type1: term.ty.clone(),
-                    type2: false_branch.ty.clone(),
-                },
-                Constraint {
-                    type1: condition.ty.clone(),
-                    type2: Type::Boolean,
-                },
-            ];
-            constraints.extend(collect_constraints_with_bindings(condition, bindings));
-            constraints.extend(collect_constraints_with_bindings(true_branch, bindings));
-            constraints.extend(collect_constraints_with_bindings(false_branch, bindings));
-            constraints
+    }
+    row[b.len()]
+}
+
+/// A problem found while collecting constraints from a [`TypedTerm`].
+/// Distinct problems across the whole term are accumulated into a `Vec`
+/// rather than surfacing only the first one, so a caller can report
+/// everything wrong with a program in a single pass.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeError {
+    /// An identifier is referenced that is neither a builtin in the
+    /// [`TypeEnv`] nor bound by an enclosing `fn` or `let`, so no type
+    /// could be found for it. `suggestion`, when present, is the closest
+    /// in-scope name by edit distance, from [`suggest_identifier`].
+    UnboundIdentifier {
+        name: String,
+        span: Span,
+        suggestion: Option<String>,
+    },
+    /// Unification found two types that can never be made equal, e.g. `int`
+    /// against `bool`, or a [`Type::Constructor`] against a
+    /// [`Type::Function`].
+    TypeMismatch {
+        expected: Box<Type>,
+        found: Box<Type>,
+        span: Span,
+    },
+    /// The occurs check rejected binding `var` to `ty` because `var`
+    /// appears somewhere inside `ty`, which would make the substitution
+    /// infinitely self-referential (e.g. unifying `'a` with `'a -> int`).
+    InfiniteType { var: u32, ty: Box<Type>, span: Span },
+}
+
+impl TypeError {
+    /// A stable identifier for this error's kind, independent of its
+    /// message wording, so tests, editors, and documentation can refer to
+    /// a specific diagnostic (e.g. "TC0001") without matching on text that
+    /// might be reworded later.
+    pub fn code(&self) -> &'static str {
+        match self {
+            TypeError::UnboundIdentifier { .. } => "TC0001",
+            TypeError::TypeMismatch { .. } => "TC0002",
+            TypeError::InfiniteType { .. } => "TC0003",
         }
-        TypedTermKind::Integer { .. } => vec![Constraint {
-            type1: term.ty.clone(),
-            type2: Type::Integer,
-        }],
-        TypedTermKind::LetExpression {
-            declaration_name,
-            declaration_value,
-            expression,
-        } => {
-            let mut constraints = vec![
-                Constraint {
-                    type1: term.ty.clone(),
-                    type2: expression.ty.clone(),
-                },
-                Constraint {
-                    type1: declaration_name.ty.clone(),
-                    type2: declaration_value.ty.clone(),
-                },
-            ];
-            constraints.extend(collect_constraints_with_bindings(
+    }
+}
+
+impl Display for TypeError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            TypeError::UnboundIdentifier { name, span, suggestion } => {
+                write!(
+                    f,
+                    "{}: unbound identifier `{}` at line {}, column {}",
+                    self.code(), name, span.line, span.column
+                )?;
+                if let Some(suggestion) = suggestion {
+                    write!(f, "; did you mean `{}`?", suggestion)?;
+                }
+                Ok(())
+            }
+            TypeError::TypeMismatch { expected, found, span } => write!(
+                f,
+                "{}: expected `{}` but found `{}` at line {}, column {}",
+                self.code(), expected, found, span.line, span.column
+            ),
+            TypeError::InfiniteType { var, ty, span } => write!(
+                f,
+                "{}: infinite type: t{} occurs in `{}` at line {}, column {}",
+                self.code(), var, ty, span.line, span.column
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TypeError {}
+
+pub fn collect_constraints(term: &TypedTerm) -> Result<Vec<Constraint>, Vec<TypeError>> {
+    collect_constraints_with_env(term, &TypeEnv::default_prelude())
+}
+
+pub fn collect_constraints_with_env(
+    term: &TypedTerm,
+    env: &TypeEnv,
+) -> Result<Vec<Constraint>, Vec<TypeError>> {
+    let constraints = collect_constraints_with_bindings(term, env, &mut Vec::new())?;
+    Ok(dedup_constraints(constraints))
+}
+
+/// One unit of pending work for the explicit stack in
+/// [`collect_constraints_with_bindings`], which walks a [`TypedTerm`]
+/// without recursing so it does not overflow the call stack on deeply
+/// nested terms.
+enum Work<'a> {
+    /// Collect the constraints (and, for identifiers, errors) local to
+    /// this node, then push work for its children.
+    Visit(&'a TypedTerm),
+    /// Push `name` onto `scope` once the work items pushed after this one
+    /// have all run, i.e. once a `let`'s declaration value has been fully
+    /// visited without its own binding in scope yet.
+    EnterScope(String),
+    /// Pop the innermost scope entry, once the binder's body/expression
+    /// has been fully visited.
+    ExitScope,
+}
+
+fn collect_constraints_with_bindings(
+    term: &TypedTerm,
+    bindings: &TypeEnv,
+    scope: &mut Vec<String>,
+) -> Result<Vec<Constraint>, Vec<TypeError>> {
+    let mut work = vec![Work::Visit(term)];
+    let mut constraints = Vec::new();
+    let mut errors = Vec::new();
+
+    while let Some(item) = work.pop() {
+        let term = match item {
+            Work::ExitScope => {
+                scope.pop();
+                continue;
+            }
+            Work::EnterScope(name) => {
+                scope.push(name);
+                continue;
+            }
+            Work::Visit(term) => term,
+        };
+        match &term.kind {
+            TypedTermKind::Boolean(_) => constraints.push(Constraint::equal(
+                term.ty.clone(),
+                Rc::new(Type::Boolean),
+                ConstraintReason::BooleanLiteral,
+                term.span,
+                term.span,
+            )),
+            TypedTermKind::FunctionApplication { function, argument } => {
+                constraints.push(Constraint::equal(
+                    function.ty.clone(),
+                    Rc::new(Type::Function {
+                        parameter_type: Box::new((*argument.ty).clone()),
+                        return_type: Box::new((*term.ty).clone()),
+                        effects: Vec::new(),
+                    }),
+                    ConstraintReason::ApplicationArgument,
+                    function.span,
+                    term.span,
+                ));
+                work.push(Work::Visit(argument));
+                work.push(Work::Visit(function));
+            }
+            TypedTermKind::FunctionDefinition { parameter, body } => {
+                constraints.push(Constraint::equal(
+                    term.ty.clone(),
+                    Rc::new(Type::Function {
+                        parameter_type: Box::new((*parameter.ty).clone()),
+                        return_type: Box::new((*body.ty).clone()),
+                        effects: collect_raise_effects(body),
+                    }),
+                    ConstraintReason::FunctionSignature,
+                    term.span,
+                    term.span,
+                ));
+                scope.push(binder_name(parameter));
+                work.push(Work::ExitScope);
+                work.push(Work::Visit(body));
+            }
+            TypedTermKind::Identifier(name) => match bindings.get(name) {
+                Some(ty) => constraints.push(Constraint::equal(
+                    term.ty.clone(),
+                    Rc::new(ty.clone()),
+                    ConstraintReason::BuiltinSignature,
+                    term.span,
+                    term.span,
+                )),
+                None if bindings.get_scheme(name).is_some() => constraints.push(Constraint::instance(
+                    bindings.get_scheme(name).cloned().expect("checked by the match guard"),
+                    term.ty.clone(),
+                    ConstraintReason::BuiltinSignature,
+                    term.span,
+                )),
+                None if scope.contains(name) => {}
+                None => errors.push(TypeError::UnboundIdentifier {
+                    name: name.clone(),
+                    span: term.span,
+                    suggestion: suggest_identifier(
+                        name,
+                        bindings.names().chain(scope.iter().map(String::as_str)),
+                    ),
+                }),
+            },
+            TypedTermKind::IfExpression {
+                condition,
+                true_branch,
+                false_branch,
+            } => {
+                constraints.push(Constraint::equal(
+                    term.ty.clone(),
+                    true_branch.ty.clone(),
+                    ConstraintReason::BranchesMustMatch,
+                    term.span,
+                    true_branch.span,
+                ));
+                constraints.push(Constraint::equal(
+                    term.ty.clone(),
+                    false_branch.ty.clone(),
+                    ConstraintReason::BranchesMustMatch,
+                    term.span,
+                    false_branch.span,
+                ));
+                constraints.push(Constraint::equal(
+                    condition.ty.clone(),
+                    Rc::new(Type::Boolean),
+                    ConstraintReason::IfConditionBool,
+                    condition.span,
+                    condition.span,
+                ));
+                work.push(Work::Visit(false_branch));
+                work.push(Work::Visit(true_branch));
+                work.push(Work::Visit(condition));
+            }
+            // A broken subtree carries a fresh, otherwise-unconstrained
+            // type variable, so it is simply skipped rather than turned
+            // into constraints that could never be satisfied.
+            TypedTermKind::Error => {}
+            // Integer literals carry a `Type::Numeric` variable rather
+            // than a hard equality constraint against `Type::Integer`, so
+            // they can later default to `int` (see
+            // `annotator::default_numeric_types`) instead of being forced
+            // there before solving even starts.
+            TypedTermKind::Integer { .. } => {}
+            TypedTermKind::LetExpression {
+                declaration_name,
                 declaration_value,
-                bindings,
-            ));
-            constraints.extend(collect_constraints_with_bindings(expression, bindings));
-            constraints
+                expression,
+            } => {
+                constraints.push(Constraint::equal(
+                    term.ty.clone(),
+                    expression.ty.clone(),
+                    ConstraintReason::LetResult,
+                    term.span,
+                    expression.span,
+                ));
+                constraints.push(Constraint::equal(
+                    declaration_name.ty.clone(),
+                    declaration_value.ty.clone(),
+                    ConstraintReason::LetBinding,
+                    declaration_name.span,
+                    declaration_value.span,
+                ));
+                // `declaration_value` is visited without its own name in
+                // scope yet, so the binding is only pushed once that
+                // subtree's work has run.
+                work.push(Work::ExitScope);
+                work.push(Work::Visit(expression));
+                work.push(Work::EnterScope(binder_name(declaration_name)));
+                work.push(Work::Visit(declaration_value));
+            }
+            // `raise e` carries `Type::Bottom`, which unifies with
+            // anything, so it is left out of the equality constraints
+            // entirely; only its exception sub-term still needs its own
+            // constraints collected.
+            TypedTermKind::RaiseExpression { exception } => {
+                work.push(Work::Visit(exception));
+            }
         }
     }
+
+    if errors.is_empty() {
+        Ok(constraints)
+    } else {
+        Err(errors)
+    }
+}
+
+fn binder_name(binder: &TypedTerm) -> String {
+    match &binder.kind {
+        TypedTermKind::Identifier(name) => name.clone(),
+        other => unreachable!("binder is always an identifier, got {:?}", other),
+    }
+}
+
+/// The sorted, deduplicated names of every exception `body` may raise,
+/// collected from its `raise` sites. Descends into every subterm except a
+/// nested [`TypedTermKind::FunctionDefinition`]'s body, since a nested
+/// function's own raises are effects of that function, not of the one
+/// being defined here. Walks with an explicit stack, like
+/// [`collect_constraints_with_bindings`], so a pathologically deep chain of
+/// raises can't overflow the call stack.
+fn collect_raise_effects(body: &TypedTerm) -> Vec<String> {
+    let mut effects = Vec::new();
+    let mut work = vec![body];
+    while let Some(term) = work.pop() {
+        match &term.kind {
+            TypedTermKind::RaiseExpression { exception } => {
+                effects.push(exception.kind.to_string());
+                work.push(exception);
+            }
+            TypedTermKind::FunctionApplication { function, argument } => {
+                work.push(function);
+                work.push(argument);
+            }
+            TypedTermKind::IfExpression { condition, true_branch, false_branch } => {
+                work.push(condition);
+                work.push(true_branch);
+                work.push(false_branch);
+            }
+            TypedTermKind::LetExpression { declaration_value, expression, .. } => {
+                work.push(declaration_value);
+                work.push(expression);
+            }
+            // A nested function's raises are its own effects, not this one's.
+            TypedTermKind::FunctionDefinition { .. } => {}
+            TypedTermKind::Boolean(_)
+            | TypedTermKind::Error
+            | TypedTermKind::Identifier(_)
+            | TypedTermKind::Integer(_) => {}
+        }
+    }
+    effects.sort();
+    effects.dedup();
+    effects
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::annotator::{annotate, Type};
-    use crate::constraint::{collect_constraints, Constraint};
-    use crate::parser::parse;
-    use crate::tokenizer::tokenize;
-    use std::collections::HashSet;
+    use crate::annotator::{annotate, RecordCheckingMode, Type, TypedTerm, TypedTermKind};
+    use crate::constraint::{
+        collect_constraints, collect_constraints_with_env, dump_constraints, edit_distance,
+        instantiate, is_subtype, record_constraint, select_branch, suggest_identifier, Constraint,
+        ConstraintReason, TypeEnv, TypeError, TypeScheme,
+    };
+    use super::alpha_name;
+    use crate::parser::{parse, Term, TermKind};
+    use crate::tokenizer::{tokenize_with_spans, Span};
+    use std::collections::{BTreeMap, HashSet};
     use std::iter::FromIterator;
+    use std::rc::Rc;
+
+    /// Rebuilds `constraint` with both spans reset to [`Span::default`], so
+    /// pipeline tests can assert on the type/reason shape of the generated
+    /// constraints without hard-coding every subexpression's exact span.
+    /// Also canonicalizes the result, since `collect_constraints` now does
+    /// the same before handing constraints back, and hand-written expected
+    /// values in these tests aren't guaranteed to already be in that order.
+    fn without_spans(constraint: Constraint) -> Constraint {
+        let constraint = match constraint {
+            Constraint::Equal { type1, type2, reason, .. } => {
+                Constraint::equal(type1, type2, reason, Span::default(), Span::default())
+            }
+            Constraint::Subtype { sub, sup, reason, .. } => {
+                Constraint::subtype(sub, sup, reason, Span::default(), Span::default())
+            }
+            Constraint::Instance { scheme, ty, reason, .. } => {
+                Constraint::instance(scheme, ty, reason, Span::default())
+            }
+        };
+        constraint.canonical()
+    }
+
+    #[test]
+    fn test_constraint_equal_constructs_the_equal_variant() {
+        assert_eq!(
+            Constraint::equal(
+                Rc::new(Type::Integer),
+                Rc::new(Type::Boolean),
+                ConstraintReason::LetBinding,
+                Span::default(),
+                Span::default(),
+            ),
+            Constraint::Equal {
+                type1: Rc::new(Type::Integer),
+                type2: Rc::new(Type::Boolean),
+                reason: ConstraintReason::LetBinding,
+                lhs_span: Span::default(),
+                rhs_span: Span::default(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_constraint_subtype_constructs_the_subtype_variant() {
+        assert_eq!(
+            Constraint::subtype(
+                Rc::new(Type::Integer),
+                Rc::new(Type::Integer),
+                ConstraintReason::LetBinding,
+                Span::default(),
+                Span::default(),
+            ),
+            Constraint::Subtype {
+                sub: Rc::new(Type::Integer),
+                sup: Rc::new(Type::Integer),
+                reason: ConstraintReason::LetBinding,
+                lhs_span: Span::default(),
+                rhs_span: Span::default(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_constraint_instance_constructs_the_instance_variant() {
+        let scheme = TypeScheme {
+            bound_vars: vec![(1, None)],
+            ty: Type::Placeholder(1),
+        };
+        assert_eq!(
+            Constraint::instance(
+                scheme.clone(),
+                Rc::new(Type::Integer),
+                ConstraintReason::LetInstantiation,
+                Span::default(),
+            ),
+            Constraint::Instance {
+                scheme,
+                ty: Rc::new(Type::Integer),
+                reason: ConstraintReason::LetInstantiation,
+                span: Span::default(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_constraint_lhs_and_rhs_for_equal() {
+        let constraint = Constraint::equal(
+            Rc::new(Type::Integer),
+            Rc::new(Type::Boolean),
+            ConstraintReason::LetBinding,
+            Span::default(),
+            Span::default(),
+        );
+        assert_eq!(constraint.lhs(), &Type::Integer);
+        assert_eq!(constraint.rhs(), &Type::Boolean);
+    }
+
+    #[test]
+    fn test_constraint_lhs_and_rhs_for_subtype() {
+        let constraint = Constraint::subtype(
+            Rc::new(Type::Integer),
+            Rc::new(Type::Boolean),
+            ConstraintReason::LetBinding,
+            Span::default(),
+            Span::default(),
+        );
+        assert_eq!(constraint.lhs(), &Type::Integer);
+        assert_eq!(constraint.rhs(), &Type::Boolean);
+    }
+
+    #[test]
+    fn test_constraint_lhs_and_rhs_for_instance() {
+        let scheme = TypeScheme {
+            bound_vars: vec![(1, None)],
+            ty: Type::Placeholder(1),
+        };
+        let constraint = Constraint::instance(
+            scheme,
+            Rc::new(Type::Integer),
+            ConstraintReason::LetInstantiation,
+            Span::default(),
+        );
+        assert_eq!(constraint.lhs(), &Type::Placeholder(1));
+        assert_eq!(constraint.rhs(), &Type::Integer);
+    }
+
+    #[test]
+    fn test_constraint_reason_for_equal_and_subtype() {
+        let equal = Constraint::equal(
+            Rc::new(Type::Integer),
+            Rc::new(Type::Boolean),
+            ConstraintReason::IfConditionBool,
+            Span::default(),
+            Span::default(),
+        );
+        let subtype = Constraint::subtype(
+            Rc::new(Type::Integer),
+            Rc::new(Type::Boolean),
+            ConstraintReason::LetResult,
+            Span::default(),
+            Span::default(),
+        );
+        assert_eq!(equal.reason(), ConstraintReason::IfConditionBool);
+        assert_eq!(subtype.reason(), ConstraintReason::LetResult);
+    }
+
+    #[test]
+    fn test_constraint_lhs_span_and_rhs_span_for_equal() {
+        let lhs_span = Span { start: 0, end: 1, line: 1, column: 1 };
+        let rhs_span = Span { start: 4, end: 5, line: 1, column: 5 };
+        let constraint = Constraint::equal(
+            Rc::new(Type::Integer),
+            Rc::new(Type::Boolean),
+            ConstraintReason::LetBinding,
+            lhs_span,
+            rhs_span,
+        );
+        assert_eq!(constraint.lhs_span(), lhs_span);
+        assert_eq!(constraint.rhs_span(), rhs_span);
+    }
+
+    #[test]
+    fn test_constraint_lhs_span_and_rhs_span_for_subtype() {
+        let lhs_span = Span { start: 2, end: 3, line: 1, column: 3 };
+        let rhs_span = Span { start: 6, end: 7, line: 1, column: 7 };
+        let constraint = Constraint::subtype(
+            Rc::new(Type::Integer),
+            Rc::new(Type::Boolean),
+            ConstraintReason::LetBinding,
+            lhs_span,
+            rhs_span,
+        );
+        assert_eq!(constraint.lhs_span(), lhs_span);
+        assert_eq!(constraint.rhs_span(), rhs_span);
+    }
+
+    #[test]
+    fn test_constraint_lhs_span_and_rhs_span_for_instance() {
+        let span = Span { start: 8, end: 9, line: 1, column: 9 };
+        let scheme = TypeScheme {
+            bound_vars: vec![(1, None)],
+            ty: Type::Placeholder(1),
+        };
+        let constraint = Constraint::instance(
+            scheme,
+            Rc::new(Type::Integer),
+            ConstraintReason::LetInstantiation,
+            span,
+        );
+        assert_eq!(constraint.lhs_span(), span);
+        assert_eq!(constraint.rhs_span(), span);
+    }
+
+    #[test]
+    fn test_display_equal_constraint() {
+        let constraint = Constraint::Equal {
+            type1: Rc::new(Type::Integer),
+            type2: Rc::new(Type::Boolean),
+            reason: ConstraintReason::LetBinding,
+            lhs_span: Span::default(),
+            rhs_span: Span::default(),
+        };
+        assert_eq!(constraint.to_string(), "int = bool");
+    }
+
+    #[test]
+    fn test_display_subtype_constraint() {
+        let constraint = Constraint::Subtype {
+            sub: Rc::new(Type::Integer),
+            sup: Rc::new(Type::Integer),
+            reason: ConstraintReason::LetBinding,
+            lhs_span: Span::default(),
+            rhs_span: Span::default(),
+        };
+        assert_eq!(constraint.to_string(), "int <: int");
+    }
+
+    #[test]
+    fn test_display_instance_constraint() {
+        let scheme = TypeScheme {
+            bound_vars: vec![(1, None)],
+            ty: Type::Function {
+                parameter_type: Box::from(Type::Placeholder(1)),
+                return_type: Box::from(Type::Placeholder(1)),
+                effects: Vec::new(),
+            },
+        };
+        let constraint = Constraint::instance(
+            scheme,
+            Rc::new(Type::Integer),
+            ConstraintReason::LetInstantiation,
+            Span::default(),
+        );
+        assert_eq!(constraint.to_string(), "int :: forall 't1. t1 => t1");
+    }
+
+    #[test]
+    fn test_alpha_name_wraps_after_z() {
+        assert_eq!(alpha_name(0), "a");
+        assert_eq!(alpha_name(25), "z");
+        assert_eq!(alpha_name(26), "aa");
+        assert_eq!(alpha_name(27), "ab");
+    }
+
+    #[test]
+    fn test_dump_constraints_renames_variables_from_first_appearance() {
+        let constraint = Constraint::equal(
+            Rc::new(Type::Placeholder(17)),
+            Rc::new(Type::Placeholder(3)),
+            ConstraintReason::LetBinding,
+            Span::default(),
+            Span::default(),
+        );
+        assert_eq!(dump_constraints(&[constraint]), "a = b\n");
+    }
+
+    #[test]
+    fn test_dump_constraints_uses_a_separate_sequence_for_numeric_variables() {
+        let constraint = Constraint::equal(
+            Rc::new(Type::Placeholder(1)),
+            Rc::new(Type::Numeric(1)),
+            ConstraintReason::LetBinding,
+            Span::default(),
+            Span::default(),
+        );
+        assert_eq!(dump_constraints(&[constraint]), "n0 = a\n");
+    }
+
+    #[test]
+    fn test_dump_constraints_is_independent_of_generation_order() {
+        let a = Constraint::equal(
+            Rc::new(Type::Placeholder(1)),
+            Rc::new(Type::Boolean),
+            ConstraintReason::BooleanLiteral,
+            Span::default(),
+            Span::default(),
+        );
+        let b = Constraint::equal(
+            Rc::new(Type::Placeholder(2)),
+            Rc::new(Type::Integer),
+            ConstraintReason::LetBinding,
+            Span::default(),
+            Span::default(),
+        );
+        assert_eq!(
+            dump_constraints(&[a.clone(), b.clone()]),
+            dump_constraints(&[b, a])
+        );
+    }
+
+    #[test]
+    fn test_dump_constraints_deduplicates_and_canonicalizes() {
+        let constraint = Constraint::equal(
+            Rc::new(Type::Boolean),
+            Rc::new(Type::Placeholder(1)),
+            ConstraintReason::BooleanLiteral,
+            Span::default(),
+            Span::default(),
+        );
+        let flipped = Constraint::equal(
+            Rc::new(Type::Placeholder(1)),
+            Rc::new(Type::Boolean),
+            ConstraintReason::BooleanLiteral,
+            Span::default(),
+            Span::default(),
+        );
+        assert_eq!(
+            dump_constraints(&[constraint, flipped]),
+            dump_constraints(&[Constraint::equal(
+                Rc::new(Type::Boolean),
+                Rc::new(Type::Placeholder(1)),
+                ConstraintReason::BooleanLiteral,
+                Span::default(),
+                Span::default(),
+            )])
+        );
+    }
+
+    #[test]
+    fn test_dump_constraints_renders_subtype_and_function_types() {
+        let constraint = Constraint::subtype(
+            Rc::new(Type::Function {
+                parameter_type: Box::from(Type::Placeholder(1)),
+                return_type: Box::from(Type::Placeholder(1)),
+                effects: Vec::new(),
+            }),
+            Rc::new(Type::Placeholder(2)),
+            ConstraintReason::LetBinding,
+            Span::default(),
+            Span::default(),
+        );
+        assert_eq!(dump_constraints(&[constraint]), "a => a <: b\n");
+    }
+
+    #[test]
+    fn test_is_subtype_reflexive() {
+        assert!(is_subtype(&Type::Integer, &Type::Integer));
+    }
+
+    #[test]
+    fn test_is_subtype_unrelated_types() {
+        assert!(!is_subtype(&Type::Integer, &Type::Boolean));
+    }
+
+    #[test]
+    fn test_is_subtype_bottom_is_a_subtype_of_everything() {
+        assert!(is_subtype(&Type::Bottom, &Type::Integer));
+        assert!(is_subtype(&Type::Bottom, &Type::Boolean));
+    }
+
+    #[test]
+    fn test_is_subtype_function_is_contravariant_in_its_parameter() {
+        // A function that only promises to accept `never` demands nothing
+        // of its caller, so it's the *super*type in parameter position: a
+        // function that actually accepts `int` can stand in for it (it
+        // accepts at least as much), but not the other way around.
+        let accepts_integer = Type::Function {
+            parameter_type: Box::from(Type::Integer),
+            return_type: Box::from(Type::Integer),
+            effects: Vec::new(),
+        };
+        let accepts_only_bottom = Type::Function {
+            parameter_type: Box::from(Type::Bottom),
+            return_type: Box::from(Type::Integer),
+            effects: Vec::new(),
+        };
+        assert!(is_subtype(&accepts_integer, &accepts_only_bottom));
+        assert!(!is_subtype(&accepts_only_bottom, &accepts_integer));
+    }
+
+    #[test]
+    fn test_is_subtype_function_is_covariant_in_its_return_type() {
+        let narrower_return = Type::Function {
+            parameter_type: Box::from(Type::Integer),
+            return_type: Box::from(Type::Bottom),
+            effects: Vec::new(),
+        };
+        let wider_return = Type::Function {
+            parameter_type: Box::from(Type::Integer),
+            return_type: Box::from(Type::Integer),
+            effects: Vec::new(),
+        };
+        assert!(is_subtype(&narrower_return, &wider_return));
+        assert!(!is_subtype(&wider_return, &narrower_return));
+    }
+
+    #[test]
+    fn test_is_subtype_constructor_is_pointwise_over_matching_arguments() {
+        let sub = Type::Constructor { name: String::from("Tagged"), arguments: vec![Type::Bottom] };
+        let sup = Type::Constructor { name: String::from("Tagged"), arguments: vec![Type::Integer] };
+        assert!(is_subtype(&sub, &sup));
+        assert!(!is_subtype(&sup, &sub));
+    }
+
+    #[test]
+    fn test_is_subtype_record_allows_extra_fields() {
+        // A record with an extra `age` field is a subtype of one that only
+        // requires `name` (width subtyping): it satisfies every field the
+        // supertype demands, and then some.
+        let sub = Type::Record(
+            BTreeMap::from([
+                (String::from("name"), Type::Integer),
+                (String::from("age"), Type::Integer),
+            ]),
+        );
+        let sup = Type::Record(BTreeMap::from([(String::from("name"), Type::Integer)]));
+        assert!(is_subtype(&sub, &sup));
+        assert!(!is_subtype(&sup, &sub));
+    }
+
+    #[test]
+    fn test_is_subtype_record_is_pointwise_over_shared_fields() {
+        // Depth subtyping: a field itself is compared via `is_subtype`, not
+        // plain equality, so `{tag: never}` is a subtype of `{tag: int}`.
+        let sub = Type::Record(BTreeMap::from([(String::from("tag"), Type::Bottom)]));
+        let sup = Type::Record(BTreeMap::from([(String::from("tag"), Type::Integer)]));
+        assert!(is_subtype(&sub, &sup));
+        assert!(!is_subtype(&sup, &sub));
+    }
+
+    #[test]
+    fn test_is_subtype_record_rejects_a_missing_field() {
+        let sub = Type::Record(BTreeMap::from([(String::from("name"), Type::Integer)]));
+        let sup = Type::Record(BTreeMap::from([(String::from("age"), Type::Integer)]));
+        assert!(!is_subtype(&sub, &sup));
+    }
+
+    #[test]
+    fn test_record_constraint_structural_mode_emits_a_subtype_constraint() {
+        let actual = Rc::new(Type::Record(
+            BTreeMap::from([
+                (String::from("name"), Type::Integer),
+                (String::from("age"), Type::Integer),
+            ]),
+        ));
+        let expected = Rc::new(Type::Record(BTreeMap::from([(String::from("name"), Type::Integer)])));
+        let constraint = record_constraint(
+            RecordCheckingMode::Structural,
+            Rc::clone(&actual),
+            Rc::clone(&expected),
+            ConstraintReason::LetBinding,
+            Span::default(),
+            Span::default(),
+        );
+        assert!(matches!(constraint, Constraint::Subtype { .. }));
+        assert_eq!(constraint.lhs(), &*actual);
+        assert_eq!(constraint.rhs(), &*expected);
+    }
+
+    #[test]
+    fn test_record_constraint_nominal_mode_emits_an_equal_constraint() {
+        let actual = Rc::new(Type::Record(BTreeMap::from([(String::from("name"), Type::Integer)])));
+        let expected = Rc::clone(&actual);
+        let constraint = record_constraint(
+            RecordCheckingMode::Nominal,
+            actual,
+            expected,
+            ConstraintReason::LetBinding,
+            Span::default(),
+            Span::default(),
+        );
+        assert!(matches!(constraint, Constraint::Equal { .. }));
+    }
+
+    #[test]
+    fn test_select_branch_picks_matching_overload() {
+        let plus_ty = Type::Intersection(vec![
+            Type::Function {
+                parameter_type: Box::from(Type::Integer),
+                return_type: Box::from(Type::Function {
+                    parameter_type: Box::from(Type::Integer),
+                    return_type: Box::from(Type::Integer),
+                    effects: Vec::new(),
+                }),
+                effects: Vec::new(),
+            },
+            Type::Function {
+                parameter_type: Box::from(Type::Boolean),
+                return_type: Box::from(Type::Boolean),
+                effects: Vec::new(),
+            },
+        ]);
+        let expected = Type::Function {
+            parameter_type: Box::from(Type::Boolean),
+            return_type: Box::from(Type::Boolean),
+            effects: Vec::new(),
+        };
+        assert_eq!(select_branch(&plus_ty, &expected), Some(expected));
+    }
+
+    #[test]
+    fn test_select_branch_none_when_not_intersection() {
+        assert_eq!(select_branch(&Type::Integer, &Type::Integer), None);
+    }
+
+    #[test]
+    fn test_instantiate_substitutes_bound_variable() {
+        // forall 'a. 'a -> 'a
+        let scheme = TypeScheme {
+            bound_vars: vec![(1, None)],
+            ty: Type::Function {
+                parameter_type: Box::from(Type::Placeholder(1)),
+                return_type: Box::from(Type::Placeholder(1)),
+                effects: Vec::new(),
+            },
+        };
+        assert_eq!(
+            instantiate(&scheme, &[Type::Integer]),
+            Ok(Type::Function {
+                parameter_type: Box::from(Type::Integer),
+                return_type: Box::from(Type::Integer),
+                effects: Vec::new(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_instantiate_rejects_bound_violation() {
+        // forall 'a <: bool. 'a -> 'a
+        let scheme = TypeScheme {
+            bound_vars: vec![(1, Some(Type::Boolean))],
+            ty: Type::Placeholder(1),
+        };
+        assert!(instantiate(&scheme, &[Type::Integer]).is_err());
+        assert_eq!(instantiate(&scheme, &[Type::Boolean]), Ok(Type::Boolean));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_type_scheme_round_trips_through_json() {
+        let scheme = TypeScheme {
+            bound_vars: vec![(0, Some(Type::Integer))],
+            ty: Type::Placeholder(0),
+        };
+        let json = serde_json::to_string(&scheme).unwrap();
+        let round_tripped: TypeScheme = serde_json::from_str(&json).unwrap();
+        assert_eq!(round_tripped, scheme);
+    }
+
+    #[test]
+    fn test_type_env_default_prelude_types_plus_as_curried_int_to_int_to_int() {
+        let env = TypeEnv::default_prelude();
+        assert_eq!(
+            env.get("+"),
+            Some(&Type::Function {
+                parameter_type: Box::from(Type::Integer),
+                return_type: Box::from(Type::Function {
+                    parameter_type: Box::from(Type::Integer),
+                    return_type: Box::from(Type::Integer),
+                    effects: Vec::new(),
+                }),
+                effects: Vec::new(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_type_env_new_has_no_bindings() {
+        assert_eq!(TypeEnv::new().get("+"), None);
+    }
+
+    #[test]
+    fn test_type_env_insert_and_remove() {
+        let mut env = TypeEnv::new();
+        env.insert("id", Type::Integer);
+        assert_eq!(env.get("id"), Some(&Type::Integer));
+        assert_eq!(env.remove("id"), Some(Type::Integer));
+        assert_eq!(env.get("id"), None);
+    }
+
+    #[test]
+    fn test_type_env_insert_scheme_and_remove_scheme() {
+        let mut env = TypeEnv::new();
+        let scheme = TypeScheme { bound_vars: vec![(0, None)], ty: Type::Placeholder(0) };
+        env.insert_scheme("id", scheme.clone());
+        assert_eq!(env.get_scheme("id"), Some(&scheme));
+        assert_eq!(env.remove_scheme("id"), Some(scheme));
+        assert_eq!(env.get_scheme("id"), None);
+    }
+
+    #[test]
+    fn test_type_env_names_includes_both_bindings_and_schemes() {
+        let mut env = TypeEnv::new();
+        env.insert("x", Type::Integer);
+        env.insert_scheme("id", TypeScheme { bound_vars: vec![(0, None)], ty: Type::Placeholder(0) });
+        let mut names: Vec<&str> = env.names().collect();
+        names.sort_unstable();
+        assert_eq!(names, vec!["id", "x"]);
+    }
+
+    #[test]
+    fn test_type_env_child_does_not_mutate_the_parent() {
+        let mut parent = TypeEnv::new();
+        parent.insert("x", Type::Integer);
+        let mut child = parent.child();
+        child.insert("y", Type::Boolean);
+        assert_eq!(child.get("x"), Some(&Type::Integer));
+        assert_eq!(child.get("y"), Some(&Type::Boolean));
+        assert_eq!(parent.get("y"), None);
+    }
+
+    #[test]
+    fn test_type_env_import_merges_bindings_and_schemes_favoring_the_other_env() {
+        let mut env = TypeEnv::new();
+        env.insert("x", Type::Integer);
+        let mut other = TypeEnv::new();
+        other.insert("x", Type::Boolean);
+        other.insert("y", Type::Boolean);
+        other.insert_scheme("id", TypeScheme { bound_vars: vec![(0, None)], ty: Type::Placeholder(0) });
+        env.import(&other);
+        assert_eq!(env.get("x"), Some(&Type::Boolean));
+        assert_eq!(env.get("y"), Some(&Type::Boolean));
+        assert!(env.get_scheme("id").is_some());
+    }
+
+    #[test]
+    fn test_collect_constraints_with_env_uses_a_custom_binding() -> Result<(), String> {
+        let tokens = tokenize_with_spans("not")?;
+        let term = parse(&tokens)?;
+        let mut env = TypeEnv::new();
+        env.insert("not", Type::Function {
+            parameter_type: Box::from(Type::Boolean),
+            return_type: Box::from(Type::Boolean),
+            effects: Vec::new(),
+        });
+        let typed_term = crate::annotator::annotate_with_env(
+            &term,
+            &BTreeMap::from([(
+                String::from("not"),
+                Type::Function {
+                    parameter_type: Box::from(Type::Boolean),
+                    return_type: Box::from(Type::Boolean),
+                    effects: Vec::new(),
+                },
+            )]),
+        )?;
+        let constraints = collect_constraints_with_env(&typed_term, &env)
+            .expect("constraint collection should succeed");
+        assert_eq!(
+            constraints,
+            vec![Constraint::equal(
+                typed_term.ty.clone(),
+                Rc::new(Type::Function {
+                    parameter_type: Box::from(Type::Boolean),
+                    return_type: Box::from(Type::Boolean),
+                    effects: Vec::new(),
+                }),
+                ConstraintReason::BuiltinSignature,
+                typed_term.span,
+                typed_term.span,
+            )]
+        );
+        Ok(())
+    }
 
     #[test]
     fn test_collect_constraints_for_identifier() -> Result<(), String> {
-        let tokens = tokenize("x")?;
+        let tokens = tokenize_with_spans("x")?;
         let term = parse(&tokens)?;
         let typed_term = annotate(&term);
         assert!(typed_term.is_err());
@@ -165,79 +1687,218 @@ mod tests {
     }
 
     #[test]
-    fn test_collect_constraints_for_integer() -> Result<(), String> {
-        let tokens = tokenize("42")?;
+    fn test_collect_constraints_reports_an_identifier_unbound_by_the_type_env() -> Result<(), String> {
+        // `annotate_with_env` knows about `foo`, but the `TypeEnv` passed to
+        // `collect_constraints_with_env` does not, and `foo` is not bound by
+        // any enclosing `fn` or `let` either, so it should be reported.
+        let tokens = tokenize_with_spans("foo")?;
         let term = parse(&tokens)?;
-        let typed_term = annotate(&term)?;
-        let constraints = collect_constraints(&typed_term);
+        let typed_term = crate::annotator::annotate_with_env(
+            &term,
+            &BTreeMap::from([(String::from("foo"), Type::Integer)]),
+        )?;
+        let errors = collect_constraints_with_env(&typed_term, &TypeEnv::new()).unwrap_err();
         assert_eq!(
-            constraints,
-            vec![Constraint {
-                // type(42) === integer
-                type1: Type::Placeholder(1),
-                type2: Type::Integer,
+            errors,
+            vec![TypeError::UnboundIdentifier {
+                name: String::from("foo"),
+                span: typed_term.span,
+                suggestion: None,
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_type_error_code_is_stable_per_variant() {
+        let span = Span::default();
+        assert_eq!(
+            TypeError::UnboundIdentifier { name: String::from("x"), span, suggestion: None }.code(),
+            "TC0001"
+        );
+        assert_eq!(
+            TypeError::TypeMismatch {
+                expected: Box::new(Type::Boolean),
+                found: Box::new(Type::Integer),
+                span,
+            }
+            .code(),
+            "TC0002"
+        );
+        assert_eq!(
+            TypeError::InfiniteType { var: 0, ty: Box::new(Type::Boolean), span }.code(),
+            "TC0003"
+        );
+    }
+
+    #[test]
+    fn test_collect_constraints_suggests_the_closest_bound_name_for_a_typo() -> Result<(), String> {
+        let tokens = tokenize_with_spans("lenght")?;
+        let term = parse(&tokens)?;
+        let typed_term = crate::annotator::annotate_with_env(
+            &term,
+            &BTreeMap::from([(String::from("lenght"), Type::Integer)]),
+        )?;
+        let mut env = TypeEnv::new();
+        env.insert("length", Type::Integer);
+        let errors = collect_constraints_with_env(&typed_term, &env).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![TypeError::UnboundIdentifier {
+                name: String::from("lenght"),
+                span: typed_term.span,
+                suggestion: Some(String::from("length")),
             }]
         );
         Ok(())
     }
 
+    #[test]
+    fn test_edit_distance_counts_a_single_substitution_as_one() {
+        assert_eq!(edit_distance("cat", "cot"), 1);
+    }
+
+    #[test]
+    fn test_suggest_identifier_ignores_candidates_too_far_away() {
+        assert_eq!(suggest_identifier("length", ["map", "filter"].iter().copied()), None);
+    }
+
+    #[test]
+    fn test_collect_constraints_accumulates_every_unbound_identifier() -> Result<(), String> {
+        let tokens = tokenize_with_spans("foo + bar")?;
+        let term = parse(&tokens)?;
+        let typed_term = crate::annotator::annotate_with_env(
+            &term,
+            &BTreeMap::from([
+                (String::from("foo"), Type::Integer),
+                (String::from("bar"), Type::Integer),
+            ]),
+        )?;
+        let errors = collect_constraints_with_env(&typed_term, &TypeEnv::default_prelude())
+            .unwrap_err();
+        let mut names: Vec<&str> = errors
+            .iter()
+            .map(|error| match error {
+                TypeError::UnboundIdentifier { name, .. } => name.as_str(),
+                other => unreachable!("collect_constraints only ever reports unbound identifiers, got {:?}", other),
+            })
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["bar", "foo"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_collect_constraints_does_not_flag_a_bound_lambda_parameter() -> Result<(), String> {
+        let tokens = tokenize_with_spans("fn x => x")?;
+        let term = parse(&tokens)?;
+        let typed_term = annotate(&term)?;
+        assert!(collect_constraints(&typed_term).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_collect_constraints_for_integer() -> Result<(), String> {
+        let tokens = tokenize_with_spans("42")?;
+        let term = parse(&tokens)?;
+        let typed_term = annotate(&term)?;
+        let constraints = collect_constraints(&typed_term)
+            .expect("constraint collection should succeed");
+        // A bare numeric literal generates no constraint; its `Type::Numeric`
+        // variable is left for `annotator::default_numeric_types` to resolve.
+        assert_eq!(constraints, vec![]);
+        Ok(())
+    }
+
     #[test]
     fn test_collect_constraints_for_if_expression() -> Result<(), String> {
-        let tokens = tokenize("if true then 1 else 0")?;
+        let tokens = tokenize_with_spans("if true then 1 else 0")?;
         let term = parse(&tokens)?;
         let typed_term = annotate(&term)?;
-        let constraints: HashSet<Constraint> = HashSet::from_iter(collect_constraints(&typed_term));
+        let constraints: HashSet<Constraint> =
+            HashSet::from_iter(
+            collect_constraints(&typed_term)
+                .expect("constraint collection should succeed")
+                .into_iter()
+                .map(without_spans),
+        );
         assert_eq!(
             constraints,
             HashSet::from_iter(vec![
                 // type(if x then 1 else 0) === type(1)
-                Constraint {
-                    type1: Type::Placeholder(1),
-                    type2: Type::Placeholder(3),
-                },
+                Constraint::equal(
+                    Rc::new(Type::Placeholder(1)),
+                    Rc::new(Type::Numeric(3)),
+                    ConstraintReason::BranchesMustMatch,
+               
+                    Span::default(),
+                    Span::default(),
+                ),
                 // type(if x then 1 else 0) === type(0)
-                Constraint {
-                    type1: Type::Placeholder(1),
-                    type2: Type::Placeholder(4),
-                },
-                // type(x) === boolean
-                Constraint {
-                    type1: Type::Placeholder(2),
-                    type2: Type::Boolean,
-                },
-                // type(1) === integer
-                Constraint {
-                    type1: Type::Placeholder(3),
-                    type2: Type::Integer,
-                },
-                // type(0) === integer
-                Constraint {
-                    type1: Type::Placeholder(4),
-                    type2: Type::Integer,
-                },
-            ])
+                Constraint::equal(
+                    Rc::new(Type::Placeholder(1)),
+                    Rc::new(Type::Numeric(4)),
+                    ConstraintReason::BranchesMustMatch,
+               
+                    Span::default(),
+                    Span::default(),
+                ),
+                // type(x) === boolean, required by the `if`'s condition
+                Constraint::equal(
+                    Rc::new(Type::Placeholder(2)),
+                    Rc::new(Type::Boolean),
+                    ConstraintReason::IfConditionBool,
+               
+                    Span::default(),
+                    Span::default(),
+                ),
+                // type(x) === boolean, from `x` itself being the literal `true`
+                Constraint::equal(
+                    Rc::new(Type::Placeholder(2)),
+                    Rc::new(Type::Boolean),
+                    ConstraintReason::BooleanLiteral,
+               
+                    Span::default(),
+                    Span::default(),
+                ),
+            ]
+            .into_iter()
+            .map(without_spans))
         );
         Ok(())
     }
 
     #[test]
     fn test_collect_constraints_for_function_definition() -> Result<(), String> {
-        let tokens = tokenize("fn x => x")?;
+        let tokens = tokenize_with_spans("fn x => x")?;
         let term = parse(&tokens)?;
         let typed_term = annotate(&term)?;
-        let constraints: HashSet<Constraint> = HashSet::from_iter(collect_constraints(&typed_term));
+        let constraints: HashSet<Constraint> =
+            HashSet::from_iter(
+            collect_constraints(&typed_term)
+                .expect("constraint collection should succeed")
+                .into_iter()
+                .map(without_spans),
+        );
         assert_eq!(
             constraints,
             HashSet::from_iter(vec![
                 // type(fn x => x) === type(x) -> type(x)
-                Constraint {
-                    type1: Type::Placeholder(1),
-                    type2: Type::Function {
+                Constraint::equal(
+                    Rc::new(Type::Placeholder(1)),
+                    Rc::new(Type::Function {
                         parameter_type: Box::from(Type::Placeholder(2)),
-                        return_type: Box::from(Type::Placeholder(2))
-                    }
-                },
-            ])
+                        return_type: Box::from(Type::Placeholder(2)),
+                        effects: Vec::new(),
+                    }),
+                    ConstraintReason::FunctionSignature,
+               
+                    Span::default(),
+                    Span::default(),
+                ),
+            ]
+            .into_iter()
+            .map(without_spans))
         );
         Ok(())
     }
@@ -245,126 +1906,366 @@ mod tests {
     #[test]
     fn test_collect_constraints_for_function_definition_with_function_application(
     ) -> Result<(), String> {
-        let tokens = tokenize("fn x => x + 1")?;
+        let tokens = tokenize_with_spans("fn x => x + 1")?;
         let term = parse(&tokens)?;
         let typed_term = annotate(&term)?;
-        let constraints: HashSet<Constraint> = HashSet::from_iter(collect_constraints(&typed_term));
+        let constraints: HashSet<Constraint> =
+            HashSet::from_iter(
+            collect_constraints(&typed_term)
+                .expect("constraint collection should succeed")
+                .into_iter()
+                .map(without_spans),
+        );
         assert_eq!(
             constraints,
             HashSet::from_iter(vec![
                 // type(fn x => x + 1) === type(x) -> type(x + 1)
-                Constraint {
-                    type1: Type::Placeholder(1),
-                    type2: Type::Function {
+                Constraint::equal(
+                    Rc::new(Type::Placeholder(1)),
+                    Rc::new(Type::Function {
                         parameter_type: Box::from(Type::Placeholder(2)),
-                        return_type: Box::from(Type::Placeholder(3))
-                    }
-                },
+                        return_type: Box::from(Type::Placeholder(3)),
+                        effects: Vec::new(),
+                    }),
+                    ConstraintReason::FunctionSignature,
+               
+                    Span::default(),
+                    Span::default(),
+                ),
                 // type(+ x) === type(1) -> type(+ x 1)
-                Constraint {
-                    type1: Type::Placeholder(4),
-                    type2: Type::Function {
-                        parameter_type: Box::from(Type::Placeholder(6)),
-                        return_type: Box::from(Type::Placeholder(3))
-                    }
-                },
+                Constraint::equal(
+                    Rc::new(Type::Placeholder(4)),
+                    Rc::new(Type::Function {
+                        parameter_type: Box::from(Type::Numeric(6)),
+                        return_type: Box::from(Type::Placeholder(3)),
+                        effects: Vec::new(),
+                    }),
+                    ConstraintReason::ApplicationArgument,
+               
+                    Span::default(),
+                    Span::default(),
+                ),
                 // type(+) === type(x) -> type(+ x)
-                Constraint {
-                    type1: Type::Placeholder(5),
-                    type2: Type::Function {
+                Constraint::equal(
+                    Rc::new(Type::Placeholder(5)),
+                    Rc::new(Type::Function {
                         parameter_type: Box::from(Type::Placeholder(2)),
-                        return_type: Box::from(Type::Placeholder(4))
-                    }
-                },
-                // type(+) === int -> int
-                Constraint {
-                    type1: Type::Placeholder(5),
-                    type2: Type::Function {
+                        return_type: Box::from(Type::Placeholder(4)),
+                        effects: Vec::new(),
+                    }),
+                    ConstraintReason::ApplicationArgument,
+               
+                    Span::default(),
+                    Span::default(),
+                ),
+                // type(+) === int -> int -> int
+                Constraint::equal(
+                    Rc::new(Type::Placeholder(5)),
+                    Rc::new(Type::Function {
                         parameter_type: Box::from(Type::Integer),
-                        return_type: Box::from(Type::Integer)
-                    }
-                },
-                // type(1) === integer
-                Constraint {
-                    type1: Type::Placeholder(6),
-                    type2: Type::Integer,
-                }
-            ])
+                        return_type: Box::from(Type::Function {
+                            parameter_type: Box::from(Type::Integer),
+                            return_type: Box::from(Type::Integer),
+                            effects: Vec::new(),
+                        }),
+                        effects: Vec::new(),
+                    }),
+                    ConstraintReason::BuiltinSignature,
+
+                    Span::default(),
+                    Span::default(),
+                ),
+            ]
+            .into_iter()
+            .map(without_spans))
         );
         Ok(())
     }
 
+    #[test]
+    fn test_collect_constraints_for_function_definition_infers_raise_effects() -> Result<(), String> {
+        let tokens = tokenize_with_spans("fn x => raise 1")?;
+        let term = parse(&tokens)?;
+        let typed_term = annotate(&term)?;
+        let constraints: HashSet<Constraint> =
+            HashSet::from_iter(
+            collect_constraints(&typed_term)
+                .expect("constraint collection should succeed")
+                .into_iter()
+                .map(without_spans),
+        );
+        assert_eq!(
+            constraints,
+            HashSet::from_iter(vec![
+                // type(fn x => raise 1) === type(x) -> never, with the
+                // literal raised in the body recorded as an effect
+                Constraint::equal(
+                    Rc::new(Type::Placeholder(1)),
+                    Rc::new(Type::Function {
+                        parameter_type: Box::from(Type::Placeholder(2)),
+                        return_type: Box::from(Type::Bottom),
+                        effects: vec![String::from("1")],
+                    }),
+                    ConstraintReason::FunctionSignature,
+
+                    Span::default(),
+                    Span::default(),
+                ),
+            ]
+            .into_iter()
+            .map(without_spans))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_collect_constraints_does_not_attribute_a_nested_functions_raise_to_its_enclosing_function(
+    ) -> Result<(), String> {
+        let tokens = tokenize_with_spans("fn x => fn y => raise 1")?;
+        let term = parse(&tokens)?;
+        let typed_term = annotate(&term)?;
+        let constraints = collect_constraints(&typed_term).expect("constraint collection should succeed");
+        let mut effect_sets: Vec<&Vec<String>> = constraints
+            .iter()
+            .filter(|constraint| constraint.reason() == ConstraintReason::FunctionSignature)
+            .map(|constraint| match (constraint.lhs(), constraint.rhs()) {
+                (Type::Function { effects, .. }, _) | (_, Type::Function { effects, .. }) => effects,
+                (other, _) => panic!("expected a function type, got {:?}", other),
+            })
+            .collect();
+        effect_sets.sort();
+        // The outer function's own effects stay empty; only the inner
+        // function directly containing the `raise` picks it up.
+        assert_eq!(effect_sets, vec![&Vec::<String>::new(), &vec![String::from("1")]]);
+        Ok(())
+    }
+
     #[test]
     fn test_collect_constraints_for_let_expression() -> Result<(), String> {
-        let tokens = tokenize("let val inc = fn x => x + 1 in inc(42) end")?;
+        let tokens = tokenize_with_spans("let val inc = fn x => x + 1 in inc(42) end")?;
         let term = parse(&tokens)?;
         let typed_term = annotate(&term)?;
-        let constraints: HashSet<Constraint> = HashSet::from_iter(collect_constraints(&typed_term));
+        let constraints: HashSet<Constraint> =
+            HashSet::from_iter(
+            collect_constraints(&typed_term)
+                .expect("constraint collection should succeed")
+                .into_iter()
+                .map(without_spans),
+        );
         assert_eq!(
             constraints,
             HashSet::from_iter(vec![
                 // type(let...end) === type(inc(42))
-                Constraint {
-                    type1: Type::Placeholder(1),
-                    type2: Type::Placeholder(9),
-                },
+                Constraint::equal(
+                    Rc::new(Type::Placeholder(1)),
+                    Rc::new(Type::Placeholder(9)),
+                    ConstraintReason::LetResult,
+               
+                    Span::default(),
+                    Span::default(),
+                ),
                 // type(inc) === type(fn x => x + 1)
-                Constraint {
-                    type1: Type::Placeholder(2),
-                    type2: Type::Placeholder(3),
-                },
+                Constraint::equal(
+                    Rc::new(Type::Placeholder(2)),
+                    Rc::new(Type::Placeholder(3)),
+                    ConstraintReason::LetBinding,
+               
+                    Span::default(),
+                    Span::default(),
+                ),
                 // type(fn x => x + 1) === type(x) -> type(+ x 1)
-                Constraint {
-                    type1: Type::Placeholder(3),
-                    type2: Type::Function {
+                Constraint::equal(
+                    Rc::new(Type::Placeholder(3)),
+                    Rc::new(Type::Function {
                         parameter_type: Box::from(Type::Placeholder(4)),
-                        return_type: Box::from(Type::Placeholder(5))
-                    }
-                },
+                        return_type: Box::from(Type::Placeholder(5)),
+                        effects: Vec::new(),
+                    }),
+                    ConstraintReason::FunctionSignature,
+               
+                    Span::default(),
+                    Span::default(),
+                ),
                 // type(+ x) === type(1) -> type(+ x 1)
-                Constraint {
-                    type1: Type::Placeholder(6),
-                    type2: Type::Function {
-                        parameter_type: Box::from(Type::Placeholder(8)),
-                        return_type: Box::from(Type::Placeholder(5))
-                    }
-                },
+                Constraint::equal(
+                    Rc::new(Type::Placeholder(6)),
+                    Rc::new(Type::Function {
+                        parameter_type: Box::from(Type::Numeric(8)),
+                        return_type: Box::from(Type::Placeholder(5)),
+                        effects: Vec::new(),
+                    }),
+                    ConstraintReason::ApplicationArgument,
+               
+                    Span::default(),
+                    Span::default(),
+                ),
                 // type(+) === type(x) -> type(+ x)
-                Constraint {
-                    type1: Type::Placeholder(7),
-                    type2: Type::Function {
+                Constraint::equal(
+                    Rc::new(Type::Placeholder(7)),
+                    Rc::new(Type::Function {
                         parameter_type: Box::from(Type::Placeholder(4)),
-                        return_type: Box::from(Type::Placeholder(6))
-                    }
-                },
-                // type(+) === int -> int
-                Constraint {
-                    type1: Type::Placeholder(7),
-                    type2: Type::Function {
+                        return_type: Box::from(Type::Placeholder(6)),
+                        effects: Vec::new(),
+                    }),
+                    ConstraintReason::ApplicationArgument,
+               
+                    Span::default(),
+                    Span::default(),
+                ),
+                // type(+) === int -> int -> int
+                Constraint::equal(
+                    Rc::new(Type::Placeholder(7)),
+                    Rc::new(Type::Function {
                         parameter_type: Box::from(Type::Integer),
-                        return_type: Box::from(Type::Integer)
-                    }
-                },
-                // type(1) === integer
-                Constraint {
-                    type1: Type::Placeholder(8),
-                    type2: Type::Integer,
-                },
-                // type(inc) === type(x) -> type(inc(x))
-                Constraint {
-                    type1: Type::Placeholder(2),
-                    type2: Type::Function {
-                        parameter_type: Box::from(Type::Placeholder(10)),
-                        return_type: Box::from(Type::Placeholder(9))
-                    }
-                },
-                // type(42) === integer
-                Constraint {
-                    type1: Type::Placeholder(10),
-                    type2: Type::Integer,
-                }
-            ])
+                        return_type: Box::from(Type::Function {
+                            parameter_type: Box::from(Type::Integer),
+                            return_type: Box::from(Type::Integer),
+                            effects: Vec::new(),
+                        }),
+                        effects: Vec::new(),
+                    }),
+                    ConstraintReason::BuiltinSignature,
+               
+                    Span::default(),
+                    Span::default(),
+                ),
+                // type(inc) === type(42) -> type(inc(42))
+                Constraint::equal(
+                    Rc::new(Type::Placeholder(2)),
+                    Rc::new(Type::Function {
+                        parameter_type: Box::from(Type::Numeric(10)),
+                        return_type: Box::from(Type::Placeholder(9)),
+                        effects: Vec::new(),
+                    }),
+                    ConstraintReason::ApplicationArgument,
+               
+                    Span::default(),
+                    Span::default(),
+                ),
+            ]
+            .into_iter()
+            .map(without_spans))
         );
         Ok(())
     }
+
+    #[test]
+    fn test_collect_constraints_skips_error_term() -> Result<(), String> {
+        let typed_term = annotate(&Term::new(TermKind::Error, Span::default()))?;
+        assert_eq!(
+            collect_constraints(&typed_term).expect("constraint collection should succeed"),
+            vec![]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_collect_constraints_for_raise_expression() -> Result<(), String> {
+        let tokens = tokenize_with_spans("if true then 0 else raise 1")?;
+        let term = parse(&tokens)?;
+        let typed_term = annotate(&term)?;
+        let constraints: HashSet<Constraint> =
+            HashSet::from_iter(
+            collect_constraints(&typed_term)
+                .expect("constraint collection should succeed")
+                .into_iter()
+                .map(without_spans),
+        );
+        assert_eq!(
+            constraints,
+            HashSet::from_iter(vec![
+                // type(if true then 0 else raise 1) === type(0)
+                Constraint::equal(
+                    Rc::new(Type::Placeholder(1)),
+                    Rc::new(Type::Numeric(3)),
+                    ConstraintReason::BranchesMustMatch,
+               
+                    Span::default(),
+                    Span::default(),
+                ),
+                // type(if true then 0 else raise 1) === type(raise 1), which
+                // is `Type::Bottom` and unifies with anything, so it does not
+                // pollute the other branch's inferred type.
+                Constraint::equal(
+                    Rc::new(Type::Placeholder(1)),
+                    Rc::new(Type::Bottom),
+                    ConstraintReason::BranchesMustMatch,
+               
+                    Span::default(),
+                    Span::default(),
+                ),
+                // type(true) === bool, required by the `if`'s condition
+                Constraint::equal(
+                    Rc::new(Type::Placeholder(2)),
+                    Rc::new(Type::Boolean),
+                    ConstraintReason::IfConditionBool,
+               
+                    Span::default(),
+                    Span::default(),
+                ),
+                // type(true) === bool, from `true` itself being a boolean literal
+                Constraint::equal(
+                    Rc::new(Type::Placeholder(2)),
+                    Rc::new(Type::Boolean),
+                    ConstraintReason::BooleanLiteral,
+
+                    Span::default(),
+                    Span::default(),
+                ),
+            ]
+            .into_iter()
+            .map(without_spans))
+        );
+        Ok(())
+    }
+
+    // Regresses `collect_constraints_with_bindings` recursing once per AST
+    // node: it is built directly (not via `tokenize`/`parse`/`annotate`,
+    // which recurse themselves and would overflow first) as a chain of
+    // 100,000 nested `raise` expressions around a single bound identifier.
+    #[test]
+    fn test_collect_constraints_does_not_overflow_on_a_deeply_nested_term() {
+        // Dropping a 100,000-deep chain of `Box<TypedTerm>` recurses just
+        // like the old constraint collector did, so this needs a larger
+        // stack than the test harness's default even though
+        // `collect_constraints_with_bindings` itself no longer recurses.
+        std::thread::Builder::new()
+            .stack_size(256 * 1024 * 1024)
+            .spawn(|| {
+                const DEPTH: usize = 100_000;
+                let mut term = TypedTerm {
+                    ty: Rc::new(Type::Boolean),
+                    kind: TypedTermKind::Identifier(String::from("x")),
+                    span: Span::default(),
+                };
+                for _ in 0..DEPTH {
+                    term = TypedTerm {
+                        ty: Rc::new(Type::Bottom),
+                        kind: TypedTermKind::RaiseExpression {
+                            exception: Box::from(term),
+                        },
+                        span: Span::default(),
+                    };
+                }
+
+                let mut env = TypeEnv::new();
+                env.insert("x", Type::Boolean);
+                let constraints = collect_constraints_with_env(&term, &env)
+                    .expect("constraint collection should succeed");
+
+                assert_eq!(
+                    constraints,
+                    vec![Constraint::equal(
+                        Rc::new(Type::Boolean),
+                        Rc::new(Type::Boolean),
+                        ConstraintReason::BuiltinSignature,
+                        Span::default(),
+                        Span::default(),
+                    )]
+                );
+            })
+            .expect("failed to spawn thread")
+            .join()
+            .expect("thread panicked");
+    }
 }
@@ -0,0 +1,522 @@
+use crate::parser::{PrecedenceTable, Term, TermKind};
+use crate::tokenizer::{tokenize_lossless, Span, Token, TriviaToken};
+
+/// Identifies which grammar production a [`SyntaxNode`] was built from.
+///
+/// Mirrors [`TermKind`], minus the recovery-only `Error` variant: the CST is
+/// built directly from source text, so there is nothing to recover from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyntaxKind {
+    Boolean,
+    FunctionApplication,
+    FunctionDefinition,
+    Identifier,
+    IfExpression,
+    Integer,
+    LetExpression,
+    RaiseExpression,
+    /// The single top-level expression, plus whatever trivia trails it.
+    Root,
+}
+
+/// A leaf or interior element of the lossless syntax tree.
+#[derive(Debug, PartialEq)]
+pub enum SyntaxElement {
+    /// A single source token, complete with the trivia (whitespace,
+    /// comments) that preceded it and its exact source text.
+    Token(TriviaToken),
+    /// A further syntax node.
+    Node(SyntaxNode),
+    /// Trivia with no following token to attach to, e.g. a trailing comment
+    /// at the end of the input.
+    Trivia(String),
+}
+
+impl SyntaxElement {
+    /// Reconstructs the exact source text this element was built from,
+    /// trivia included.
+    pub fn text(&self) -> String {
+        match self {
+            SyntaxElement::Token(token) => format!("{}{}", token.leading_trivia, token.text),
+            SyntaxElement::Node(node) => node.text(),
+            SyntaxElement::Trivia(trivia) => trivia.clone(),
+        }
+    }
+}
+
+/// An interior node of the lossless syntax tree: every token that went into
+/// the production it represents, in source order, so a formatter or
+/// refactoring tool can either reconstruct the exact source text or replace
+/// one child without disturbing the trivia around the others.
+#[derive(Debug, PartialEq)]
+pub struct SyntaxNode {
+    pub kind: SyntaxKind,
+    pub children: Vec<SyntaxElement>,
+}
+
+impl SyntaxNode {
+    /// Reconstructs the exact source text this node spans, trivia included.
+    pub fn text(&self) -> String {
+        self.children.iter().map(SyntaxElement::text).collect()
+    }
+
+    /// The [`Span`] covering every token under this node, or [`Span::default`]
+    /// if it has none (which never happens for a node produced by
+    /// [`parse_cst`], only for a hand-built one).
+    pub fn span(&self) -> Span {
+        let tokens = self.tokens();
+        match (tokens.first(), tokens.last()) {
+            (Some(first), Some(last)) => Span {
+                start: first.span.start,
+                end: last.span.end,
+                line: first.span.line,
+                column: first.span.column,
+            },
+            _ => Span::default(),
+        }
+    }
+
+    fn tokens(&self) -> Vec<&TriviaToken> {
+        self.children
+            .iter()
+            .flat_map(|child| match child {
+                SyntaxElement::Token(token) => vec![token],
+                SyntaxElement::Node(node) => node.tokens(),
+                SyntaxElement::Trivia(_) => Vec::new(),
+            })
+            .collect()
+    }
+
+    fn nodes(&self) -> Vec<&SyntaxNode> {
+        self.children
+            .iter()
+            .filter_map(|child| match child {
+                SyntaxElement::Node(node) => Some(node),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Parses `input` into a lossless [`SyntaxNode`] tree using the default
+/// operator [`PrecedenceTable`], preserving every token and every piece of
+/// trivia between them.
+///
+/// This is a simplified grammar alongside [`parse`](crate::parser::parse):
+/// it accepts the same surface syntax with the default precedence table,
+/// but reports errors as a plain message instead of a structured
+/// [`ParseError`](crate::parser::ParseError) and has no recovery mode, since
+/// its purpose is preserving source text for round-tripping rather than
+/// diagnosing broken input.
+pub fn parse_cst(input: &str) -> Result<SyntaxNode, String> {
+    let lossless = tokenize_lossless(input)?;
+    let mut cursor = Cursor {
+        tokens: lossless.tokens.into_iter().peekable(),
+    };
+    let table = PrecedenceTable::default();
+    let expression = parse_expression(&mut cursor, &table)?;
+    if let Some(token) = cursor.tokens.peek() {
+        return Err(format!("unexpected trailing token: {:?}", token.token));
+    }
+    let mut children = vec![SyntaxElement::Node(expression)];
+    if !lossless.trailing_trivia.is_empty() {
+        children.push(SyntaxElement::Trivia(lossless.trailing_trivia));
+    }
+    Ok(SyntaxNode {
+        kind: SyntaxKind::Root,
+        children,
+    })
+}
+
+/// Lowers a lossless [`SyntaxNode`] tree into the core [`Term`] language,
+/// discarding trivia but keeping each node's [`Span`].
+pub fn lower(node: &SyntaxNode) -> Term {
+    let span = node.span();
+    let kind = match node.kind {
+        SyntaxKind::Root => return lower(node.nodes()[0]),
+        SyntaxKind::Boolean => TermKind::Boolean(boolean_value(node)),
+        SyntaxKind::Integer => TermKind::Integer(integer_value(node)),
+        SyntaxKind::Identifier => TermKind::Identifier(identifier_name(node)),
+        SyntaxKind::FunctionApplication => {
+            let children = node.nodes();
+            match children.len() {
+                // Explicit call syntax `f(x)`: the node's two children are
+                // already the function and the argument, in that order.
+                2 => TermKind::FunctionApplication {
+                    function: Box::from(lower(children[0])),
+                    argument: Box::from(lower(children[1])),
+                },
+                // Binary operator sugar `left op right`: the source order
+                // is operand, operator, operand, but the term it lowers to
+                // is the curried application `op(left)(right)`, matching
+                // parse_binary_expression's `Term` construction.
+                3 => {
+                    let (left, operator, right) = (children[0], children[1], children[2]);
+                    TermKind::FunctionApplication {
+                        function: Box::from(Term::new(
+                            TermKind::FunctionApplication {
+                                function: Box::from(lower(operator)),
+                                argument: Box::from(lower(left)),
+                            },
+                            span,
+                        )),
+                        argument: Box::from(lower(right)),
+                    }
+                }
+                other => unreachable!("FunctionApplication node with {} children", other),
+            }
+        }
+        SyntaxKind::FunctionDefinition => {
+            let children = node.nodes();
+            TermKind::FunctionDefinition {
+                parameter: Box::from(lower(children[0])),
+                body: Box::from(lower(children[1])),
+            }
+        }
+        SyntaxKind::IfExpression => {
+            let children = node.nodes();
+            TermKind::IfExpression {
+                condition: Box::from(lower(children[0])),
+                true_branch: Box::from(lower(children[1])),
+                false_branch: Box::from(lower(children[2])),
+            }
+        }
+        SyntaxKind::LetExpression => {
+            let children = node.nodes();
+            TermKind::LetExpression {
+                declaration_name: Box::from(lower(children[0])),
+                declaration_value: Box::from(lower(children[1])),
+                expression: Box::from(lower(children[2])),
+            }
+        }
+        SyntaxKind::RaiseExpression => {
+            let children = node.nodes();
+            TermKind::RaiseExpression {
+                exception: Box::from(lower(children[0])),
+            }
+        }
+    };
+    Term::new(kind, span)
+}
+
+fn boolean_value(node: &SyntaxNode) -> bool {
+    node.tokens()
+        .into_iter()
+        .find_map(|token| match token.token {
+            Token::Boolean(value) => Some(value),
+            _ => None,
+        })
+        .expect("Boolean node has no Token::Boolean child")
+}
+
+fn integer_value(node: &SyntaxNode) -> i32 {
+    let is_negated = node
+        .tokens()
+        .first()
+        .map(|token| token.token == Token::Minus)
+        .unwrap_or(false);
+    let value = node
+        .tokens()
+        .into_iter()
+        .find_map(|token| match token.token {
+            Token::Integer(value) => Some(value),
+            _ => None,
+        })
+        .expect("Integer node has no Token::Integer child");
+    if is_negated {
+        -value
+    } else {
+        value
+    }
+}
+
+// An Identifier node always wraps exactly one token: either a real
+// `Token::Identifier`, or (for the synthetic identifier nodes
+// `parse_binary_expression` builds for operators) the operator token
+// itself, whose exact source text is already the name the token-based
+// parser assigns it (see `operator_name` in the parser module).
+fn identifier_name(node: &SyntaxNode) -> String {
+    node.tokens()
+        .first()
+        .expect("Identifier node has no token child")
+        .text
+        .clone()
+}
+
+struct Cursor {
+    tokens: std::iter::Peekable<std::vec::IntoIter<TriviaToken>>,
+}
+
+impl Cursor {
+    fn peek(&mut self) -> Option<&Token> {
+        self.tokens.peek().map(|token| &token.token)
+    }
+
+    fn advance(&mut self) -> Result<TriviaToken, String> {
+        self.tokens
+            .next()
+            .ok_or_else(|| String::from("unexpected end of input"))
+    }
+
+    fn expect(&mut self, expected: &Token) -> Result<TriviaToken, String> {
+        let token = self.advance()?;
+        if &token.token == expected {
+            Ok(token)
+        } else {
+            Err(format!("expected {:?} but found {:?}", expected, token.token))
+        }
+    }
+}
+
+fn parse_expression(cursor: &mut Cursor, table: &PrecedenceTable) -> Result<SyntaxNode, String> {
+    match cursor.peek() {
+        Some(Token::KeywordFn) => parse_function_definition(cursor, table),
+        Some(Token::KeywordIf) => parse_if_expression(cursor, table),
+        Some(Token::KeywordLet) => parse_let_expression(cursor, table),
+        Some(Token::KeywordRaise) => parse_raise_expression(cursor, table),
+        _ => parse_binary_expression(cursor, table, 0),
+    }
+}
+
+fn parse_function_definition(
+    cursor: &mut Cursor,
+    table: &PrecedenceTable,
+) -> Result<SyntaxNode, String> {
+    let fn_token = cursor.expect(&Token::KeywordFn)?;
+    let parameter = parse_identifier(cursor)?;
+    let arrow_token = cursor.expect(&Token::Arrow)?;
+    let body = parse_expression(cursor, table)?;
+    Ok(SyntaxNode {
+        kind: SyntaxKind::FunctionDefinition,
+        children: vec![
+            SyntaxElement::Token(fn_token),
+            SyntaxElement::Node(parameter),
+            SyntaxElement::Token(arrow_token),
+            SyntaxElement::Node(body),
+        ],
+    })
+}
+
+fn parse_if_expression(cursor: &mut Cursor, table: &PrecedenceTable) -> Result<SyntaxNode, String> {
+    let if_token = cursor.expect(&Token::KeywordIf)?;
+    let condition = parse_expression(cursor, table)?;
+    let then_token = cursor.expect(&Token::KeywordThen)?;
+    let true_branch = parse_expression(cursor, table)?;
+    let else_token = cursor.expect(&Token::KeywordElse)?;
+    let false_branch = parse_expression(cursor, table)?;
+    Ok(SyntaxNode {
+        kind: SyntaxKind::IfExpression,
+        children: vec![
+            SyntaxElement::Token(if_token),
+            SyntaxElement::Node(condition),
+            SyntaxElement::Token(then_token),
+            SyntaxElement::Node(true_branch),
+            SyntaxElement::Token(else_token),
+            SyntaxElement::Node(false_branch),
+        ],
+    })
+}
+
+fn parse_let_expression(
+    cursor: &mut Cursor,
+    table: &PrecedenceTable,
+) -> Result<SyntaxNode, String> {
+    let let_token = cursor.expect(&Token::KeywordLet)?;
+    let val_token = cursor.expect(&Token::KeywordVal)?;
+    let declaration_name = parse_identifier(cursor)?;
+    let equals_token = cursor.expect(&Token::Equals)?;
+    let declaration_value = parse_expression(cursor, table)?;
+    let in_token = cursor.expect(&Token::KeywordIn)?;
+    let expression = parse_expression(cursor, table)?;
+    let end_token = cursor.expect(&Token::KeywordEnd)?;
+    Ok(SyntaxNode {
+        kind: SyntaxKind::LetExpression,
+        children: vec![
+            SyntaxElement::Token(let_token),
+            SyntaxElement::Token(val_token),
+            SyntaxElement::Node(declaration_name),
+            SyntaxElement::Token(equals_token),
+            SyntaxElement::Node(declaration_value),
+            SyntaxElement::Token(in_token),
+            SyntaxElement::Node(expression),
+            SyntaxElement::Token(end_token),
+        ],
+    })
+}
+
+fn parse_raise_expression(
+    cursor: &mut Cursor,
+    table: &PrecedenceTable,
+) -> Result<SyntaxNode, String> {
+    let raise_token = cursor.expect(&Token::KeywordRaise)?;
+    let exception = parse_expression(cursor, table)?;
+    Ok(SyntaxNode {
+        kind: SyntaxKind::RaiseExpression,
+        children: vec![
+            SyntaxElement::Token(raise_token),
+            SyntaxElement::Node(exception),
+        ],
+    })
+}
+
+fn parse_identifier(cursor: &mut Cursor) -> Result<SyntaxNode, String> {
+    let token = cursor.advance()?;
+    match token.token {
+        Token::Identifier(_) => Ok(SyntaxNode {
+            kind: SyntaxKind::Identifier,
+            children: vec![SyntaxElement::Token(token)],
+        }),
+        _ => Err(format!("expected identifier but found {:?}", token.token)),
+    }
+}
+
+fn parse_binary_expression(
+    cursor: &mut Cursor,
+    table: &PrecedenceTable,
+    min_precedence: u8,
+) -> Result<SyntaxNode, String> {
+    let mut left = parse_primary(cursor, table)?;
+    while let Some(token) = cursor.peek() {
+        let operator = match table.get(token) {
+            Some(info) if info.precedence >= min_precedence => token.clone(),
+            _ => break,
+        };
+        let info = table.get(&operator).unwrap();
+        let operator_token = cursor.advance()?;
+        let operator_node = SyntaxNode {
+            kind: SyntaxKind::Identifier,
+            children: vec![SyntaxElement::Token(operator_token)],
+        };
+        let next_min_precedence = match info.associativity {
+            crate::parser::Associativity::Left => info.precedence + 1,
+            crate::parser::Associativity::Right => info.precedence,
+        };
+        let right = parse_binary_expression(cursor, table, next_min_precedence)?;
+        // Children are kept in source order (operand, operator, operand)
+        // so `SyntaxNode::text` round-trips correctly; `lower` recognizes
+        // this three-node shape and rebuilds the curried `Term` it means.
+        left = SyntaxNode {
+            kind: SyntaxKind::FunctionApplication,
+            children: vec![
+                SyntaxElement::Node(left),
+                SyntaxElement::Node(operator_node),
+                SyntaxElement::Node(right),
+            ],
+        };
+    }
+    Ok(left)
+}
+
+fn parse_primary(cursor: &mut Cursor, table: &PrecedenceTable) -> Result<SyntaxNode, String> {
+    match cursor.peek() {
+        Some(Token::Boolean(_)) => {
+            let token = cursor.advance()?;
+            Ok(SyntaxNode {
+                kind: SyntaxKind::Boolean,
+                children: vec![SyntaxElement::Token(token)],
+            })
+        }
+        Some(Token::Integer(_)) => {
+            let token = cursor.advance()?;
+            Ok(SyntaxNode {
+                kind: SyntaxKind::Integer,
+                children: vec![SyntaxElement::Token(token)],
+            })
+        }
+        Some(Token::Minus) => {
+            let minus_token = cursor.advance()?;
+            if !matches!(cursor.peek(), Some(Token::Integer(_))) {
+                return Err(String::from("expected integer after unary `-`"));
+            }
+            let integer_token = cursor.advance()?;
+            Ok(SyntaxNode {
+                kind: SyntaxKind::Integer,
+                children: vec![
+                    SyntaxElement::Token(minus_token),
+                    SyntaxElement::Token(integer_token),
+                ],
+            })
+        }
+        Some(Token::Identifier(_)) => {
+            let identifier_token = cursor.advance()?;
+            match cursor.peek() {
+                Some(Token::LeftParenthesis) => {
+                    let left_paren = cursor.advance()?;
+                    let argument = parse_expression(cursor, table)?;
+                    let right_paren = cursor.expect(&Token::RightParenthesis)?;
+                    Ok(SyntaxNode {
+                        kind: SyntaxKind::FunctionApplication,
+                        children: vec![
+                            SyntaxElement::Node(SyntaxNode {
+                                kind: SyntaxKind::Identifier,
+                                children: vec![SyntaxElement::Token(identifier_token)],
+                            }),
+                            SyntaxElement::Token(left_paren),
+                            SyntaxElement::Node(argument),
+                            SyntaxElement::Token(right_paren),
+                        ],
+                    })
+                }
+                _ => Ok(SyntaxNode {
+                    kind: SyntaxKind::Identifier,
+                    children: vec![SyntaxElement::Token(identifier_token)],
+                }),
+            }
+        }
+        other => Err(format!("unexpected token: {:?}", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cst::{lower, parse_cst};
+    use crate::parser::parse;
+    use crate::tokenizer::tokenize_with_spans;
+
+    #[test]
+    fn test_parse_cst_round_trips_the_exact_source_text() {
+        let input = "  fn x => x + 1  -- trailing comment";
+        let node = parse_cst(input).unwrap();
+        assert_eq!(node.text(), input);
+    }
+
+    #[test]
+    fn test_parse_cst_preserves_a_leading_comment() {
+        let input = "(* explains the literal *) 42";
+        let node = parse_cst(input).unwrap();
+        assert_eq!(node.text(), input);
+    }
+
+    #[test]
+    fn test_lower_matches_the_token_based_parser_for_an_arithmetic_expression() -> Result<(), String>
+    {
+        let input = "fn x => x + 1";
+        let tokens = tokenize_with_spans(input)?;
+        let expected = parse(&tokens)?;
+        let node = parse_cst(input)?;
+        assert_eq!(lower(&node), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_lower_matches_the_token_based_parser_for_if_and_let() -> Result<(), String> {
+        for input in [
+            "if true then 0 else 1",
+            "let val x = 1 in x end",
+            "raise 0",
+            "f(1)",
+            "-42",
+        ] {
+            let tokens = tokenize_with_spans(input)?;
+            let expected = parse(&tokens)?;
+            let node = parse_cst(input)?;
+            assert_eq!(lower(&node), expected, "input: {}", input);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_cst_reports_trailing_input_as_an_error() {
+        assert!(parse_cst("1 2").is_err());
+    }
+}
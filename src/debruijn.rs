@@ -0,0 +1,233 @@
+use crate::parser::{Term, TermKind};
+
+/// A locally nameless representation of [`Term`]: variables bound by an
+/// enclosing `fn` or `let` are [`DebruijnTerm::Bound`] indices counting the
+/// number of binders between the occurrence and the one that introduces it,
+/// while variables with no enclosing binder stay [`DebruijnTerm::Free`] by
+/// name. Binders themselves no longer carry a parameter name at all, since
+/// nothing inside them needs one to refer back.
+///
+/// Two terms that are alpha-equivalent as [`Term`]s convert to the *same*
+/// `DebruijnTerm`, and comparing two `DebruijnTerm`s with [`PartialEq`] is
+/// exactly [`Term::alpha_eq`] on their named originals — the representation
+/// this module exists to provide.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DebruijnTerm {
+    Boolean(bool),
+    Bound(u32),
+    Error,
+    Free(String),
+    FunctionApplication {
+        function: Box<DebruijnTerm>,
+        argument: Box<DebruijnTerm>,
+    },
+    FunctionDefinition {
+        body: Box<DebruijnTerm>,
+    },
+    IfExpression {
+        condition: Box<DebruijnTerm>,
+        true_branch: Box<DebruijnTerm>,
+        false_branch: Box<DebruijnTerm>,
+    },
+    Integer(i32),
+    LetExpression {
+        declaration_value: Box<DebruijnTerm>,
+        expression: Box<DebruijnTerm>,
+    },
+    RaiseExpression {
+        exception: Box<DebruijnTerm>,
+    },
+}
+
+/// Converts a named [`Term`] to its locally nameless [`DebruijnTerm`] form.
+pub fn to_debruijn(term: &Term) -> DebruijnTerm {
+    to_debruijn_in_scope(term, &mut Vec::new())
+}
+
+fn to_debruijn_in_scope(term: &Term, bound_names: &mut Vec<String>) -> DebruijnTerm {
+    match &term.kind {
+        TermKind::Boolean(value) => DebruijnTerm::Boolean(*value),
+        TermKind::Error => DebruijnTerm::Error,
+        TermKind::Integer(value) => DebruijnTerm::Integer(*value),
+        TermKind::Identifier(name) => match bound_names.iter().rev().position(|bound| bound == name) {
+            Some(index) => DebruijnTerm::Bound(index as u32),
+            None => DebruijnTerm::Free(name.clone()),
+        },
+        TermKind::FunctionApplication { function, argument } => DebruijnTerm::FunctionApplication {
+            function: Box::from(to_debruijn_in_scope(function, bound_names)),
+            argument: Box::from(to_debruijn_in_scope(argument, bound_names)),
+        },
+        TermKind::FunctionDefinition { parameter, body } => {
+            bound_names.push(binder_name(parameter));
+            let body = to_debruijn_in_scope(body, bound_names);
+            bound_names.pop();
+            DebruijnTerm::FunctionDefinition {
+                body: Box::from(body),
+            }
+        }
+        TermKind::IfExpression {
+            condition,
+            true_branch,
+            false_branch,
+        } => DebruijnTerm::IfExpression {
+            condition: Box::from(to_debruijn_in_scope(condition, bound_names)),
+            true_branch: Box::from(to_debruijn_in_scope(true_branch, bound_names)),
+            false_branch: Box::from(to_debruijn_in_scope(false_branch, bound_names)),
+        },
+        TermKind::LetExpression {
+            declaration_name,
+            declaration_value,
+            expression,
+        } => {
+            let declaration_value = to_debruijn_in_scope(declaration_value, bound_names);
+            bound_names.push(binder_name(declaration_name));
+            let expression = to_debruijn_in_scope(expression, bound_names);
+            bound_names.pop();
+            DebruijnTerm::LetExpression {
+                declaration_value: Box::from(declaration_value),
+                expression: Box::from(expression),
+            }
+        }
+        TermKind::RaiseExpression { exception } => DebruijnTerm::RaiseExpression {
+            exception: Box::from(to_debruijn_in_scope(exception, bound_names)),
+        },
+    }
+}
+
+fn binder_name(binder: &Term) -> String {
+    match &binder.kind {
+        TermKind::Identifier(name) => name.clone(),
+        other => unreachable!("binder is always an identifier, got {:?}", other),
+    }
+}
+
+/// Converts a [`DebruijnTerm`] back to a named [`Term`], inventing a fresh
+/// name for every binder since the nameless form doesn't remember the ones
+/// it started with. The generated names are drawn from a counter rather
+/// than reused source text, so they can't collide with each other or with
+/// any free variable already present in the term.
+pub fn from_debruijn(term: &DebruijnTerm) -> Term {
+    let mut names = Vec::new();
+    let mut next_name = 0;
+    from_debruijn_in_scope(term, &mut names, &mut next_name)
+}
+
+fn from_debruijn_in_scope(term: &DebruijnTerm, names: &mut Vec<String>, next_name: &mut u32) -> Term {
+    match term {
+        DebruijnTerm::Boolean(value) => Term::boolean(*value),
+        DebruijnTerm::Error => Term::error(),
+        DebruijnTerm::Integer(value) => Term::integer(*value),
+        DebruijnTerm::Free(name) => Term::identifier(name.clone()),
+        DebruijnTerm::Bound(index) => {
+            let name = names
+                .iter()
+                .rev()
+                .nth(*index as usize)
+                .expect("De Bruijn index has no enclosing binder");
+            Term::identifier(name.clone())
+        }
+        DebruijnTerm::FunctionApplication { function, argument } => Term::app(
+            from_debruijn_in_scope(function, names, next_name),
+            from_debruijn_in_scope(argument, names, next_name),
+        ),
+        DebruijnTerm::FunctionDefinition { body } => {
+            let parameter = fresh_name(next_name);
+            names.push(parameter.clone());
+            let body = from_debruijn_in_scope(body, names, next_name);
+            names.pop();
+            Term::lambda(parameter, body)
+        }
+        DebruijnTerm::IfExpression {
+            condition,
+            true_branch,
+            false_branch,
+        } => Term::if_then_else(
+            from_debruijn_in_scope(condition, names, next_name),
+            from_debruijn_in_scope(true_branch, names, next_name),
+            from_debruijn_in_scope(false_branch, names, next_name),
+        ),
+        DebruijnTerm::LetExpression {
+            declaration_value,
+            expression,
+        } => {
+            let declaration_value = from_debruijn_in_scope(declaration_value, names, next_name);
+            let declaration_name = fresh_name(next_name);
+            names.push(declaration_name.clone());
+            let expression = from_debruijn_in_scope(expression, names, next_name);
+            names.pop();
+            Term::let_in(declaration_name, declaration_value, expression)
+        }
+        DebruijnTerm::RaiseExpression { exception } => {
+            Term::raise(from_debruijn_in_scope(exception, names, next_name))
+        }
+    }
+}
+
+fn fresh_name(next_name: &mut u32) -> String {
+    let name = format!("_v{}", next_name);
+    *next_name += 1;
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::debruijn::{from_debruijn, to_debruijn, DebruijnTerm};
+    use crate::parser::Term;
+
+    #[test]
+    fn test_to_debruijn_indexes_a_bound_variable_by_its_binder_distance() {
+        assert_eq!(
+            to_debruijn(&Term::lambda("x", Term::identifier("x"))),
+            DebruijnTerm::FunctionDefinition {
+                body: Box::from(DebruijnTerm::Bound(0)),
+            }
+        );
+    }
+
+    #[test]
+    fn test_to_debruijn_counts_outward_through_nested_binders() {
+        // In `fn x => fn y => x`, `x` is bound one level out from where it's
+        // used, so it converts to index 1, not 0.
+        let term = Term::lambda("x", Term::lambda("y", Term::identifier("x")));
+        assert_eq!(
+            to_debruijn(&term),
+            DebruijnTerm::FunctionDefinition {
+                body: Box::from(DebruijnTerm::FunctionDefinition {
+                    body: Box::from(DebruijnTerm::Bound(1)),
+                }),
+            }
+        );
+    }
+
+    #[test]
+    fn test_to_debruijn_leaves_an_unbound_identifier_free() {
+        let term = Term::lambda("x", Term::identifier("y"));
+        assert_eq!(
+            to_debruijn(&term),
+            DebruijnTerm::FunctionDefinition {
+                body: Box::from(DebruijnTerm::Free(String::from("y"))),
+            }
+        );
+    }
+
+    #[test]
+    fn test_to_debruijn_treats_alpha_equivalent_terms_as_equal() {
+        let refers_to_outer = Term::lambda("x", Term::lambda("y", Term::identifier("x")));
+        let renamed = Term::lambda("a", Term::lambda("b", Term::identifier("a")));
+        assert_eq!(to_debruijn(&refers_to_outer), to_debruijn(&renamed));
+    }
+
+    #[test]
+    fn test_from_debruijn_round_trips_to_an_alpha_equivalent_term() {
+        let term = Term::lambda("x", Term::lambda("y", Term::identifier("x")));
+        let round_tripped = from_debruijn(&to_debruijn(&term));
+        assert!(term.alpha_eq(&round_tripped));
+    }
+
+    #[test]
+    fn test_from_debruijn_preserves_a_let_expressions_free_declaration_value() {
+        let term = Term::let_in("x", Term::identifier("y"), Term::identifier("x"));
+        let round_tripped = from_debruijn(&to_debruijn(&term));
+        assert!(term.alpha_eq(&round_tripped));
+    }
+}
@@ -0,0 +1,87 @@
+use crate::parser::{Term, TermFolder};
+
+/// Lowers a [`Term`] fully into the core language the annotator and
+/// constraint generator understand, so those passes never have to special-
+/// case surface sugar themselves.
+///
+/// The grammar [`parse`](crate::parser::parse) accepts today has no sugar of
+/// its own — no multi-parameter `fn`, no `fun` clauses, no operator
+/// sections, no `;` sequencing — so this is currently a structural identity
+/// pass over already-core terms. It exists as the single place such forms
+/// will lower once the tokenizer and parser grow them, and as the pass that
+/// [`sequence`] and [`lambda_many`] terms (built directly, without going
+/// through the string front end) are run through before annotation.
+pub fn desugar(term: &Term) -> Term {
+    struct Desugarer;
+    impl TermFolder for Desugarer {}
+    Desugarer.fold_term(term)
+}
+
+/// Builds `first; second`, sugar for evaluating `first` and discarding its
+/// result before evaluating `second`, desugared immediately to
+/// `let val _ = first in second end` since the grammar has no sequencing
+/// operator of its own.
+pub fn sequence(first: Term, second: Term) -> Term {
+    Term::let_in("_", first, second)
+}
+
+/// Builds a curried multi-parameter function `fn p1 p2 ... => body`, sugar
+/// for `fn p1 => fn p2 => ... => body`, desugared immediately since the
+/// grammar only parses one parameter per `fn`.
+///
+/// Panics if `parameters` is empty; a function needs at least one
+/// parameter to curry over.
+pub fn lambda_many(parameters: &[&str], body: Term) -> Term {
+    let mut parameters = parameters.iter().rev();
+    let last = parameters
+        .next()
+        .expect("lambda_many requires at least one parameter");
+    parameters.fold(Term::lambda(*last, body), |body, parameter| {
+        Term::lambda(*parameter, body)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::desugar::{desugar, lambda_many, sequence};
+    use crate::parser::{parse, Term};
+    use crate::tokenizer::tokenize_with_spans;
+
+    #[test]
+    fn test_desugar_is_the_identity_on_already_core_terms() -> Result<(), String> {
+        let tokens = tokenize_with_spans("fn x => x + 1")?;
+        let term = parse(&tokens)?;
+        assert_eq!(desugar(&term), term);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sequence_desugars_to_a_let_binding_that_discards_its_value() {
+        assert_eq!(
+            sequence(Term::integer(1), Term::integer(2)),
+            Term::let_in("_", Term::integer(1), Term::integer(2))
+        );
+    }
+
+    #[test]
+    fn test_lambda_many_curries_its_parameters() {
+        assert_eq!(
+            lambda_many(&["x", "y"], Term::identifier("x")),
+            Term::lambda("x", Term::lambda("y", Term::identifier("x")))
+        );
+    }
+
+    #[test]
+    fn test_lambda_many_with_a_single_parameter_matches_lambda() {
+        assert_eq!(
+            lambda_many(&["x"], Term::identifier("x")),
+            Term::lambda("x", Term::identifier("x"))
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_lambda_many_panics_with_no_parameters() {
+        lambda_many(&[], Term::integer(0));
+    }
+}
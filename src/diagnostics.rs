@@ -0,0 +1,278 @@
+//! Rustc-style rendering of a [`TypeError`] against the original source
+//! text: the offending line(s) with caret underlines under each relevant
+//! [`Span`], plus a provenance note tracing where a conflicting type came
+//! from, using [`crate::unifier::explain_steps`].
+
+use crate::annotator::diff_types;
+use crate::constraint::{Constraint, TypeError};
+use crate::tokenizer::Span;
+use crate::unifier::explain_steps;
+use std::io::IsTerminal;
+use std::str::FromStr;
+
+/// When [`render_with_options`] should emit ANSI color escapes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+    /// Always emit color escapes, even when stdout isn't a terminal —
+    /// e.g. a user piping into `less -R`.
+    Always,
+    /// Never emit color escapes, so output stays plain when piped into a
+    /// file or compared against in a test.
+    Never,
+    /// Emit color escapes only when stdout is a terminal.
+    Auto,
+}
+
+impl ColorMode {
+    fn enabled(self) -> bool {
+        match self {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            ColorMode::Auto => std::io::stdout().is_terminal(),
+        }
+    }
+}
+
+impl FromStr for ColorMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, String> {
+        match value {
+            "always" => Ok(ColorMode::Always),
+            "never" => Ok(ColorMode::Never),
+            "auto" => Ok(ColorMode::Auto),
+            other => Err(format!(
+                "invalid color mode `{}` (expected `always`, `never`, or `auto`)",
+                other
+            )),
+        }
+    }
+}
+
+/// Options controlling how [`render_with_options`] renders a diagnostic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderOptions {
+    pub color: ColorMode,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions { color: ColorMode::Auto }
+    }
+}
+
+/// Renders `error` as a multi-line, human-readable report against
+/// `source`, in the style of rustc's diagnostics, using the default
+/// [`RenderOptions`] (colored only when stdout is a terminal).
+pub fn render(source: &str, error: &TypeError, constraints: &[Constraint]) -> String {
+    render_with_options(source, error, constraints, RenderOptions::default())
+}
+
+/// Like [`render`], but with explicit control over color via `options`,
+/// e.g. to honor a `--color=always|never|auto` flag or to force plain
+/// output when piping into a file or comparing against in a test.
+///
+/// The report has a header naming the error's [`TypeError::code`], a
+/// snippet of the offending line(s) with a caret underline, and — for a
+/// [`TypeError::TypeMismatch`] whose conflicting sides can be traced back
+/// through `constraints` — a note showing where each side's type was
+/// pinned down. A [`TypeError::TypeMismatch`] between two structured types
+/// is narrowed with [`diff_types`] first, so the header names only the
+/// sub-component that actually differs (e.g. a function's return type)
+/// instead of printing both types in full and leaving the reader to spot
+/// the difference.
+pub fn render_with_options(
+    source: &str,
+    error: &TypeError,
+    constraints: &[Constraint],
+    options: RenderOptions,
+) -> String {
+    let color = options.color.enabled();
+    let mut report = match error {
+        TypeError::UnboundIdentifier { name, span, suggestion } => format!(
+            "{}: unbound identifier `{}`{}\n{}",
+            paint_error_header(error.code(), color),
+            name,
+            suggestion
+                .as_ref()
+                .map(|suggestion| format!("; did you mean `{}`?", suggestion))
+                .unwrap_or_default(),
+            render_snippet(source, *span, color)
+        ),
+        TypeError::TypeMismatch { expected, found, span } => {
+            let diff = diff_types(expected, found);
+            let location = if diff.path.is_empty() {
+                String::new()
+            } else {
+                format!(" — differs in the {}", diff.path.join(" → "))
+            };
+            format!(
+                "{}: expected `{}` but found `{}`{}\n{}",
+                paint_error_header(error.code(), color),
+                diff.expected,
+                diff.found,
+                location,
+                render_snippet(source, *span, color)
+            )
+        }
+        TypeError::InfiniteType { var, ty, span } => format!(
+            "{}: infinite type: t{} occurs in `{}`\n{}",
+            paint_error_header(error.code(), color),
+            var,
+            ty,
+            render_snippet(source, *span, color)
+        ),
+    };
+    if let TypeError::TypeMismatch { span, .. } = error {
+        if let Some((step1, step2)) = explain_steps(constraints, *span) {
+            report.push_str(&render_provenance_note(source, &step1, color));
+            report.push_str(&render_provenance_note(source, &step2, color));
+        }
+    }
+    report
+}
+
+fn paint_error_header(code: &str, color: bool) -> String {
+    paint(&format!("error[{}]", code), "1;31", color)
+}
+
+fn render_provenance_note(
+    source: &str,
+    step: &crate::unifier::ExplanationStep,
+    color: bool,
+) -> String {
+    format!(
+        "{}: this {}, so it must be `{}`\n{}",
+        paint("note", "1;36", color),
+        step.reason.description(),
+        step.ty,
+        render_snippet(source, step.span, color)
+    )
+}
+
+/// Renders the single source line `span` falls on, followed by a
+/// caret (`^`) underline spanning its width, in a rustc-style gutter.
+fn render_snippet(source: &str, span: Span, color: bool) -> String {
+    let text = source.lines().nth(span.line.saturating_sub(1)).unwrap_or("");
+    let gutter = span.line.to_string();
+    let padding = " ".repeat(gutter.len());
+    let indent = " ".repeat(span.column.saturating_sub(1));
+    let carets = paint(
+        &"^".repeat((span.end - span.start).max(1)),
+        "1;31",
+        color,
+    );
+    format!(
+        "{padding} |\n{gutter} | {text}\n{padding} | {indent}{carets}\n",
+        padding = padding,
+        gutter = gutter,
+        text = text,
+        indent = indent,
+        carets = carets,
+    )
+}
+
+/// Wraps `text` in the ANSI escape for `code` when `enabled`, or returns it
+/// unchanged otherwise, so plain and colored rendering share one code path.
+fn paint(text: &str, code: &str, enabled: bool) -> String {
+    if enabled {
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annotator::annotate;
+    use crate::constraint::collect_constraints;
+    use crate::parser::parse;
+    use crate::tokenizer::tokenize_with_spans;
+
+    #[test]
+    fn test_render_underlines_the_offending_span() {
+        let source = "if true then true else fn x => x";
+        let tokens = tokenize_with_spans(source).unwrap();
+        let term = parse(&tokens).unwrap();
+        let typed_term = annotate(&term).unwrap();
+        let constraints = collect_constraints(&typed_term).unwrap();
+        let errors = crate::unifier::unify(&constraints).unwrap_err();
+        let report = render(source, &errors[0], &constraints);
+        assert!(report.starts_with("error[TC0002]:"));
+        assert!(report.contains(source));
+        assert!(report.contains('^'));
+    }
+
+    #[test]
+    fn test_render_includes_a_provenance_note_for_each_conflicting_side() {
+        let source = "if true then true else fn x => x";
+        let tokens = tokenize_with_spans(source).unwrap();
+        let term = parse(&tokens).unwrap();
+        let typed_term = annotate(&term).unwrap();
+        let constraints = collect_constraints(&typed_term).unwrap();
+        let errors = crate::unifier::unify(&constraints).unwrap_err();
+        let report = render(source, &errors[0], &constraints);
+        assert_eq!(report.matches("note:").count(), 2);
+    }
+
+    #[test]
+    fn test_render_with_options_never_omits_ansi_escapes() {
+        let source = "if true then true else fn x => x";
+        let tokens = tokenize_with_spans(source).unwrap();
+        let term = parse(&tokens).unwrap();
+        let typed_term = annotate(&term).unwrap();
+        let constraints = collect_constraints(&typed_term).unwrap();
+        let errors = crate::unifier::unify(&constraints).unwrap_err();
+        let options = RenderOptions { color: ColorMode::Never };
+        let report = render_with_options(source, &errors[0], &constraints, options);
+        assert!(!report.contains('\x1b'));
+    }
+
+    #[test]
+    fn test_render_with_options_always_includes_ansi_escapes() {
+        let source = "if true then true else fn x => x";
+        let tokens = tokenize_with_spans(source).unwrap();
+        let term = parse(&tokens).unwrap();
+        let typed_term = annotate(&term).unwrap();
+        let constraints = collect_constraints(&typed_term).unwrap();
+        let errors = crate::unifier::unify(&constraints).unwrap_err();
+        let options = RenderOptions { color: ColorMode::Always };
+        let report = render_with_options(source, &errors[0], &constraints, options);
+        assert!(report.contains('\x1b'));
+    }
+
+    #[test]
+    fn test_color_mode_from_str_accepts_the_three_documented_values() {
+        assert_eq!("always".parse(), Ok(ColorMode::Always));
+        assert_eq!("never".parse(), Ok(ColorMode::Never));
+        assert_eq!("auto".parse(), Ok(ColorMode::Auto));
+    }
+
+    #[test]
+    fn test_color_mode_from_str_rejects_anything_else() {
+        assert!("sometimes".parse::<ColorMode>().is_err());
+    }
+
+    #[test]
+    fn test_render_narrows_a_mismatch_between_function_types_to_the_differing_return_type() {
+        use crate::annotator::Type;
+        let source = "fn x => x";
+        let error = TypeError::TypeMismatch {
+            expected: Box::new(Type::Function {
+                parameter_type: Box::new(Type::Integer),
+                return_type: Box::new(Type::Boolean),
+                effects: Vec::new(),
+            }),
+            found: Box::new(Type::Function {
+                parameter_type: Box::new(Type::Integer),
+                return_type: Box::new(Type::Integer),
+                effects: Vec::new(),
+            }),
+            span: Span::default(),
+        };
+        let report = render(source, &error, &[]);
+        assert!(report.contains("expected `bool` but found `int`"));
+        assert!(report.contains("differs in the return type"));
+    }
+}
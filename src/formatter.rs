@@ -0,0 +1,217 @@
+use crate::parser::{Term, TermKind};
+
+/// Options controlling how a [`Term`] is rendered back to surface syntax.
+#[derive(Debug, Clone, Copy)]
+pub struct FormatOptions {
+    /// The line length a construct is allowed to reach before it is broken
+    /// across multiple lines.
+    pub width: usize,
+    /// The number of spaces added per nesting level when a construct is
+    /// broken across lines.
+    pub indent: usize,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            width: 80,
+            indent: 4,
+        }
+    }
+}
+
+/// Renders `term` back to canonical surface syntax using the default
+/// [`FormatOptions`].
+pub fn format(term: &Term) -> String {
+    format_with_options(term, &FormatOptions::default())
+}
+
+/// Renders `term` back to canonical surface syntax, breaking constructs
+/// across lines once they would exceed `options.width`.
+pub fn format_with_options(term: &Term, options: &FormatOptions) -> String {
+    format_at(term, options, 0)
+}
+
+fn format_at(term: &Term, options: &FormatOptions, indent_level: usize) -> String {
+    let flat = format_flat(term);
+    if indent_level * options.indent + flat.len() <= options.width || !breakable(&term.kind) {
+        return flat;
+    }
+    let indent = " ".repeat(indent_level * options.indent);
+    let inner_indent = " ".repeat((indent_level + 1) * options.indent);
+    match &term.kind {
+        TermKind::FunctionDefinition { parameter, body } => format!(
+            "fn {} =>\n{}{}",
+            format_flat(parameter),
+            inner_indent,
+            format_at(body, options, indent_level + 1)
+        ),
+        TermKind::IfExpression {
+            condition,
+            true_branch,
+            false_branch,
+        } => format!(
+            "if {}\n{}then {}\n{}else {}",
+            format_flat(condition),
+            indent,
+            format_at(true_branch, options, indent_level),
+            indent,
+            format_at(false_branch, options, indent_level),
+        ),
+        TermKind::LetExpression {
+            declaration_name,
+            declaration_value,
+            expression,
+        } => format!(
+            "let val {} = {}\n{}in {}\n{}end",
+            format_flat(declaration_name),
+            format_at(declaration_value, options, indent_level + 1),
+            indent,
+            format_at(expression, options, indent_level + 1),
+            indent,
+        ),
+        TermKind::FunctionApplication { function, argument } => format!(
+            "{}(\n{}{}\n{})",
+            format_flat(function),
+            inner_indent,
+            format_at(argument, options, indent_level + 1),
+            indent,
+        ),
+        _ => flat,
+    }
+}
+
+/// Whether `kind` has a multi-line rendering to fall back on when the
+/// single-line form doesn't fit within the configured width.
+fn breakable(kind: &TermKind) -> bool {
+    matches!(
+        kind,
+        TermKind::FunctionApplication { .. }
+            | TermKind::FunctionDefinition { .. }
+            | TermKind::IfExpression { .. }
+            | TermKind::LetExpression { .. }
+    )
+}
+
+fn format_flat(term: &Term) -> String {
+    match &term.kind {
+        TermKind::Boolean(value) => value.to_string(),
+        TermKind::Error => String::from("<error>"),
+        TermKind::FunctionApplication { function, argument } => {
+            format!("{}({})", format_flat(function), format_flat(argument))
+        }
+        TermKind::FunctionDefinition { parameter, body } => {
+            format!("fn {} => {}", format_flat(parameter), format_flat(body))
+        }
+        TermKind::Identifier(name) => name.clone(),
+        TermKind::IfExpression {
+            condition,
+            true_branch,
+            false_branch,
+        } => format!(
+            "if {} then {} else {}",
+            format_flat(condition),
+            format_flat(true_branch),
+            format_flat(false_branch)
+        ),
+        TermKind::Integer(value) => value.to_string(),
+        TermKind::LetExpression {
+            declaration_name,
+            declaration_value,
+            expression,
+        } => format!(
+            "let val {} = {} in {} end",
+            format_flat(declaration_name),
+            format_flat(declaration_value),
+            format_flat(expression)
+        ),
+        TermKind::RaiseExpression { exception } => format!("raise {}", format_flat(exception)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatter::{format, format_with_options, FormatOptions};
+    use crate::parser::parse;
+    use crate::tokenizer::tokenize_with_spans;
+
+    #[test]
+    fn test_format_options_default_width_and_indent() {
+        let options = FormatOptions::default();
+        assert_eq!(options.width, 80);
+        assert_eq!(options.indent, 4);
+    }
+
+    #[test]
+    fn test_format_integer() -> Result<(), String> {
+        let tokens = tokenize_with_spans("42")?;
+        let term = parse(&tokens)?;
+        assert_eq!(format(&term), "42");
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_boolean() -> Result<(), String> {
+        let tokens = tokenize_with_spans("true")?;
+        let term = parse(&tokens)?;
+        assert_eq!(format(&term), "true");
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_function_application() -> Result<(), String> {
+        let tokens = tokenize_with_spans("f(1)")?;
+        let term = parse(&tokens)?;
+        assert_eq!(format(&term), "f(1)");
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_function_definition() -> Result<(), String> {
+        let tokens = tokenize_with_spans("fn x => x + 1")?;
+        let term = parse(&tokens)?;
+        assert_eq!(format(&term), "fn x => +(x)(1)");
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_if_expression() -> Result<(), String> {
+        let tokens = tokenize_with_spans("if true then 0 else 1")?;
+        let term = parse(&tokens)?;
+        assert_eq!(format(&term), "if true then 0 else 1");
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_let_expression() -> Result<(), String> {
+        let tokens = tokenize_with_spans("let val x = 1 in x end")?;
+        let term = parse(&tokens)?;
+        assert_eq!(format(&term), "let val x = 1 in x end");
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_wraps_a_let_expression_once_it_exceeds_the_width() -> Result<(), String> {
+        let tokens = tokenize_with_spans("let val x = 1 in x end")?;
+        let term = parse(&tokens)?;
+        let options = FormatOptions {
+            width: 10,
+            indent: 2,
+        };
+        assert_eq!(
+            format_with_options(&term, &options),
+            "let val x = 1\nin x\nend"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_keeps_a_short_construct_on_one_line_even_with_a_tight_width() -> Result<(), String>
+    {
+        let tokens = tokenize_with_spans("true")?;
+        let term = parse(&tokens)?;
+        let options = FormatOptions { width: 1, indent: 2 };
+        assert_eq!(format_with_options(&term, &options), "true");
+        Ok(())
+    }
+}
@@ -0,0 +1,174 @@
+use crate::annotator::Type;
+use crate::constraint::{Constraint, ConstraintReason};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Renders `constraints` as a Graphviz DOT graph: one node per distinct
+/// [`Type`] appearing as either side of a constraint (using its own
+/// [`Display`](std::fmt::Display) rendering as both the node's identity and
+/// its label, so a type variable and a named constructor each get their
+/// own node), and one directed edge per constraint, colored by the
+/// [`ConstraintReason`] responsible for it. Feeding the output to
+/// `dot -Tsvg` lets a user visually trace which constraints connect which
+/// types, which is often the fastest way to see why a set of them turned
+/// out unsatisfiable.
+///
+/// A [`Type::Function`] or [`Type::Intersection`] is not expanded into its
+/// own sub-nodes; it renders as a single node labeled with its full nested
+/// type text, since the constraints themselves never relate a function's
+/// parameter or return type in isolation.
+pub fn constraints_to_dot(constraints: &[Constraint]) -> String {
+    let mut labels: Vec<String> = Vec::new();
+    let mut node_ids: HashMap<String, usize> = HashMap::new();
+    let mut edges: Vec<(usize, usize, &'static str, ConstraintReason)> = Vec::new();
+
+    for constraint in constraints {
+        let lhs = intern_node(&mut labels, &mut node_ids, constraint.lhs());
+        let rhs = intern_node(&mut labels, &mut node_ids, constraint.rhs());
+        edges.push((lhs, rhs, edge_label(constraint), constraint.reason()));
+    }
+
+    let mut dot = String::from("digraph constraints {\n");
+    for (id, label) in labels.iter().enumerate() {
+        writeln!(dot, "    n{} [label=\"{}\"];", id, escape_label(label)).unwrap();
+    }
+    for (lhs, rhs, label, reason) in edges {
+        writeln!(
+            dot,
+            "    n{} -> n{} [label=\"{}\", color=\"{}\"];",
+            lhs,
+            rhs,
+            label,
+            reason_color(reason)
+        )
+        .unwrap();
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// Returns the node id for `ty`, creating one (and recording its label) the
+/// first time this exact rendered type is seen.
+fn intern_node(labels: &mut Vec<String>, node_ids: &mut HashMap<String, usize>, ty: &Type) -> usize {
+    let label = ty.to_string();
+    *node_ids.entry(label.clone()).or_insert_with(|| {
+        labels.push(label);
+        labels.len() - 1
+    })
+}
+
+fn edge_label(constraint: &Constraint) -> &'static str {
+    match constraint {
+        Constraint::Equal { .. } => "=",
+        Constraint::Subtype { .. } => "<:",
+        Constraint::Instance { .. } => "::",
+    }
+}
+
+/// Picks a distinct Graphviz color name per [`ConstraintReason`], so edges
+/// from different syntactic constructs are visually distinguishable
+/// without needing the label text.
+fn reason_color(reason: ConstraintReason) -> &'static str {
+    match reason {
+        ConstraintReason::BooleanLiteral => "red",
+        ConstraintReason::IntegerLiteral => "pink",
+        ConstraintReason::IfConditionBool => "orange",
+        ConstraintReason::BranchesMustMatch => "gold",
+        ConstraintReason::ApplicationArgument => "forestgreen",
+        ConstraintReason::FunctionSignature => "teal",
+        ConstraintReason::BuiltinSignature => "blue",
+        ConstraintReason::LetBinding => "purple",
+        ConstraintReason::LetResult => "brown",
+        ConstraintReason::LetInstantiation => "gray",
+    }
+}
+
+/// Escapes `label` for use inside a DOT string literal: backslashes and
+/// double quotes are escaped, per the DOT language's quoted-string syntax.
+fn escape_label(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constraint::TypeScheme;
+    use crate::tokenizer::Span;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_constraints_to_dot_wraps_output_in_a_digraph() {
+        let dot = constraints_to_dot(&[]);
+        assert!(dot.starts_with("digraph constraints {\n"));
+        assert!(dot.trim_end().ends_with('}'));
+    }
+
+    #[test]
+    fn test_constraints_to_dot_emits_one_node_per_distinct_type() {
+        let constraint = Constraint::equal(
+            Rc::new(Type::Boolean),
+            Rc::new(Type::Placeholder(1)),
+            ConstraintReason::BooleanLiteral,
+            Span::default(),
+            Span::default(),
+        );
+        let dot = constraints_to_dot(&[constraint]);
+        assert!(dot.contains("n0 [label=\"bool\"];"));
+        assert!(dot.contains("n1 [label=\"t1\"];"));
+        assert!(dot.contains("n0 -> n1 [label=\"=\", color=\"red\"];"));
+    }
+
+    #[test]
+    fn test_constraints_to_dot_reuses_the_node_for_a_repeated_type() {
+        let a = Constraint::equal(
+            Rc::new(Type::Placeholder(1)),
+            Rc::new(Type::Boolean),
+            ConstraintReason::BooleanLiteral,
+            Span::default(),
+            Span::default(),
+        );
+        let b = Constraint::equal(
+            Rc::new(Type::Placeholder(1)),
+            Rc::new(Type::Integer),
+            ConstraintReason::LetBinding,
+            Span::default(),
+            Span::default(),
+        );
+        let dot = constraints_to_dot(&[a, b]);
+        assert_eq!(dot.matches("label=\"t1\"").count(), 1);
+    }
+
+    #[test]
+    fn test_constraints_to_dot_colors_edges_by_reason() {
+        let constraint = Constraint::subtype(
+            Rc::new(Type::Integer),
+            Rc::new(Type::Integer),
+            ConstraintReason::LetResult,
+            Span::default(),
+            Span::default(),
+        );
+        let dot = constraints_to_dot(&[constraint]);
+        assert!(dot.contains("[label=\"<:\", color=\"brown\"]"));
+    }
+
+    #[test]
+    fn test_constraints_to_dot_renders_an_instance_constraint() {
+        let scheme = TypeScheme {
+            bound_vars: vec![(1, None)],
+            ty: Type::Placeholder(1),
+        };
+        let constraint = Constraint::instance(
+            scheme,
+            Rc::new(Type::Integer),
+            ConstraintReason::LetInstantiation,
+            Span::default(),
+        );
+        let dot = constraints_to_dot(&[constraint]);
+        assert!(dot.contains("[label=\"::\", color=\"gray\"]"));
+    }
+
+    #[test]
+    fn test_escape_label_escapes_quotes_and_backslashes() {
+        assert_eq!(escape_label("a\"b\\c"), "a\\\"b\\\\c");
+    }
+}
@@ -0,0 +1,109 @@
+use crate::annotator::Type;
+use std::collections::HashMap;
+
+/// A handle to a [`Type`] stored in a [`TypeInterner`]. Copying a `TypeId`
+/// is a single `u32` copy regardless of how deeply nested the `Type` it
+/// refers to is, unlike cloning a `Type` itself, which walks and
+/// reallocates every `Box`ed subtree.
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
+pub struct TypeId(u32);
+
+/// Hash-conses [`Type`] values behind cheap, copyable [`TypeId`]s.
+/// Structurally equal types (including equal nested `Box` subtrees) are
+/// interned exactly once, so comparing two `TypeId`s for equality is
+/// also a constant-time substitute for `Type`'s recursive `PartialEq`.
+///
+/// This is offered as a building block for callers that currently pay
+/// for `Type::clone()` on every constraint (see
+/// `constraint::collect_constraints`) — it does not yet replace `Type`'s
+/// own `Box`-based shape or rewire the constraint collector, both of
+/// which would mean threading a `TypeInterner` through the entire
+/// annotator and constraint-collection pipeline.
+#[derive(Debug, Default)]
+pub struct TypeInterner {
+    types: Vec<Type>,
+    ids: HashMap<Type, TypeId>,
+}
+
+impl TypeInterner {
+    pub fn new() -> TypeInterner {
+        TypeInterner::default()
+    }
+
+    /// Interns `ty`, returning its existing [`TypeId`] if an equal type
+    /// was interned before, or allocating a new one otherwise.
+    pub fn intern(&mut self, ty: Type) -> TypeId {
+        if let Some(id) = self.ids.get(&ty) {
+            return *id;
+        }
+        let id = TypeId(self.types.len() as u32);
+        self.ids.insert(ty.clone(), id);
+        self.types.push(ty);
+        id
+    }
+
+    /// Looks up the [`Type`] behind `id`, panicking if `id` was not
+    /// produced by this interner.
+    pub fn resolve(&self, id: TypeId) -> &Type {
+        &self.types[id.0 as usize]
+    }
+
+    /// The number of distinct types interned so far.
+    pub fn len(&self) -> usize {
+        self.types.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::annotator::Type;
+    use crate::intern::TypeInterner;
+
+    #[test]
+    fn test_intern_returns_the_same_id_for_equal_types() {
+        let mut interner = TypeInterner::new();
+        let first = interner.intern(Type::Integer);
+        let second = interner.intern(Type::Integer);
+        assert_eq!(first, second);
+        assert_eq!(interner.len(), 1);
+    }
+
+    #[test]
+    fn test_intern_returns_distinct_ids_for_distinct_types() {
+        let mut interner = TypeInterner::new();
+        let integer = interner.intern(Type::Integer);
+        let boolean = interner.intern(Type::Boolean);
+        assert_ne!(integer, boolean);
+        assert_eq!(interner.len(), 2);
+    }
+
+    #[test]
+    fn test_intern_deduplicates_structurally_equal_nested_types() {
+        let mut interner = TypeInterner::new();
+        let make = || Type::Function {
+            parameter_type: Box::from(Type::Integer),
+            return_type: Box::from(Type::Boolean),
+            effects: Vec::new(),
+        };
+        let first = interner.intern(make());
+        let second = interner.intern(make());
+        assert_eq!(first, second);
+        assert_eq!(interner.len(), 1);
+    }
+
+    #[test]
+    fn test_resolve_returns_the_original_type() {
+        let mut interner = TypeInterner::new();
+        let id = interner.intern(Type::Placeholder(3));
+        assert_eq!(interner.resolve(id), &Type::Placeholder(3));
+    }
+
+    #[test]
+    fn test_new_interner_is_empty() {
+        assert!(TypeInterner::new().is_empty());
+    }
+}
@@ -1,4 +1,565 @@
+pub mod algorithm_m;
+pub mod algorithm_w;
 pub mod annotator;
 pub mod constraint;
+pub mod cst;
+pub mod debruijn;
+pub mod desugar;
+pub mod diagnostics;
+pub mod formatter;
+pub mod graphviz;
+pub mod intern;
+pub mod lint;
 pub mod parser;
+#[cfg(feature = "pyo3")]
+pub mod python;
+pub mod resolve;
+pub mod resolver;
+pub mod simplify;
+#[cfg(feature = "smt")]
+pub mod smt_solver;
+pub mod smtlib;
 pub mod tokenizer;
+pub mod unifier;
+#[cfg(feature = "wasm-bindgen")]
+pub mod wasm;
+
+use annotator::{annotate, annotate_with_env, default_numeric_types, InferenceEngine, Type};
+use constraint::{collect_constraints, collect_constraints_with_env, TypeEnv, TypeError};
+use desugar::desugar;
+use lint::{LintDiagnostic, WarningsConfig};
+use parser::{parse, parse_with_recovery, ParseError, Term};
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::path::Path;
+use tokenizer::{tokenize_with_spans, LexError};
+use unifier::unify;
+
+/// A single failure from any stage of [`infer`]'s pipeline, so a caller
+/// only has to match on one error type instead of the five distinct ones
+/// [`tokenize_with_spans`], [`parse`], [`annotate`], [`collect_constraints`],
+/// and [`unify`] report on their own.
+#[derive(Debug)]
+pub enum Diagnostic {
+    /// `source` couldn't be tokenized.
+    Lex(LexError),
+    /// The token stream couldn't be parsed.
+    Parse(ParseError),
+    /// An identifier referenced by the term is neither a builtin nor bound
+    /// by an enclosing `fn` or `let`. Carries [`annotate`]'s message
+    /// verbatim, since it doesn't yet report the name or a span separately.
+    UnboundIdentifier(String),
+    /// Constraint collection or unification failed.
+    Type(TypeError),
+    /// A non-fatal finding from [`lint::check`], e.g. an unused binding.
+    Lint(LintDiagnostic),
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Diagnostic::Lex(error) => Display::fmt(error, f),
+            Diagnostic::Parse(error) => Display::fmt(error, f),
+            Diagnostic::UnboundIdentifier(message) => write!(f, "{}", message),
+            Diagnostic::Type(error) => Display::fmt(error, f),
+            Diagnostic::Lint(diagnostic) => write!(f, "{}", diagnostic.message),
+        }
+    }
+}
+
+/// Runs the full pipeline — tokenize, parse, desugar, annotate, collect
+/// constraints, solve, and canonicalize — on `source`, so a caller doesn't
+/// have to wire the five modules underneath together or reconcile their
+/// mismatched error types.
+///
+/// On success, the returned [`Type`] has had [`default_numeric_types`]
+/// applied, so a numeric literal with no constraint pinning it to anything
+/// more specific reads as `int` rather than a bare, unsolved type variable.
+pub fn infer(source: &str) -> Result<Type, Vec<Diagnostic>> {
+    let tokens = tokenize_with_spans(source).map_err(|error| vec![Diagnostic::Lex(error)])?;
+    let term = parse(&tokens).map_err(|errors| {
+        errors
+            .0
+            .into_iter()
+            .map(Diagnostic::Parse)
+            .collect::<Vec<_>>()
+    })?;
+    let term = desugar(&term);
+    let typed_term =
+        annotate(&term).map_err(|name| vec![Diagnostic::UnboundIdentifier(name)])?;
+    let constraints = collect_constraints(&typed_term)
+        .map_err(|errors| errors.into_iter().map(Diagnostic::Type).collect::<Vec<_>>())?;
+    let substitution =
+        unify(&constraints).map_err(|errors| errors.into_iter().map(Diagnostic::Type).collect::<Vec<_>>())?;
+    Ok(default_numeric_types(&substitution.apply(&typed_term.ty)))
+}
+
+/// A bounded collector of [`Diagnostic`]s gathered across every stage of
+/// [`check`]'s pipeline, so one invocation can report every problem it
+/// finds instead of stopping at the first, the way [`infer`] does.
+///
+/// `max_diagnostics` caps how many are kept, so a badly broken input can't
+/// force a caller to buffer an unbounded report.
+#[derive(Debug)]
+pub struct DiagnosticSink {
+    diagnostics: Vec<Diagnostic>,
+    max_diagnostics: usize,
+}
+
+impl DiagnosticSink {
+    /// Creates an empty sink that stops accepting diagnostics once it
+    /// holds `max_diagnostics` of them.
+    pub fn new(max_diagnostics: usize) -> Self {
+        DiagnosticSink { diagnostics: Vec::new(), max_diagnostics }
+    }
+
+    /// Records `diagnostic` unless the sink is already full. Returns
+    /// whether the caller should keep looking for more problems — `false`
+    /// once the limit has been reached, so a loop reporting several
+    /// diagnostics at once knows when to give up early.
+    pub fn report(&mut self, diagnostic: Diagnostic) -> bool {
+        if self.is_full() {
+            return false;
+        }
+        self.diagnostics.push(diagnostic);
+        !self.is_full()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.diagnostics.len() >= self.max_diagnostics
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    pub fn into_diagnostics(self) -> Vec<Diagnostic> {
+        self.diagnostics
+    }
+}
+
+/// Runs the same pipeline as [`infer`] against `source`, but keeps going
+/// past a stage that can still produce useful diagnostics on its own,
+/// collecting everything it finds into one [`DiagnosticSink`] bounded by
+/// `max_diagnostics`, instead of bailing out at the very first failing
+/// stage.
+///
+/// Parsing recovers past a syntax error (via [`parse_with_recovery`]) and
+/// keeps checking the rest of the input; constraint collection and
+/// unification already report every problem they find in a single pass.
+/// A stage whose failure leaves nothing usable for the next one — a lex
+/// error, or annotation failing to resolve an identifier — still ends the
+/// run early, since there's no term left to check further. Resolution
+/// (see [`resolve::resolve`]) runs as part of the pipeline too, though it
+/// can't itself fail and so never contributes a diagnostic.
+pub fn check(source: &str, max_diagnostics: usize) -> DiagnosticSink {
+    let mut sink = DiagnosticSink::new(max_diagnostics);
+    let tokens = match tokenize_with_spans(source) {
+        Ok(tokens) => tokens,
+        Err(error) => {
+            sink.report(Diagnostic::Lex(error));
+            return sink;
+        }
+    };
+    let (term, parse_errors) = parse_with_recovery(&tokens);
+    for error in parse_errors {
+        if !sink.report(Diagnostic::Parse(error)) {
+            return sink;
+        }
+    }
+    let term = desugar(&term);
+    resolve::resolve(&term);
+    let typed_term = match annotate(&term) {
+        Ok(typed_term) => typed_term,
+        Err(message) => {
+            sink.report(Diagnostic::UnboundIdentifier(message));
+            return sink;
+        }
+    };
+    let constraints = match collect_constraints(&typed_term) {
+        Ok(constraints) => constraints,
+        Err(errors) => {
+            for error in errors {
+                if !sink.report(Diagnostic::Type(error)) {
+                    break;
+                }
+            }
+            return sink;
+        }
+    };
+    if let Err(errors) = unify(&constraints) {
+        for error in errors {
+            if !sink.report(Diagnostic::Type(error)) {
+                break;
+            }
+        }
+    }
+    sink
+}
+
+/// Converts a [`TypeEnv`] into the `env` shape [`annotate_with_env`]
+/// expects, since the two predate each other and were never unified: one
+/// is a name-to-type map used to resolve identifiers before constraints
+/// exist, the other tracks bindings once they do.
+fn type_env_to_btree_map(env: &TypeEnv) -> BTreeMap<String, Type> {
+    env.names()
+        .filter_map(|name| env.get(name).map(|ty| (name.to_string(), ty.clone())))
+        .collect()
+}
+
+/// A hook called with a one-line description of each pipeline stage as
+/// [`TypeChecker::check_source`] reaches it.
+type TraceHook = Box<dyn Fn(&str)>;
+
+/// The checker's configurable options: which [`InferenceEngine`] to run,
+/// which identifiers are bound before checking starts, which [`lint::Lint`]s
+/// to report, how many diagnostics to collect before giving up, and an
+/// optional [`TraceHook`] for a caller that wants to show progress, or log
+/// how far a check got before failing.
+///
+/// Built with [`TypeChecker::builder`]; see [`TypeCheckerBuilder`].
+pub struct TypeChecker {
+    inference_engine: InferenceEngine,
+    prelude: TypeEnv,
+    warnings: WarningsConfig,
+    max_diagnostics: usize,
+    trace: Option<TraceHook>,
+}
+
+impl TypeChecker {
+    /// Starts building a [`TypeChecker`] from [`TypeCheckerBuilder`]'s
+    /// defaults: the constraint-based engine, the default prelude, default
+    /// lint levels, no cap on diagnostics, and no trace hook.
+    pub fn builder() -> TypeCheckerBuilder {
+        TypeCheckerBuilder::default()
+    }
+
+    fn trace(&self, message: &str) {
+        if let Some(hook) = &self.trace {
+            hook(message);
+        }
+    }
+
+    /// Runs the full pipeline against `source` — tokenizing, parsing with
+    /// recovery, linting, and then [`check_term`](TypeChecker::check_term)
+    /// — collecting every diagnostic it finds, up to this checker's
+    /// `max_diagnostics`, instead of stopping at the first the way
+    /// [`infer`] does.
+    pub fn check_source(&self, source: &str) -> DiagnosticSink {
+        let mut sink = DiagnosticSink::new(self.max_diagnostics);
+        self.trace("tokenizing");
+        let tokens = match tokenize_with_spans(source) {
+            Ok(tokens) => tokens,
+            Err(error) => {
+                sink.report(Diagnostic::Lex(error));
+                return sink;
+            }
+        };
+        self.trace("parsing");
+        let (term, parse_errors) = parse_with_recovery(&tokens);
+        for error in parse_errors {
+            if !sink.report(Diagnostic::Parse(error)) {
+                return sink;
+            }
+        }
+        self.trace("linting");
+        for finding in lint::check(&term, &self.warnings) {
+            if finding.level == lint::Level::Allow {
+                continue;
+            }
+            if !sink.report(Diagnostic::Lint(finding)) {
+                return sink;
+            }
+        }
+        self.check_term(&term, &mut sink);
+        sink
+    }
+
+    /// Runs desugaring, annotation, and inference (via this checker's
+    /// configured [`InferenceEngine`] and prelude) against an already
+    /// parsed `term`, appending every diagnostic found to `sink`. Exposed
+    /// separately from [`check_source`](TypeChecker::check_source) for a
+    /// caller that already has a [`Term`] — from its own parser front end,
+    /// or from editing an existing AST — and doesn't want to round-trip it
+    /// through source text first.
+    pub fn check_term(&self, term: &Term, sink: &mut DiagnosticSink) {
+        self.trace("desugaring");
+        let term = desugar(term);
+        resolve::resolve(&term);
+        match self.inference_engine {
+            InferenceEngine::AlgorithmW => {
+                self.trace("inferring (algorithm w)");
+                if let Err(error) = algorithm_w::infer_with_env(&term, &self.prelude) {
+                    sink.report(Diagnostic::Type(error));
+                }
+            }
+            InferenceEngine::AlgorithmM => {
+                self.trace("inferring (algorithm m)");
+                if let Err(error) = algorithm_m::infer_with_env(&term, &self.prelude) {
+                    sink.report(Diagnostic::Type(error));
+                }
+            }
+            InferenceEngine::ConstraintBased => {
+                self.trace("annotating");
+                let typed_term = match annotate_with_env(&term, &type_env_to_btree_map(&self.prelude)) {
+                    Ok(typed_term) => typed_term,
+                    Err(message) => {
+                        sink.report(Diagnostic::UnboundIdentifier(message));
+                        return;
+                    }
+                };
+                self.trace("collecting constraints");
+                let constraints = match collect_constraints_with_env(&typed_term, &self.prelude) {
+                    Ok(constraints) => constraints,
+                    Err(errors) => {
+                        for error in errors {
+                            if !sink.report(Diagnostic::Type(error)) {
+                                return;
+                            }
+                        }
+                        return;
+                    }
+                };
+                self.trace("solving");
+                if let Err(errors) = unify(&constraints) {
+                    for error in errors {
+                        if !sink.report(Diagnostic::Type(error)) {
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Like [`check_source`](TypeChecker::check_source), but reads the
+    /// source text from `path` first.
+    pub fn check_file(&self, path: impl AsRef<Path>) -> std::io::Result<DiagnosticSink> {
+        let source = std::fs::read_to_string(path)?;
+        Ok(self.check_source(&source))
+    }
+}
+
+/// Collects [`TypeChecker`]'s options one at a time before building it,
+/// since most callers only want to override one or two of them and leave
+/// the rest at their defaults.
+pub struct TypeCheckerBuilder {
+    inference_engine: InferenceEngine,
+    prelude: TypeEnv,
+    warnings: WarningsConfig,
+    max_diagnostics: usize,
+    trace: Option<TraceHook>,
+}
+
+impl Default for TypeCheckerBuilder {
+    fn default() -> Self {
+        TypeCheckerBuilder {
+            inference_engine: InferenceEngine::default(),
+            prelude: TypeEnv::default_prelude(),
+            warnings: WarningsConfig::new(),
+            max_diagnostics: usize::MAX,
+            trace: None,
+        }
+    }
+}
+
+impl TypeCheckerBuilder {
+    /// Selects which [`InferenceEngine`] [`TypeChecker::check_term`] runs.
+    pub fn inference_engine(mut self, inference_engine: InferenceEngine) -> Self {
+        self.inference_engine = inference_engine;
+        self
+    }
+
+    /// Sets which identifiers are bound before checking starts, replacing
+    /// [`TypeEnv::default_prelude`].
+    pub fn prelude(mut self, prelude: TypeEnv) -> Self {
+        self.prelude = prelude;
+        self
+    }
+
+    /// Sets which [`lint::Lint`]s [`TypeChecker::check_source`] reports and
+    /// at what level.
+    pub fn warnings(mut self, warnings: WarningsConfig) -> Self {
+        self.warnings = warnings;
+        self
+    }
+
+    /// Caps how many diagnostics a single check collects before giving up,
+    /// the same limit [`DiagnosticSink`] enforces.
+    pub fn max_diagnostics(mut self, max_diagnostics: usize) -> Self {
+        self.max_diagnostics = max_diagnostics;
+        self
+    }
+
+    /// Registers a hook called with a one-line description of each
+    /// pipeline stage as it's reached, e.g. for progress reporting.
+    pub fn trace(mut self, hook: impl Fn(&str) + 'static) -> Self {
+        self.trace = Some(Box::new(hook));
+        self
+    }
+
+    pub fn build(self) -> TypeChecker {
+        TypeChecker {
+            inference_engine: self.inference_engine,
+            prelude: self.prelude,
+            warnings: self.warnings,
+            max_diagnostics: self.max_diagnostics,
+            trace: self.trace,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_infer_solves_an_identity_function() {
+        let ty = infer("fn x => if x then true else false").unwrap();
+        assert_eq!(
+            ty,
+            Type::Function {
+                parameter_type: Box::new(Type::Boolean),
+                return_type: Box::new(Type::Boolean),
+                effects: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_infer_defaults_an_unconstrained_integer_literal_to_int() {
+        assert_eq!(infer("1").unwrap(), Type::Integer);
+    }
+
+    #[test]
+    fn test_infer_defaults_a_numeric_variable_applied_through_a_polymorphic_function_to_int() {
+        assert_eq!(infer("let val id = fn x => x in id(42) end").unwrap(), Type::Integer);
+    }
+
+    #[test]
+    fn test_infer_unifies_a_numeric_literal_with_a_placeholder_bound_to_a_builtin_signature() {
+        assert_eq!(
+            infer("let val inc = fn x => x + 1 in inc(5) end").unwrap(),
+            Type::Integer
+        );
+    }
+
+    #[test]
+    fn test_infer_reports_a_lex_error() {
+        let errors = infer("$").unwrap_err();
+        assert!(matches!(errors.as_slice(), [Diagnostic::Lex(_)]));
+    }
+
+    #[test]
+    fn test_infer_reports_a_parse_error() {
+        let errors = infer("if true then true").unwrap_err();
+        assert!(matches!(errors.as_slice(), [Diagnostic::Parse(_)]));
+    }
+
+    #[test]
+    fn test_infer_reports_an_unbound_identifier() {
+        let errors = infer("x").unwrap_err();
+        assert!(
+            matches!(errors.as_slice(), [Diagnostic::UnboundIdentifier(message)] if message.contains('x'))
+        );
+    }
+
+    #[test]
+    fn test_infer_reports_a_type_mismatch() {
+        let errors = infer("if true then true else fn x => x").unwrap_err();
+        assert!(matches!(errors.as_slice(), [Diagnostic::Type(_)]));
+    }
+
+    #[test]
+    fn test_check_accumulates_every_parse_error_instead_of_stopping_at_the_first() {
+        let sink = check("let x end let val y = 2 in y end", 10);
+        assert!(sink.diagnostics().len() > 1);
+        assert!(sink
+            .diagnostics()
+            .iter()
+            .all(|diagnostic| matches!(diagnostic, Diagnostic::Parse(_))));
+    }
+
+    #[test]
+    fn test_check_stops_collecting_once_max_diagnostics_is_reached() {
+        let sink = check("let x end let val y = 2 in y end", 1);
+        assert_eq!(sink.diagnostics().len(), 1);
+    }
+
+    #[test]
+    fn test_check_succeeds_on_well_typed_source_with_an_empty_sink() {
+        let sink = check("fn x => if x then true else false", 10);
+        assert!(sink.is_empty());
+    }
+
+    #[test]
+    fn test_diagnostic_sink_report_returns_false_once_full() {
+        let mut sink = DiagnosticSink::new(1);
+        assert!(!sink.report(Diagnostic::UnboundIdentifier(String::from("a"))));
+        assert!(!sink.report(Diagnostic::UnboundIdentifier(String::from("b"))));
+        assert_eq!(sink.diagnostics().len(), 1);
+    }
+
+    #[test]
+    fn test_type_checker_builder_defaults_match_check() {
+        let checker = TypeChecker::builder().build();
+        let sink = checker.check_source("if true then true else fn x => x");
+        assert!(matches!(sink.diagnostics(), [Diagnostic::Type(_)]));
+    }
+
+    #[test]
+    fn test_type_checker_resolves_identifiers_against_a_custom_prelude() {
+        let mut prelude = TypeEnv::new();
+        prelude.insert("answer", Type::Integer);
+        let checker = TypeChecker::builder().prelude(prelude).build();
+        let sink = checker.check_source("answer");
+        assert!(sink.is_empty());
+    }
+
+    #[test]
+    fn test_type_checker_agrees_across_engines_on_a_well_typed_term() {
+        for engine in [InferenceEngine::ConstraintBased, InferenceEngine::AlgorithmW, InferenceEngine::AlgorithmM] {
+            let checker = TypeChecker::builder().inference_engine(engine).build();
+            let sink = checker.check_source("fn x => if x then true else false");
+            assert!(sink.is_empty(), "engine {:?} reported {:?}", engine, sink.diagnostics());
+        }
+    }
+
+    #[test]
+    fn test_type_checker_reports_a_warned_lint() {
+        let mut warnings = WarningsConfig::new();
+        warnings.set(lint::Lint::Unused, lint::Level::Warn);
+        let checker = TypeChecker::builder().warnings(warnings).build();
+        let sink = checker.check_source("let val x = 1 in true end");
+        assert!(sink.diagnostics().iter().any(|diagnostic| matches!(diagnostic, Diagnostic::Lint(_))));
+    }
+
+    #[test]
+    fn test_type_checker_trace_hook_observes_every_pipeline_stage() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+        let stages = Rc::new(RefCell::new(Vec::new()));
+        let recorded = Rc::clone(&stages);
+        let checker = TypeChecker::builder()
+            .trace(move |stage| recorded.borrow_mut().push(stage.to_string()))
+            .build();
+        checker.check_source("1");
+        assert_eq!(
+            *stages.borrow(),
+            vec!["tokenizing", "parsing", "linting", "desugaring", "annotating", "collecting constraints", "solving"]
+        );
+    }
+
+    #[test]
+    fn test_type_checker_check_file_reads_source_from_disk() {
+        let path = std::env::temp_dir().join("type_checker_test_check_file.txt");
+        std::fs::write(&path, "1").unwrap();
+        let sink = TypeChecker::builder().build().check_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(sink.is_empty());
+    }
+}
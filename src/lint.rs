@@ -0,0 +1,239 @@
+//! Non-fatal quality lints — unused bindings and variable shadowing — and
+//! the [`WarningsConfig`] that lets a caller allow, warn on, or deny each
+//! one independently, the way `-W`/`-D` flags do for a typical compiler.
+//! Unlike [`crate::constraint::TypeError`], nothing here can fail a check:
+//! [`check`] only ever reports findings, it never turns them into an
+//! error of its own.
+
+use crate::parser::{Term, TermKind};
+use crate::resolver::build_symbol_table;
+use crate::tokenizer::Span;
+use std::collections::HashMap;
+
+/// One independently configurable lint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lint {
+    /// A `fn` parameter or `let` binding that's never referenced.
+    Unused,
+    /// A `fn` parameter or `let` binding that reuses the name of an
+    /// enclosing binder, hiding it for the rest of its scope.
+    Shadowing,
+}
+
+impl Lint {
+    /// The level a lint reports at when a [`WarningsConfig`] hasn't said
+    /// otherwise. Shadowing is a normal, idiomatic pattern in this
+    /// calculus (`let val x = ... in let val x = ... end end` is how
+    /// several existing tests rebind a name on purpose), so it defaults to
+    /// [`Level::Allow`]; an unreferenced binding is more often a mistake,
+    /// so it defaults to [`Level::Warn`].
+    fn default_level(self) -> Level {
+        match self {
+            Lint::Unused => Level::Warn,
+            Lint::Shadowing => Level::Allow,
+        }
+    }
+}
+
+/// How strongly a [`Lint`] should be surfaced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    /// Don't report it at all.
+    Allow,
+    /// Report it as a warning.
+    Warn,
+    /// Report it as an error-level diagnostic. Still non-fatal — checking
+    /// completes and produces a type regardless — this only changes how
+    /// prominently the finding is surfaced.
+    Deny,
+}
+
+/// One lint's finding: which [`Lint`] fired, at what [`Level`], where, and
+/// why.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintDiagnostic {
+    pub lint: Lint,
+    pub level: Level,
+    pub span: Span,
+    pub message: String,
+}
+
+/// Per-[`Lint`] allow/warn/deny levels, so a project can tune which
+/// non-fatal diagnostics [`check`] reports without recompiling the
+/// checker. A lint that hasn't been [`set`](WarningsConfig::set) falls
+/// back to its own default level.
+#[derive(Debug, Clone, Default)]
+pub struct WarningsConfig {
+    levels: HashMap<Lint, Level>,
+}
+
+impl WarningsConfig {
+    pub fn new() -> Self {
+        WarningsConfig::default()
+    }
+
+    /// Sets `lint`'s level, overriding its default.
+    pub fn set(&mut self, lint: Lint, level: Level) -> &mut Self {
+        self.levels.insert(lint, level);
+        self
+    }
+
+    /// `lint`'s configured level, or its default if it hasn't been set.
+    pub fn level(&self, lint: Lint) -> Level {
+        self.levels.get(&lint).copied().unwrap_or_else(|| lint.default_level())
+    }
+}
+
+/// Runs every lint not set to [`Level::Allow`] against `term`, returning
+/// their findings in the order the lints ran (unused bindings, then
+/// shadowing).
+pub fn check(term: &Term, config: &WarningsConfig) -> Vec<LintDiagnostic> {
+    let mut diagnostics = Vec::new();
+    let unused_level = config.level(Lint::Unused);
+    if unused_level != Level::Allow {
+        diagnostics.extend(check_unused(term, unused_level));
+    }
+    let shadowing_level = config.level(Lint::Shadowing);
+    if shadowing_level != Level::Allow {
+        diagnostics.extend(check_shadowing(term, shadowing_level));
+    }
+    diagnostics
+}
+
+fn check_unused(term: &Term, level: Level) -> Vec<LintDiagnostic> {
+    let table = build_symbol_table(term);
+    let mut unused: Vec<_> = table.unused().into_iter().filter_map(|symbol| table.get(symbol)).collect();
+    unused.sort_by_key(|info| (info.definition.line, info.definition.column));
+    unused
+        .into_iter()
+        .map(|info| LintDiagnostic {
+            lint: Lint::Unused,
+            level,
+            span: info.definition,
+            message: format!("unused binding `{}`", info.name),
+        })
+        .collect()
+}
+
+fn check_shadowing(term: &Term, level: Level) -> Vec<LintDiagnostic> {
+    let mut diagnostics = Vec::new();
+    walk_shadowing(term, &mut Vec::new(), level, &mut diagnostics);
+    diagnostics
+}
+
+fn walk_shadowing(term: &Term, scope: &mut Vec<String>, level: Level, diagnostics: &mut Vec<LintDiagnostic>) {
+    match &term.kind {
+        TermKind::Boolean(_) | TermKind::Error | TermKind::Identifier(_) | TermKind::Integer(_) => {}
+        TermKind::FunctionApplication { function, argument } => {
+            walk_shadowing(function, scope, level, diagnostics);
+            walk_shadowing(argument, scope, level, diagnostics);
+        }
+        TermKind::FunctionDefinition { parameter, body } => {
+            if let TermKind::Identifier(name) = &parameter.kind {
+                report_if_shadowed(name, parameter.span, scope, level, diagnostics);
+                scope.push(name.clone());
+                walk_shadowing(body, scope, level, diagnostics);
+                scope.pop();
+            } else {
+                walk_shadowing(body, scope, level, diagnostics);
+            }
+        }
+        TermKind::IfExpression { condition, true_branch, false_branch } => {
+            walk_shadowing(condition, scope, level, diagnostics);
+            walk_shadowing(true_branch, scope, level, diagnostics);
+            walk_shadowing(false_branch, scope, level, diagnostics);
+        }
+        TermKind::LetExpression { declaration_name, declaration_value, expression } => {
+            walk_shadowing(declaration_value, scope, level, diagnostics);
+            if let TermKind::Identifier(name) = &declaration_name.kind {
+                report_if_shadowed(name, declaration_name.span, scope, level, diagnostics);
+                scope.push(name.clone());
+                walk_shadowing(expression, scope, level, diagnostics);
+                scope.pop();
+            } else {
+                walk_shadowing(expression, scope, level, diagnostics);
+            }
+        }
+        TermKind::RaiseExpression { exception } => walk_shadowing(exception, scope, level, diagnostics),
+    }
+}
+
+fn report_if_shadowed(name: &str, span: Span, scope: &[String], level: Level, diagnostics: &mut Vec<LintDiagnostic>) {
+    if scope.iter().any(|bound| bound == name) {
+        diagnostics.push(LintDiagnostic {
+            lint: Lint::Shadowing,
+            level,
+            span,
+            message: format!("binding `{}` shadows an outer binding of the same name", name),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+    use crate::tokenizer::tokenize_with_spans;
+
+    fn parse_source(source: &str) -> Term {
+        let tokens = tokenize_with_spans(source).expect("tokenizing should succeed");
+        parse(&tokens).expect("parsing should succeed")
+    }
+
+    #[test]
+    fn test_warnings_config_defaults_unused_to_warn_and_shadowing_to_allow() {
+        let config = WarningsConfig::new();
+        assert_eq!(config.level(Lint::Unused), Level::Warn);
+        assert_eq!(config.level(Lint::Shadowing), Level::Allow);
+    }
+
+    #[test]
+    fn test_warnings_config_set_overrides_the_default() {
+        let mut config = WarningsConfig::new();
+        config.set(Lint::Unused, Level::Deny);
+        assert_eq!(config.level(Lint::Unused), Level::Deny);
+    }
+
+    #[test]
+    fn test_check_reports_an_unused_let_binding_by_default() {
+        let term = parse_source("let val x = 1 in true end");
+        let diagnostics = check(&term, &WarningsConfig::new());
+        assert!(diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.lint == Lint::Unused && diagnostic.message.contains('x')));
+    }
+
+    #[test]
+    fn test_check_does_not_report_a_referenced_binding() {
+        let term = parse_source("let val x = 1 in x end");
+        let diagnostics = check(&term, &WarningsConfig::new());
+        assert!(diagnostics.iter().all(|diagnostic| diagnostic.lint != Lint::Unused));
+    }
+
+    #[test]
+    fn test_check_does_not_report_shadowing_when_allowed_by_default() {
+        let term = parse_source("let val x = 1 in let val x = true in x end end");
+        let diagnostics = check(&term, &WarningsConfig::new());
+        assert!(diagnostics.iter().all(|diagnostic| diagnostic.lint != Lint::Shadowing));
+    }
+
+    #[test]
+    fn test_check_reports_shadowing_once_warned_on() {
+        let term = parse_source("let val x = 1 in let val x = true in x end end");
+        let mut config = WarningsConfig::new();
+        config.set(Lint::Shadowing, Level::Warn);
+        let diagnostics = check(&term, &config);
+        assert!(diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.lint == Lint::Shadowing && diagnostic.message.contains('x')));
+    }
+
+    #[test]
+    fn test_check_respects_a_denied_lints_level_in_its_diagnostics() {
+        let term = parse_source("fn x => true");
+        let mut config = WarningsConfig::new();
+        config.set(Lint::Unused, Level::Deny);
+        let diagnostics = check(&term, &config);
+        assert!(diagnostics.iter().any(|diagnostic| diagnostic.level == Level::Deny));
+    }
+}
@@ -1,16 +1,85 @@
 use type_checker::annotator::annotate;
 use type_checker::constraint::collect_constraints;
+use type_checker::desugar::desugar;
+use type_checker::diagnostics::{render_with_options, ColorMode, RenderOptions};
+use type_checker::lint::{self, Level, Lint, WarningsConfig};
 use type_checker::parser::parse;
-use type_checker::tokenizer::tokenize;
+use type_checker::tokenizer::tokenize_with_spans;
+use type_checker::unifier::unify;
 
 fn main() -> Result<(), String> {
-    let tokens = tokenize("fn x => x + 1")?;
-    let term = parse(&tokens)?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let color = parse_color_flag(args.iter().cloned());
+    let warnings = parse_warnings_flags(&args);
+    let source = "fn x => x + 1";
+    let tokens = tokenize_with_spans(source)?;
+    let parsed = parse(&tokens)?;
+    for diagnostic in lint::check(&parsed, &warnings) {
+        let label = match diagnostic.level {
+            Level::Allow => continue,
+            Level::Warn => "warning",
+            Level::Deny => "error",
+        };
+        println!("{}: {}", label, diagnostic.message);
+    }
+    let term = desugar(&parsed);
     let typed_term = annotate(&term)?;
-    let constraints = collect_constraints(&typed_term);
+    let constraints = collect_constraints(&typed_term).map_err(|errors| {
+        errors
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("; ")
+    })?;
     println!("{}", typed_term);
-    for constraint in constraints {
+    for constraint in &constraints {
         println!("{}", constraint);
     }
+    if let Err(errors) = unify(&constraints) {
+        let options = RenderOptions { color };
+        for error in &errors {
+            println!("{}", render_with_options(source, error, &constraints, options));
+        }
+    }
     Ok(())
 }
+
+/// Parses a `--color=always|never|auto` flag out of `args`, defaulting to
+/// `ColorMode::Auto` when it's absent or its value doesn't parse, since a
+/// mistyped flag shouldn't stop the checker from running.
+fn parse_color_flag(args: impl Iterator<Item = String>) -> ColorMode {
+    args.filter_map(|arg| arg.strip_prefix("--color=").and_then(|value| value.parse().ok()))
+        .last()
+        .unwrap_or(ColorMode::Auto)
+}
+
+/// Builds a [`WarningsConfig`] out of `-A`/`-W`/`-D` flags, each followed by
+/// a lint name (`unused` or `shadowing`), e.g. `-W unused -D shadowing`. An
+/// unrecognized flag or lint name is ignored rather than treated as an
+/// error, since a mistyped flag shouldn't stop the checker from running.
+fn parse_warnings_flags(args: &[String]) -> WarningsConfig {
+    let mut config = WarningsConfig::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let level = match arg.as_str() {
+            "-A" => Level::Allow,
+            "-W" => Level::Warn,
+            "-D" => Level::Deny,
+            _ => continue,
+        };
+        if let Some(name) = iter.next() {
+            if let Some(lint) = parse_lint_name(name) {
+                config.set(lint, level);
+            }
+        }
+    }
+    config
+}
+
+fn parse_lint_name(name: &str) -> Option<Lint> {
+    match name {
+        "unused" => Some(Lint::Unused),
+        "shadowing" => Some(Lint::Shadowing),
+        _ => None,
+    }
+}
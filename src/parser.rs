@@ -1,8 +1,365 @@
-use crate::tokenizer::Token;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Error, Formatter};
+use std::ops::Deref;
+
+use crate::tokenizer::{Span, SpannedToken, Token};
+
+/// A parsed term together with the [`Span`] of source text it was parsed
+/// from, so downstream passes (type errors, constraints) can point back at
+/// exact source locations instead of just the term's shape.
+///
+/// Two terms are equal exactly when their `kind`s are equal — the span is
+/// provenance, not part of a term's identity, so code that only cares about
+/// AST shape (including every test written before spans existed) can keep
+/// comparing terms structurally without pinning down exact source positions.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Term {
+    pub kind: TermKind,
+    pub span: Span,
+}
+
+impl Term {
+    pub fn new(kind: TermKind, span: Span) -> Term {
+        Term { kind, span }
+    }
+
+    /// Builds a boolean literal with no source [`Span`], for library users
+    /// constructing an AST directly instead of going through [`parse`].
+    pub fn boolean(value: bool) -> Term {
+        Term::new(TermKind::Boolean(value), Span::default())
+    }
+
+    /// Builds an integer literal with no source [`Span`].
+    pub fn integer(value: i32) -> Term {
+        Term::new(TermKind::Integer(value), Span::default())
+    }
+
+    /// Builds an identifier reference with no source [`Span`].
+    pub fn identifier(name: impl Into<String>) -> Term {
+        Term::new(TermKind::Identifier(name.into()), Span::default())
+    }
+
+    /// Builds a function application `function(argument)` with no source
+    /// [`Span`].
+    pub fn app(function: Term, argument: Term) -> Term {
+        Term::new(
+            TermKind::FunctionApplication {
+                function: Box::from(function),
+                argument: Box::from(argument),
+            },
+            Span::default(),
+        )
+    }
+
+    /// Builds a function definition `fn parameter => body` with no source
+    /// [`Span`].
+    pub fn lambda(parameter: impl Into<String>, body: Term) -> Term {
+        Term::new(
+            TermKind::FunctionDefinition {
+                parameter: Box::from(Term::identifier(parameter)),
+                body: Box::from(body),
+            },
+            Span::default(),
+        )
+    }
+
+    /// Builds an `if condition then true_branch else false_branch` with no
+    /// source [`Span`].
+    pub fn if_then_else(condition: Term, true_branch: Term, false_branch: Term) -> Term {
+        Term::new(
+            TermKind::IfExpression {
+                condition: Box::from(condition),
+                true_branch: Box::from(true_branch),
+                false_branch: Box::from(false_branch),
+            },
+            Span::default(),
+        )
+    }
+
+    /// Builds a `let val declaration_name = declaration_value in expression
+    /// end` with no source [`Span`].
+    pub fn let_in(
+        declaration_name: impl Into<String>,
+        declaration_value: Term,
+        expression: Term,
+    ) -> Term {
+        Term::new(
+            TermKind::LetExpression {
+                declaration_name: Box::from(Term::identifier(declaration_name)),
+                declaration_value: Box::from(declaration_value),
+                expression: Box::from(expression),
+            },
+            Span::default(),
+        )
+    }
+
+    /// Builds a `raise exception` with no source [`Span`].
+    pub fn raise(exception: Term) -> Term {
+        Term::new(
+            TermKind::RaiseExpression {
+                exception: Box::from(exception),
+            },
+            Span::default(),
+        )
+    }
+
+    /// Builds the placeholder used for a subtree that could not be parsed,
+    /// with no source [`Span`]; see [`TermKind::Error`].
+    pub fn error() -> Term {
+        Term::new(TermKind::Error, Span::default())
+    }
+
+    /// Compares two terms for structural equality up to the names of bound
+    /// variables, so `fn x => x` and `fn y => y` are considered equal even
+    /// though [`PartialEq`] would tell them apart. Free variables (those not
+    /// bound by an enclosing `fn` or `let` in either term) still have to
+    /// match by name. Useful for testing desugarings and other
+    /// transformations that are allowed to rename binders.
+    pub fn alpha_eq(&self, other: &Term) -> bool {
+        alpha_eq(&mut Vec::new(), self, other)
+    }
+}
+
+fn alpha_eq(bound: &mut Vec<(String, String)>, left: &Term, right: &Term) -> bool {
+    match (&left.kind, &right.kind) {
+        (TermKind::Boolean(a), TermKind::Boolean(b)) => a == b,
+        (TermKind::Error, TermKind::Error) => true,
+        (TermKind::Integer(a), TermKind::Integer(b)) => a == b,
+        (TermKind::Identifier(a), TermKind::Identifier(b)) => {
+            let left_depth = bound.iter().rev().position(|(l, _)| l == a);
+            let right_depth = bound.iter().rev().position(|(_, r)| r == b);
+            match (left_depth, right_depth) {
+                (Some(i), Some(j)) => i == j,
+                (None, None) => a == b,
+                _ => false,
+            }
+        }
+        (
+            TermKind::FunctionApplication { function: f1, argument: a1 },
+            TermKind::FunctionApplication { function: f2, argument: a2 },
+        ) => alpha_eq(bound, f1, f2) && alpha_eq(bound, a1, a2),
+        (
+            TermKind::FunctionDefinition { parameter: p1, body: b1 },
+            TermKind::FunctionDefinition { parameter: p2, body: b2 },
+        ) => match (&p1.kind, &p2.kind) {
+            (TermKind::Identifier(n1), TermKind::Identifier(n2)) => {
+                bound.push((n1.clone(), n2.clone()));
+                let equal = alpha_eq(bound, b1, b2);
+                bound.pop();
+                equal
+            }
+            _ => false,
+        },
+        (
+            TermKind::IfExpression { condition: c1, true_branch: t1, false_branch: n1 },
+            TermKind::IfExpression { condition: c2, true_branch: t2, false_branch: n2 },
+        ) => alpha_eq(bound, c1, c2) && alpha_eq(bound, t1, t2) && alpha_eq(bound, n1, n2),
+        (
+            TermKind::LetExpression {
+                declaration_name: n1,
+                declaration_value: v1,
+                expression: e1,
+            },
+            TermKind::LetExpression {
+                declaration_name: n2,
+                declaration_value: v2,
+                expression: e2,
+            },
+        ) => {
+            if !alpha_eq(bound, v1, v2) {
+                return false;
+            }
+            match (&n1.kind, &n2.kind) {
+                (TermKind::Identifier(name1), TermKind::Identifier(name2)) => {
+                    bound.push((name1.clone(), name2.clone()));
+                    let equal = alpha_eq(bound, e1, e2);
+                    bound.pop();
+                    equal
+                }
+                _ => false,
+            }
+        }
+        (TermKind::RaiseExpression { exception: e1 }, TermKind::RaiseExpression { exception: e2 }) => {
+            alpha_eq(bound, e1, e2)
+        }
+        _ => false,
+    }
+}
+
+/// Collects every identifier in `term` that isn't bound by an enclosing
+/// `fn` parameter or `let` declaration, needed by generalization, closure
+/// conversion, and lint passes that would otherwise each have to walk the
+/// tree by hand.
+pub fn free_variables(term: &Term) -> HashSet<String> {
+    let mut free = HashSet::new();
+    collect_free_variables(term, &mut Vec::new(), &mut free);
+    free
+}
+
+fn collect_free_variables(term: &Term, bound: &mut Vec<String>, free: &mut HashSet<String>) {
+    match &term.kind {
+        TermKind::Boolean(_) | TermKind::Error | TermKind::Integer(_) => {}
+        TermKind::Identifier(name) => {
+            if !bound.contains(name) {
+                free.insert(name.clone());
+            }
+        }
+        TermKind::FunctionApplication { function, argument } => {
+            collect_free_variables(function, bound, free);
+            collect_free_variables(argument, bound, free);
+        }
+        TermKind::FunctionDefinition { parameter, body } => {
+            if let TermKind::Identifier(name) = &parameter.kind {
+                bound.push(name.clone());
+                collect_free_variables(body, bound, free);
+                bound.pop();
+            }
+        }
+        TermKind::IfExpression {
+            condition,
+            true_branch,
+            false_branch,
+        } => {
+            collect_free_variables(condition, bound, free);
+            collect_free_variables(true_branch, bound, free);
+            collect_free_variables(false_branch, bound, free);
+        }
+        TermKind::LetExpression {
+            declaration_name,
+            declaration_value,
+            expression,
+        } => {
+            collect_free_variables(declaration_value, bound, free);
+            if let TermKind::Identifier(name) = &declaration_name.kind {
+                bound.push(name.clone());
+                collect_free_variables(expression, bound, free);
+                bound.pop();
+            }
+        }
+        TermKind::RaiseExpression { exception } => collect_free_variables(exception, bound, free),
+    }
+}
+
+/// Replaces every free occurrence of `name` in `term` with `replacement`,
+/// renaming a `fn` parameter or `let` binding when necessary so that a free
+/// variable of `replacement` isn't accidentally captured by a binder it
+/// passes through, the primitive an evaluator or program transformation
+/// needs to be correct rather than merely convenient.
+pub fn substitute(term: &Term, name: &str, replacement: &Term) -> Term {
+    let kind = match &term.kind {
+        TermKind::Boolean(value) => TermKind::Boolean(*value),
+        TermKind::Error => TermKind::Error,
+        TermKind::Integer(value) => TermKind::Integer(*value),
+        TermKind::Identifier(identifier) => {
+            if identifier == name {
+                return replacement.clone();
+            }
+            TermKind::Identifier(identifier.clone())
+        }
+        TermKind::FunctionApplication { function, argument } => TermKind::FunctionApplication {
+            function: Box::from(substitute(function, name, replacement)),
+            argument: Box::from(substitute(argument, name, replacement)),
+        },
+        TermKind::FunctionDefinition { parameter, body } => {
+            let (parameter, body) = substitute_under_binder(parameter, body, name, replacement);
+            TermKind::FunctionDefinition {
+                parameter: Box::from(parameter),
+                body: Box::from(body),
+            }
+        }
+        TermKind::IfExpression {
+            condition,
+            true_branch,
+            false_branch,
+        } => TermKind::IfExpression {
+            condition: Box::from(substitute(condition, name, replacement)),
+            true_branch: Box::from(substitute(true_branch, name, replacement)),
+            false_branch: Box::from(substitute(false_branch, name, replacement)),
+        },
+        TermKind::LetExpression {
+            declaration_name,
+            declaration_value,
+            expression,
+        } => {
+            let declaration_value = substitute(declaration_value, name, replacement);
+            let (declaration_name, expression) =
+                substitute_under_binder(declaration_name, expression, name, replacement);
+            TermKind::LetExpression {
+                declaration_name: Box::from(declaration_name),
+                declaration_value: Box::from(declaration_value),
+                expression: Box::from(expression),
+            }
+        }
+        TermKind::RaiseExpression { exception } => TermKind::RaiseExpression {
+            exception: Box::from(substitute(exception, name, replacement)),
+        },
+    };
+    Term::new(kind, term.span)
+}
+
+/// Substitutes into the body of a `fn` or `let` binder, renaming the bound
+/// name first if it would otherwise capture a free variable of
+/// `replacement`. Returns the (possibly renamed) binder and the
+/// substituted body.
+fn substitute_under_binder(
+    parameter: &Term,
+    body: &Term,
+    name: &str,
+    replacement: &Term,
+) -> (Term, Term) {
+    let bound_name = match &parameter.kind {
+        TermKind::Identifier(bound_name) => bound_name.clone(),
+        // Not a well-formed binder (only reachable via a hand-built Term);
+        // there's nothing to shadow or rename, so recurse as normal.
+        _ => return (parameter.clone(), substitute(body, name, replacement)),
+    };
+    if bound_name == name {
+        // The binder shadows `name`; nothing under it is substituted.
+        return (parameter.clone(), body.clone());
+    }
+    let free_in_replacement = free_variables(replacement);
+    if free_in_replacement.contains(&bound_name) {
+        let fresh_name = fresh_variable_name(&bound_name, body, &free_in_replacement);
+        let renamed_parameter = Term::identifier(fresh_name.clone());
+        let renamed_body = substitute(body, &bound_name, &renamed_parameter);
+        (renamed_parameter, substitute(&renamed_body, name, replacement))
+    } else {
+        (parameter.clone(), substitute(body, name, replacement))
+    }
+}
+
+/// Finds a variant of `base` (by appending `'`) that occurs free in neither
+/// `body` nor `avoid`, so renaming a binder to it can't introduce a new
+/// capture of its own.
+fn fresh_variable_name(base: &str, body: &Term, avoid: &HashSet<String>) -> String {
+    let free_in_body = free_variables(body);
+    let mut candidate = format!("{}'", base);
+    while avoid.contains(&candidate) || free_in_body.contains(&candidate) {
+        candidate.push('\'');
+    }
+    candidate
+}
+
+impl PartialEq for Term {
+    fn eq(&self, other: &Term) -> bool {
+        self.kind == other.kind
+    }
+}
 
 #[derive(Clone, Debug, PartialEq)]
-pub enum Term {
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum TermKind {
     Boolean(bool),
+    /// Stands in for a subtree that could not be parsed: the parser puts it
+    /// in place of a missing right-hand operand (see [`parse_partial`]), and
+    /// [`parse_partial`], [`parse_with_recovery`] and
+    /// [`parse_with_recovery_and_table`] return it as the whole term when
+    /// parsing fails without completing one. Downstream
+    /// passes are expected to skip over it rather than treat it as real
+    /// data (see `annotator::annotate_term` and
+    /// `constraint::collect_constraints_with_bindings`).
+    Error,
     FunctionApplication {
         function: Box<Term>,
         argument: Box<Term>,
@@ -23,533 +380,1819 @@ pub enum Term {
         declaration_value: Box<Term>,
         expression: Box<Term>,
     },
+    RaiseExpression {
+        exception: Box<Term>,
+    },
 }
 
-pub fn parse(tokens: &Vec<Token>) -> Result<Term, String> {
-    let (term, _) = parse_expression(tokens, 0)?;
-    Ok(term)
+/// Visits every node of a [`Term`] tree, so an analysis (a linter, a metric,
+/// a free-variable collector) can hook the variants it cares about without
+/// reimplementing the recursion over the rest of [`TermKind`]. The default
+/// method bodies just recurse via [`walk_term`], so overriding none of them
+/// visits every node without doing anything.
+pub trait TermVisitor {
+    fn visit_term(&mut self, term: &Term) {
+        walk_term(self, term);
+    }
+    fn visit_boolean(&mut self, _value: bool) {}
+    fn visit_error(&mut self) {}
+    fn visit_identifier(&mut self, _name: &str) {}
+    fn visit_integer(&mut self, _value: i32) {}
 }
 
-fn parse_expression(tokens: &Vec<Token>, position: usize) -> Result<(Term, usize), String> {
-    if let Some(token) = tokens.get(position) {
-        match token {
-            Token::Boolean(value) => Ok((Term::Boolean(*value), position + 1)),
-            Token::Identifier(name) => {
-                if let Some(next_token) = tokens.get(position + 1) {
-                    if is_binary_operator(next_token) {
-                        parse_binary_operation(tokens, position)
-                    } else {
-                        match next_token {
-                            Token::LeftParenthesis => parse_function_application(tokens, position),
-                            _ => Ok((Term::Identifier(name.clone()), position + 1)),
-                        }
+/// The default recursion for [`TermVisitor::visit_term`]: dispatches leaf
+/// variants to their dedicated `visit_*` method and recurses into the
+/// children of every compound variant.
+pub fn walk_term<V: TermVisitor + ?Sized>(visitor: &mut V, term: &Term) {
+    match &term.kind {
+        TermKind::Boolean(value) => visitor.visit_boolean(*value),
+        TermKind::Error => visitor.visit_error(),
+        TermKind::FunctionApplication { function, argument } => {
+            visitor.visit_term(function);
+            visitor.visit_term(argument);
+        }
+        TermKind::FunctionDefinition { parameter, body } => {
+            visitor.visit_term(parameter);
+            visitor.visit_term(body);
+        }
+        TermKind::Identifier(name) => visitor.visit_identifier(name),
+        TermKind::IfExpression {
+            condition,
+            true_branch,
+            false_branch,
+        } => {
+            visitor.visit_term(condition);
+            visitor.visit_term(true_branch);
+            visitor.visit_term(false_branch);
+        }
+        TermKind::Integer(value) => visitor.visit_integer(*value),
+        TermKind::LetExpression {
+            declaration_name,
+            declaration_value,
+            expression,
+        } => {
+            visitor.visit_term(declaration_name);
+            visitor.visit_term(declaration_value);
+            visitor.visit_term(expression);
+        }
+        TermKind::RaiseExpression { exception } => {
+            visitor.visit_term(exception);
+        }
+    }
+}
+
+/// Rebuilds a [`Term`] tree bottom-up, so a transformation (desugaring,
+/// constant folding, refactoring) can override just the variants it cares
+/// about instead of writing a full match over [`TermKind`]. Overriding
+/// [`fold_term`](TermFolder::fold_term) and calling [`fold_term_default`]
+/// inside it gives access to the already-folded children, e.g. to collapse
+/// `+(1)(2)` into `3` once both operands have been folded to integers.
+pub trait TermFolder {
+    fn fold_term(&mut self, term: &Term) -> Term {
+        fold_term_default(self, term)
+    }
+    fn fold_boolean(&mut self, value: bool) -> TermKind {
+        TermKind::Boolean(value)
+    }
+    fn fold_error(&mut self) -> TermKind {
+        TermKind::Error
+    }
+    fn fold_identifier(&mut self, name: &str) -> TermKind {
+        TermKind::Identifier(String::from(name))
+    }
+    fn fold_integer(&mut self, value: i32) -> TermKind {
+        TermKind::Integer(value)
+    }
+}
+
+/// The default bottom-up rebuild for [`TermFolder::fold_term`]: dispatches
+/// leaf variants to their dedicated `fold_*` method and rebuilds every
+/// compound variant from its recursively folded children, keeping the
+/// original [`Span`].
+pub fn fold_term_default<F: TermFolder + ?Sized>(folder: &mut F, term: &Term) -> Term {
+    let kind = match &term.kind {
+        TermKind::Boolean(value) => folder.fold_boolean(*value),
+        TermKind::Error => folder.fold_error(),
+        TermKind::FunctionApplication { function, argument } => TermKind::FunctionApplication {
+            function: Box::from(folder.fold_term(function)),
+            argument: Box::from(folder.fold_term(argument)),
+        },
+        TermKind::FunctionDefinition { parameter, body } => TermKind::FunctionDefinition {
+            parameter: Box::from(folder.fold_term(parameter)),
+            body: Box::from(folder.fold_term(body)),
+        },
+        TermKind::Identifier(name) => folder.fold_identifier(name),
+        TermKind::IfExpression {
+            condition,
+            true_branch,
+            false_branch,
+        } => TermKind::IfExpression {
+            condition: Box::from(folder.fold_term(condition)),
+            true_branch: Box::from(folder.fold_term(true_branch)),
+            false_branch: Box::from(folder.fold_term(false_branch)),
+        },
+        TermKind::Integer(value) => folder.fold_integer(*value),
+        TermKind::LetExpression {
+            declaration_name,
+            declaration_value,
+            expression,
+        } => TermKind::LetExpression {
+            declaration_name: Box::from(folder.fold_term(declaration_name)),
+            declaration_value: Box::from(folder.fold_term(declaration_value)),
+            expression: Box::from(folder.fold_term(expression)),
+        },
+        TermKind::RaiseExpression { exception } => TermKind::RaiseExpression {
+            exception: Box::from(folder.fold_term(exception)),
+        },
+    };
+    Term::new(kind, term.span)
+}
+
+/// A token, or category of token, that would have been accepted at some
+/// point in the grammar. Kept separate from [`Token`] because a grammar
+/// position sometimes accepts any token of a kind (e.g. any identifier)
+/// rather than one specific token, and a data-carrying `Token` variant like
+/// `Token::Identifier` can't stand in for "any identifier" on its own.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExpectedToken {
+    Token(Token),
+    Identifier,
+    Integer,
+}
+
+impl Display for ExpectedToken {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            ExpectedToken::Token(token) => write!(f, "{}", describe_token(token)),
+            ExpectedToken::Identifier => write!(f, "identifier"),
+            ExpectedToken::Integer => write!(f, "integer"),
+        }
+    }
+}
+
+fn describe_token(token: &Token) -> &'static str {
+    match token {
+        Token::Arrow => "`=>`",
+        Token::Boolean(_) => "boolean",
+        Token::Divide => "`/`",
+        Token::Equals => "`=`",
+        Token::Identifier(_) => "identifier",
+        Token::Integer(_) => "integer",
+        Token::KeywordElse => "`else`",
+        Token::KeywordEnd => "`end`",
+        Token::KeywordFn => "`fn`",
+        Token::KeywordIf => "`if`",
+        Token::KeywordIn => "`in`",
+        Token::KeywordLet => "`let`",
+        Token::KeywordRaise => "`raise`",
+        Token::KeywordThen => "`then`",
+        Token::KeywordVal => "`val`",
+        Token::LeftParenthesis => "`(`",
+        Token::Minus => "`-`",
+        Token::Plus => "`+`",
+        Token::RightParenthesis => "`)`",
+        Token::String(_) => "string",
+        Token::Times => "`*`",
+    }
+}
+
+/// A syntax error raised while parsing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// A token (or category of token) would have been accepted at the point
+    /// where parsing failed, so a caller can render a message like
+    /// "expected `then` or `)` but found `end`" instead of a generic
+    /// failure.
+    UnexpectedToken {
+        found: Option<Token>,
+        expected: Vec<ExpectedToken>,
+    },
+    /// Parsing gave up because it recursed more than `limit` levels deep,
+    /// which is how pathologically nested input (e.g. `f(f(f(...)))` ten
+    /// thousand calls deep) is reported instead of overflowing the stack.
+    NestingTooDeep { limit: usize },
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            ParseError::UnexpectedToken { found, expected } => {
+                let expected = expected
+                    .iter()
+                    .map(|token| token.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" or ");
+                match found {
+                    Some(token) => {
+                        write!(f, "expected {} but found {}", expected, describe_token(token))
                     }
-                } else {
-                    Ok((Term::Identifier(name.clone()), position + 1))
+                    None => write!(f, "expected {} but found end of input", expected),
                 }
             }
-            Token::Integer(value) => {
-                if let Some(next_token) = tokens.get(position + 1) {
-                    if is_binary_operator(next_token) {
-                        parse_binary_operation(tokens, position)
-                    } else {
-                        Ok((Term::Integer(*value), position + 1))
-                    }
-                } else {
-                    Ok((Term::Integer(*value), position + 1))
-                }
+            ParseError::NestingTooDeep { limit } => {
+                write!(f, "expression nested more than {} levels deep", limit)
             }
-            Token::KeywordFn => parse_function_definition(tokens, position),
-            Token::KeywordIf => parse_if_expression(tokens, position),
-            Token::KeywordLet => parse_let_expression(tokens, position),
-            _ => Err(format!(
-                "expected `fn` keyword, `if` keyword, identifier, or integer but got {:?}",
-                token,
-            )),
         }
+    }
+}
+
+impl From<ParseError> for String {
+    fn from(error: ParseError) -> String {
+        error.to_string()
+    }
+}
+
+/// Every [`ParseError`] collected during one call to [`parse`] (or one of
+/// its variants), in the order they were encountered, so a caller can
+/// report them all instead of fixing one mistake at a time.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseErrors(pub Vec<ParseError>);
+
+impl Deref for ParseErrors {
+    type Target = [ParseError];
+
+    fn deref(&self) -> &[ParseError] {
+        &self.0
+    }
+}
+
+impl IntoIterator for ParseErrors {
+    type Item = ParseError;
+    type IntoIter = std::vec::IntoIter<ParseError>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl Display for ParseErrors {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(
+            f,
+            "{}",
+            self.0
+                .iter()
+                .map(ParseError::to_string)
+                .collect::<Vec<String>>()
+                .join("; ")
+        )
+    }
+}
+
+impl From<ParseErrors> for String {
+    fn from(errors: ParseErrors) -> String {
+        errors.to_string()
+    }
+}
+
+fn unexpected(found: Option<&Token>, expected: Vec<ExpectedToken>) -> ParseError {
+    ParseError::UnexpectedToken {
+        found: found.cloned(),
+        expected,
+    }
+}
+
+/// The recursion limit used by [`parse`] and [`parse_with_precedence_table`]
+/// before giving up with [`ParseError::NestingTooDeep`] instead of
+/// overflowing the stack on pathologically nested input; use
+/// [`parse_with_max_depth`] to raise or lower it.
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+fn check_depth(depth: usize, max_depth: usize) -> Result<(), ParseError> {
+    if depth > max_depth {
+        Err(ParseError::NestingTooDeep { limit: max_depth })
     } else {
-        Err(String::from(
-            "expected `fn` keyword, `if` keyword, identifier, or integer but got nothing",
-        ))
-    }
-}
-
-fn parse_let_expression(tokens: &Vec<Token>, position: usize) -> Result<(Term, usize), String> {
-    if let Some(token) = tokens.get(position) {
-        match token {
-            Token::KeywordLet => match parse_declaration_clause(tokens, position + 1)? {
-                (declaration_name_term, declaration_value_term, position) => {
-                    if let Some(token) = tokens.get(position) {
-                        match token {
-                            Token::KeywordIn => match parse_expression(tokens, position + 1)? {
-                                (expression_term, position) => {
-                                    if let Some(token) = tokens.get(position) {
-                                        match token {
-                                            Token::KeywordEnd => Ok((
-                                                Term::LetExpression {
-                                                    declaration_name: Box::from(
-                                                        declaration_name_term,
-                                                    ),
-                                                    declaration_value: Box::from(
-                                                        declaration_value_term,
-                                                    ),
-                                                    expression: Box::from(expression_term),
-                                                },
-                                                position,
-                                            )),
-                                            _ => Err(format!(
-                                                "expected `end` keyword but got {:?}",
-                                                token
-                                            )),
-                                        }
-                                    } else {
-                                        Err(String::from("expected `end` keyword but got nothing"))
-                                    }
-                                }
-                            },
-                            _ => Err(format!("expected `in` keyword but got {:?}", token)),
-                        }
-                    } else {
-                        Err(String::from("expected `in` keyword but got nothing"))
-                    }
+        Ok(())
+    }
+}
+
+/// A cursor over a token slice that supports lookahead and backtracking, so
+/// the parser doesn't have to thread a `(tokens, position)` pair by hand
+/// through every function the way it used to.
+pub struct TokenStream<'a> {
+    tokens: &'a [SpannedToken],
+    position: usize,
+}
+
+impl<'a> TokenStream<'a> {
+    pub fn new(tokens: &'a [SpannedToken]) -> TokenStream<'a> {
+        TokenStream { tokens, position: 0 }
+    }
+
+    /// The token at the current position, without consuming it.
+    pub fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.position).map(|token| &token.token)
+    }
+
+    /// The token `n` positions ahead of the current one, without consuming
+    /// anything; `peek_n(0)` is the same as [`peek`](TokenStream::peek).
+    pub fn peek_n(&self, n: usize) -> Option<&'a Token> {
+        self.tokens
+            .get(self.position + n)
+            .map(|token| &token.token)
+    }
+
+    /// Consumes and returns the token at the current position.
+    pub fn advance(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.position);
+        if token.is_some() {
+            self.position += 1;
+        }
+        token.map(|token| &token.token)
+    }
+
+    /// The span of the token at `position`, or `None` past the end of the
+    /// stream.
+    pub fn span_at(&self, position: usize) -> Option<Span> {
+        self.tokens.get(position).map(|token| token.span)
+    }
+
+    /// The span covering every token in `[start, end)`, combining the start
+    /// of the first with the end of the last; falls back to a dummy span if
+    /// the range is empty or past the end of the stream (e.g. an empty file).
+    pub fn span_range(&self, start: usize, end: usize) -> Span {
+        let first = self.span_at(start);
+        let last = if end > start {
+            self.span_at(end - 1)
+        } else {
+            first
+        };
+        match (first, last) {
+            (Some(first), Some(last)) => Span {
+                start: first.start,
+                end: last.end,
+                line: first.line,
+                column: first.column,
+            },
+            _ => Span::default(),
+        }
+    }
+
+    /// The current position, to be passed back to
+    /// [`rewind`](TokenStream::rewind) later to undo any tokens consumed
+    /// since, for speculative parsing that didn't pan out.
+    pub fn checkpoint(&self) -> usize {
+        self.position
+    }
+
+    /// Resets the stream to a position previously returned by
+    /// [`checkpoint`](TokenStream::checkpoint).
+    pub fn rewind(&mut self, checkpoint: usize) {
+        self.position = checkpoint;
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Advances past tokens until the next synchronizing keyword (`val`,
+    /// `in`, `end`, or `let`) or the end of input, always consuming at least one
+    /// token first so a caller can't loop forever when the stream is
+    /// already sitting on a synchronizing token. Returns whether there are
+    /// any tokens left to resume parsing from.
+    pub fn synchronize(&mut self) -> bool {
+        self.advance();
+        while let Some(token) = self.peek() {
+            if is_synchronizing_token(token) {
+                break;
+            }
+            self.advance();
+        }
+        self.peek().is_some()
+    }
+}
+
+fn is_synchronizing_token(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::KeywordVal | Token::KeywordIn | Token::KeywordEnd | Token::KeywordLet
+    )
+}
+
+/// Parses a complete token stream into a [`Term`], stopping at the first
+/// syntax error. Returns every [`ParseError`] collected while parsing (not
+/// just the first) so a caller can report them all at once instead of
+/// fixing one mistake at a time; use [`parse_with_recovery`] to keep
+/// parsing past a broken construct and surface diagnostics from the rest of
+/// the input too.
+pub fn parse(tokens: &[SpannedToken]) -> Result<Term, ParseErrors> {
+    parse_with_precedence_table(tokens, &PrecedenceTable::default())
+}
+
+/// Like [`parse`], but consults `table` for operator precedence and
+/// associativity instead of the built-in defaults, for embedders that need
+/// to register their own operators (e.g. from an `infix` declaration)
+/// without recompiling the crate.
+pub fn parse_with_precedence_table(
+    tokens: &[SpannedToken],
+    table: &PrecedenceTable,
+) -> Result<Term, ParseErrors> {
+    parse_with_table_and_max_depth(tokens, table, DEFAULT_MAX_DEPTH)
+}
+
+/// Like [`parse`], but gives up with [`ParseError::NestingTooDeep`] once
+/// expressions nest more than `max_depth` levels deep, instead of always
+/// enforcing [`DEFAULT_MAX_DEPTH`].
+pub fn parse_with_max_depth(tokens: &[SpannedToken], max_depth: usize) -> Result<Term, ParseErrors> {
+    parse_with_table_and_max_depth(tokens, &PrecedenceTable::default(), max_depth)
+}
+
+fn parse_with_table_and_max_depth(
+    tokens: &[SpannedToken],
+    table: &PrecedenceTable,
+    max_depth: usize,
+) -> Result<Term, ParseErrors> {
+    let mut stream = TokenStream::new(tokens);
+    let mut errors = Vec::new();
+    match parse_expression(&mut stream, table, &mut errors, 0, max_depth) {
+        Ok(term) if errors.is_empty() => Ok(term),
+        Ok(_) => Err(ParseErrors(errors)),
+        Err(error) => {
+            errors.push(error);
+            Err(ParseErrors(errors))
+        }
+    }
+}
+
+/// Like [`parse`], but tolerates a missing right-hand operand at the tail of
+/// a binary expression (e.g. `x +` with nothing after it) by substituting
+/// [`TermKind::Error`] instead of failing outright, so a REPL or editor can
+/// still build completions against a best-effort AST for input that simply
+/// hasn't been finished yet. Every error encountered along the way,
+/// including ones that were papered over with [`TermKind::Error`], is returned
+/// alongside the term. Unlike [`parse_with_recovery`], it does not
+/// synchronize past unrelated syntax errors elsewhere in the input.
+pub fn parse_partial(tokens: &[SpannedToken]) -> (Term, Vec<ParseError>) {
+    parse_partial_with_table(tokens, &PrecedenceTable::default())
+}
+
+/// Like [`parse_partial`], but consults `table` for operator precedence and
+/// associativity instead of the built-in defaults.
+pub fn parse_partial_with_table(tokens: &[SpannedToken], table: &PrecedenceTable) -> (Term, Vec<ParseError>) {
+    let mut stream = TokenStream::new(tokens);
+    let mut errors = Vec::new();
+    match parse_expression(&mut stream, table, &mut errors, 0, DEFAULT_MAX_DEPTH) {
+        Ok(term) => (term, errors),
+        Err(error) => {
+            errors.push(error);
+            (Term::new(TermKind::Error, Span::default()), errors)
+        }
+    }
+}
+
+/// Like [`parse`], but instead of stopping at the first syntax error,
+/// synchronizes to the next `val`, `in`, `end`, or `let` keyword and keeps
+/// trying, so a single call can surface several diagnostics from one input
+/// (useful for editor tooling, where the whole file is usually broken at
+/// once). Returns the last term it managed to parse — or [`TermKind::Error`] if
+/// recovery ran out of input before completing one — alongside every error
+/// encountered along the way, in the order encountered.
+pub fn parse_with_recovery(tokens: &[SpannedToken]) -> (Term, Vec<ParseError>) {
+    parse_with_recovery_and_table(tokens, &PrecedenceTable::default())
+}
+
+/// Like [`parse_with_recovery`], but consults `table` for operator
+/// precedence and associativity instead of the built-in defaults.
+pub fn parse_with_recovery_and_table(
+    tokens: &[SpannedToken],
+    table: &PrecedenceTable,
+) -> (Term, Vec<ParseError>) {
+    let mut stream = TokenStream::new(tokens);
+    let mut errors = Vec::new();
+    let mut term = Term::new(TermKind::Error, Span::default());
+    loop {
+        let checkpoint = stream.checkpoint();
+        let mut attempt_errors = Vec::new();
+        match parse_expression(&mut stream, table, &mut attempt_errors, 0, DEFAULT_MAX_DEPTH) {
+            Ok(parsed) => {
+                term = parsed;
+                errors.extend(attempt_errors);
+                break;
+            }
+            Err(error) => {
+                errors.extend(attempt_errors);
+                errors.push(error);
+                stream.rewind(checkpoint);
+                if !stream.synchronize() {
+                    break;
                 }
+            }
+        }
+    }
+    (term, errors)
+}
+
+fn parse_expression(
+    stream: &mut TokenStream,
+    table: &PrecedenceTable,
+    errors: &mut Vec<ParseError>,
+    depth: usize,
+    max_depth: usize,
+) -> Result<Term, ParseError> {
+    check_depth(depth, max_depth)?;
+    let depth = depth + 1;
+    match stream.peek() {
+        Some(Token::KeywordFn) => parse_function_definition(stream, table, errors, depth, max_depth),
+        Some(Token::KeywordIf) => parse_if_expression(stream, table, errors, depth, max_depth),
+        Some(Token::KeywordLet) => parse_let_expression(stream, table, errors, depth, max_depth),
+        Some(Token::KeywordRaise) => parse_raise_expression(stream, table, errors, depth, max_depth),
+        _ => parse_binary_expression(stream, table, 0, errors, depth, max_depth),
+    }
+}
+
+/// The relative binding power of a binary operator and the direction in
+/// which a run of same-precedence operators groups, e.g. `1 - 2 - 3` groups
+/// as `(1 - 2) - 3` under [`Associativity::Left`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Associativity {
+    Left,
+    Right,
+}
+
+/// An entry in the operator precedence table: how tightly an operator binds
+/// relative to its neighbors, and which way it associates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OperatorInfo {
+    pub precedence: u8,
+    pub associativity: Associativity,
+}
+
+/// A runtime-mutable table of operator precedence and associativity,
+/// consulted by the precedence-climbing parser in
+/// [`parse_binary_expression`] instead of a hardcoded table, so embedders
+/// can register additional operators (or change existing ones) without
+/// recompiling the crate.
+#[derive(Clone, Debug)]
+pub struct PrecedenceTable {
+    operators: HashMap<Token, OperatorInfo>,
+}
+
+impl PrecedenceTable {
+    /// An empty table with no operators registered.
+    pub fn new() -> PrecedenceTable {
+        PrecedenceTable {
+            operators: HashMap::new(),
+        }
+    }
+
+    /// Registers `token` as a binary operator with the given precedence and
+    /// associativity, overwriting any previous registration for it.
+    pub fn register(&mut self, token: Token, precedence: u8, associativity: Associativity) {
+        self.operators.insert(
+            token,
+            OperatorInfo {
+                precedence,
+                associativity,
+            },
+        );
+    }
+
+    pub(crate) fn get(&self, token: &Token) -> Option<OperatorInfo> {
+        self.operators.get(token).copied()
+    }
+}
+
+impl Default for PrecedenceTable {
+    /// `=` binds loosest, `+`/`-` bind tighter than that, and `*`/`/` bind
+    /// tightest, so `1 + 2 * 3` parses as `1 + (2 * 3)`.
+    fn default() -> PrecedenceTable {
+        let mut table = PrecedenceTable::new();
+        table.register(Token::Equals, 1, Associativity::Left);
+        table.register(Token::Plus, 2, Associativity::Left);
+        table.register(Token::Minus, 2, Associativity::Left);
+        table.register(Token::Times, 3, Associativity::Left);
+        table.register(Token::Divide, 3, Associativity::Left);
+        table
+    }
+}
+
+fn operator_name(token: &Token) -> String {
+    String::from(match token {
+        Token::Plus => "+",
+        Token::Minus => "-",
+        Token::Times => "*",
+        Token::Divide => "/",
+        Token::Equals => "=",
+        _ => unreachable!(),
+    })
+}
+
+/// Parses a chain of binary operations using precedence climbing: a primary
+/// term followed by zero or more `operator primary` pairs, folding each one
+/// into the running left-hand side as long as the operator's precedence is
+/// at least `min_precedence`. A right-hand side is itself parsed with a
+/// raised minimum precedence (or the same one, for right-associative
+/// operators), which is what makes `2 * 3` bind before the surrounding `+`.
+fn parse_binary_expression(
+    stream: &mut TokenStream,
+    table: &PrecedenceTable,
+    min_precedence: u8,
+    errors: &mut Vec<ParseError>,
+    depth: usize,
+    max_depth: usize,
+) -> Result<Term, ParseError> {
+    check_depth(depth, max_depth)?;
+    let depth = depth + 1;
+    let start = stream.position();
+    let mut left = parse_primary(stream, table, errors, depth, max_depth)?;
+    loop {
+        let operator = match stream.peek().and_then(|token| table.get(token)) {
+            Some(info) if info.precedence >= min_precedence => stream.peek().unwrap().clone(),
+            _ => break,
+        };
+        let info = table.get(&operator).unwrap();
+        let operator_position = stream.position();
+        stream.advance();
+        let next_min_precedence = match info.associativity {
+            Associativity::Left => info.precedence + 1,
+            Associativity::Right => info.precedence,
+        };
+        // A binary operator with nothing after it (e.g. `x +` at the end of
+        // an unfinished input) is reported as an error but does not abort
+        // the parse: the missing operand becomes `Term::Error` so the
+        // caller still gets back a term to work with.
+        let right = match parse_binary_expression(
+            stream,
+            table,
+            next_min_precedence,
+            errors,
+            depth,
+            max_depth,
+        ) {
+            Ok(term) => term,
+            Err(error) => {
+                errors.push(error);
+                Term::new(TermKind::Error, Span::default())
+            }
+        };
+        let span = stream.span_range(start, stream.position());
+        let operator_span = stream.span_range(operator_position, operator_position + 1);
+        left = Term::new(
+            TermKind::FunctionApplication {
+                function: Box::from(Term::new(
+                    TermKind::FunctionApplication {
+                        function: Box::from(Term::new(
+                            TermKind::Identifier(operator_name(&operator)),
+                            operator_span,
+                        )),
+                        argument: Box::from(left),
+                    },
+                    span,
+                )),
+                argument: Box::from(right),
             },
-            _ => Err(format!("expected `let` keyword but got {:?}", token)),
+            span,
+        );
+    }
+    Ok(left)
+}
+
+fn parse_primary(
+    stream: &mut TokenStream,
+    table: &PrecedenceTable,
+    errors: &mut Vec<ParseError>,
+    depth: usize,
+    max_depth: usize,
+) -> Result<Term, ParseError> {
+    let start = stream.position();
+    match stream.peek() {
+        Some(Token::Boolean(value)) => {
+            let value = *value;
+            stream.advance();
+            Ok(Term::new(
+                TermKind::Boolean(value),
+                stream.span_range(start, stream.position()),
+            ))
         }
-    } else {
-        Err(String::from("expected `let` keyword but got nothing"))
+        Some(Token::Identifier(name)) => {
+            let name = name.clone();
+            if stream.peek_n(1) == Some(&Token::LeftParenthesis) {
+                parse_function_application(stream, table, errors, depth, max_depth)
+            } else {
+                stream.advance();
+                Ok(Term::new(
+                    TermKind::Identifier(name),
+                    stream.span_range(start, stream.position()),
+                ))
+            }
+        }
+        Some(Token::Integer(value)) => {
+            let value = *value;
+            stream.advance();
+            Ok(Term::new(
+                TermKind::Integer(value),
+                stream.span_range(start, stream.position()),
+            ))
+        }
+        // A leading `-` is unary negation of the integer literal that
+        // follows, folded directly into the literal rather than kept as a
+        // separate AST node.
+        Some(Token::Minus) => {
+            stream.advance();
+            let other = stream.advance();
+            match other {
+                Some(Token::Integer(value)) => Ok(Term::new(
+                    TermKind::Integer(-value),
+                    stream.span_range(start, stream.position()),
+                )),
+                _ => Err(unexpected(other, vec![ExpectedToken::Integer])),
+            }
+        }
+        other => Err(unexpected(
+            other,
+            vec![
+                ExpectedToken::Token(Token::KeywordFn),
+                ExpectedToken::Token(Token::KeywordIf),
+                ExpectedToken::Token(Token::KeywordRaise),
+                ExpectedToken::Identifier,
+                ExpectedToken::Integer,
+            ],
+        )),
     }
 }
 
-fn parse_declaration_clause(
-    tokens: &Vec<Token>,
-    position: usize,
-) -> Result<(Term, Term, usize), String> {
-    if let Some(token) = tokens.get(position) {
-        match token {
-            Token::KeywordVal => match parse_identifier(tokens, position + 1)? {
-                (val_name_term, position) => {
-                    if let Some(token) = tokens.get(position) {
-                        match token {
-                            Token::Equals => match parse_expression(tokens, position + 1)? {
-                                (val_value_term, position) => {
-                                    Ok((val_name_term, val_value_term, position))
-                                }
+fn parse_raise_expression(
+    stream: &mut TokenStream,
+    table: &PrecedenceTable,
+    errors: &mut Vec<ParseError>,
+    depth: usize,
+    max_depth: usize,
+) -> Result<Term, ParseError> {
+    let start = stream.position();
+    let token = stream.advance();
+    match token {
+        Some(Token::KeywordRaise) => {
+            let exception_term = parse_expression(stream, table, errors, depth, max_depth)?;
+            Ok(Term::new(
+                TermKind::RaiseExpression {
+                    exception: Box::from(exception_term),
+                },
+                stream.span_range(start, stream.position()),
+            ))
+        }
+        _ => Err(unexpected(
+            token,
+            vec![ExpectedToken::Token(Token::KeywordRaise)],
+        )),
+    }
+}
+
+fn parse_let_expression(
+    stream: &mut TokenStream,
+    table: &PrecedenceTable,
+    errors: &mut Vec<ParseError>,
+    depth: usize,
+    max_depth: usize,
+) -> Result<Term, ParseError> {
+    let start = stream.position();
+    let token = stream.advance();
+    match token {
+        Some(Token::KeywordLet) => {
+            let (declaration_name_term, declaration_value_term) =
+                parse_declaration_clause(stream, table, errors, depth, max_depth)?;
+            let in_token = stream.advance();
+            match in_token {
+                Some(Token::KeywordIn) => {
+                    let expression_term = parse_expression(stream, table, errors, depth, max_depth)?;
+                    let end_token = stream.advance();
+                    match end_token {
+                        Some(Token::KeywordEnd) => Ok(Term::new(
+                            TermKind::LetExpression {
+                                declaration_name: Box::from(declaration_name_term),
+                                declaration_value: Box::from(declaration_value_term),
+                                expression: Box::from(expression_term),
                             },
-                            _ => Err(format!("expected `=` but got {:?}", token)),
-                        }
-                    } else {
-                        Err(String::from("expected `=` but got nothing"))
+                            stream.span_range(start, stream.position()),
+                        )),
+                        _ => Err(unexpected(
+                            end_token,
+                            vec![ExpectedToken::Token(Token::KeywordEnd)],
+                        )),
                     }
                 }
-            },
-            _ => Err(format!("expected `val` keyword but got {:?}", token)),
+                _ => Err(unexpected(
+                    in_token,
+                    vec![ExpectedToken::Token(Token::KeywordIn)],
+                )),
+            }
         }
-    } else {
-        Err(String::from("expected `val` keyword but got nothing"))
+        _ => Err(unexpected(
+            token,
+            vec![ExpectedToken::Token(Token::KeywordLet)],
+        )),
+    }
+}
+
+fn parse_declaration_clause(
+    stream: &mut TokenStream,
+    table: &PrecedenceTable,
+    errors: &mut Vec<ParseError>,
+    depth: usize,
+    max_depth: usize,
+) -> Result<(Term, Term), ParseError> {
+    let token = stream.advance();
+    match token {
+        Some(Token::KeywordVal) => {
+            let val_name_term = parse_identifier(stream)?;
+            let equals_token = stream.advance();
+            match equals_token {
+                Some(Token::Equals) => {
+                    let val_value_term = parse_expression(stream, table, errors, depth, max_depth)?;
+                    Ok((val_name_term, val_value_term))
+                }
+                _ => Err(unexpected(
+                    equals_token,
+                    vec![ExpectedToken::Token(Token::Equals)],
+                )),
+            }
+        }
+        _ => Err(unexpected(
+            token,
+            vec![ExpectedToken::Token(Token::KeywordVal)],
+        )),
     }
 }
 
 fn parse_function_definition(
-    tokens: &Vec<Token>,
-    position: usize,
-) -> Result<(Term, usize), String> {
-    if let Some(token) = tokens.get(position) {
-        match token {
-            Token::KeywordFn => match parse_identifier(tokens, position + 1)? {
-                (parameter_term, position) => {
-                    if let Some(token) = tokens.get(position) {
-                        match token {
-                            Token::Arrow => match parse_expression(tokens, position + 1)? {
-                                (body_term, position) => Ok((
-                                    Term::FunctionDefinition {
-                                        parameter: Box::from(parameter_term),
-                                        body: Box::from(body_term),
-                                    },
-                                    position,
-                                )),
-                            },
-                            _ => Err(format!(
-                                "expected `=>` after `fn` keyword and function parameter but got {:?}",
-                                token
-                            )),
-                        }
-                    } else {
-                        Err(String::from(
-                            "expected `=>` after `fn` keyword and function parameter but got nothing",
-                        ))
-                    }
+    stream: &mut TokenStream,
+    table: &PrecedenceTable,
+    errors: &mut Vec<ParseError>,
+    depth: usize,
+    max_depth: usize,
+) -> Result<Term, ParseError> {
+    let start = stream.position();
+    let token = stream.advance();
+    match token {
+        Some(Token::KeywordFn) => {
+            let parameter_term = parse_identifier(stream)?;
+            let arrow_token = stream.advance();
+            match arrow_token {
+                Some(Token::Arrow) => {
+                    let body_term = parse_expression(stream, table, errors, depth, max_depth)?;
+                    Ok(Term::new(
+                        TermKind::FunctionDefinition {
+                            parameter: Box::from(parameter_term),
+                            body: Box::from(body_term),
+                        },
+                        stream.span_range(start, stream.position()),
+                    ))
                 }
-            },
-            _ => Err(format!("expected `fn` keyword but got {:?}", token)),
+                _ => Err(unexpected(
+                    arrow_token,
+                    vec![ExpectedToken::Token(Token::Arrow)],
+                )),
+            }
         }
-    } else {
-        Err(String::from("expected `fn` keyword but got nothing"))
+        _ => Err(unexpected(
+            token,
+            vec![ExpectedToken::Token(Token::KeywordFn)],
+        )),
     }
 }
 
 fn parse_function_application(
-    tokens: &Vec<Token>,
-    position: usize,
-) -> Result<(Term, usize), String> {
-    if let Some(token) = tokens.get(position) {
-        match token {
-            Token::Identifier(name) => {
-                if let Some(token) = tokens.get(position + 1) {
-                    match token {
-                        Token::LeftParenthesis => {
-                            let (argument_term, position) = parse_expression(tokens, position + 2)?;
-                            if let Some(token) = tokens.get(position) {
-                                match token {
-                                    Token::RightParenthesis => Ok((
-                                        Term::FunctionApplication {
-                                            function: Box::from(Term::Identifier(name.clone())),
-                                            argument: Box::from(argument_term),
-                                        },
-                                        position + 1,
-                                    )),
-                                    _ => Err(format!("expected ')' but got {:?}", token)),
-                                }
-                            } else {
-                                Err(String::from("expected ')' but got nothing"))
-                            }
-                        }
-                        _ => Err(format!("expected '(' but got {:?}", token)),
+    stream: &mut TokenStream,
+    table: &PrecedenceTable,
+    errors: &mut Vec<ParseError>,
+    depth: usize,
+    max_depth: usize,
+) -> Result<Term, ParseError> {
+    let start = stream.position();
+    let identifier_span = stream.span_at(start).unwrap_or_default();
+    let token = stream.advance();
+    match token {
+        Some(Token::Identifier(name)) => {
+            let name = name.clone();
+            let paren_token = stream.advance();
+            match paren_token {
+                Some(Token::LeftParenthesis) => {
+                    let argument_term = parse_expression(stream, table, errors, depth, max_depth)?;
+                    let closing_token = stream.advance();
+                    match closing_token {
+                        Some(Token::RightParenthesis) => Ok(Term::new(
+                            TermKind::FunctionApplication {
+                                function: Box::from(Term::new(TermKind::Identifier(name), identifier_span)),
+                                argument: Box::from(argument_term),
+                            },
+                            stream.span_range(start, stream.position()),
+                        )),
+                        _ => Err(unexpected(
+                            closing_token,
+                            vec![ExpectedToken::Token(Token::RightParenthesis)],
+                        )),
                     }
-                } else {
-                    Err(String::from("expected '(' but got nothing"))
                 }
+                _ => Err(unexpected(
+                    paren_token,
+                    vec![ExpectedToken::Token(Token::LeftParenthesis)],
+                )),
             }
-            _ => Err(format!("expected identifier but got {:?}", token)),
         }
-    } else {
-        Err(String::from("expected identifier but got nothing"))
-    }
-}
-
-fn parse_if_expression(tokens: &Vec<Token>, position: usize) -> Result<(Term, usize), String> {
-    if let Some(token) = tokens.get(position) {
-        match token {
-            Token::KeywordIf => match parse_expression(tokens, position + 1)? {
-                (test_condition_term, position) => {
-                    if let Some(token) = tokens.get(position) {
-                        match token {
-                            Token::KeywordThen => match parse_expression(tokens, position + 1)? {
-                                (true_branch_term, position) => {
-                                    if let Some(token) = tokens.get(position) {
-                                        match token {
-                                            Token::KeywordElse => {
-                                                match parse_expression(tokens, position + 1)? {
-                                                    (false_branch_term, position) => Ok((
-                                                        Term::IfExpression {
-                                                            condition: Box::from(
-                                                                test_condition_term,
-                                                            ),
-                                                            true_branch: Box::from(
-                                                                true_branch_term,
-                                                            ),
-                                                            false_branch: Box::from(
-                                                                false_branch_term,
-                                                            ),
-                                                        },
-                                                        position,
-                                                    )),
-                                                }
-                                            }
-                                            _ => Err(format!(
-                                                "expected `else` keyword but got {:?}",
-                                                token
-                                            )),
-                                        }
-                                    } else {
-                                        Err(String::from("expected `else` keyword but got nothing"))
-                                    }
-                                }
-                            },
-                            _ => Err(format!("expected `then` keyword but got {:?}", token)),
+        _ => Err(unexpected(token, vec![ExpectedToken::Identifier])),
+    }
+}
+
+fn parse_if_expression(
+    stream: &mut TokenStream,
+    table: &PrecedenceTable,
+    errors: &mut Vec<ParseError>,
+    depth: usize,
+    max_depth: usize,
+) -> Result<Term, ParseError> {
+    let start = stream.position();
+    let token = stream.advance();
+    match token {
+        Some(Token::KeywordIf) => {
+            let test_condition_term = parse_expression(stream, table, errors, depth, max_depth)?;
+            let then_token = stream.advance();
+            match then_token {
+                Some(Token::KeywordThen) => {
+                    let true_branch_term = parse_expression(stream, table, errors, depth, max_depth)?;
+                    let else_token = stream.advance();
+                    match else_token {
+                        Some(Token::KeywordElse) => {
+                            let false_branch_term = parse_expression(stream, table, errors, depth, max_depth)?;
+                            Ok(Term::new(
+                                TermKind::IfExpression {
+                                    condition: Box::from(test_condition_term),
+                                    true_branch: Box::from(true_branch_term),
+                                    false_branch: Box::from(false_branch_term),
+                                },
+                                stream.span_range(start, stream.position()),
+                            ))
                         }
-                    } else {
-                        Err(String::from("expected `then` keyword but got nothing"))
+                        _ => Err(unexpected(
+                            else_token,
+                            vec![ExpectedToken::Token(Token::KeywordElse)],
+                        )),
                     }
                 }
-            },
-            _ => Err(format!("expected `if` keyword but got {:?}", token)),
+                _ => Err(unexpected(
+                    then_token,
+                    vec![ExpectedToken::Token(Token::KeywordThen)],
+                )),
+            }
         }
-    } else {
-        Err(String::from("expected `if` keyword but got nothing"))
+        _ => Err(unexpected(
+            token,
+            vec![ExpectedToken::Token(Token::KeywordIf)],
+        )),
     }
 }
 
-fn is_binary_operator(token: &Token) -> bool {
+fn parse_identifier(stream: &mut TokenStream) -> Result<Term, ParseError> {
+    let start = stream.position();
+    let token = stream.advance();
     match token {
-        Token::Plus | Token::Minus | Token::Times | Token::Divide | Token::Equals => true,
-        _ => false,
+        Some(Token::Identifier(name)) => Ok(Term::new(
+            TermKind::Identifier(name.clone()),
+            stream.span_range(start, stream.position()),
+        )),
+        _ => Err(unexpected(token, vec![ExpectedToken::Identifier])),
     }
 }
 
-fn parse_binary_operation(tokens: &Vec<Token>, position: usize) -> Result<(Term, usize), String> {
-    match parse_integer_or_identifier(tokens, position)? {
-        (left_term, position) => {
-            if let Some(middle_token) = tokens.get(position) {
-                if is_binary_operator(middle_token) {
-                    match parse_integer_or_identifier(tokens, position + 1)? {
-                        (right_term, position) => Ok((
-                            Term::FunctionApplication {
-                                function: Box::from(Term::FunctionApplication {
-                                    function: Box::from(Term::Identifier(match middle_token {
-                                        Token::Plus => String::from("+"),
-                                        Token::Minus => String::from("-"),
-                                        Token::Times => String::from("*"),
-                                        Token::Divide => String::from("/"),
-                                        Token::Equals => String::from("="),
-                                        _ => unimplemented!(),
-                                    })),
-                                    argument: Box::from(left_term),
-                                }),
-                                argument: Box::from(right_term),
-                            },
-                            position,
-                        )),
-                    }
-                } else {
-                    Err(format!(
-                        "expected binary operator but got {:?}",
-                        middle_token
-                    ))
-                }
-            } else {
-                Err(String::from("expected binary operator but got nothing"))
+#[cfg(test)]
+mod tests {
+    use crate::parser::{
+        fold_term_default, free_variables, parse, parse_declaration_clause, parse_partial,
+        parse_with_max_depth, parse_with_recovery, substitute, Associativity, ExpectedToken,
+        ParseError, ParseErrors, PrecedenceTable, Term, TermFolder, TermKind, TermVisitor,
+        TokenStream, DEFAULT_MAX_DEPTH,
+    };
+    use crate::tokenizer::{tokenize_with_spans, Span, Token};
+    use std::collections::HashSet;
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_term_serializes_to_the_expected_json_shape() -> Result<(), String> {
+        let tokens = tokenize_with_spans("42")?;
+        let term = parse(&tokens)?;
+        let json = serde_json::to_value(&term).unwrap();
+        assert_eq!(json["kind"]["Integer"], 42);
+        Ok(())
+    }
+
+    #[test]
+    fn test_term_builders_match_the_equivalent_parsed_term() -> Result<(), String> {
+        let tokens = tokenize_with_spans("fn x => x + 1")?;
+        let built = Term::lambda(
+            "x",
+            Term::app(
+                Term::app(Term::identifier("+"), Term::identifier("x")),
+                Term::integer(1),
+            ),
+        );
+        assert_eq!(parse(&tokens)?, built);
+        Ok(())
+    }
+
+    #[test]
+    fn test_term_builders_for_if_let_and_raise() {
+        assert_eq!(
+            Term::if_then_else(Term::boolean(true), Term::integer(0), Term::integer(1)),
+            Term::new(
+                TermKind::IfExpression {
+                    condition: Box::from(Term::boolean(true)),
+                    true_branch: Box::from(Term::integer(0)),
+                    false_branch: Box::from(Term::integer(1)),
+                },
+                Span::default()
+            )
+        );
+        assert_eq!(
+            Term::let_in("x", Term::integer(1), Term::identifier("x")),
+            Term::new(
+                TermKind::LetExpression {
+                    declaration_name: Box::from(Term::identifier("x")),
+                    declaration_value: Box::from(Term::integer(1)),
+                    expression: Box::from(Term::identifier("x")),
+                },
+                Span::default()
+            )
+        );
+        assert_eq!(
+            Term::raise(Term::integer(0)),
+            Term::new(
+                TermKind::RaiseExpression {
+                    exception: Box::from(Term::integer(0)),
+                },
+                Span::default()
+            )
+        );
+        assert_eq!(Term::error(), Term::new(TermKind::Error, Span::default()));
+    }
+
+    #[test]
+    fn test_alpha_eq_ignores_the_names_of_bound_variables() {
+        assert!(Term::lambda("x", Term::identifier("x")).alpha_eq(&Term::lambda("y", Term::identifier("y"))));
+        assert!(Term::let_in("a", Term::integer(1), Term::identifier("a"))
+            .alpha_eq(&Term::let_in("b", Term::integer(1), Term::identifier("b"))));
+    }
+
+    #[test]
+    fn test_alpha_eq_still_requires_free_variables_to_match_by_name() {
+        assert!(!Term::identifier("x").alpha_eq(&Term::identifier("y")));
+        assert!(!Term::lambda("x", Term::identifier("y")).alpha_eq(&Term::lambda("x", Term::identifier("z"))));
+    }
+
+    #[test]
+    fn test_alpha_eq_distinguishes_shadowing_from_capture() {
+        // `fn x => fn y => x` and `fn x => fn y => y` are not alpha-
+        // equivalent: renaming the outer `x` to `y` in the second term would
+        // capture the inner binder, so a correct implementation must treat
+        // them as different terms rather than aligning by position alone.
+        let refers_to_outer = Term::lambda("x", Term::lambda("y", Term::identifier("x")));
+        let refers_to_inner = Term::lambda("x", Term::lambda("y", Term::identifier("y")));
+        assert!(!refers_to_outer.alpha_eq(&refers_to_inner));
+        assert!(refers_to_outer.alpha_eq(&refers_to_outer.clone()));
+    }
+
+    #[test]
+    fn test_alpha_eq_matches_partial_eq_when_names_already_agree() -> Result<(), String> {
+        let tokens = tokenize_with_spans("fn x => x + 1")?;
+        let term = parse(&tokens)?;
+        assert!(term.alpha_eq(&term));
+        Ok(())
+    }
+
+    #[test]
+    fn test_free_variables_excludes_a_function_parameter() {
+        assert_eq!(
+            free_variables(&Term::lambda("x", Term::identifier("x"))),
+            HashSet::new()
+        );
+    }
+
+    #[test]
+    fn test_free_variables_includes_names_not_bound_anywhere() {
+        let term = Term::app(Term::identifier("f"), Term::identifier("x"));
+        assert_eq!(
+            free_variables(&term),
+            HashSet::from([String::from("f"), String::from("x")])
+        );
+    }
+
+    #[test]
+    fn test_free_variables_excludes_a_let_binding_only_within_its_scope() {
+        // `y` is free (it's the declaration's value, evaluated before `x` is
+        // bound); `x` is bound within the body and so isn't free.
+        let term = Term::let_in("x", Term::identifier("y"), Term::identifier("x"));
+        assert_eq!(free_variables(&term), HashSet::from([String::from("y")]));
+    }
+
+    #[test]
+    fn test_free_variables_of_a_parsed_term() -> Result<(), String> {
+        // `+` is itself parsed as an identifier (see `operator_name`), so it
+        // shows up as free alongside `y`; only the bound parameter `x` is
+        // excluded.
+        let tokens = tokenize_with_spans("fn x => x + y")?;
+        let term = parse(&tokens)?;
+        assert_eq!(
+            free_variables(&term),
+            HashSet::from([String::from("+"), String::from("y")])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_substitute_replaces_a_free_occurrence() {
+        let term = Term::app(Term::identifier("f"), Term::identifier("x"));
+        assert_eq!(
+            substitute(&term, "x", &Term::integer(1)),
+            Term::app(Term::identifier("f"), Term::integer(1))
+        );
+    }
+
+    #[test]
+    fn test_substitute_is_a_no_op_when_the_name_is_shadowed() {
+        let term = Term::lambda("x", Term::identifier("x"));
+        assert_eq!(substitute(&term, "x", &Term::integer(1)), term);
+    }
+
+    #[test]
+    fn test_substitute_leaves_a_let_bindings_scope_alone_when_shadowed() {
+        let term = Term::let_in("x", Term::identifier("y"), Term::identifier("x"));
+        // `y` (the declaration value) is substituted, but the body's `x`
+        // refers to the let binding, not the substituted name.
+        assert_eq!(
+            substitute(&term, "y", &Term::integer(1)),
+            Term::let_in("x", Term::integer(1), Term::identifier("x"))
+        );
+        assert_eq!(substitute(&term, "x", &Term::integer(1)), term);
+    }
+
+    #[test]
+    fn test_substitute_renames_a_binder_to_avoid_capture() {
+        // Substituting `y` for `x` in `fn y => x` must not let the
+        // parameter capture the replacement's `y`: the parameter has to be
+        // renamed, so the body's `y` remains free and still refers to
+        // whatever `y` means outside the function, not to the parameter.
+        let term = Term::lambda("y", Term::identifier("x"));
+        let result = substitute(&term, "x", &Term::identifier("y"));
+        match &result.kind {
+            TermKind::FunctionDefinition { parameter, body } => {
+                assert_ne!(parameter.kind, TermKind::Identifier(String::from("y")));
+                assert_eq!(body.kind, TermKind::Identifier(String::from("y")));
             }
+            _ => panic!("expected a FunctionDefinition"),
         }
     }
-}
 
-fn parse_integer_or_identifier(
-    tokens: &Vec<Token>,
-    position: usize,
-) -> Result<(Term, usize), String> {
-    if let Some(token) = tokens.get(position) {
-        match token {
-            Token::Integer(value) => Ok((Term::Integer(*value), position + 1)),
-            Token::Identifier(name) => Ok((Term::Identifier(name.clone()), position + 1)),
-            _ => Err(format!(
-                "expected integer or identifier but got {:?}",
-                token
-            )),
+    #[test]
+    fn test_substitute_into_a_parsed_term() -> Result<(), String> {
+        let tokens = tokenize_with_spans("fn x => x + y")?;
+        let term = parse(&tokens)?;
+        let substituted = substitute(&term, "y", &Term::integer(1));
+        assert!(substituted.alpha_eq(&Term::lambda(
+            "x",
+            Term::app(
+                Term::app(Term::identifier("+"), Term::identifier("x")),
+                Term::integer(1),
+            )
+        )));
+        Ok(())
+    }
+
+    #[test]
+    fn test_term_visitor_default_walk_collects_every_identifier() -> Result<(), String> {
+        struct IdentifierCollector {
+            names: Vec<String>,
         }
-    } else {
-        Err(String::from(
-            "expected integer or identifier but got nothing",
-        ))
+
+        impl TermVisitor for IdentifierCollector {
+            fn visit_identifier(&mut self, name: &str) {
+                self.names.push(String::from(name));
+            }
+        }
+
+        let tokens = tokenize_with_spans("fn x => x + 1")?;
+        let term = parse(&tokens)?;
+        let mut collector = IdentifierCollector { names: Vec::new() };
+        collector.visit_term(&term);
+        assert_eq!(collector.names, vec!["x", "+", "x"]);
+        Ok(())
     }
-}
 
-fn parse_identifier(tokens: &Vec<Token>, position: usize) -> Result<(Term, usize), String> {
-    if let Some(token) = tokens.get(position) {
-        match token {
-            Token::Identifier(name) => Ok((Term::Identifier(name.clone()), position + 1)),
-            _ => Err(format!("expected identifier but got {:?}", token)),
+    #[test]
+    fn test_term_folder_default_walk_renames_every_identifier() -> Result<(), String> {
+        struct Renamer;
+
+        impl TermFolder for Renamer {
+            fn fold_identifier(&mut self, name: &str) -> TermKind {
+                TermKind::Identifier(format!("{}_renamed", name))
+            }
         }
-    } else {
-        Err(String::from("expected identifier but got nothing"))
+
+        let tokens = tokenize_with_spans("fn x => x")?;
+        let term = parse(&tokens)?;
+        let folded = Renamer.fold_term(&term);
+        assert_eq!(
+            folded,
+            Term::lambda("x_renamed", Term::identifier("x_renamed"))
+        );
+        Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use crate::parser::{parse, parse_declaration_clause, Term};
-    use crate::tokenizer::tokenize;
+    #[test]
+    fn test_term_folder_can_constant_fold_after_the_default_recursion() -> Result<(), String> {
+        struct ConstantFolder;
+
+        impl TermFolder for ConstantFolder {
+            fn fold_term(&mut self, term: &Term) -> Term {
+                let folded = fold_term_default(self, term);
+                match &folded.kind {
+                    TermKind::FunctionApplication { function, argument } => match &function.kind {
+                        TermKind::FunctionApplication {
+                            function: operator,
+                            argument: left,
+                        } => match (&operator.kind, &left.kind, &argument.kind) {
+                            (
+                                TermKind::Identifier(name),
+                                TermKind::Integer(left_value),
+                                TermKind::Integer(right_value),
+                            ) if name == "+" => {
+                                Term::new(TermKind::Integer(left_value + right_value), folded.span)
+                            }
+                            _ => folded,
+                        },
+                        _ => folded,
+                    },
+                    _ => folded,
+                }
+            }
+        }
+
+        let tokens = tokenize_with_spans("1 + 2")?;
+        let term = parse(&tokens)?;
+        let folded = ConstantFolder.fold_term(&term);
+        assert_eq!(folded, Term::integer(3));
+        Ok(())
+    }
 
     #[test]
     fn test_parse_integer() -> Result<(), String> {
-        let tokens = tokenize("1")?;
-        assert_eq!(parse(&tokens), Ok(Term::Integer(1)));
+        let tokens = tokenize_with_spans("1")?;
+        assert_eq!(parse(&tokens), Ok(Term::new(TermKind::Integer(1), Span::default())));
         Ok(())
     }
 
     #[test]
     fn test_parse_identifier() -> Result<(), String> {
-        let tokens = tokenize("x")?;
-        assert_eq!(parse(&tokens), Ok(Term::Identifier(String::from("x"))));
+        let tokens = tokenize_with_spans("x")?;
+        assert_eq!(parse(&tokens), Ok(Term::new(TermKind::Identifier(String::from("x")), Span::default())));
         Ok(())
     }
 
     #[test]
     fn test_parse_boolean_true() -> Result<(), String> {
-        let tokens = tokenize("true")?;
-        assert_eq!(parse(&tokens), Ok(Term::Boolean(true)));
+        let tokens = tokenize_with_spans("true")?;
+        assert_eq!(parse(&tokens), Ok(Term::new(TermKind::Boolean(true), Span::default())));
         Ok(())
     }
 
     #[test]
     fn test_parse_boolean_false() -> Result<(), String> {
-        let tokens = tokenize("false")?;
-        assert_eq!(parse(&tokens), Ok(Term::Boolean(false)));
+        let tokens = tokenize_with_spans("false")?;
+        assert_eq!(parse(&tokens), Ok(Term::new(TermKind::Boolean(false), Span::default())));
         Ok(())
     }
 
     #[test]
     fn test_parse_addition() -> Result<(), String> {
-        let tokens = tokenize("x + 1")?;
+        let tokens = tokenize_with_spans("x + 1")?;
         assert_eq!(
             parse(&tokens),
-            Ok(Term::FunctionApplication {
-                function: Box::from(Term::FunctionApplication {
-                    function: Box::from(Term::Identifier(String::from("+"))),
-                    argument: Box::from(Term::Identifier(String::from("x")))
-                }),
-                argument: Box::from(Term::Integer(1))
-            })
+            Ok(Term::new(TermKind::FunctionApplication {
+                function: Box::from(Term::new(TermKind::FunctionApplication {
+                    function: Box::from(Term::new(TermKind::Identifier(String::from("+")), Span::default())),
+                    argument: Box::from(Term::new(TermKind::Identifier(String::from("x")), Span::default()))
+                }, Span::default())),
+                argument: Box::from(Term::new(TermKind::Integer(1), Span::default()))
+            }, Span::default()))
         );
         Ok(())
     }
 
     #[test]
     fn test_parse_subtraction() -> Result<(), String> {
-        let tokens = tokenize("x - 1")?;
+        let tokens = tokenize_with_spans("x - 1")?;
+        assert_eq!(
+            parse(&tokens),
+            Ok(Term::new(TermKind::FunctionApplication {
+                function: Box::from(Term::new(TermKind::FunctionApplication {
+                    function: Box::from(Term::new(TermKind::Identifier(String::from("-")), Span::default())),
+                    argument: Box::from(Term::new(TermKind::Identifier(String::from("x")), Span::default()))
+                }, Span::default())),
+                argument: Box::from(Term::new(TermKind::Integer(1), Span::default()))
+            }, Span::default()))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_negative_integer_literal() -> Result<(), String> {
+        let tokens = tokenize_with_spans("-42")?;
+        assert_eq!(parse(&tokens), Ok(Term::new(TermKind::Integer(-42), Span::default())));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_subtraction_of_negative_integer() -> Result<(), String> {
+        let tokens = tokenize_with_spans("3 - -2")?;
         assert_eq!(
             parse(&tokens),
-            Ok(Term::FunctionApplication {
-                function: Box::from(Term::FunctionApplication {
-                    function: Box::from(Term::Identifier(String::from("-"))),
-                    argument: Box::from(Term::Identifier(String::from("x")))
-                }),
-                argument: Box::from(Term::Integer(1))
-            })
+            Ok(Term::new(TermKind::FunctionApplication {
+                function: Box::from(Term::new(TermKind::FunctionApplication {
+                    function: Box::from(Term::new(TermKind::Identifier(String::from("-")), Span::default())),
+                    argument: Box::from(Term::new(TermKind::Integer(3), Span::default()))
+                }, Span::default())),
+                argument: Box::from(Term::new(TermKind::Integer(-2), Span::default()))
+            }, Span::default()))
         );
         Ok(())
     }
 
     #[test]
     fn test_parse_multiplication() -> Result<(), String> {
-        let tokens = tokenize("x * 2")?;
+        let tokens = tokenize_with_spans("x * 2")?;
         assert_eq!(
             parse(&tokens),
-            Ok(Term::FunctionApplication {
-                function: Box::from(Term::FunctionApplication {
-                    function: Box::from(Term::Identifier(String::from("*"))),
-                    argument: Box::from(Term::Identifier(String::from("x")))
-                }),
-                argument: Box::from(Term::Integer(2))
-            })
+            Ok(Term::new(TermKind::FunctionApplication {
+                function: Box::from(Term::new(TermKind::FunctionApplication {
+                    function: Box::from(Term::new(TermKind::Identifier(String::from("*")), Span::default())),
+                    argument: Box::from(Term::new(TermKind::Identifier(String::from("x")), Span::default()))
+                }, Span::default())),
+                argument: Box::from(Term::new(TermKind::Integer(2), Span::default()))
+            }, Span::default()))
         );
         Ok(())
     }
 
     #[test]
     fn test_parse_division() -> Result<(), String> {
-        let tokens = tokenize("x / 2")?;
+        let tokens = tokenize_with_spans("x / 2")?;
         assert_eq!(
             parse(&tokens),
-            Ok(Term::FunctionApplication {
-                function: Box::from(Term::FunctionApplication {
-                    function: Box::from(Term::Identifier(String::from("/"))),
-                    argument: Box::from(Term::Identifier(String::from("x")))
-                }),
-                argument: Box::from(Term::Integer(2))
-            })
+            Ok(Term::new(TermKind::FunctionApplication {
+                function: Box::from(Term::new(TermKind::FunctionApplication {
+                    function: Box::from(Term::new(TermKind::Identifier(String::from("/")), Span::default())),
+                    argument: Box::from(Term::new(TermKind::Identifier(String::from("x")), Span::default()))
+                }, Span::default())),
+                argument: Box::from(Term::new(TermKind::Integer(2), Span::default()))
+            }, Span::default()))
         );
         Ok(())
     }
 
     #[test]
     fn test_parse_identity_function() -> Result<(), String> {
-        let tokens = tokenize("fn x => x")?;
+        let tokens = tokenize_with_spans("fn x => x")?;
         assert_eq!(
             parse(&tokens),
-            Ok(Term::FunctionDefinition {
-                parameter: Box::from(Term::Identifier(String::from("x"))),
-                body: Box::from(Term::Identifier(String::from("x")))
-            })
+            Ok(Term::new(TermKind::FunctionDefinition {
+                parameter: Box::from(Term::new(TermKind::Identifier(String::from("x")), Span::default())),
+                body: Box::from(Term::new(TermKind::Identifier(String::from("x")), Span::default()))
+            }, Span::default()))
         );
         Ok(())
     }
 
     #[test]
     fn test_parse_increment_function() -> Result<(), String> {
-        let tokens = tokenize("fn x => x + 1")?;
+        let tokens = tokenize_with_spans("fn x => x + 1")?;
         assert_eq!(
             parse(&tokens),
-            Ok(Term::FunctionDefinition {
-                parameter: Box::from(Term::Identifier(String::from("x"))),
-                body: Box::from(Term::FunctionApplication {
-                    function: Box::from(Term::FunctionApplication {
-                        function: Box::from(Term::Identifier(String::from("+"))),
-                        argument: Box::from(Term::Identifier(String::from("x")))
-                    }),
-                    argument: Box::from(Term::Integer(1))
-                })
-            })
+            Ok(Term::new(TermKind::FunctionDefinition {
+                parameter: Box::from(Term::new(TermKind::Identifier(String::from("x")), Span::default())),
+                body: Box::from(Term::new(TermKind::FunctionApplication {
+                    function: Box::from(Term::new(TermKind::FunctionApplication {
+                        function: Box::from(Term::new(TermKind::Identifier(String::from("+")), Span::default())),
+                        argument: Box::from(Term::new(TermKind::Identifier(String::from("x")), Span::default()))
+                    }, Span::default())),
+                    argument: Box::from(Term::new(TermKind::Integer(1), Span::default()))
+                }, Span::default()))
+            }, Span::default()))
         );
         Ok(())
     }
 
     #[test]
     fn test_parse_if_expression() -> Result<(), String> {
-        let tokens = tokenize("if x = y then 0 else 1")?;
+        let tokens = tokenize_with_spans("if x = y then 0 else 1")?;
+        assert_eq!(
+            parse(&tokens),
+            Ok(Term::new(TermKind::IfExpression {
+                condition: Box::from(Term::new(TermKind::FunctionApplication {
+                    function: Box::from(Term::new(TermKind::FunctionApplication {
+                        function: Box::from(Term::new(TermKind::Identifier(String::from("=")), Span::default())),
+                        argument: Box::from(Term::new(TermKind::Identifier(String::from("x")), Span::default()))
+                    }, Span::default())),
+                    argument: Box::from(Term::new(TermKind::Identifier(String::from("y")), Span::default()))
+                }, Span::default())),
+                true_branch: Box::from(Term::new(TermKind::Integer(0), Span::default())),
+                false_branch: Box::from(Term::new(TermKind::Integer(1), Span::default())),
+            }, Span::default()))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_raise_expression() -> Result<(), String> {
+        let tokens = tokenize_with_spans("raise x")?;
         assert_eq!(
             parse(&tokens),
-            Ok(Term::IfExpression {
-                condition: Box::from(Term::FunctionApplication {
-                    function: Box::from(Term::FunctionApplication {
-                        function: Box::from(Term::Identifier(String::from("="))),
-                        argument: Box::from(Term::Identifier(String::from("x")))
-                    }),
-                    argument: Box::from(Term::Identifier(String::from("y")))
-                }),
-                true_branch: Box::from(Term::Integer(0)),
-                false_branch: Box::from(Term::Integer(1)),
-            })
+            Ok(Term::new(TermKind::RaiseExpression {
+                exception: Box::from(Term::new(TermKind::Identifier(String::from("x")), Span::default())),
+            }, Span::default()))
         );
         Ok(())
     }
 
     #[test]
     fn test_parse_declaration_clause() -> Result<(), String> {
-        let tokens = tokenize("val inc = fn x => x + 1")?;
+        let tokens = tokenize_with_spans("val inc = fn x => x + 1")?;
+        let mut stream = TokenStream::new(&tokens);
+        let table = PrecedenceTable::default();
+        let mut errors = Vec::new();
         assert_eq!(
-            parse_declaration_clause(&tokens, 0),
+            parse_declaration_clause(&mut stream, &table, &mut errors, 0, DEFAULT_MAX_DEPTH),
             Ok((
-                Term::Identifier(String::from("inc")),
-                Term::FunctionDefinition {
-                    parameter: Box::from(Term::Identifier(String::from("x"))),
-                    body: Box::from(Term::FunctionApplication {
-                        function: Box::from(Term::FunctionApplication {
-                            function: Box::from(Term::Identifier(String::from("+"))),
-                            argument: Box::from(Term::Identifier(String::from("x")))
-                        }),
-                        argument: Box::from(Term::Integer(1))
-                    }),
-                },
-                tokens.len()
+                Term::new(TermKind::Identifier(String::from("inc")), Span::default()),
+                Term::new(TermKind::FunctionDefinition {
+                    parameter: Box::from(Term::new(TermKind::Identifier(String::from("x")), Span::default())),
+                    body: Box::from(Term::new(TermKind::FunctionApplication {
+                        function: Box::from(Term::new(TermKind::FunctionApplication {
+                            function: Box::from(Term::new(TermKind::Identifier(String::from("+")), Span::default())),
+                            argument: Box::from(Term::new(TermKind::Identifier(String::from("x")), Span::default()))
+                        }, Span::default())),
+                        argument: Box::from(Term::new(TermKind::Integer(1), Span::default()))
+                    }, Span::default())),
+                }, Span::default()),
             )),
         );
+        assert_eq!(stream.position(), tokens.len());
         Ok(())
     }
 
     #[test]
     fn test_parse_let_expression() -> Result<(), String> {
-        let tokens = tokenize("let val inc = fn x => x + 1 in inc(42) end")?;
+        let tokens = tokenize_with_spans("let val inc = fn x => x + 1 in inc(42) end")?;
+        assert_eq!(
+            parse(&tokens),
+            Ok(Term::new(TermKind::LetExpression {
+                declaration_name: Box::from(Term::new(TermKind::Identifier(String::from("inc")), Span::default())),
+                declaration_value: Box::from(Term::new(TermKind::FunctionDefinition {
+                    parameter: Box::from(Term::new(TermKind::Identifier(String::from("x")), Span::default())),
+                    body: Box::from(Term::new(TermKind::FunctionApplication {
+                        function: Box::from(Term::new(TermKind::FunctionApplication {
+                            function: Box::from(Term::new(TermKind::Identifier(String::from("+")), Span::default())),
+                            argument: Box::from(Term::new(TermKind::Identifier(String::from("x")), Span::default()))
+                        }, Span::default())),
+                        argument: Box::from(Term::new(TermKind::Integer(1), Span::default()))
+                    }, Span::default())),
+                }, Span::default())),
+                expression: Box::from(Term::new(TermKind::FunctionApplication {
+                    function: Box::from(Term::new(TermKind::Identifier(String::from("inc")), Span::default())),
+                    argument: Box::from(Term::new(TermKind::Integer(42), Span::default()))
+                }, Span::default()))
+            }, Span::default()))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_token_stream_peek_and_peek_n_do_not_consume() -> Result<(), String> {
+        let tokens = tokenize_with_spans("x + 1")?;
+        let stream = TokenStream::new(&tokens);
+        assert_eq!(stream.peek(), Some(&Token::Identifier(String::from("x"))));
+        assert_eq!(stream.peek_n(1), Some(&Token::Plus));
+        assert_eq!(stream.peek(), Some(&Token::Identifier(String::from("x"))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_token_stream_checkpoint_and_rewind() -> Result<(), String> {
+        let tokens = tokenize_with_spans("x + 1")?;
+        let mut stream = TokenStream::new(&tokens);
+        let checkpoint = stream.checkpoint();
+        stream.advance();
+        stream.advance();
+        assert_eq!(stream.peek(), Some(&Token::Integer(1)));
+        stream.rewind(checkpoint);
+        assert_eq!(stream.peek(), Some(&Token::Identifier(String::from("x"))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_multiplication_binds_tighter_than_addition() -> Result<(), String> {
+        let tokens = tokenize_with_spans("1 + 2 * 3")?;
+        assert_eq!(
+            parse(&tokens),
+            Ok(Term::new(TermKind::FunctionApplication {
+                function: Box::from(Term::new(TermKind::FunctionApplication {
+                    function: Box::from(Term::new(TermKind::Identifier(String::from("+")), Span::default())),
+                    argument: Box::from(Term::new(TermKind::Integer(1), Span::default()))
+                }, Span::default())),
+                argument: Box::from(Term::new(TermKind::FunctionApplication {
+                    function: Box::from(Term::new(TermKind::FunctionApplication {
+                        function: Box::from(Term::new(TermKind::Identifier(String::from("*")), Span::default())),
+                        argument: Box::from(Term::new(TermKind::Integer(2), Span::default()))
+                    }, Span::default())),
+                    argument: Box::from(Term::new(TermKind::Integer(3), Span::default()))
+                }, Span::default()))
+            }, Span::default()))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_same_precedence_operators_are_left_associative() -> Result<(), String> {
+        let tokens = tokenize_with_spans("1 - 2 - 3")?;
+        assert_eq!(
+            parse(&tokens),
+            Ok(Term::new(TermKind::FunctionApplication {
+                function: Box::from(Term::new(TermKind::FunctionApplication {
+                    function: Box::from(Term::new(TermKind::Identifier(String::from("-")), Span::default())),
+                    argument: Box::from(Term::new(TermKind::FunctionApplication {
+                        function: Box::from(Term::new(TermKind::FunctionApplication {
+                            function: Box::from(Term::new(TermKind::Identifier(String::from("-")), Span::default())),
+                            argument: Box::from(Term::new(TermKind::Integer(1), Span::default()))
+                        }, Span::default())),
+                        argument: Box::from(Term::new(TermKind::Integer(2), Span::default()))
+                    }, Span::default()))
+                }, Span::default())),
+                argument: Box::from(Term::new(TermKind::Integer(3), Span::default()))
+            }, Span::default()))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_parenthesized_precedence_via_grouping_is_not_supported() -> Result<(), String> {
+        // The grammar has no general parenthesized subexpression, only
+        // `identifier(argument)` function application, so `*` still binds
+        // tighter than `+` even when a long chain is involved.
+        let tokens = tokenize_with_spans("2 * 3 + 4 * 5")?;
+        assert_eq!(
+            parse(&tokens),
+            Ok(Term::new(TermKind::FunctionApplication {
+                function: Box::from(Term::new(TermKind::FunctionApplication {
+                    function: Box::from(Term::new(TermKind::Identifier(String::from("+")), Span::default())),
+                    argument: Box::from(Term::new(TermKind::FunctionApplication {
+                        function: Box::from(Term::new(TermKind::FunctionApplication {
+                            function: Box::from(Term::new(TermKind::Identifier(String::from("*")), Span::default())),
+                            argument: Box::from(Term::new(TermKind::Integer(2), Span::default()))
+                        }, Span::default())),
+                        argument: Box::from(Term::new(TermKind::Integer(3), Span::default()))
+                    }, Span::default()))
+                }, Span::default())),
+                argument: Box::from(Term::new(TermKind::FunctionApplication {
+                    function: Box::from(Term::new(TermKind::FunctionApplication {
+                        function: Box::from(Term::new(TermKind::Identifier(String::from("*")), Span::default())),
+                        argument: Box::from(Term::new(TermKind::Integer(4), Span::default()))
+                    }, Span::default())),
+                    argument: Box::from(Term::new(TermKind::Integer(5), Span::default()))
+                }, Span::default()))
+            }, Span::default()))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_with_precedence_table_honors_custom_precedence() -> Result<(), String> {
+        // With `+` registered to bind tighter than `*`, the same source that
+        // parses as `(2 * 3) + 4` under the defaults should instead parse as
+        // `2 * (3 + 4)`.
+        let tokens = tokenize_with_spans("2 * 3 + 4")?;
+        let mut table = PrecedenceTable::new();
+        table.register(Token::Times, 1, Associativity::Left);
+        table.register(Token::Plus, 2, Associativity::Left);
+        assert_eq!(
+            crate::parser::parse_with_precedence_table(&tokens, &table),
+            Ok(Term::new(TermKind::FunctionApplication {
+                function: Box::from(Term::new(TermKind::FunctionApplication {
+                    function: Box::from(Term::new(TermKind::Identifier(String::from("*")), Span::default())),
+                    argument: Box::from(Term::new(TermKind::Integer(2), Span::default()))
+                }, Span::default())),
+                argument: Box::from(Term::new(TermKind::FunctionApplication {
+                    function: Box::from(Term::new(TermKind::FunctionApplication {
+                        function: Box::from(Term::new(TermKind::Identifier(String::from("+")), Span::default())),
+                        argument: Box::from(Term::new(TermKind::Integer(3), Span::default()))
+                    }, Span::default())),
+                    argument: Box::from(Term::new(TermKind::Integer(4), Span::default()))
+                }, Span::default()))
+            }, Span::default()))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_with_precedence_table_honors_right_associativity() -> Result<(), String> {
+        // Registering `+` as right-associative should make `1 + 2 + 3` parse
+        // as `1 + (2 + 3)` instead of the default `(1 + 2) + 3`.
+        let tokens = tokenize_with_spans("1 + 2 + 3")?;
+        let mut table = PrecedenceTable::new();
+        table.register(Token::Plus, 1, Associativity::Right);
+        assert_eq!(
+            crate::parser::parse_with_precedence_table(&tokens, &table),
+            Ok(Term::new(TermKind::FunctionApplication {
+                function: Box::from(Term::new(TermKind::FunctionApplication {
+                    function: Box::from(Term::new(TermKind::Identifier(String::from("+")), Span::default())),
+                    argument: Box::from(Term::new(TermKind::Integer(1), Span::default()))
+                }, Span::default())),
+                argument: Box::from(Term::new(TermKind::FunctionApplication {
+                    function: Box::from(Term::new(TermKind::FunctionApplication {
+                        function: Box::from(Term::new(TermKind::Identifier(String::from("+")), Span::default())),
+                        argument: Box::from(Term::new(TermKind::Integer(2), Span::default()))
+                    }, Span::default())),
+                    argument: Box::from(Term::new(TermKind::Integer(3), Span::default()))
+                }, Span::default()))
+            }, Span::default()))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_with_recovery_returns_the_single_error_for_valid_input() -> Result<(), String> {
+        let tokens = tokenize_with_spans("x + 1")?;
+        let (term, errors) = parse_with_recovery(&tokens);
+        assert_eq!(term, parse(&tokens)?);
+        assert!(errors.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_with_recovery_synchronizes_past_a_broken_let_binding() -> Result<(), String> {
+        // The first `let` is missing its `in` keyword, which is a syntax
+        // error, but recovery should synchronize forward to the second
+        // (well-formed) `let` and still return a term for it.
+        let tokens = tokenize_with_spans("let val x = 1 x end let val y = 2 in y end")?;
+        let (term, errors) = parse_with_recovery(&tokens);
+        assert!(!errors.is_empty());
+        assert_eq!(
+            term,
+            Term::new(TermKind::LetExpression {
+                declaration_name: Box::from(Term::new(TermKind::Identifier(String::from("y")), Span::default())),
+                declaration_value: Box::from(Term::new(TermKind::Integer(2), Span::default())),
+                expression: Box::from(Term::new(TermKind::Identifier(String::from("y")), Span::default())),
+            }, Span::default())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_with_recovery_reports_multiple_errors() -> Result<(), String> {
+        // The first `let` is missing its `val` keyword entirely, which
+        // forces recovery to synchronize twice (past the stray `x`, then
+        // past the dangling `end`) before it reaches the second, valid
+        // `let` block.
+        let tokens = tokenize_with_spans("let x end let val y = 2 in y end")?;
+        let (term, errors) = parse_with_recovery(&tokens);
+        assert!(errors.len() > 1);
+        assert_eq!(
+            term,
+            Term::new(TermKind::LetExpression {
+                declaration_name: Box::from(Term::new(TermKind::Identifier(String::from("y")), Span::default())),
+                declaration_value: Box::from(Term::new(TermKind::Integer(2), Span::default())),
+                expression: Box::from(Term::new(TermKind::Identifier(String::from("y")), Span::default())),
+            }, Span::default())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_error_names_the_expected_and_found_tokens() -> Result<(), String> {
+        let tokens = tokenize_with_spans("if x then 0")?;
+        assert_eq!(
+            parse(&tokens),
+            Err(ParseErrors(vec![ParseError::UnexpectedToken {
+                found: None,
+                expected: vec![ExpectedToken::Token(Token::KeywordElse)],
+            }]))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_returns_every_error_instead_of_only_the_first() -> Result<(), String> {
+        // Both branches of the `if` have a dangling `+` with nothing after
+        // it, which `parse_binary_expression` tolerates by substituting
+        // `Term::Error` and pushing a diagnostic rather than aborting, so a
+        // single `parse` call collects both instead of just the first.
+        let tokens = tokenize_with_spans("if 1 + then 2 else 3 +")?;
+        let errors = parse(&tokens).expect_err("dangling operands should be reported");
+        assert_eq!(errors.len(), 2);
+        assert!(errors
+            .iter()
+            .all(|error| matches!(error, ParseError::UnexpectedToken { .. })));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_error_display_names_a_choice_of_expected_tokens() -> Result<(), String> {
+        let error = ParseError::UnexpectedToken {
+            found: Some(Token::KeywordEnd),
+            expected: vec![
+                ExpectedToken::Token(Token::KeywordThen),
+                ExpectedToken::Token(Token::RightParenthesis),
+            ],
+        };
+        assert_eq!(
+            error.to_string(),
+            "expected `then` or `)` but found `end`"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_with_recovery_returns_error_term_when_nothing_ever_parses() -> Result<(), String> {
+        let tokens = tokenize_with_spans("val val val")?;
+        let (term, errors) = parse_with_recovery(&tokens);
+        assert_eq!(term, Term::new(TermKind::Error, Span::default()));
+        assert!(!errors.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_partial_returns_the_same_term_as_parse_for_complete_input() -> Result<(), String>
+    {
+        let tokens = tokenize_with_spans("x + 1")?;
+        let (term, errors) = parse_partial(&tokens);
+        assert_eq!(term, parse(&tokens)?);
+        assert!(errors.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_partial_fills_a_dangling_operand_with_an_error_node() -> Result<(), String> {
+        // `x +` is exactly the kind of prefix a REPL or editor sees while
+        // the user is still typing: the operator is there, but nothing
+        // follows it yet.
+        let tokens = tokenize_with_spans("x +")?;
+        let (term, errors) = parse_partial(&tokens);
+        assert_eq!(
+            term,
+            Term::new(TermKind::FunctionApplication {
+                function: Box::from(Term::new(TermKind::FunctionApplication {
+                    function: Box::from(Term::new(TermKind::Identifier(String::from("+")), Span::default())),
+                    argument: Box::from(Term::new(TermKind::Identifier(String::from("x")), Span::default())),
+                }, Span::default())),
+                argument: Box::from(Term::new(TermKind::Error, Span::default())),
+            }, Span::default())
+        );
+        assert_eq!(errors.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_partial_fills_a_dangling_operand_inside_a_let_expression() -> Result<(), String>
+    {
+        let tokens = tokenize_with_spans("let val x = 1 in x + end")?;
+        let (term, errors) = parse_partial(&tokens);
+        assert_eq!(
+            term,
+            Term::new(TermKind::LetExpression {
+                declaration_name: Box::from(Term::new(TermKind::Identifier(String::from("x")), Span::default())),
+                declaration_value: Box::from(Term::new(TermKind::Integer(1), Span::default())),
+                expression: Box::from(Term::new(TermKind::FunctionApplication {
+                    function: Box::from(Term::new(TermKind::FunctionApplication {
+                        function: Box::from(Term::new(TermKind::Identifier(String::from("+")), Span::default())),
+                        argument: Box::from(Term::new(TermKind::Identifier(String::from("x")), Span::default())),
+                    }, Span::default())),
+                    argument: Box::from(Term::new(TermKind::Error, Span::default())),
+                }, Span::default())),
+            }, Span::default())
+        );
+        assert_eq!(errors.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_rejects_pathologically_nested_input() -> Result<(), String> {
+        // Each `raise` recurses one level deeper into `parse_expression`, so
+        // stacking enough of them exercises the depth limit without needing
+        // a stack overflow to actually happen in the test.
+        let source = format!("{}x", "raise ".repeat(DEFAULT_MAX_DEPTH + 1));
+        let tokens = tokenize_with_spans(&source)?;
         assert_eq!(
             parse(&tokens),
-            Ok(Term::LetExpression {
-                declaration_name: Box::from(Term::Identifier(String::from("inc"))),
-                declaration_value: Box::from(Term::FunctionDefinition {
-                    parameter: Box::from(Term::Identifier(String::from("x"))),
-                    body: Box::from(Term::FunctionApplication {
-                        function: Box::from(Term::FunctionApplication {
-                            function: Box::from(Term::Identifier(String::from("+"))),
-                            argument: Box::from(Term::Identifier(String::from("x")))
-                        }),
-                        argument: Box::from(Term::Integer(1))
-                    }),
-                }),
-                expression: Box::from(Term::FunctionApplication {
-                    function: Box::from(Term::Identifier(String::from("inc"))),
-                    argument: Box::from(Term::Integer(42))
-                })
-            })
+            Err(ParseErrors(vec![ParseError::NestingTooDeep {
+                limit: DEFAULT_MAX_DEPTH,
+            }]))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_with_max_depth_honors_a_custom_limit() -> Result<(), String> {
+        let source = "raise raise raise x";
+        let tokens = tokenize_with_spans(source)?;
+        assert_eq!(
+            parse_with_max_depth(&tokens, 2),
+            Err(ParseErrors(vec![ParseError::NestingTooDeep { limit: 2 }]))
         );
+        assert_eq!(parse_with_max_depth(&tokens, 5), parse(&tokens));
         Ok(())
     }
 }
@@ -0,0 +1,117 @@
+//! Optional `pyo3` bindings exposing [`parse`], [`infer`], and [`check`] to
+//! Python, gated behind the `pyo3` feature, aimed at instructors who build
+//! course tooling and autograders on top of this checker in Python rather
+//! than Rust.
+
+use crate::formatter::format;
+use crate::parser::parse as parse_term;
+use crate::tokenizer::tokenize_with_spans;
+use crate::Diagnostic;
+use pyo3::exceptions::{PySyntaxError, PyValueError};
+use pyo3::prelude::*;
+
+/// A single problem reported by [`check`], exposed to Python as a plain
+/// object with a `message` attribute rather than the full [`Diagnostic`]
+/// enum, since a `pyclass` per variant would be a lot of ceremony for what
+/// an autograder script actually needs: a human-readable message to
+/// display or match against.
+#[pyclass(name = "Diagnostic")]
+pub struct PyDiagnostic {
+    #[pyo3(get)]
+    message: String,
+}
+
+#[pymethods]
+impl PyDiagnostic {
+    fn __repr__(&self) -> String {
+        format!("Diagnostic({:?})", self.message)
+    }
+}
+
+impl From<Diagnostic> for PyDiagnostic {
+    fn from(diagnostic: Diagnostic) -> Self {
+        PyDiagnostic { message: diagnostic.to_string() }
+    }
+}
+
+/// Tokenizes and parses `source`, returning it pretty-printed back through
+/// [`format`], or raising a `SyntaxError` naming the problem otherwise.
+#[pyfunction]
+fn parse(source: &str) -> PyResult<String> {
+    let tokens = tokenize_with_spans(source).map_err(|error| PySyntaxError::new_err(error.to_string()))?;
+    let term = parse_term(&tokens).map_err(|errors| PySyntaxError::new_err(errors.to_string()))?;
+    Ok(format(&term))
+}
+
+/// Runs the full pipeline on `source` and returns the inferred type's
+/// display string, or raises a `ValueError` listing every problem found.
+#[pyfunction]
+fn infer(source: &str) -> PyResult<String> {
+    match crate::infer(source) {
+        Ok(ty) => Ok(ty.to_string()),
+        Err(diagnostics) => Err(PyValueError::new_err(
+            diagnostics.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "),
+        )),
+    }
+}
+
+/// Like [`infer`], but never raises: it runs [`crate::check`] and returns
+/// every [`PyDiagnostic`] it collected (empty on a clean check), the way an
+/// autograder wants to inspect every problem in a submission at once
+/// instead of only the first one that stops execution.
+#[pyfunction]
+fn check(source: &str) -> Vec<PyDiagnostic> {
+    crate::check(source, usize::MAX)
+        .into_diagnostics()
+        .into_iter()
+        .map(PyDiagnostic::from)
+        .collect()
+}
+
+/// The `type_checker` Python module: `parse`, `infer`, `check`, and the
+/// [`PyDiagnostic`] class `check` returns.
+#[pymodule]
+fn type_checker(_py: Python<'_>, module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(parse, module)?)?;
+    module.add_function(wrap_pyfunction!(infer, module)?)?;
+    module.add_function(wrap_pyfunction!(check, module)?)?;
+    module.add_class::<PyDiagnostic>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, infer, parse};
+
+    #[test]
+    fn test_parse_pretty_prints_the_parsed_term() {
+        assert_eq!(parse("fn x => x").unwrap(), "fn x => x");
+    }
+
+    #[test]
+    fn test_parse_raises_a_syntax_error_on_bad_input() {
+        assert!(parse("if true then true").is_err());
+    }
+
+    #[test]
+    fn test_infer_returns_the_inferred_types_display_string() {
+        assert_eq!(infer("fn x => if x then true else false").unwrap(), "bool => bool");
+    }
+
+    #[test]
+    fn test_infer_raises_a_value_error_on_an_unbound_identifier() {
+        assert!(infer("x").is_err());
+    }
+
+    #[test]
+    fn test_check_returns_no_diagnostics_for_a_clean_source() {
+        assert!(check("fn x => x").is_empty());
+    }
+
+    #[test]
+    fn test_check_returns_a_diagnostic_instead_of_raising() {
+        let diagnostics = check("x");
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].message.contains('x'));
+    }
+}
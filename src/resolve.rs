@@ -0,0 +1,209 @@
+use crate::parser::{Term, TermKind};
+
+/// A unique identifier assigned to a single binder by [`resolve`]. Two
+/// identifier occurrences that resolve to the same `Symbol` refer to the
+/// same binding, even when an inner binder shadows an outer one by reusing
+/// its source name — something a plain string lookup by name alone cannot
+/// distinguish.
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    /// Wraps a raw id as a `Symbol`, for callers (e.g. `resolver`) that
+    /// mint their own symbols with their own counter rather than going
+    /// through [`resolve`].
+    pub(crate) fn from_raw(id: u32) -> Symbol {
+        Symbol(id)
+    }
+}
+
+/// A copy of [`Term`]'s shape with every binder and identifier occurrence
+/// additionally carrying the [`Symbol`] [`resolve`] assigned it. This is
+/// offered as a foundation for shadowing-safe symbol lookups — e.g. in
+/// `constraint::collect_constraints_with_bindings`, which currently keys
+/// its environment by name — without yet changing `Term`, `TypedTerm`, or
+/// the constraint collector themselves, which would mean carrying a
+/// `Symbol` through the entire annotate/constraint pipeline.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResolvedTerm {
+    Boolean(bool),
+    Error,
+    FunctionApplication {
+        function: Box<ResolvedTerm>,
+        argument: Box<ResolvedTerm>,
+    },
+    FunctionDefinition {
+        parameter: Symbol,
+        body: Box<ResolvedTerm>,
+    },
+    /// `symbol` is `None` for an identifier with no enclosing binder,
+    /// e.g. a builtin like `+` or a genuinely free variable.
+    Identifier {
+        name: String,
+        symbol: Option<Symbol>,
+    },
+    IfExpression {
+        condition: Box<ResolvedTerm>,
+        true_branch: Box<ResolvedTerm>,
+        false_branch: Box<ResolvedTerm>,
+    },
+    Integer(i32),
+    LetExpression {
+        declaration_name: Symbol,
+        declaration_value: Box<ResolvedTerm>,
+        expression: Box<ResolvedTerm>,
+    },
+    RaiseExpression {
+        exception: Box<ResolvedTerm>,
+    },
+}
+
+/// Assigns every binder in `term` a fresh, unique [`Symbol`] and resolves
+/// each identifier occurrence to the symbol of its nearest enclosing
+/// binder of the same name.
+pub fn resolve(term: &Term) -> ResolvedTerm {
+    resolve_in_scope(term, &mut Vec::new(), &mut 0)
+}
+
+fn resolve_in_scope(term: &Term, scope: &mut Vec<(String, Symbol)>, next_symbol: &mut u32) -> ResolvedTerm {
+    match &term.kind {
+        TermKind::Boolean(value) => ResolvedTerm::Boolean(*value),
+        TermKind::Error => ResolvedTerm::Error,
+        TermKind::Integer(value) => ResolvedTerm::Integer(*value),
+        TermKind::Identifier(name) => {
+            let symbol = scope.iter().rev().find(|(bound, _)| bound == name).map(|(_, symbol)| *symbol);
+            ResolvedTerm::Identifier {
+                name: name.clone(),
+                symbol,
+            }
+        }
+        TermKind::FunctionApplication { function, argument } => ResolvedTerm::FunctionApplication {
+            function: Box::from(resolve_in_scope(function, scope, next_symbol)),
+            argument: Box::from(resolve_in_scope(argument, scope, next_symbol)),
+        },
+        TermKind::FunctionDefinition { parameter, body } => {
+            let symbol = fresh_symbol(next_symbol);
+            scope.push((binder_name(parameter), symbol));
+            let body = resolve_in_scope(body, scope, next_symbol);
+            scope.pop();
+            ResolvedTerm::FunctionDefinition {
+                parameter: symbol,
+                body: Box::from(body),
+            }
+        }
+        TermKind::IfExpression {
+            condition,
+            true_branch,
+            false_branch,
+        } => ResolvedTerm::IfExpression {
+            condition: Box::from(resolve_in_scope(condition, scope, next_symbol)),
+            true_branch: Box::from(resolve_in_scope(true_branch, scope, next_symbol)),
+            false_branch: Box::from(resolve_in_scope(false_branch, scope, next_symbol)),
+        },
+        TermKind::LetExpression {
+            declaration_name,
+            declaration_value,
+            expression,
+        } => {
+            let declaration_value = resolve_in_scope(declaration_value, scope, next_symbol);
+            let symbol = fresh_symbol(next_symbol);
+            scope.push((binder_name(declaration_name), symbol));
+            let expression = resolve_in_scope(expression, scope, next_symbol);
+            scope.pop();
+            ResolvedTerm::LetExpression {
+                declaration_name: symbol,
+                declaration_value: Box::from(declaration_value),
+                expression: Box::from(expression),
+            }
+        }
+        TermKind::RaiseExpression { exception } => ResolvedTerm::RaiseExpression {
+            exception: Box::from(resolve_in_scope(exception, scope, next_symbol)),
+        },
+    }
+}
+
+fn binder_name(binder: &Term) -> String {
+    match &binder.kind {
+        TermKind::Identifier(name) => name.clone(),
+        other => unreachable!("binder is always an identifier, got {:?}", other),
+    }
+}
+
+fn fresh_symbol(next_symbol: &mut u32) -> Symbol {
+    let symbol = Symbol(*next_symbol);
+    *next_symbol += 1;
+    symbol
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::resolve::{resolve, ResolvedTerm};
+    use crate::parser::Term;
+
+    #[test]
+    fn test_resolve_gives_a_lambda_parameter_a_symbol() {
+        let resolved = resolve(&Term::lambda("x", Term::identifier("x")));
+        let ResolvedTerm::FunctionDefinition { parameter, body } = resolved else {
+            panic!("expected FunctionDefinition");
+        };
+        assert_eq!(*body, ResolvedTerm::Identifier {
+            name: String::from("x"),
+            symbol: Some(parameter),
+        });
+    }
+
+    #[test]
+    fn test_resolve_distinguishes_shadowed_bindings_by_symbol() {
+        // let val x = 1 in let val x = true in x end end
+        let term = Term::let_in(
+            "x",
+            Term::integer(1),
+            Term::let_in("x", Term::boolean(true), Term::identifier("x")),
+        );
+        let ResolvedTerm::LetExpression {
+            declaration_name: outer_x,
+            expression: outer_expression,
+            ..
+        } = resolve(&term)
+        else {
+            panic!("expected LetExpression");
+        };
+        let ResolvedTerm::LetExpression {
+            declaration_name: inner_x,
+            expression: inner_expression,
+            ..
+        } = *outer_expression
+        else {
+            panic!("expected inner LetExpression");
+        };
+        assert_ne!(outer_x, inner_x);
+        assert_eq!(*inner_expression, ResolvedTerm::Identifier {
+            name: String::from("x"),
+            symbol: Some(inner_x),
+        });
+    }
+
+    #[test]
+    fn test_resolve_leaves_a_free_identifier_unsymbolized() {
+        let resolved = resolve(&Term::identifier("undefined"));
+        assert_eq!(
+            resolved,
+            ResolvedTerm::Identifier {
+                name: String::from("undefined"),
+                symbol: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_resolve_gives_nested_binders_distinct_symbols() {
+        let term = Term::lambda("x", Term::lambda("y", Term::identifier("x")));
+        let ResolvedTerm::FunctionDefinition { parameter: outer, body } = resolve(&term) else {
+            panic!("expected FunctionDefinition");
+        };
+        let ResolvedTerm::FunctionDefinition { parameter: inner, .. } = *body else {
+            panic!("expected inner FunctionDefinition");
+        };
+        assert_ne!(outer, inner);
+    }
+}
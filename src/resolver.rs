@@ -0,0 +1,167 @@
+use crate::parser::{Term, TermKind};
+use crate::resolve::Symbol;
+use crate::tokenizer::Span;
+use std::collections::HashMap;
+
+/// Everything a whole-program tool needs to know about one binding: where
+/// it was introduced and every place it was subsequently referenced. An
+/// empty `references` list is exactly what an unused-variable lint is
+/// looking for; `definition` is what a go-to-definition request jumps to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolInfo {
+    pub name: String,
+    pub definition: Span,
+    pub references: Vec<Span>,
+}
+
+/// A whole-program map from each binder's [`Symbol`] to its [`SymbolInfo`],
+/// built once by [`build_symbol_table`] and then queried repeatedly, rather
+/// than re-walking the AST for every lint or IDE request that needs it.
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTable {
+    symbols: HashMap<Symbol, SymbolInfo>,
+}
+
+impl SymbolTable {
+    pub fn get(&self, symbol: Symbol) -> Option<&SymbolInfo> {
+        self.symbols.get(&symbol)
+    }
+
+    /// The binders that were never referenced anywhere in the program,
+    /// i.e. what an unused-variable lint would report.
+    pub fn unused(&self) -> Vec<Symbol> {
+        self.symbols
+            .iter()
+            .filter(|(_, info)| info.references.is_empty())
+            .map(|(symbol, _)| *symbol)
+            .collect()
+    }
+}
+
+/// Walks `term`, assigning every `fn` parameter and `let` declaration a
+/// fresh [`Symbol`] and recording each identifier occurrence that resolves
+/// to it, into a [`SymbolTable`] covering the whole program.
+pub fn build_symbol_table(term: &Term) -> SymbolTable {
+    let mut table = SymbolTable::default();
+    let mut next_symbol = 0;
+    walk(term, &mut Vec::new(), &mut next_symbol, &mut table);
+    table
+}
+
+fn walk(term: &Term, scope: &mut Vec<(String, Symbol)>, next_symbol: &mut u32, table: &mut SymbolTable) {
+    match &term.kind {
+        TermKind::Boolean(_) | TermKind::Error | TermKind::Integer(_) => {}
+        TermKind::Identifier(name) => {
+            if let Some((_, symbol)) = scope.iter().rev().find(|(bound, _)| bound == name) {
+                table
+                    .symbols
+                    .get_mut(symbol)
+                    .expect("every scoped symbol has an entry from the binder that introduced it")
+                    .references
+                    .push(term.span);
+            }
+        }
+        TermKind::FunctionApplication { function, argument } => {
+            walk(function, scope, next_symbol, table);
+            walk(argument, scope, next_symbol, table);
+        }
+        TermKind::FunctionDefinition { parameter, body } => {
+            let symbol = define(parameter, next_symbol, table);
+            scope.push((binder_name(parameter), symbol));
+            walk(body, scope, next_symbol, table);
+            scope.pop();
+        }
+        TermKind::IfExpression {
+            condition,
+            true_branch,
+            false_branch,
+        } => {
+            walk(condition, scope, next_symbol, table);
+            walk(true_branch, scope, next_symbol, table);
+            walk(false_branch, scope, next_symbol, table);
+        }
+        TermKind::LetExpression {
+            declaration_name,
+            declaration_value,
+            expression,
+        } => {
+            walk(declaration_value, scope, next_symbol, table);
+            let symbol = define(declaration_name, next_symbol, table);
+            scope.push((binder_name(declaration_name), symbol));
+            walk(expression, scope, next_symbol, table);
+            scope.pop();
+        }
+        TermKind::RaiseExpression { exception } => walk(exception, scope, next_symbol, table),
+    }
+}
+
+/// Records a fresh symbol for a binder (a `fn` parameter or `let`
+/// declaration name), starting it out with no references.
+fn define(binder: &Term, next_symbol: &mut u32, table: &mut SymbolTable) -> Symbol {
+    let symbol = fresh_symbol(next_symbol);
+    table.symbols.insert(
+        symbol,
+        SymbolInfo {
+            name: binder_name(binder),
+            definition: binder.span,
+            references: Vec::new(),
+        },
+    );
+    symbol
+}
+
+fn binder_name(binder: &Term) -> String {
+    match &binder.kind {
+        TermKind::Identifier(name) => name.clone(),
+        other => unreachable!("binder is always an identifier, got {:?}", other),
+    }
+}
+
+fn fresh_symbol(next_symbol: &mut u32) -> Symbol {
+    let symbol = Symbol::from_raw(*next_symbol);
+    *next_symbol += 1;
+    symbol
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::Term;
+    use crate::resolver::build_symbol_table;
+
+    #[test]
+    fn test_build_symbol_table_does_not_flag_a_referenced_lambda_parameter() {
+        let term = Term::lambda("x", Term::identifier("x"));
+        let table = build_symbol_table(&term);
+        let symbol = table.unused();
+        assert!(symbol.is_empty(), "the parameter is referenced by its body");
+    }
+
+    #[test]
+    fn test_build_symbol_table_records_a_reference_to_a_bound_variable() {
+        let term = Term::lambda("x", Term::identifier("x"));
+        let table = build_symbol_table(&term);
+        let defined = table.symbols.values().next().expect("one symbol defined");
+        assert_eq!(defined.name, "x");
+        assert_eq!(defined.references.len(), 1);
+    }
+
+    #[test]
+    fn test_build_symbol_table_flags_an_unused_let_binding() {
+        let term = Term::let_in("x", Term::integer(1), Term::integer(2));
+        let table = build_symbol_table(&term);
+        assert_eq!(table.unused().len(), 1);
+    }
+
+    #[test]
+    fn test_build_symbol_table_does_not_flag_a_referenced_binding() {
+        let term = Term::let_in("x", Term::integer(1), Term::identifier("x"));
+        let table = build_symbol_table(&term);
+        assert!(table.unused().is_empty());
+    }
+
+    #[test]
+    fn test_symbol_table_get_returns_none_for_an_unknown_symbol() {
+        let table = build_symbol_table(&Term::integer(1));
+        assert!(table.get(crate::resolve::Symbol::from_raw(0)).is_none());
+    }
+}
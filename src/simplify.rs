@@ -0,0 +1,194 @@
+use crate::annotator::{Type, TypedTerm, TypedTermKind};
+use crate::tokenizer::Span;
+use std::rc::Rc;
+
+/// Simplifies a typed AST by folding literal arithmetic (`1 + 2` becomes
+/// `3`) and dropping the untaken branch of a conditional whose condition is
+/// already a literal `true` or `false`. This is an optional pass — nothing
+/// downstream requires it — offered as groundwork for a future compiler
+/// backend and to shrink the amount of work an evaluator would otherwise
+/// repeat on every run.
+pub fn simplify(term: &TypedTerm) -> TypedTerm {
+    match &term.kind {
+        TypedTermKind::Boolean(value) => TypedTerm {
+            ty: term.ty.clone(),
+            kind: TypedTermKind::Boolean(*value),
+            span: term.span,
+        },
+        TypedTermKind::Error => TypedTerm {
+            ty: term.ty.clone(),
+            kind: TypedTermKind::Error,
+            span: term.span,
+        },
+        TypedTermKind::Integer(value) => TypedTerm {
+            ty: term.ty.clone(),
+            kind: TypedTermKind::Integer(*value),
+            span: term.span,
+        },
+        TypedTermKind::Identifier(name) => TypedTerm {
+            ty: term.ty.clone(),
+            kind: TypedTermKind::Identifier(name.clone()),
+            span: term.span,
+        },
+        TypedTermKind::FunctionApplication { function, argument } => {
+            let function = simplify(function);
+            let argument = simplify(argument);
+            fold_arithmetic(&function, &argument, term.ty.clone(), term.span).unwrap_or(TypedTerm {
+                ty: term.ty.clone(),
+                kind: TypedTermKind::FunctionApplication {
+                    function: Box::from(function),
+                    argument: Box::from(argument),
+                },
+                span: term.span,
+            })
+        }
+        TypedTermKind::FunctionDefinition { parameter, body } => TypedTerm {
+            ty: term.ty.clone(),
+            kind: TypedTermKind::FunctionDefinition {
+                parameter: Box::from(simplify(parameter)),
+                body: Box::from(simplify(body)),
+            },
+            span: term.span,
+        },
+        TypedTermKind::IfExpression {
+            condition,
+            true_branch,
+            false_branch,
+        } => {
+            let condition = simplify(condition);
+            let true_branch = simplify(true_branch);
+            let false_branch = simplify(false_branch);
+            match condition.kind {
+                TypedTermKind::Boolean(true) => true_branch,
+                TypedTermKind::Boolean(false) => false_branch,
+                _ => TypedTerm {
+                    ty: term.ty.clone(),
+                    kind: TypedTermKind::IfExpression {
+                        condition: Box::from(condition),
+                        true_branch: Box::from(true_branch),
+                        false_branch: Box::from(false_branch),
+                    },
+                    span: term.span,
+                },
+            }
+        }
+        TypedTermKind::LetExpression {
+            declaration_name,
+            declaration_value,
+            expression,
+        } => TypedTerm {
+            ty: term.ty.clone(),
+            kind: TypedTermKind::LetExpression {
+                declaration_name: Box::from(simplify(declaration_name)),
+                declaration_value: Box::from(simplify(declaration_value)),
+                expression: Box::from(simplify(expression)),
+            },
+            span: term.span,
+        },
+        TypedTermKind::RaiseExpression { exception } => TypedTerm {
+            ty: term.ty.clone(),
+            kind: TypedTermKind::RaiseExpression {
+                exception: Box::from(simplify(exception)),
+            },
+            span: term.span,
+        },
+    }
+}
+
+/// Folds `operator(left)(right)` into a single integer literal when
+/// `operator` is one of the builtin arithmetic identifiers and both
+/// operands are already literals, mirroring the shape `annotate_term`
+/// gives a parsed `left + right` (nested `FunctionApplication`s around the
+/// operator's identifier — see `operator_name` in the parser). Returns
+/// `None` for anything else, including division by zero, which is left for
+/// the evaluator to report rather than folded away here.
+fn fold_arithmetic(function: &TypedTerm, argument: &TypedTerm, ty: Rc<Type>, span: Span) -> Option<TypedTerm> {
+    let TypedTermKind::FunctionApplication {
+        function: operator,
+        argument: left,
+    } = &function.kind
+    else {
+        return None;
+    };
+    let TypedTermKind::Identifier(operator) = &operator.kind else {
+        return None;
+    };
+    let (TypedTermKind::Integer(left), TypedTermKind::Integer(right)) = (&left.kind, &argument.kind) else {
+        return None;
+    };
+    let value = match operator.as_str() {
+        "+" => left.checked_add(*right),
+        "-" => left.checked_sub(*right),
+        "*" => left.checked_mul(*right),
+        "/" if *right != 0 => left.checked_div(*right),
+        _ => None,
+    }?;
+    Some(TypedTerm {
+        ty,
+        kind: TypedTermKind::Integer(value),
+        span,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::annotator::{annotate, TypedTermKind};
+    use crate::desugar::desugar;
+    use crate::parser::parse;
+    use crate::simplify::simplify;
+    use crate::tokenizer::tokenize_with_spans;
+
+    #[test]
+    fn test_simplify_folds_a_literal_addition() -> Result<(), String> {
+        let tokens = tokenize_with_spans("1 + 2")?;
+        let term = annotate(&desugar(&parse(&tokens)?))?;
+        assert_eq!(simplify(&term).kind, TypedTermKind::Integer(3));
+        Ok(())
+    }
+
+    #[test]
+    fn test_simplify_folds_a_nested_literal_expression() -> Result<(), String> {
+        let tokens = tokenize_with_spans("1 + 2 * 3")?;
+        let term = annotate(&desugar(&parse(&tokens)?))?;
+        assert_eq!(simplify(&term).kind, TypedTermKind::Integer(7));
+        Ok(())
+    }
+
+    #[test]
+    fn test_simplify_leaves_division_by_zero_unfolded() -> Result<(), String> {
+        let tokens = tokenize_with_spans("1 / 0")?;
+        let term = annotate(&desugar(&parse(&tokens)?))?;
+        assert!(matches!(
+            simplify(&term).kind,
+            TypedTermKind::FunctionApplication { .. }
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_simplify_takes_the_true_branch_of_a_literal_true_condition() -> Result<(), String> {
+        let tokens = tokenize_with_spans("if true then 1 else 2")?;
+        let term = annotate(&desugar(&parse(&tokens)?))?;
+        assert_eq!(simplify(&term).kind, TypedTermKind::Integer(1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_simplify_takes_the_false_branch_of_a_literal_false_condition() -> Result<(), String> {
+        let tokens = tokenize_with_spans("if false then 1 else 2")?;
+        let term = annotate(&desugar(&parse(&tokens)?))?;
+        assert_eq!(simplify(&term).kind, TypedTermKind::Integer(2));
+        Ok(())
+    }
+
+    #[test]
+    fn test_simplify_leaves_a_non_literal_condition_alone() -> Result<(), String> {
+        let tokens = tokenize_with_spans("fn x => if x then 1 else 2")?;
+        let term = annotate(&desugar(&parse(&tokens)?))?;
+        assert!(matches!(
+            simplify(&term).kind,
+            TypedTermKind::FunctionDefinition { .. }
+        ));
+        Ok(())
+    }
+}
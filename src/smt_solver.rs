@@ -0,0 +1,89 @@
+//! Optional SMT-backed constraint solving, gated behind the `smt` feature.
+//! This exists to cross-validate [`crate::unifier`]'s union-find solver on
+//! fuzzed constraint sets — feeding the same [`Constraint`]s to both and
+//! comparing satisfiability — not to serve as a checker's primary backend:
+//! it shells out to an external solver process per call, which is far too
+//! slow for that.
+
+use crate::constraint::Constraint;
+use crate::smtlib::constraints_to_smtlib;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Why [`solve`] or [`agrees_with_unifier`] couldn't produce a verdict.
+#[derive(Debug)]
+pub enum SmtError {
+    /// The solver binary couldn't be started, e.g. it isn't on `PATH`.
+    SolverUnavailable(std::io::Error),
+    /// The solver ran but its `(check-sat)` output wasn't `sat` or `unsat`
+    /// on the first line, e.g. it reported `unknown` or an error message.
+    UnexpectedOutput(String),
+}
+
+/// Runs `solver_command` (expected to accept an SMT-LIB v2 script on
+/// stdin, as `z3 -in` does) on the encoding of `constraints`, and reports
+/// whether it found them satisfiable.
+pub fn solve(constraints: &[Constraint], solver_command: &str) -> Result<bool, SmtError> {
+    let script = constraints_to_smtlib(constraints);
+    let mut child = Command::new(solver_command)
+        .arg("-in")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(SmtError::SolverUnavailable)?;
+    child
+        .stdin
+        .take()
+        .expect("stdin was requested as piped")
+        .write_all(script.as_bytes())
+        .map_err(SmtError::SolverUnavailable)?;
+    let output = child.wait_with_output().map_err(SmtError::SolverUnavailable)?;
+    parse_check_sat_output(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parses the response to a script's trailing `(check-sat)` from the
+/// solver's stdout, which SMT-LIB v2 solvers put on its own first line.
+fn parse_check_sat_output(stdout: &str) -> Result<bool, SmtError> {
+    match stdout.lines().next().map(str::trim) {
+        Some("sat") => Ok(true),
+        Some("unsat") => Ok(false),
+        _ => Err(SmtError::UnexpectedOutput(stdout.to_string())),
+    }
+}
+
+/// Discharges `constraints` through `z3` (assumed to be on `PATH`) and
+/// reports whether its verdict agrees with [`crate::unifier::unify`]'s:
+/// both should find `constraints` solvable, or both should reject them.
+pub fn agrees_with_unifier(constraints: &[Constraint]) -> Result<bool, SmtError> {
+    let unifier_solved_it = crate::unifier::unify(constraints).is_ok();
+    let smt_solved_it = solve(constraints, "z3")?;
+    Ok(unifier_solved_it == smt_solved_it)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_check_sat_output_recognizes_sat() {
+        assert!(parse_check_sat_output("sat\n").unwrap());
+    }
+
+    #[test]
+    fn test_parse_check_sat_output_recognizes_unsat() {
+        assert!(!parse_check_sat_output("unsat\n").unwrap());
+    }
+
+    #[test]
+    fn test_parse_check_sat_output_rejects_anything_else() {
+        let error = parse_check_sat_output("unknown\n").unwrap_err();
+        assert!(matches!(error, SmtError::UnexpectedOutput(_)));
+    }
+
+    #[test]
+    fn test_solve_reports_solver_unavailable_for_a_nonexistent_binary() {
+        let error = solve(&[], "not-a-real-smt-solver-binary").unwrap_err();
+        assert!(matches!(error, SmtError::SolverUnavailable(_)));
+    }
+}
@@ -0,0 +1,295 @@
+use crate::annotator::Type;
+use crate::constraint::{Constraint, TypeScheme};
+use std::collections::HashMap;
+
+/// The SMT-LIB v2 declaration of an algebraic sort mirroring [`Type`], plus
+/// the auxiliary `TyList` sort [`Type::Constructor`] and [`Type::Intersection`]
+/// need to hold their variable-length argument lists, and `FieldList`
+/// [`Type::Record`] needs to hold its name/type pairs. Constructor names are
+/// prefixed with `ty-` so they can't collide with a solver's own builtin
+/// names (`Integer`, in particular, is one Z3 already uses).
+///
+/// `Type::Function`'s `effects` field is left out: nothing downstream of
+/// constraint generation reasons about effects yet, so encoding them would
+/// only add sort machinery with no constraint ever using it.
+const TYPE_DATATYPE: &str = "(declare-datatypes ((Ty 0) (TyList 0) (FieldList 0)) (\n\
+    \x20   ((ty-boolean) (ty-bottom) (ty-integer)\n\
+    \x20    (ty-numeric (numeric-id Int))\n\
+    \x20    (ty-placeholder (placeholder-id Int))\n\
+    \x20    (ty-constructor (constructor-name String) (constructor-args TyList))\n\
+    \x20    (ty-record (record-fields FieldList))\n\
+    \x20    (ty-function (parameter-type Ty) (return-type Ty))\n\
+    \x20    (ty-intersection (intersection-members TyList)))\n\
+    \x20   ((ty-nil) (ty-cons (head Ty) (tail TyList)))\n\
+    \x20   ((field-nil) (field-cons (field-name String) (field-type Ty) (field-rest FieldList)))))\n";
+
+/// Encodes `constraints` as a standalone SMT-LIB v2 script: the [`Type`]
+/// datatype declaration, one `(assert ...)` per constraint, and a trailing
+/// `(check-sat)`, so the exact problem this crate's own solver would face
+/// can be handed to an external solver like Z3 or CVC5 for comparison.
+pub fn constraints_to_smtlib(constraints: &[Constraint]) -> String {
+    let mut script = String::from(TYPE_DATATYPE);
+    for constraint in constraints {
+        script.push_str("(assert ");
+        script.push_str(&encode_constraint(constraint));
+        script.push_str(")\n");
+    }
+    script.push_str("(check-sat)\n");
+    script
+}
+
+fn encode_constraint(constraint: &Constraint) -> String {
+    match constraint {
+        Constraint::Equal { type1, type2, .. } => format!(
+            "(= {} {})",
+            encode_type(type1, &HashMap::new()),
+            encode_type(type2, &HashMap::new())
+        ),
+        // `is_subtype` now recognizes function variance, constructor
+        // arguments, and intersection members, none of which this crate's
+        // flat SMT-LIB encoding can express without its own `<:` relation.
+        // Encoding `sub <: sup` as `sub = sup` is a conservative
+        // approximation: an external solver may report a script derived
+        // from a real (non-reflexive) subtyping constraint as unsatisfiable
+        // even though this crate's own solver accepts it.
+        Constraint::Subtype { sub, sup, .. } => format!(
+            "(= {} {})",
+            encode_type(sub, &HashMap::new()),
+            encode_type(sup, &HashMap::new())
+        ),
+        Constraint::Instance { scheme, ty, .. } => encode_instance(scheme, ty),
+    }
+}
+
+/// Encodes `ty must be some instantiation of scheme` as an `exists` over one
+/// fresh SMT-LIB variable per bound variable in `scheme`. A bound (`'a <:
+/// bound`) is encoded as an extra equality conjunct rather than a subtyping
+/// predicate, for the same reason [`Constraint::Subtype`] is: this crate's
+/// flat SMT-LIB encoding can't express the variance and pointwise structure
+/// `is_subtype` now recognizes.
+fn encode_instance(scheme: &TypeScheme, ty: &Type) -> String {
+    if scheme.bound_vars.is_empty() {
+        return format!(
+            "(= {} {})",
+            encode_type(&scheme.ty, &HashMap::new()),
+            encode_type(ty, &HashMap::new())
+        );
+    }
+    let mut substitutions = HashMap::new();
+    let mut quantified = Vec::new();
+    let mut bounds = Vec::new();
+    for (var, bound) in &scheme.bound_vars {
+        let name = format!("t{}", var);
+        quantified.push(format!("({} Ty)", name));
+        if let Some(bound) = bound {
+            bounds.push(format!("(= {} {})", name, encode_type(bound, &HashMap::new())));
+        }
+        substitutions.insert(*var, name);
+    }
+    let equality = format!(
+        "(= {} {})",
+        encode_type(&scheme.ty, &substitutions),
+        encode_type(ty, &HashMap::new())
+    );
+    let body = if bounds.is_empty() {
+        equality
+    } else {
+        bounds.push(equality);
+        format!("(and {})", bounds.join(" "))
+    };
+    format!("(exists ({}) {})", quantified.join(" "), body)
+}
+
+/// Encodes `ty` as an SMT-LIB `Ty` term. `substitutions` replaces a
+/// [`Type::Placeholder`] with the name of the existentially-quantified
+/// variable standing in for it, when encoding the body of a
+/// [`Constraint::Instance`]'s scheme; it is empty everywhere else.
+fn encode_type(ty: &Type, substitutions: &HashMap<u32, String>) -> String {
+    match ty {
+        Type::Boolean => String::from("ty-boolean"),
+        Type::Bottom => String::from("ty-bottom"),
+        Type::Integer => String::from("ty-integer"),
+        Type::Numeric(id) => format!("(ty-numeric {})", id),
+        Type::Placeholder(id) => match substitutions.get(id) {
+            Some(name) => name.clone(),
+            None => format!("(ty-placeholder {})", id),
+        },
+        Type::Constructor { name, arguments } => format!(
+            "(ty-constructor {} {})",
+            encode_string(name),
+            encode_type_list(arguments, substitutions)
+        ),
+        Type::Record(fields) => format!("(ty-record {})", encode_field_list(fields, substitutions)),
+        Type::Function {
+            parameter_type,
+            return_type,
+            ..
+        } => format!(
+            "(ty-function {} {})",
+            encode_type(parameter_type, substitutions),
+            encode_type(return_type, substitutions)
+        ),
+        Type::Intersection(members) => {
+            format!("(ty-intersection {})", encode_type_list(members, substitutions))
+        }
+    }
+}
+
+fn encode_type_list(types: &[Type], substitutions: &HashMap<u32, String>) -> String {
+    types.iter().rev().fold(String::from("ty-nil"), |tail, ty| {
+        format!("(ty-cons {} {})", encode_type(ty, substitutions), tail)
+    })
+}
+
+fn encode_field_list(
+    fields: &std::collections::BTreeMap<String, Type>,
+    substitutions: &HashMap<u32, String>,
+) -> String {
+    fields.iter().rev().fold(String::from("field-nil"), |rest, (name, field_type)| {
+        format!("(field-cons {} {} {})", encode_string(name), encode_type(field_type, substitutions), rest)
+    })
+}
+
+/// Escapes `value` as an SMT-LIB string literal: wrapped in double quotes,
+/// with any embedded double quote doubled, per the SMT-LIB v2 lexical
+/// grammar for `<string>`.
+fn encode_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constraint::{Constraint, ConstraintReason, TypeScheme};
+    use crate::tokenizer::Span;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_constraints_to_smtlib_declares_the_type_datatype() {
+        let script = constraints_to_smtlib(&[]);
+        assert!(script.starts_with("(declare-datatypes ((Ty 0) (TyList 0) (FieldList 0))"));
+        assert!(script.trim_end().ends_with("(check-sat)"));
+    }
+
+    #[test]
+    fn test_constraints_to_smtlib_encodes_an_equal_constraint() {
+        let constraint = Constraint::equal(
+            Rc::new(Type::Boolean),
+            Rc::new(Type::Placeholder(1)),
+            ConstraintReason::BooleanLiteral,
+            Span::default(),
+            Span::default(),
+        );
+        let script = constraints_to_smtlib(&[constraint]);
+        assert!(script.contains("(assert (= ty-boolean (ty-placeholder 1)))"));
+    }
+
+    #[test]
+    fn test_constraints_to_smtlib_encodes_a_subtype_constraint_as_equality() {
+        let constraint = Constraint::subtype(
+            Rc::new(Type::Integer),
+            Rc::new(Type::Integer),
+            ConstraintReason::LetBinding,
+            Span::default(),
+            Span::default(),
+        );
+        let script = constraints_to_smtlib(&[constraint]);
+        assert!(script.contains("(assert (= ty-integer ty-integer))"));
+    }
+
+    #[test]
+    fn test_constraints_to_smtlib_encodes_a_function_type() {
+        let constraint = Constraint::equal(
+            Rc::new(Type::Placeholder(1)),
+            Rc::new(Type::Function {
+                parameter_type: Box::from(Type::Integer),
+                return_type: Box::from(Type::Boolean),
+                effects: Vec::new(),
+            }),
+            ConstraintReason::FunctionSignature,
+            Span::default(),
+            Span::default(),
+        );
+        let script = constraints_to_smtlib(&[constraint]);
+        assert!(script.contains("(ty-function ty-integer ty-boolean)"));
+    }
+
+    #[test]
+    fn test_constraints_to_smtlib_encodes_a_constructor_type_with_arguments() {
+        let constraint = Constraint::equal(
+            Rc::new(Type::Constructor {
+                name: String::from("list"),
+                arguments: vec![Type::Integer],
+            }),
+            Rc::new(Type::Placeholder(1)),
+            ConstraintReason::LetBinding,
+            Span::default(),
+            Span::default(),
+        );
+        let script = constraints_to_smtlib(&[constraint]);
+        assert!(script.contains("(ty-constructor \"list\" (ty-cons ty-integer ty-nil))"));
+    }
+
+    #[test]
+    fn test_constraints_to_smtlib_encodes_an_unbound_instance_constraint() {
+        let scheme = TypeScheme {
+            bound_vars: vec![(1, None)],
+            ty: Type::Function {
+                parameter_type: Box::from(Type::Placeholder(1)),
+                return_type: Box::from(Type::Placeholder(1)),
+                effects: Vec::new(),
+            },
+        };
+        let constraint = Constraint::instance(
+            scheme,
+            Rc::new(Type::Integer),
+            ConstraintReason::LetInstantiation,
+            Span::default(),
+        );
+        let script = constraints_to_smtlib(&[constraint]);
+        assert!(script.contains(
+            "(assert (exists ((t1 Ty)) (= (ty-function t1 t1) ty-integer)))"
+        ));
+    }
+
+    #[test]
+    fn test_constraints_to_smtlib_encodes_a_bounded_instance_constraint() {
+        let scheme = TypeScheme {
+            bound_vars: vec![(1, Some(Type::Boolean))],
+            ty: Type::Placeholder(1),
+        };
+        let constraint = Constraint::instance(
+            scheme,
+            Rc::new(Type::Boolean),
+            ConstraintReason::LetInstantiation,
+            Span::default(),
+        );
+        let script = constraints_to_smtlib(&[constraint]);
+        assert!(script.contains(
+            "(assert (exists ((t1 Ty)) (and (= t1 ty-boolean) (= t1 ty-boolean))))"
+        ));
+    }
+
+    #[test]
+    fn test_constraints_to_smtlib_encodes_a_record_type_with_fields() {
+        let constraint = Constraint::equal(
+            Rc::new(Type::Record(std::collections::BTreeMap::from([(
+                String::from("name"),
+                Type::Integer,
+            )]))),
+            Rc::new(Type::Placeholder(1)),
+            ConstraintReason::LetBinding,
+            Span::default(),
+            Span::default(),
+        );
+        let script = constraints_to_smtlib(&[constraint]);
+        assert!(script.contains(
+            "(ty-record (field-cons \"name\" ty-integer field-nil))"
+        ));
+    }
+
+    #[test]
+    fn test_encode_string_escapes_embedded_quotes() {
+        assert_eq!(encode_string("a\"b"), "\"a\"\"b\"");
+    }
+}
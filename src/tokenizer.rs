@@ -1,4 +1,6 @@
-#[derive(Debug, PartialEq)]
+use std::fmt::{Display, Error, Formatter};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Token {
     Arrow,
     Boolean(bool),
@@ -12,31 +14,313 @@ pub enum Token {
     KeywordIf,
     KeywordIn,
     KeywordLet,
+    KeywordRaise,
     KeywordThen,
     KeywordVal,
     LeftParenthesis,
     Minus,
     Plus,
     RightParenthesis,
+    String(String),
     Times,
 }
 
-pub fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+/// A byte range in the source text, along with the 1-indexed line and column
+/// of its first character, used to point diagnostics back at the original
+/// input.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// A [`Token`] together with the [`Span`] of source text it was read from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+}
+
+/// A lexical error raised while scanning the input, positioned so a caller
+/// can point back at the offending source text instead of just seeing a
+/// bare message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LexError {
+    pub position: usize,
+    pub line: usize,
+    pub column: usize,
+    pub found: String,
+}
+
+impl Display for LexError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(
+            f,
+            "{} at line {}, column {}",
+            self.found, self.line, self.column
+        )
+    }
+}
+
+impl From<LexError> for String {
+    fn from(error: LexError) -> String {
+        error.to_string()
+    }
+}
+
+pub fn tokenize(input: &str) -> Result<Vec<Token>, LexError> {
+    let tokens = tokenize_with_spans(input)?;
+    Ok(tokens.into_iter().map(|token| token.token).collect())
+}
+
+/// Like [`tokenize`], but pairs each token with the [`Span`] it was read
+/// from, for callers that need to report errors against the original input.
+pub fn tokenize_with_spans(input: &str) -> Result<Vec<SpannedToken>, LexError> {
     let mut tokenizer = Tokenizer::new(input);
     tokenizer.tokenize()
 }
 
+/// A [`Token`] together with the raw source text it was scanned from and
+/// whatever whitespace or comments preceded it, so the exact input can be
+/// reconstructed from the token stream instead of only approximated from
+/// the parsed token values.
+#[derive(Debug, PartialEq)]
+pub struct TriviaToken {
+    pub token: Token,
+    pub span: Span,
+    pub leading_trivia: String,
+    pub text: String,
+}
+
+/// The result of [`tokenize_lossless`]: every token paired with its leading
+/// trivia, plus whatever trivia trails the last token (e.g. a final
+/// newline or comment with no token after it).
+#[derive(Debug, PartialEq)]
+pub struct LosslessTokens {
+    pub tokens: Vec<TriviaToken>,
+    pub trailing_trivia: String,
+}
+
+/// Like [`tokenize`], but keeps whitespace and comments as trivia attached
+/// to the token that follows them, so a formatter can lay tokens back out
+/// exactly as they appeared in the source.
+pub fn tokenize_lossless(input: &str) -> Result<LosslessTokens, LexError> {
+    let mut tokenizer = Tokenizer::new(input);
+    let mut tokens = Vec::new();
+    loop {
+        let trivia_start = tokenizer.position;
+        tokenizer.skip_trivia()?;
+        let leading_trivia = input[trivia_start..tokenizer.position].to_string();
+        match tokenizer.scan_token()? {
+            Some(spanned_token) => {
+                let text = input[spanned_token.span.start..spanned_token.span.end].to_string();
+                tokens.push(TriviaToken {
+                    token: spanned_token.token,
+                    span: spanned_token.span,
+                    leading_trivia,
+                    text,
+                });
+            }
+            None => {
+                return Ok(LosslessTokens {
+                    tokens,
+                    trailing_trivia: leading_trivia,
+                });
+            }
+        }
+    }
+}
+
+/// Like [`tokenize`], but streams tokens one at a time instead of
+/// collecting them into a `Vec` up front, so a large file or a REPL line
+/// can start parsing before the rest of the input has been scanned.
+pub fn tokenize_iter(input: &str) -> TokenIterator<'_> {
+    TokenIterator {
+        tokenizer: Tokenizer::new(input),
+        done: false,
+    }
+}
+
+pub struct TokenIterator<'a> {
+    tokenizer: Tokenizer<'a>,
+    done: bool,
+}
+
+impl<'a> Iterator for TokenIterator<'a> {
+    type Item = Result<Token, LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.tokenizer.next_token() {
+            Ok(Some(spanned_token)) => Some(Ok(spanned_token.token)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(error) => {
+                self.done = true;
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+/// A single contiguous text replacement: the byte range `[start, end)` of
+/// the old input is replaced with `replacement`. Matches the shape of an
+/// editor keystroke or an LSP `textDocument/didChange` range edit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub start: usize,
+    pub end: usize,
+    pub replacement: String,
+}
+
+/// Relexes only the region of `new_input` affected by `edit`, reusing the
+/// tokens from `old_tokens` that lie entirely outside the edited region
+/// instead of rescanning the whole file, for editor/LSP integration where
+/// files change keystroke by keystroke.
+///
+/// A token is only reused if it cannot possibly have changed shape because
+/// of the edit: tokens that end exactly where the edit starts (or begin
+/// exactly where it ends) are also rescanned when they are the kind of
+/// token — an identifier or an integer — that could be extended by
+/// characters newly adjacent to it.
+///
+/// Byte offsets in the returned spans are always correct, but the line and
+/// column of a reused token after the edit are only guaranteed correct when
+/// the edit stays on a single line and does not change the number of
+/// newlines in the file; a line-counting mode for multi-line edits is
+/// future work.
+pub fn retokenize(
+    old_tokens: &[SpannedToken],
+    edit: &TextEdit,
+    new_input: &str,
+) -> Result<Vec<SpannedToken>, LexError> {
+    let delta = edit.replacement.len() as isize - (edit.end as isize - edit.start as isize);
+
+    let mut prefix_len = old_tokens
+        .iter()
+        .take_while(|token| token.span.end <= edit.start)
+        .count();
+    while prefix_len > 0 && is_extendable(&old_tokens[prefix_len - 1].token) {
+        prefix_len -= 1;
+    }
+    let prefix = &old_tokens[..prefix_len];
+
+    let mut suffix_start = old_tokens
+        .iter()
+        .position(|token| token.span.start >= edit.end)
+        .unwrap_or(old_tokens.len());
+    suffix_start = suffix_start.max(prefix_len);
+    while suffix_start < old_tokens.len() && is_extendable(&old_tokens[suffix_start].token) {
+        suffix_start += 1;
+    }
+    let edit_line = line_and_column_at(new_input, edit.start).0;
+    let suffix: Vec<SpannedToken> = old_tokens[suffix_start..]
+        .iter()
+        .map(|token| {
+            let column_delta = if token.span.line == edit_line { delta } else { 0 };
+            shift_spanned_token(token, delta, column_delta)
+        })
+        .collect();
+
+    let rescan_start = prefix.last().map(|token| token.span.end).unwrap_or(0);
+    let rescan_end = suffix.first().map(|token| token.span.start).unwrap_or(new_input.len());
+
+    let (line, column) = line_and_column_at(new_input, rescan_start);
+    let mut tokenizer = Tokenizer {
+        input: new_input,
+        position: rescan_start,
+        line,
+        column,
+    };
+    let mut rescanned = Vec::new();
+    loop {
+        tokenizer.skip_trivia()?;
+        if tokenizer.position >= rescan_end {
+            break;
+        }
+        match tokenizer.scan_token()? {
+            Some(token) => rescanned.push(token),
+            None => break,
+        }
+    }
+
+    let mut tokens = Vec::with_capacity(prefix.len() + rescanned.len() + suffix.len());
+    tokens.extend(prefix.iter().cloned());
+    tokens.extend(rescanned);
+    tokens.extend(suffix);
+    Ok(tokens)
+}
+
+/// Whether a token of this kind could grow to absorb a character newly
+/// placed immediately next to it, and so must be rescanned rather than
+/// reused whenever an edit touches its boundary.
+fn is_extendable(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Identifier(_) | Token::Integer(_) | Token::KeywordElse
+            | Token::KeywordEnd
+            | Token::KeywordFn
+            | Token::KeywordIf
+            | Token::KeywordIn
+            | Token::KeywordLet
+            | Token::KeywordRaise
+            | Token::KeywordThen
+            | Token::KeywordVal
+            | Token::Boolean(_)
+    )
+}
+
+fn shift_spanned_token(token: &SpannedToken, delta: isize, column_delta: isize) -> SpannedToken {
+    SpannedToken {
+        token: token.token.clone(),
+        span: Span {
+            start: (token.span.start as isize + delta) as usize,
+            end: (token.span.end as isize + delta) as usize,
+            line: token.span.line,
+            column: (token.span.column as isize + column_delta) as usize,
+        },
+    }
+}
+
+fn line_and_column_at(input: &str, byte_offset: usize) -> (usize, usize) {
+    let mut line = 1;
+    let mut column = 1;
+    for c in input[..byte_offset].chars() {
+        if c == '\n' {
+            line += 1;
+            column = 1;
+        } else {
+            column += 1;
+        }
+    }
+    (line, column)
+}
+
 struct Tokenizer<'a> {
     input: &'a str,
     position: usize,
+    line: usize,
+    column: usize,
 }
 
 impl<'a> Tokenizer<'a> {
     fn new(input: &str) -> Tokenizer {
-        Tokenizer { input, position: 0 }
+        Tokenizer {
+            input,
+            position: 0,
+            line: 1,
+            column: 1,
+        }
     }
 
-    fn tokenize(&mut self) -> Result<Vec<Token>, String> {
+    fn tokenize(&mut self) -> Result<Vec<SpannedToken>, LexError> {
         let mut tokens = Vec::new();
         while let Some(token) = self.next_token()? {
             tokens.push(token);
@@ -44,60 +328,99 @@ impl<'a> Tokenizer<'a> {
         Ok(tokens)
     }
 
-    fn next_token(&mut self) -> Result<Option<Token>, String> {
-        self.skip_whitespace();
-        match self.next_char() {
+    fn next_token(&mut self) -> Result<Option<SpannedToken>, LexError> {
+        self.skip_trivia()?;
+        self.scan_token()
+    }
+
+    /// Scans the next token starting at the current position, assuming any
+    /// leading trivia has already been skipped. Split out from [`next_token`]
+    /// so [`tokenize_lossless`] can capture the trivia it skips instead of
+    /// discarding it.
+    fn scan_token(&mut self) -> Result<Option<SpannedToken>, LexError> {
+        let start = self.position;
+        let (line, column) = (self.line, self.column);
+        let token = match self.next_char() {
             Some(c) => {
                 if c.is_alphabetic() {
-                    Ok(Some(self.read_identifier()))
+                    Some(self.read_identifier())
                 } else if c.is_numeric() {
-                    Ok(Some(self.read_integer()))
+                    Some(self.read_integer(start, line, column)?)
                 } else {
                     match c {
                         '(' => {
-                            self.position += 1;
-                            Ok(Some(Token::LeftParenthesis))
+                            self.advance();
+                            Some(Token::LeftParenthesis)
                         }
                         ')' => {
-                            self.position += 1;
-                            Ok(Some(Token::RightParenthesis))
+                            self.advance();
+                            Some(Token::RightParenthesis)
                         }
                         '+' => {
-                            self.position += 1;
-                            Ok(Some(Token::Plus))
+                            self.advance();
+                            Some(Token::Plus)
                         }
+                        // `-` always tokenizes as `Token::Minus`, whether it
+                        // reads as subtraction or as unary negation, so that
+                        // tokenization never depends on the characters
+                        // around it; the parser decides which one it is.
                         '-' => {
-                            if let Some(c2) = self.peek_char() {
-                                if c2.is_numeric() {
-                                    return Ok(Some(self.read_integer()));
-                                }
-                            }
-                            self.position += 1;
-                            Ok(Some(Token::Minus))
+                            self.advance();
+                            Some(Token::Minus)
                         }
                         '*' => {
-                            self.position += 1;
-                            Ok(Some(Token::Times))
+                            self.advance();
+                            Some(Token::Times)
                         }
                         '/' => {
-                            self.position += 1;
-                            Ok(Some(Token::Divide))
+                            self.advance();
+                            Some(Token::Divide)
                         }
                         '=' => {
                             if let Some(c2) = self.peek_char() {
                                 if c2 == '>' {
-                                    self.position += 2;
-                                    return Ok(Some(Token::Arrow));
+                                    self.advance();
+                                    self.advance();
+                                    return Ok(Some(self.finish_token(
+                                        Token::Arrow,
+                                        start,
+                                        line,
+                                        column,
+                                    )));
                                 }
                             }
-                            self.position += 1;
-                            Ok(Some(Token::Equals))
+                            self.advance();
+                            Some(Token::Equals)
+                        }
+                        '"' => {
+                            let string = self.read_string()?;
+                            return Ok(Some(self.finish_token(string, start, line, column)));
+                        }
+                        _ => {
+                            return Err(LexError {
+                                position: start,
+                                line,
+                                column,
+                                found: format!("unexpected character '{}'", c),
+                            })
                         }
-                        _ => Err(format!("unexpected character: {}", c)),
                     }
                 }
             }
-            None => Ok(None),
+            None => None,
+        };
+        Ok(token.map(|token| self.finish_token(token, start, line, column)))
+    }
+
+    fn finish_token(&self, token: Token, start: usize, line: usize, column: usize) -> SpannedToken {
+        SpannedToken {
+            token,
+            span: Span {
+                start,
+                end: self.position,
+                line,
+                column,
+            },
         }
     }
 
@@ -121,14 +444,85 @@ impl<'a> Tokenizer<'a> {
         }
     }
 
+    fn advance(&mut self) {
+        if let Some(c) = self.next_char() {
+            self.position += 1;
+            if c == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
+    }
+
     fn skip_whitespace(&mut self) {
         while let Some(c) = self.next_char() {
             if c.is_whitespace() {
-                self.position += 1;
+                self.advance();
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Skips whitespace, `(* ... *)` block comments (which may nest), and
+    /// `-- ...` line comments, in whatever order they appear before the
+    /// next token. Comments are discarded here; [`tokenize_lossless`] keeps
+    /// the text skipped before each token as that token's trivia.
+    fn skip_trivia(&mut self) -> Result<(), LexError> {
+        loop {
+            self.skip_whitespace();
+            if self.next_char() == Some('(') && self.peek_char() == Some('*') {
+                self.skip_block_comment()?;
+            } else if self.next_char() == Some('-') && self.peek_char() == Some('-') {
+                self.skip_line_comment();
             } else {
                 break;
             }
         }
+        Ok(())
+    }
+
+    fn skip_line_comment(&mut self) {
+        while let Some(c) = self.next_char() {
+            if c == '\n' {
+                break;
+            }
+            self.advance();
+        }
+    }
+
+    fn skip_block_comment(&mut self) -> Result<(), LexError> {
+        let start = self.position;
+        let (line, column) = (self.line, self.column);
+        self.advance();
+        self.advance();
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next_char() {
+                None => {
+                    return Err(LexError {
+                        position: start,
+                        line,
+                        column,
+                        found: String::from("unterminated block comment"),
+                    })
+                }
+                Some('(') if self.peek_char() == Some('*') => {
+                    self.advance();
+                    self.advance();
+                    depth += 1;
+                }
+                Some('*') if self.peek_char() == Some(')') => {
+                    self.advance();
+                    self.advance();
+                    depth -= 1;
+                }
+                Some(_) => self.advance(),
+            }
+        }
+        Ok(())
     }
 
     fn read_identifier(&mut self) -> Token {
@@ -136,7 +530,7 @@ impl<'a> Tokenizer<'a> {
         while let Some(c) = self.next_char() {
             if c.is_alphabetic() {
                 buffer.push(c);
-                self.position += 1;
+                self.advance();
             } else {
                 break;
             }
@@ -150,36 +544,146 @@ impl<'a> Tokenizer<'a> {
             "if" => Token::KeywordIf,
             "in" => Token::KeywordIn,
             "let" => Token::KeywordLet,
+            "raise" => Token::KeywordRaise,
             "then" => Token::KeywordThen,
             "val" => Token::KeywordVal,
             _ => Token::Identifier(buffer),
         }
     }
 
-    fn read_integer(&mut self) -> Token {
+    fn read_integer(&mut self, start: usize, line: usize, column: usize) -> Result<Token, LexError> {
         let mut buffer = String::new();
-        if let Some(c) = self.next_char() {
-            if c == '-' {
-                buffer.push(c);
-                self.position += 1;
-            }
-        }
         while let Some(c) = self.next_char() {
             if c.is_numeric() {
                 buffer.push(c);
-                self.position += 1;
+                self.advance();
+            } else if c == '_' {
+                // Underscores are digit-group separators (e.g. `1_000_000`)
+                // and are stripped rather than fed to the integer parser.
+                self.advance();
             } else {
                 break;
             }
         }
-        let value: i32 = buffer.parse().unwrap();
-        Token::Integer(value)
+        buffer.parse().map(Token::Integer).map_err(|_| LexError {
+            position: start,
+            line,
+            column,
+            found: format!("integer literal `{}` out of range", buffer),
+        })
+    }
+
+    /// Reads a `"..."` string literal, resolving `\n`, `\t`, `\\`, `\"`, and
+    /// `\u{...}` escapes as it goes rather than copying raw bytes.
+    fn read_string(&mut self) -> Result<Token, LexError> {
+        let start = self.position;
+        let (line, column) = (self.line, self.column);
+        self.advance();
+        let mut buffer = String::new();
+        loop {
+            match self.next_char() {
+                None => {
+                    return Err(LexError {
+                        position: start,
+                        line,
+                        column,
+                        found: String::from("unterminated string literal"),
+                    })
+                }
+                Some('"') => {
+                    self.advance();
+                    break;
+                }
+                Some('\\') => {
+                    buffer.push(self.read_escape_sequence()?);
+                }
+                Some(c) => {
+                    buffer.push(c);
+                    self.advance();
+                }
+            }
+        }
+        Ok(Token::String(buffer))
+    }
+
+    fn read_escape_sequence(&mut self) -> Result<char, LexError> {
+        let start = self.position;
+        let (line, column) = (self.line, self.column);
+        self.advance();
+        match self.next_char() {
+            Some('n') => {
+                self.advance();
+                Ok('\n')
+            }
+            Some('t') => {
+                self.advance();
+                Ok('\t')
+            }
+            Some('\\') => {
+                self.advance();
+                Ok('\\')
+            }
+            Some('"') => {
+                self.advance();
+                Ok('"')
+            }
+            Some('u') => {
+                self.advance();
+                self.read_unicode_escape(start, line, column)
+            }
+            other => Err(LexError {
+                position: start,
+                line,
+                column,
+                found: match other {
+                    Some(c) => format!("invalid escape sequence '\\{}'", c),
+                    None => String::from("invalid escape sequence"),
+                },
+            }),
+        }
+    }
+
+    fn read_unicode_escape(
+        &mut self,
+        start: usize,
+        line: usize,
+        column: usize,
+    ) -> Result<char, LexError> {
+        let invalid = || LexError {
+            position: start,
+            line,
+            column,
+            found: String::from("invalid unicode escape"),
+        };
+        if self.next_char() != Some('{') {
+            return Err(invalid());
+        }
+        self.advance();
+        let mut hex = String::new();
+        while let Some(c) = self.next_char() {
+            if c == '}' {
+                break;
+            }
+            hex.push(c);
+            self.advance();
+        }
+        if self.next_char() != Some('}') {
+            return Err(invalid());
+        }
+        self.advance();
+        u32::from_str_radix(&hex, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(invalid)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::tokenizer::{tokenize, Token};
+    use crate::tokenizer::{
+        retokenize, tokenize, tokenize_iter, tokenize_lossless, tokenize_with_spans, LexError,
+        Span, SpannedToken, TextEdit, Token,
+    };
 
     #[test]
     fn test_tokenize_boolean_true() {
@@ -219,7 +723,70 @@ mod tests {
 
     #[test]
     fn test_tokenize_negative_integer() {
-        assert_eq!(tokenize("-42"), Ok(vec![Token::Integer(-42)]));
+        // Negation is resolved by the parser, not the tokenizer, so `-42`
+        // and `- 42` tokenize identically regardless of spacing.
+        assert_eq!(tokenize("-42"), Ok(vec![Token::Minus, Token::Integer(42)]));
+    }
+
+    #[test]
+    fn test_tokenize_integer_with_underscore_separators() {
+        assert_eq!(tokenize("1_000_000"), Ok(vec![Token::Integer(1_000_000)]));
+    }
+
+    #[test]
+    fn test_tokenize_reports_an_error_instead_of_panicking_on_an_out_of_range_integer() {
+        let error = tokenize("99999999999999999999").unwrap_err();
+        assert!(error.found.contains("out of range"));
+    }
+
+    #[test]
+    fn test_tokenize_string_literal() {
+        assert_eq!(
+            tokenize("\"hello\""),
+            Ok(vec![Token::String(String::from("hello"))])
+        );
+    }
+
+    #[test]
+    fn test_tokenize_string_literal_with_escapes() {
+        assert_eq!(
+            tokenize("\"a\\nb\\tc\\\\d\\\"e\""),
+            Ok(vec![Token::String(String::from("a\nb\tc\\d\"e"))])
+        );
+    }
+
+    #[test]
+    fn test_tokenize_string_literal_with_unicode_escape() {
+        assert_eq!(
+            tokenize("\"\\u{1F600}\""),
+            Ok(vec![Token::String(String::from("\u{1F600}"))])
+        );
+    }
+
+    #[test]
+    fn test_tokenize_string_literal_with_invalid_escape_is_an_error() {
+        assert_eq!(
+            tokenize("\"\\q\""),
+            Err(LexError {
+                position: 1,
+                line: 1,
+                column: 2,
+                found: String::from("invalid escape sequence '\\q'"),
+            })
+        );
+    }
+
+    #[test]
+    fn test_tokenize_unterminated_string_literal_is_an_error() {
+        assert_eq!(
+            tokenize("\"never closed"),
+            Err(LexError {
+                position: 0,
+                line: 1,
+                column: 1,
+                found: String::from("unterminated string literal"),
+            })
+        );
     }
 
     #[test]
@@ -254,6 +821,17 @@ mod tests {
         assert_eq!(tokenize("=>"), Ok(vec![Token::Arrow]));
     }
 
+    #[test]
+    fn test_tokenize_raise_expression() {
+        assert_eq!(
+            tokenize("raise x"),
+            Ok(vec![
+                Token::KeywordRaise,
+                Token::Identifier(String::from("x")),
+            ])
+        );
+    }
+
     #[test]
     fn test_tokenize_with_leading_and_trailing_whitespace() {
         assert_eq!(tokenize(" 42 "), Ok(vec![Token::Integer(42)]));
@@ -267,6 +845,161 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_tokenize_with_spans_single_line() {
+        assert_eq!(
+            tokenize_with_spans("1 + 2"),
+            Ok(vec![
+                SpannedToken {
+                    token: Token::Integer(1),
+                    span: Span {
+                        start: 0,
+                        end: 1,
+                        line: 1,
+                        column: 1,
+                    },
+                },
+                SpannedToken {
+                    token: Token::Plus,
+                    span: Span {
+                        start: 2,
+                        end: 3,
+                        line: 1,
+                        column: 3,
+                    },
+                },
+                SpannedToken {
+                    token: Token::Integer(2),
+                    span: Span {
+                        start: 4,
+                        end: 5,
+                        line: 1,
+                        column: 5,
+                    },
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_tokenize_with_spans_tracks_line_and_column_across_newlines() {
+        assert_eq!(
+            tokenize_with_spans("1 +\n2"),
+            Ok(vec![
+                SpannedToken {
+                    token: Token::Integer(1),
+                    span: Span {
+                        start: 0,
+                        end: 1,
+                        line: 1,
+                        column: 1,
+                    },
+                },
+                SpannedToken {
+                    token: Token::Plus,
+                    span: Span {
+                        start: 2,
+                        end: 3,
+                        line: 1,
+                        column: 3,
+                    },
+                },
+                SpannedToken {
+                    token: Token::Integer(2),
+                    span: Span {
+                        start: 4,
+                        end: 5,
+                        line: 2,
+                        column: 1,
+                    },
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_tokenize_iter_yields_the_same_tokens_as_tokenize() {
+        let tokens: Result<Vec<Token>, LexError> = tokenize_iter("fn x => x + 1").collect();
+        assert_eq!(tokens, tokenize("fn x => x + 1"));
+    }
+
+    #[test]
+    fn test_tokenize_iter_stops_after_a_lex_error() {
+        let mut iter = tokenize_iter("1 @ 2");
+        assert_eq!(iter.next(), Some(Ok(Token::Integer(1))));
+        assert!(iter.next().unwrap().is_err());
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_tokenize_skips_block_comment() {
+        assert_eq!(
+            tokenize("1 (* the number one *) + 2"),
+            Ok(vec![Token::Integer(1), Token::Plus, Token::Integer(2)])
+        );
+    }
+
+    #[test]
+    fn test_tokenize_skips_nested_block_comment() {
+        assert_eq!(
+            tokenize("1 (* outer (* inner *) still outer *) + 2"),
+            Ok(vec![Token::Integer(1), Token::Plus, Token::Integer(2)])
+        );
+    }
+
+    #[test]
+    fn test_tokenize_unterminated_block_comment_is_an_error() {
+        assert_eq!(
+            tokenize("1 (* never closed"),
+            Err(LexError {
+                position: 2,
+                line: 1,
+                column: 3,
+                found: String::from("unterminated block comment"),
+            })
+        );
+    }
+
+    #[test]
+    fn test_tokenize_unexpected_character_is_a_positioned_lex_error() {
+        assert_eq!(
+            tokenize("1 @ 2"),
+            Err(LexError {
+                position: 2,
+                line: 1,
+                column: 3,
+                found: String::from("unexpected character '@'"),
+            })
+        );
+    }
+
+    #[test]
+    fn test_display_lex_error() {
+        let error = LexError {
+            position: 2,
+            line: 1,
+            column: 3,
+            found: String::from("unexpected character '@'"),
+        };
+        assert_eq!(
+            error.to_string(),
+            "unexpected character '@' at line 1, column 3"
+        );
+    }
+
+    #[test]
+    fn test_tokenize_skips_line_comment() {
+        assert_eq!(
+            tokenize("1 -- the number one\n+ 2"),
+            Ok(vec![Token::Integer(1), Token::Plus, Token::Integer(2)])
+        );
+    }
+
+    #[test]
+    fn test_tokenize_line_comment_runs_to_end_of_input() {
+        assert_eq!(tokenize("1 -- trailing comment"), Ok(vec![Token::Integer(1)]));
+    }
+
     #[test]
     fn test_tokenize_function_definition() {
         assert_eq!(
@@ -323,4 +1056,101 @@ mod tests {
             ])
         );
     }
+
+    #[test]
+    fn test_tokenize_lossless_captures_leading_trivia() {
+        let result = tokenize_lossless("  1 + -- comment\n2").unwrap();
+        assert_eq!(result.tokens[0].token, Token::Integer(1));
+        assert_eq!(result.tokens[0].leading_trivia, "  ");
+        assert_eq!(result.tokens[0].text, "1");
+        assert_eq!(result.tokens[1].token, Token::Plus);
+        assert_eq!(result.tokens[1].leading_trivia, " ");
+        assert_eq!(result.tokens[2].token, Token::Integer(2));
+        assert_eq!(result.tokens[2].leading_trivia, " -- comment\n");
+        assert_eq!(result.trailing_trivia, "");
+    }
+
+    #[test]
+    fn test_tokenize_lossless_captures_trailing_trivia() {
+        let result = tokenize_lossless("1 (* trailing *)").unwrap();
+        assert_eq!(result.tokens.len(), 1);
+        assert_eq!(result.trailing_trivia, " (* trailing *)");
+    }
+
+    #[test]
+    fn test_tokenize_lossless_reconstructs_source_exactly() {
+        let input = "  let val x = 1 in -- comment\n  x end  ";
+        let result = tokenize_lossless(input).unwrap();
+        let mut reconstructed = String::new();
+        for token in &result.tokens {
+            reconstructed.push_str(&token.leading_trivia);
+            reconstructed.push_str(&token.text);
+        }
+        reconstructed.push_str(&result.trailing_trivia);
+        assert_eq!(reconstructed, input);
+    }
+
+    #[test]
+    fn test_tokenize_lossless_propagates_lex_errors() {
+        assert_eq!(
+            tokenize_lossless("1 @"),
+            Err(LexError {
+                position: 2,
+                line: 1,
+                column: 3,
+                found: String::from("unexpected character '@'"),
+            })
+        );
+    }
+
+    #[test]
+    fn test_retokenize_matches_full_tokenize_for_an_isolated_edit() {
+        let old_input = "let val x = 1 in x + 1 end";
+        let old_tokens = tokenize_with_spans(old_input).unwrap();
+        // Change the `1` in the declaration to `42`.
+        let edit = TextEdit {
+            start: 12,
+            end: 13,
+            replacement: String::from("42"),
+        };
+        let new_input = "let val x = 42 in x + 1 end";
+        let result = retokenize(&old_tokens, &edit, new_input).unwrap();
+        assert_eq!(result, tokenize_with_spans(new_input).unwrap());
+    }
+
+    #[test]
+    fn test_retokenize_rescans_a_token_extended_by_the_edit() {
+        let old_input = "let val x = 1 in x end";
+        let old_tokens = tokenize_with_spans(old_input).unwrap();
+        // Insert characters directly after the identifier `x`, extending it.
+        let edit = TextEdit {
+            start: 9,
+            end: 9,
+            replacement: String::from("yz"),
+        };
+        let new_input = "let val xyz = 1 in x end";
+        let result = retokenize(&old_tokens, &edit, new_input).unwrap();
+        assert_eq!(result, tokenize_with_spans(new_input).unwrap());
+    }
+
+    #[test]
+    fn test_retokenize_propagates_lex_errors_from_the_rescanned_region() {
+        let old_input = "1 + 2";
+        let old_tokens = tokenize_with_spans(old_input).unwrap();
+        let edit = TextEdit {
+            start: 4,
+            end: 5,
+            replacement: String::from("@"),
+        };
+        let new_input = "1 + @";
+        assert_eq!(
+            retokenize(&old_tokens, &edit, new_input),
+            Err(LexError {
+                position: 4,
+                line: 1,
+                column: 5,
+                found: String::from("unexpected character '@'"),
+            })
+        );
+    }
 }
@@ -0,0 +1,1434 @@
+use crate::annotator::{Type, TypedTerm, TypedTermKind};
+use crate::constraint::{instantiate, is_subtype, Constraint, ConstraintReason, TypeError, TypeScheme};
+use crate::tokenizer::Span;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Error, Formatter};
+use std::rc::Rc;
+
+/// A disjoint-set forest over type variable ids (shared by [`Type::Numeric`]
+/// and [`Type::Placeholder`], since both draw from the same
+/// `annotator::TypeVarGen` counter), with path compression and union by
+/// rank. Unifying two free variables merges their sets in near-constant
+/// amortized time instead of chasing and rewriting a growing substitution
+/// list, which is what makes [`unify`] stay close to linear on the tens of
+/// thousands of constraints a large program can generate. A set's solved
+/// type, once known, is recorded on its root in `bound`.
+///
+/// Every mutation is appended to `log` before it happens, recording
+/// whatever the map held at that key beforehand, so [`UnionFind::undo_to`]
+/// can roll the whole structure back to an earlier point in near-constant
+/// time per logged edit — the basis for [`Substitution::checkpoint`] and
+/// [`Substitution::undo`], which let a caller like a REPL or an IDE add a
+/// line's worth of constraints and cheaply retract them again instead of
+/// re-solving everything from scratch on every keystroke.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct UnionFind {
+    parent: HashMap<u32, u32>,
+    rank: HashMap<u32, u32>,
+    bound: HashMap<u32, Type>,
+    /// Roots of a set that originated from at least one [`Type::Numeric`]
+    /// variable, tracked independently of `bound` so that a numeric
+    /// literal's "defaultable to int" tag survives being unioned with a
+    /// [`Type::Placeholder`] variable — whichever variant happens to label
+    /// the id at a given occurrence is irrelevant; what matters is whether
+    /// *the set it ended up in* ever contained a numeric literal. Consulted
+    /// by [`UnionFind::resolve_var`]/[`Substitution::apply_var`] so an
+    /// unbound but numeric-tagged set still reads back as [`Type::Numeric`]
+    /// (and so [`crate::annotator::default_numeric_types`] can default it),
+    /// no matter which of the merged occurrences' variants a caller asks
+    /// [`UnionFind::resolve`]/[`Substitution::apply`] to render it as.
+    numeric: HashSet<u32>,
+    log: Vec<Edit>,
+}
+
+/// One logged mutation to a [`UnionFind`]'s maps, carrying whatever value
+/// (if any) the key held immediately before the edit, so undoing it is
+/// just restoring — or removing, if there was nothing there before — that
+/// old value.
+#[derive(Debug, Clone, PartialEq)]
+enum Edit {
+    Parent(u32, Option<u32>),
+    Rank(u32, Option<u32>),
+    Bound(u32, Option<Type>),
+    /// `id` was, or was not, in `numeric` immediately before this edit.
+    Numeric(u32, bool),
+}
+
+impl UnionFind {
+    fn set_parent(&mut self, id: u32, value: u32) {
+        let previous = self.parent.insert(id, value);
+        self.log.push(Edit::Parent(id, previous));
+    }
+
+    fn set_rank(&mut self, id: u32, value: u32) {
+        let previous = self.rank.insert(id, value);
+        self.log.push(Edit::Rank(id, previous));
+    }
+
+    fn set_bound(&mut self, id: u32, value: Type) {
+        let previous = self.bound.insert(id, value);
+        self.log.push(Edit::Bound(id, previous));
+    }
+
+    fn remove_bound(&mut self, id: u32) -> Option<Type> {
+        let previous = self.bound.remove(&id)?;
+        self.log.push(Edit::Bound(id, Some(previous.clone())));
+        Some(previous)
+    }
+
+    /// Marks `id`'s current set as numeric-tagged (see [`UnionFind::numeric`]).
+    fn mark_numeric(&mut self, id: u32) {
+        let root = self.find(id);
+        if self.numeric.insert(root) {
+            self.log.push(Edit::Numeric(root, false));
+        }
+    }
+
+    /// Undoes every edit logged since `mark` (a prior `log.len()`), in
+    /// reverse order, restoring each key to whatever it held before that
+    /// edit.
+    fn undo_to(&mut self, mark: usize) {
+        while self.log.len() > mark {
+            match self.log.pop().expect("log.len() > mark implies at least one entry") {
+                Edit::Parent(id, Some(value)) => {
+                    self.parent.insert(id, value);
+                }
+                Edit::Parent(id, None) => {
+                    self.parent.remove(&id);
+                }
+                Edit::Rank(id, Some(value)) => {
+                    self.rank.insert(id, value);
+                }
+                Edit::Rank(id, None) => {
+                    self.rank.remove(&id);
+                }
+                Edit::Bound(id, Some(value)) => {
+                    self.bound.insert(id, value);
+                }
+                Edit::Bound(id, None) => {
+                    self.bound.remove(&id);
+                }
+                Edit::Numeric(id, true) => {
+                    self.numeric.insert(id);
+                }
+                Edit::Numeric(id, false) => {
+                    self.numeric.remove(&id);
+                }
+            }
+        }
+    }
+
+    /// The representative id of `id`'s set, path-compressing every node
+    /// visited along the way so the next lookup for any of them is O(1).
+    fn find(&mut self, id: u32) -> u32 {
+        let parent = *self.parent.get(&id).unwrap_or(&id);
+        if parent == id {
+            return id;
+        }
+        let root = self.find(parent);
+        self.set_parent(id, root);
+        root
+    }
+
+    /// The same lookup as [`UnionFind::find`], but read-only: it follows
+    /// the parent chain without compressing it, for callers that only have
+    /// a shared reference (e.g. [`Substitution::apply`] after solving is
+    /// done, when compression no longer pays for itself).
+    fn find_readonly(&self, id: u32) -> u32 {
+        let mut current = id;
+        while let Some(&parent) = self.parent.get(&current) {
+            if parent == current {
+                break;
+            }
+            current = parent;
+        }
+        current
+    }
+
+    /// Merges the sets containing `a` and `b`, attaching the lower-rank
+    /// root under the higher-rank one (breaking ties by bumping the
+    /// surviving root's rank) so the tree stays shallow no matter what
+    /// order variables are unified in. If either set was already bound to
+    /// a type, that binding is carried over to the surviving root; if
+    /// either set was numeric-tagged, so is the surviving root — merging a
+    /// numeric literal's set into a plain variable's must not lose track
+    /// of the literal, or vice versa.
+    fn union(&mut self, a: u32, b: u32) {
+        let root_a = self.find(a);
+        let root_b = self.find(b);
+        if root_a == root_b {
+            return;
+        }
+        let rank_a = *self.rank.get(&root_a).unwrap_or(&0);
+        let rank_b = *self.rank.get(&root_b).unwrap_or(&0);
+        let (child, surviving) = if rank_a < rank_b { (root_a, root_b) } else { (root_b, root_a) };
+        if let Some(ty) = self.remove_bound(child) {
+            self.set_bound(surviving, ty);
+        }
+        if self.numeric.remove(&child) {
+            self.log.push(Edit::Numeric(child, true));
+            self.mark_numeric(surviving);
+        }
+        self.set_parent(child, surviving);
+        if rank_a == rank_b {
+            let bumped = *self.rank.get(&surviving).unwrap_or(&0) + 1;
+            self.set_rank(surviving, bumped);
+        }
+    }
+
+    /// Records that `id`'s set has been solved to `ty`, replacing every
+    /// [`Type::Numeric`]/[`Type::Placeholder`] inside `ty` with its own
+    /// resolved type or canonical representative first, then rejecting the
+    /// binding if the occurs check finds `id`'s root inside the result.
+    fn bind(&mut self, id: u32, ty: &Type, span: Span) -> Result<(), TypeError> {
+        let root = self.find(id);
+        let resolved = self.resolve(ty);
+        if let Type::Numeric(other) | Type::Placeholder(other) = resolved {
+            if other == root {
+                return Ok(());
+            }
+        }
+        if occurs(root, &resolved) {
+            return Err(TypeError::InfiniteType {
+                var: root,
+                ty: Box::new(resolved),
+                span,
+            });
+        }
+        self.set_bound(root, resolved);
+        Ok(())
+    }
+
+    /// Fully resolves `ty`: every variable is replaced by whatever its set
+    /// was ultimately bound to, recursing through however many variables
+    /// that chains through, or by its set's representative id if the set
+    /// is still unbound.
+    fn resolve(&mut self, ty: &Type) -> Type {
+        match ty {
+            Type::Numeric(id) => self.resolve_var(*id, Type::Numeric),
+            Type::Placeholder(id) => self.resolve_var(*id, Type::Placeholder),
+            Type::Function {
+                parameter_type,
+                return_type,
+                effects,
+            } => Type::Function {
+                parameter_type: Box::new(self.resolve(parameter_type)),
+                return_type: Box::new(self.resolve(return_type)),
+                effects: effects.clone(),
+            },
+            Type::Intersection(members) => {
+                Type::Intersection(members.iter().map(|member| self.resolve(member)).collect())
+            }
+            Type::Constructor { name, arguments } => Type::Constructor {
+                name: name.clone(),
+                arguments: arguments.iter().map(|argument| self.resolve(argument)).collect(),
+            },
+            Type::Record(fields) => Type::Record(
+                fields.iter().map(|(name, field_type)| (name.clone(), self.resolve(field_type))).collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+
+    fn resolve_var(&mut self, id: u32, variant: fn(u32) -> Type) -> Type {
+        let root = self.find(id);
+        match self.bound.get(&root).cloned() {
+            Some(bound) => self.resolve(&bound),
+            None if self.numeric.contains(&root) => Type::Numeric(root),
+            None => variant(root),
+        }
+    }
+}
+
+/// The result of a successful [`unify`]: which type each variable's set was
+/// ultimately solved to. Looking a variable up gives its *fully resolved*
+/// type — one with no further variables of its own left to chase — via
+/// [`Substitution::apply`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Substitution {
+    sets: UnionFind,
+    fresh: FreshVars,
+}
+
+/// A point in a [`Substitution`]'s history that [`Substitution::undo`] can
+/// roll back to, obtained from [`Substitution::checkpoint`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint(usize);
+
+/// Something that can incorporate one [`Constraint`] at a time into
+/// whatever state it's accumulating. [`Substitution`] is this crate's only
+/// implementor, handling `Equal`, `Subtype`, and `Instance`; the trait
+/// exists so a constraint domain this crate doesn't know about yet — a
+/// subtyping lattice richer than simple equality, row types, type classes
+/// — can plug a different implementor into [`Substitution::add_constraints`]'s
+/// call sites without that loop needing to change.
+pub trait ConstraintSolver {
+    /// Incorporates `constraint`, returning the error it caused if it
+    /// couldn't be reconciled with what's already been solved.
+    fn solve(&mut self, constraint: &Constraint) -> Result<(), TypeError>;
+}
+
+impl ConstraintSolver for Substitution {
+    fn solve(&mut self, constraint: &Constraint) -> Result<(), TypeError> {
+        match constraint {
+            Constraint::Equal { type1, type2, lhs_span, .. } => unify_pair(&mut self.sets, type1, type2, *lhs_span),
+            Constraint::Subtype { sub, sup, lhs_span, .. } => solve_subtype(&mut self.sets, sub, sup, *lhs_span),
+            Constraint::Instance { scheme, ty, span, .. } => {
+                unify_instance(&mut self.sets, &mut self.fresh, scheme, ty, *span)
+            }
+        }
+    }
+}
+
+/// Solves a [`Constraint::Subtype`]. If either side still has an unresolved
+/// [`Type::Numeric`]/[`Type::Placeholder`] in it, there isn't enough
+/// information yet to tell which of several possible subtypes was meant, so
+/// this falls back to [`unify_pair`] and binds the variable exactly like an
+/// equality constraint would — the same simplification a plain
+/// Hindley-Milner solver without bounded polymorphism already makes. Once
+/// both sides are fully concrete, the real [`is_subtype`] lattice decides
+/// it, which — unlike equality — accepts e.g. a wider-parameter function
+/// where a narrower one was expected.
+fn solve_subtype(sets: &mut UnionFind, sub: &Type, sup: &Type, span: Span) -> Result<(), TypeError> {
+    let resolved_sub = sets.resolve(sub);
+    let resolved_sup = sets.resolve(sup);
+    if has_unresolved_variable(&resolved_sub) || has_unresolved_variable(&resolved_sup) {
+        return unify_pair(sets, sub, sup, span);
+    }
+    if is_subtype(&resolved_sub, &resolved_sup) {
+        Ok(())
+    } else {
+        Err(TypeError::TypeMismatch {
+            expected: Box::new(resolved_sup),
+            found: Box::new(resolved_sub),
+            span,
+        })
+    }
+}
+
+/// Whether `ty` still contains a [`Type::Numeric`] or [`Type::Placeholder`]
+/// that [`UnionFind::resolve`] couldn't replace with a concrete type,
+/// meaning it isn't fully known yet.
+fn has_unresolved_variable(ty: &Type) -> bool {
+    match ty {
+        Type::Numeric(_) | Type::Placeholder(_) => true,
+        Type::Constructor { arguments, .. } => arguments.iter().any(has_unresolved_variable),
+        Type::Function { parameter_type, return_type, .. } => {
+            has_unresolved_variable(parameter_type) || has_unresolved_variable(return_type)
+        }
+        Type::Intersection(members) => members.iter().any(has_unresolved_variable),
+        Type::Record(fields) => fields.values().any(has_unresolved_variable),
+        Type::Boolean | Type::Bottom | Type::Integer => false,
+    }
+}
+
+impl Substitution {
+    /// The type variable `id`'s set was bound to, if any, without
+    /// resolving further variables inside it. Most callers want
+    /// [`Substitution::apply`] instead.
+    pub fn get(&self, id: u32) -> Option<&Type> {
+        self.sets.bound.get(&self.sets.find_readonly(id))
+    }
+
+    /// Replaces every [`Type::Numeric`]/[`Type::Placeholder`] in `ty` with
+    /// what it was ultimately unified to, recursing through however many
+    /// variables the substitution chains through, and leaving any
+    /// still-unbound variable's set representative in its place.
+    pub fn apply(&self, ty: &Type) -> Type {
+        match ty {
+            Type::Numeric(id) => self.apply_var(*id, Type::Numeric),
+            Type::Placeholder(id) => self.apply_var(*id, Type::Placeholder),
+            Type::Function {
+                parameter_type,
+                return_type,
+                effects,
+            } => Type::Function {
+                parameter_type: Box::new(self.apply(parameter_type)),
+                return_type: Box::new(self.apply(return_type)),
+                effects: effects.clone(),
+            },
+            Type::Intersection(members) => {
+                Type::Intersection(members.iter().map(|member| self.apply(member)).collect())
+            }
+            Type::Constructor { name, arguments } => Type::Constructor {
+                name: name.clone(),
+                arguments: arguments.iter().map(|argument| self.apply(argument)).collect(),
+            },
+            Type::Record(fields) => Type::Record(
+                fields.iter().map(|(name, field_type)| (name.clone(), self.apply(field_type))).collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+
+    fn apply_var(&self, id: u32, variant: fn(u32) -> Type) -> Type {
+        let root = self.sets.find_readonly(id);
+        match self.sets.bound.get(&root) {
+            Some(bound) => self.apply(bound),
+            None if self.sets.numeric.contains(&root) => Type::Numeric(root),
+            None => variant(root),
+        }
+    }
+
+    /// Rebuilds `term`, replacing every node's [`TypedTerm::ty`] with the
+    /// result of applying this substitution to it, so a caller can turn the
+    /// placeholder-filled output of [`crate::annotator::annotate`] into a
+    /// fully solved tree after [`unify`] succeeds.
+    pub fn apply_term(&self, term: &TypedTerm) -> TypedTerm {
+        TypedTerm {
+            ty: Rc::new(self.apply(&term.ty)),
+            kind: self.apply_term_kind(&term.kind),
+            span: term.span,
+        }
+    }
+
+    fn apply_term_kind(&self, kind: &TypedTermKind) -> TypedTermKind {
+        match kind {
+            TypedTermKind::Boolean(value) => TypedTermKind::Boolean(*value),
+            TypedTermKind::Error => TypedTermKind::Error,
+            TypedTermKind::FunctionApplication { function, argument } => TypedTermKind::FunctionApplication {
+                function: Box::new(self.apply_term(function)),
+                argument: Box::new(self.apply_term(argument)),
+            },
+            TypedTermKind::FunctionDefinition { parameter, body } => TypedTermKind::FunctionDefinition {
+                parameter: Box::new(self.apply_term(parameter)),
+                body: Box::new(self.apply_term(body)),
+            },
+            TypedTermKind::Identifier(name) => TypedTermKind::Identifier(name.clone()),
+            TypedTermKind::IfExpression {
+                condition,
+                true_branch,
+                false_branch,
+            } => TypedTermKind::IfExpression {
+                condition: Box::new(self.apply_term(condition)),
+                true_branch: Box::new(self.apply_term(true_branch)),
+                false_branch: Box::new(self.apply_term(false_branch)),
+            },
+            TypedTermKind::Integer(value) => TypedTermKind::Integer(*value),
+            TypedTermKind::LetExpression {
+                declaration_name,
+                declaration_value,
+                expression,
+            } => TypedTermKind::LetExpression {
+                declaration_name: Box::new(self.apply_term(declaration_name)),
+                declaration_value: Box::new(self.apply_term(declaration_value)),
+                expression: Box::new(self.apply_term(expression)),
+            },
+            TypedTermKind::RaiseExpression { exception } => TypedTermKind::RaiseExpression {
+                exception: Box::new(self.apply_term(exception)),
+            },
+        }
+    }
+
+    /// Marks the current state, for a later [`Substitution::undo`] to roll
+    /// back to. Cheap to take — it's just the undo log's current length.
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint(self.sets.log.len())
+    }
+
+    /// Reverts every binding and union made since `checkpoint` was taken,
+    /// leaving the substitution exactly as it was at that point. Lets a
+    /// caller like a REPL solve a prelude once, checkpoint, then try (and
+    /// cheaply retract) one line at a time, instead of re-solving the
+    /// prelude from scratch on every attempt.
+    pub fn undo(&mut self, checkpoint: Checkpoint) {
+        self.sets.undo_to(checkpoint.0);
+    }
+
+    /// Extends this already-solved substitution with `constraints`,
+    /// mutating it in place exactly as [`unify`] would if it were starting
+    /// from this substitution's current state instead of an empty one —
+    /// including accumulating every error found rather than stopping at
+    /// the first. Fresh variables for any [`Constraint::Instance`] are
+    /// numbered past every id already bound or aliased in this
+    /// substitution, as well as past every id in `constraints` itself, so
+    /// they can't collide with either.
+    pub fn add_constraints(&mut self, constraints: &[Constraint]) -> Result<(), Vec<TypeError>> {
+        self.fresh.next = self
+            .fresh
+            .next
+            .max(FreshVars::starting_after(constraints).next)
+            .max(max_var_id_in_union_find(&self.sets) + 1);
+        let mut errors = Vec::new();
+        for constraint in constraints {
+            if let Err(error) = self.solve(constraint) {
+                errors.push(error);
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Merges `other` into a fresh [`Substitution`] that has the same
+    /// effect as applying `self` and then `other`: every binding already
+    /// resolved by `self` is re-resolved through `other` (so a chain like
+    /// `'a -> 'b` in `self` composed with `'b -> int` in `other` collapses
+    /// to `'a -> int`), and every binding `other` makes that `self` didn't
+    /// is carried over as-is.
+    pub fn compose(&self, other: &Substitution) -> Substitution {
+        let mut composed = other.clone();
+        let mut self_sets = self.sets.clone();
+        for &id in self.sets.parent.keys() {
+            let root = self_sets.find(id);
+            if root != id {
+                composed.sets.union(id, root);
+            }
+        }
+        for (&id, ty) in &self.sets.bound {
+            let root = composed.sets.find(id);
+            let resolved = other.apply(ty);
+            composed.sets.bound.entry(root).or_insert(resolved);
+        }
+        composed
+    }
+}
+
+/// Solves `constraints` by unification, completing the inference pipeline
+/// that [`crate::constraint::collect_constraints`] otherwise leaves at a
+/// flat constraint list: walks the constraints in order, merging type
+/// variables into a union-find forest as it goes. A constraint that can't
+/// be satisfied — either because two types are structurally incompatible,
+/// or because the occurs check rejects a self-referential binding — has its
+/// error recorded and is otherwise skipped, rather than aborting the whole
+/// pass, so one bad constraint doesn't hide every other type error in the
+/// program; every other constraint still gets to run against the partial
+/// substitution built up so far. Only fails, with every error found, once
+/// all constraints have been tried.
+///
+/// [`Constraint::Subtype`] is unified exactly like [`Constraint::Equal`]
+/// when either side is still an unresolved variable, and decided by the
+/// real [`is_subtype`] lattice once both sides are concrete.
+/// [`Constraint::Instance`] is solved by
+/// instantiating its scheme with fresh type variables (numbered past every
+/// id already appearing in `constraints`, so they can't collide) and then
+/// unifying the result against the instance's concrete type.
+pub fn unify(constraints: &[Constraint]) -> Result<Substitution, Vec<TypeError>> {
+    let mut substitution = Substitution::default();
+    substitution.add_constraints(constraints)?;
+    Ok(substitution)
+}
+
+/// The most general unifier of `type1` and `type2` on their own, without a
+/// surrounding constraint set — useful for tooling that just wants to ask
+/// "do these types fit together?", such as ranking candidate signatures for
+/// completion or suggesting a fill for a hole. Unlike [`unify`], this only
+/// ever has one constraint to satisfy, so it fails outright instead of
+/// accumulating errors.
+pub fn mgu(type1: &Type, type2: &Type) -> Result<Substitution, UnifyError> {
+    let mut substitution = Substitution::default();
+    unify_pair(&mut substitution.sets, type1, type2, Span::default())?;
+    Ok(substitution)
+}
+
+/// The reason [`mgu`] couldn't unify two types, stripped of the [`Span`]
+/// [`TypeError`] carries: `mgu` is handed bare types with no source
+/// location to point at.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnifyError {
+    /// The two types can never be made equal, e.g. `int` against `bool`, or
+    /// a [`Type::Constructor`] against a [`Type::Function`].
+    TypeMismatch { expected: Box<Type>, found: Box<Type> },
+    /// The occurs check rejected binding `var` to `ty` because `var`
+    /// appears somewhere inside `ty`.
+    InfiniteType { var: u32, ty: Box<Type> },
+}
+
+impl From<TypeError> for UnifyError {
+    fn from(error: TypeError) -> UnifyError {
+        match error {
+            TypeError::TypeMismatch { expected, found, .. } => UnifyError::TypeMismatch { expected, found },
+            TypeError::InfiniteType { var, ty, .. } => UnifyError::InfiniteType { var, ty },
+            TypeError::UnboundIdentifier { .. } => {
+                unreachable!("unify_pair never produces an unbound identifier error")
+            }
+        }
+    }
+}
+
+impl Display for UnifyError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            UnifyError::TypeMismatch { expected, found } => {
+                write!(f, "expected `{}` but found `{}`", expected, found)
+            }
+            UnifyError::InfiniteType { var, ty } => write!(f, "infinite type: t{} occurs in `{}`", var, ty),
+        }
+    }
+}
+
+fn unify_instance(
+    sets: &mut UnionFind,
+    fresh: &mut FreshVars,
+    scheme: &TypeScheme,
+    ty: &Type,
+    span: Span,
+) -> Result<(), TypeError> {
+    let arguments: Vec<Type> = scheme
+        .bound_vars
+        .iter()
+        .map(|_| Type::Placeholder(fresh.fresh()))
+        .collect();
+    let instantiated = instantiate(scheme, &arguments).map_err(|_| TypeError::TypeMismatch {
+        expected: Box::new(scheme.ty.clone()),
+        found: Box::new(ty.clone()),
+        span,
+    })?;
+    unify_pair(sets, &instantiated, ty, span)
+}
+
+fn unify_pair(sets: &mut UnionFind, type1: &Type, type2: &Type, span: Span) -> Result<(), TypeError> {
+    let type1 = sets.resolve(type1);
+    let type2 = sets.resolve(type2);
+    match (&type1, &type2) {
+        (Type::Boolean, Type::Boolean) => Ok(()),
+        (Type::Integer, Type::Integer) => Ok(()),
+        // `raise`'s type unifies with anything without constraining it, so
+        // this has to run before the variable-binding arms below: binding a
+        // variable to `Type::Bottom` here would wrongly commit it to
+        // `never` instead of leaving it free for a later, more specific
+        // constraint to decide.
+        (Type::Bottom, _) | (_, Type::Bottom) => Ok(()),
+        (Type::Numeric(a), Type::Numeric(b) | Type::Placeholder(b))
+        | (Type::Placeholder(b), Type::Numeric(a)) => {
+            sets.mark_numeric(*a);
+            if a != b {
+                sets.union(*a, *b);
+            }
+            Ok(())
+        }
+        (Type::Placeholder(a), Type::Placeholder(b)) => {
+            if a != b {
+                sets.union(*a, *b);
+            }
+            Ok(())
+        }
+        (Type::Numeric(id), _) => {
+            sets.mark_numeric(*id);
+            sets.bind(*id, &type2, span)
+        }
+        (_, Type::Numeric(id)) => {
+            sets.mark_numeric(*id);
+            sets.bind(*id, &type1, span)
+        }
+        (Type::Placeholder(id), _) => sets.bind(*id, &type2, span),
+        (_, Type::Placeholder(id)) => sets.bind(*id, &type1, span),
+        (
+            Type::Constructor { name: name1, arguments: arguments1 },
+            Type::Constructor { name: name2, arguments: arguments2 },
+        ) if name1 == name2 && arguments1.len() == arguments2.len() => {
+            for (argument1, argument2) in arguments1.iter().zip(arguments2) {
+                unify_pair(sets, argument1, argument2, span)?;
+            }
+            Ok(())
+        }
+        (
+            Type::Function { parameter_type: p1, return_type: r1, .. },
+            Type::Function { parameter_type: p2, return_type: r2, .. },
+        ) => {
+            unify_pair(sets, p1, p2, span)?;
+            unify_pair(sets, r1, r2, span)
+        }
+        (Type::Record(fields1), Type::Record(fields2)) if fields1.keys().eq(fields2.keys()) => {
+            for (field_type1, field_type2) in fields1.values().zip(fields2.values()) {
+                unify_pair(sets, field_type1, field_type2, span)?;
+            }
+            Ok(())
+        }
+        _ => Err(TypeError::TypeMismatch {
+            expected: Box::new(type1),
+            found: Box::new(type2),
+            span,
+        }),
+    }
+}
+
+/// One side of the trace [`explain`] renders: the constraint that most
+/// recently forced a type variable to `ty`, or — if that side of the
+/// failing constraint was never a variable at all — the failing
+/// constraint's own reason and span.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExplanationStep {
+    pub reason: ConstraintReason,
+    pub span: Span,
+    pub ty: Type,
+}
+
+/// Explains why the [`Constraint::Equal`] or [`Constraint::Subtype`] at
+/// `target_span` fails to unify, by re-solving `constraints` up to that
+/// point while recording which constraint most recently forced each type
+/// variable to a concrete type, then describing the chain of constraints
+/// responsible for each side of the conflict. Returns `None` if no
+/// constraint has that span, or if that constraint does not in fact fail.
+///
+/// [`Constraint::Instance`] constraints are skipped, both as candidates
+/// for `target_span` and while replaying `constraints` beforehand: tracing
+/// provenance through a scheme's fresh instantiation would need to relate
+/// a use-site variable back to the `let`-bound scheme it came from, which
+/// is a larger feature than explaining a straightforward type conflict.
+///
+/// This mirrors [`unify`]'s own solving logic rather than calling it,
+/// since explaining a failure needs the provenance of each binding — which
+/// constraint and reason produced it — and `unify` itself only needs the
+/// final substitution, so it doesn't pay to track that on every call.
+pub fn explain(constraints: &[Constraint], target_span: Span) -> Option<String> {
+    let (step1, step2) = explain_steps(constraints, target_span)?;
+    Some(format!(
+        "{} at line {}, column {}, so it must be `{}`; but {} at line {}, column {}, so it must be `{}`",
+        step1.reason.description(),
+        step1.span.line,
+        step1.span.column,
+        step1.ty,
+        step2.reason.description(),
+        step2.span.line,
+        step2.span.column,
+        step2.ty,
+    ))
+}
+
+/// Like [`explain`], but returns the two [`ExplanationStep`]s that make up
+/// the conflict instead of formatting them into one sentence, so a caller
+/// that wants to lay them out itself — e.g. rendering both sides against
+/// their own source snippet — doesn't have to re-derive them.
+pub fn explain_steps(
+    constraints: &[Constraint],
+    target_span: Span,
+) -> Option<(ExplanationStep, ExplanationStep)> {
+    let mut sets = UnionFind::default();
+    let mut provenance: HashMap<u32, (ConstraintReason, Span)> = HashMap::new();
+    for constraint in constraints {
+        let (type1, type2, reason, span, is_subtype_constraint) = match constraint {
+            Constraint::Equal { type1, type2, reason, lhs_span, .. } => {
+                (type1.as_ref(), type2.as_ref(), *reason, *lhs_span, false)
+            }
+            Constraint::Subtype { sub, sup, reason, lhs_span, .. } => {
+                (sub.as_ref(), sup.as_ref(), *reason, *lhs_span, true)
+            }
+            Constraint::Instance { .. } => continue,
+        };
+        if span == target_span {
+            let resolved1 = sets.resolve(type1);
+            let resolved2 = sets.resolve(type2);
+            if solve_pair(&mut sets, is_subtype_constraint, type1, type2, span).is_ok() {
+                return None;
+            }
+            let step1 = explain_side(type1, resolved1, reason, span, &provenance);
+            let step2 = explain_side(type2, resolved2, reason, span, &provenance);
+            return Some((step1, step2));
+        }
+        record_provenance(&mut sets, &mut provenance, is_subtype_constraint, type1, type2, reason, span);
+    }
+    None
+}
+
+/// Dispatches to [`unify_pair`] or [`solve_subtype`] depending on which kind
+/// of constraint is being replayed, so [`explain_steps`] traces a
+/// [`Constraint::Subtype`] the same way [`Substitution::solve`] would
+/// actually solve it, rather than always falling back to equality.
+fn solve_pair(
+    sets: &mut UnionFind,
+    is_subtype_constraint: bool,
+    type1: &Type,
+    type2: &Type,
+    span: Span,
+) -> Result<(), TypeError> {
+    if is_subtype_constraint {
+        solve_subtype(sets, type1, type2, span)
+    } else {
+        unify_pair(sets, type1, type2, span)
+    }
+}
+
+fn explain_side(
+    original: &Type,
+    resolved: Type,
+    fallback_reason: ConstraintReason,
+    fallback_span: Span,
+    provenance: &HashMap<u32, (ConstraintReason, Span)>,
+) -> ExplanationStep {
+    if let Type::Numeric(id) | Type::Placeholder(id) = original {
+        if let Some(&(reason, span)) = provenance.get(id) {
+            return ExplanationStep { reason, span, ty: resolved };
+        }
+    }
+    ExplanationStep {
+        reason: fallback_reason,
+        span: fallback_span,
+        ty: resolved,
+    }
+}
+
+/// Solves one constraint while replaying it, recording `reason`/`span` as
+/// the most recent provenance for whichever side(s) are bare type
+/// variables. A constraint that fails to unify during replay is simply
+/// skipped, same as [`unify`] itself does — its provenance just doesn't
+/// get recorded.
+fn record_provenance(
+    sets: &mut UnionFind,
+    provenance: &mut HashMap<u32, (ConstraintReason, Span)>,
+    is_subtype_constraint: bool,
+    type1: &Type,
+    type2: &Type,
+    reason: ConstraintReason,
+    span: Span,
+) {
+    if solve_pair(sets, is_subtype_constraint, type1, type2, span).is_err() {
+        return;
+    }
+    if let Type::Numeric(id) | Type::Placeholder(id) = type1 {
+        let root = sets.find(*id);
+        provenance.insert(root, (reason, span));
+    }
+    if let Type::Numeric(id) | Type::Placeholder(id) = type2 {
+        let root = sets.find(*id);
+        provenance.insert(root, (reason, span));
+    }
+}
+
+/// Whether `var` appears anywhere inside `ty`. `ty` is always already fully
+/// resolved by the time this is called (both [`UnionFind::bind`] and
+/// [`unify_pair`] resolve before matching), so this only needs to walk
+/// `ty`'s own structure, not chase any variables inside it.
+fn occurs(var: u32, ty: &Type) -> bool {
+    match ty {
+        Type::Numeric(id) | Type::Placeholder(id) => *id == var,
+        Type::Constructor { arguments, .. } => arguments.iter().any(|argument| occurs(var, argument)),
+        Type::Function { parameter_type, return_type, .. } => {
+            occurs(var, parameter_type) || occurs(var, return_type)
+        }
+        Type::Intersection(members) => members.iter().any(|member| occurs(var, member)),
+        Type::Record(fields) => fields.values().any(|field_type| occurs(var, field_type)),
+        _ => false,
+    }
+}
+
+/// Hands out type variable ids past every id already used in a constraint
+/// set, so instantiating a [`Constraint::Instance`]'s scheme can't collide
+/// with a variable the constraints already talk about.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct FreshVars {
+    next: u32,
+}
+
+impl FreshVars {
+    fn starting_after(constraints: &[Constraint]) -> FreshVars {
+        FreshVars { next: max_var_id(constraints) + 1 }
+    }
+
+    fn fresh(&mut self) -> u32 {
+        let id = self.next;
+        self.next += 1;
+        id
+    }
+}
+
+fn max_var_id(constraints: &[Constraint]) -> u32 {
+    let mut max = 0;
+    for constraint in constraints {
+        max = max.max(max_id_in_type(constraint.lhs()));
+        max = max.max(max_id_in_type(constraint.rhs()));
+        if let Constraint::Instance { scheme, .. } = constraint {
+            for (var, bound) in &scheme.bound_vars {
+                max = max.max(*var);
+                if let Some(bound) = bound {
+                    max = max.max(max_id_in_type(bound));
+                }
+            }
+        }
+    }
+    max
+}
+
+fn max_id_in_type(ty: &Type) -> u32 {
+    match ty {
+        Type::Numeric(id) | Type::Placeholder(id) => *id,
+        Type::Constructor { arguments, .. } => arguments.iter().map(max_id_in_type).max().unwrap_or(0),
+        Type::Function { parameter_type, return_type, .. } => {
+            max_id_in_type(parameter_type).max(max_id_in_type(return_type))
+        }
+        Type::Intersection(members) => members.iter().map(max_id_in_type).max().unwrap_or(0),
+        Type::Record(fields) => fields.values().map(max_id_in_type).max().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+/// The same bound as [`max_var_id`], but over an already-built
+/// [`UnionFind`] instead of a fresh constraint list, so
+/// [`Substitution::add_constraints`] can seed its fresh variables past
+/// every id this substitution already knows about, not just the ids in the
+/// constraints it's about to add.
+fn max_var_id_in_union_find(sets: &UnionFind) -> u32 {
+    let mut max = 0;
+    for (&id, &parent) in &sets.parent {
+        max = max.max(id).max(parent);
+    }
+    for (&id, ty) in &sets.bound {
+        max = max.max(id).max(max_id_in_type(ty));
+    }
+    max
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constraint::ConstraintReason;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_constraint_solver_trait_is_satisfied_by_a_solver_that_ignores_every_constraint() {
+        struct NoopSolver;
+        impl ConstraintSolver for NoopSolver {
+            fn solve(&mut self, _constraint: &Constraint) -> Result<(), TypeError> {
+                Ok(())
+            }
+        }
+        let mut solver = NoopSolver;
+        let result = solver.solve(&Constraint::equal(
+            Rc::new(Type::Placeholder(1)),
+            Rc::new(Type::Boolean),
+            ConstraintReason::BooleanLiteral,
+            Span::default(),
+            Span::default(),
+        ));
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_unify_solves_a_variable_equal_to_a_concrete_type() {
+        let substitution = unify(&[Constraint::equal(
+            Rc::new(Type::Placeholder(1)),
+            Rc::new(Type::Boolean),
+            ConstraintReason::BooleanLiteral,
+            Span::default(),
+            Span::default(),
+        )])
+        .expect("unification should succeed");
+        assert_eq!(substitution.apply(&Type::Placeholder(1)), Type::Boolean);
+    }
+
+    #[test]
+    fn test_unify_propagates_a_binding_through_a_chain_of_variables() {
+        let constraints = [
+            Constraint::equal(
+                Rc::new(Type::Placeholder(1)),
+                Rc::new(Type::Placeholder(2)),
+                ConstraintReason::LetBinding,
+                Span::default(),
+                Span::default(),
+            ),
+            Constraint::equal(
+                Rc::new(Type::Placeholder(2)),
+                Rc::new(Type::Integer),
+                ConstraintReason::LetBinding,
+                Span::default(),
+                Span::default(),
+            ),
+        ];
+        let substitution = unify(&constraints).expect("unification should succeed");
+        assert_eq!(substitution.apply(&Type::Placeholder(1)), Type::Integer);
+        assert_eq!(substitution.apply(&Type::Placeholder(2)), Type::Integer);
+    }
+
+    #[test]
+    fn test_unify_unions_two_free_variables_so_either_reflects_a_later_binding() {
+        let constraints = [
+            Constraint::equal(
+                Rc::new(Type::Placeholder(1)),
+                Rc::new(Type::Placeholder(2)),
+                ConstraintReason::LetBinding,
+                Span::default(),
+                Span::default(),
+            ),
+            Constraint::equal(
+                Rc::new(Type::Placeholder(1)),
+                Rc::new(Type::Boolean),
+                ConstraintReason::LetBinding,
+                Span::default(),
+                Span::default(),
+            ),
+        ];
+        let substitution = unify(&constraints).expect("unification should succeed");
+        assert_eq!(substitution.apply(&Type::Placeholder(2)), Type::Boolean);
+    }
+
+    #[test]
+    fn test_unify_keeps_a_placeholder_numeric_after_unioning_with_another_placeholder() {
+        // t1 = t2 (two plain variables share a set), then t2 = Numeric(3),
+        // so t1 must still default to a number, not dangle as an unbound placeholder.
+        let constraints = [
+            Constraint::equal(
+                Rc::new(Type::Placeholder(1)),
+                Rc::new(Type::Placeholder(2)),
+                ConstraintReason::LetBinding,
+                Span::default(),
+                Span::default(),
+            ),
+            Constraint::equal(
+                Rc::new(Type::Placeholder(2)),
+                Rc::new(Type::Numeric(3)),
+                ConstraintReason::LetBinding,
+                Span::default(),
+                Span::default(),
+            ),
+        ];
+        let substitution = unify(&constraints).expect("unification should succeed");
+        assert!(matches!(substitution.apply(&Type::Placeholder(1)), Type::Numeric(_)));
+    }
+
+    #[test]
+    fn test_unify_solves_matching_function_types_argument_by_argument() {
+        let constraints = [Constraint::equal(
+            Rc::new(Type::Function {
+                parameter_type: Box::from(Type::Placeholder(1)),
+                return_type: Box::from(Type::Placeholder(2)),
+                effects: Vec::new(),
+            }),
+            Rc::new(Type::Function {
+                parameter_type: Box::from(Type::Integer),
+                return_type: Box::from(Type::Boolean),
+                effects: Vec::new(),
+            }),
+            ConstraintReason::FunctionSignature,
+            Span::default(),
+            Span::default(),
+        )];
+        let substitution = unify(&constraints).expect("unification should succeed");
+        assert_eq!(substitution.apply(&Type::Placeholder(1)), Type::Integer);
+        assert_eq!(substitution.apply(&Type::Placeholder(2)), Type::Boolean);
+    }
+
+    #[test]
+    fn test_unify_treats_subtype_constraints_as_equality_when_a_side_is_unresolved() {
+        let substitution = unify(&[Constraint::subtype(
+            Rc::new(Type::Placeholder(1)),
+            Rc::new(Type::Integer),
+            ConstraintReason::LetResult,
+            Span::default(),
+            Span::default(),
+        )])
+        .expect("unification should succeed");
+        assert_eq!(substitution.apply(&Type::Placeholder(1)), Type::Integer);
+    }
+
+    #[test]
+    fn test_unify_accepts_a_genuine_subtype_that_would_fail_plain_equality() {
+        // A function that accepts `int` and returns `never` is a subtype of
+        // one that only promises to accept `never` and returns `int`, by
+        // contravariance in the parameter and covariance in the return type
+        // — but the two are not *equal*, so this only succeeds because
+        // `Constraint::Subtype` is actually decided by `is_subtype` once
+        // both sides are concrete.
+        let sub = Type::Function {
+            parameter_type: Box::from(Type::Integer),
+            return_type: Box::from(Type::Bottom),
+            effects: Vec::new(),
+        };
+        let sup = Type::Function {
+            parameter_type: Box::from(Type::Bottom),
+            return_type: Box::from(Type::Integer),
+            effects: Vec::new(),
+        };
+        let constraints = [Constraint::subtype(
+            Rc::new(sub),
+            Rc::new(sup),
+            ConstraintReason::FunctionSignature,
+            Span::default(),
+            Span::default(),
+        )];
+        unify(&constraints).expect("a genuine subtype should unify");
+    }
+
+    #[test]
+    fn test_unify_lets_bottom_unify_with_anything_without_binding_it() {
+        let constraints = [
+            Constraint::equal(
+                Rc::new(Type::Placeholder(1)),
+                Rc::new(Type::Bottom),
+                ConstraintReason::BranchesMustMatch,
+                Span::default(),
+                Span::default(),
+            ),
+            Constraint::equal(
+                Rc::new(Type::Placeholder(1)),
+                Rc::new(Type::Integer),
+                ConstraintReason::BranchesMustMatch,
+                Span::default(),
+                Span::default(),
+            ),
+        ];
+        let substitution = unify(&constraints).expect("unification should succeed");
+        assert_eq!(substitution.apply(&Type::Placeholder(1)), Type::Integer);
+    }
+
+    #[test]
+    fn test_unify_reports_a_type_mismatch() {
+        let error = unify(&[Constraint::equal(
+            Rc::new(Type::Integer),
+            Rc::new(Type::Boolean),
+            ConstraintReason::BooleanLiteral,
+            Span::default(),
+            Span::default(),
+        )])
+        .unwrap_err();
+        assert!(matches!(error.as_slice(), [TypeError::TypeMismatch { .. }]));
+    }
+
+    #[test]
+    fn test_unify_rejects_an_infinite_type() {
+        let error = unify(&[Constraint::equal(
+            Rc::new(Type::Placeholder(1)),
+            Rc::new(Type::Function {
+                parameter_type: Box::from(Type::Placeholder(1)),
+                return_type: Box::from(Type::Integer),
+                effects: Vec::new(),
+            }),
+            ConstraintReason::FunctionSignature,
+            Span::default(),
+            Span::default(),
+        )])
+        .unwrap_err();
+        assert!(matches!(error.as_slice(), [TypeError::InfiniteType { var: 1, .. }]));
+    }
+
+    #[test]
+    fn test_unify_solves_an_instance_constraint_with_a_fresh_variable_per_use() {
+        let scheme = TypeScheme {
+            bound_vars: vec![(1, None)],
+            ty: Type::Function {
+                parameter_type: Box::from(Type::Placeholder(1)),
+                return_type: Box::from(Type::Placeholder(1)),
+                effects: Vec::new(),
+            },
+        };
+        let constraints = [
+            Constraint::instance(
+                scheme.clone(),
+                Rc::new(Type::Function {
+                    parameter_type: Box::from(Type::Integer),
+                    return_type: Box::from(Type::Integer),
+                    effects: Vec::new(),
+                }),
+                ConstraintReason::LetInstantiation,
+                Span::default(),
+            ),
+            Constraint::instance(
+                scheme,
+                Rc::new(Type::Function {
+                    parameter_type: Box::from(Type::Boolean),
+                    return_type: Box::from(Type::Boolean),
+                    effects: Vec::new(),
+                }),
+                ConstraintReason::LetInstantiation,
+                Span::default(),
+            ),
+        ];
+        assert!(unify(&constraints).is_ok());
+    }
+
+    #[test]
+    fn test_unify_rejects_an_instance_constraint_violating_its_bound() {
+        let scheme = TypeScheme {
+            bound_vars: vec![(1, Some(Type::Boolean))],
+            ty: Type::Placeholder(1),
+        };
+        let error = unify(&[Constraint::instance(
+            scheme,
+            Rc::new(Type::Integer),
+            ConstraintReason::LetInstantiation,
+            Span::default(),
+        )])
+        .unwrap_err();
+        assert!(matches!(error.as_slice(), [TypeError::TypeMismatch { .. }]));
+    }
+
+    #[test]
+    fn test_substitution_apply_term_replaces_every_nodes_type() {
+        let substitution = unify(&[Constraint::equal(
+            Rc::new(Type::Placeholder(1)),
+            Rc::new(Type::Integer),
+            ConstraintReason::BooleanLiteral,
+            Span::default(),
+            Span::default(),
+        )])
+        .expect("unification should succeed");
+        let term = TypedTerm {
+            ty: Rc::new(Type::Placeholder(1)),
+            kind: TypedTermKind::RaiseExpression {
+                exception: Box::new(TypedTerm {
+                    ty: Rc::new(Type::Placeholder(1)),
+                    kind: TypedTermKind::Integer(0),
+                    span: Span::default(),
+                }),
+            },
+            span: Span::default(),
+        };
+        let solved = substitution.apply_term(&term);
+        assert_eq!(*solved.ty, Type::Integer);
+        match solved.kind {
+            TypedTermKind::RaiseExpression { exception } => assert_eq!(*exception.ty, Type::Integer),
+            other => panic!("expected a raise expression, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_substitution_compose_chases_a_binding_through_both_substitutions() {
+        let first = unify(&[Constraint::equal(
+            Rc::new(Type::Placeholder(1)),
+            Rc::new(Type::Placeholder(2)),
+            ConstraintReason::LetBinding,
+            Span::default(),
+            Span::default(),
+        )])
+        .expect("unification should succeed");
+        let second = unify(&[Constraint::equal(
+            Rc::new(Type::Placeholder(2)),
+            Rc::new(Type::Integer),
+            ConstraintReason::LetBinding,
+            Span::default(),
+            Span::default(),
+        )])
+        .expect("unification should succeed");
+        let composed = first.compose(&second);
+        assert_eq!(composed.apply(&Type::Placeholder(1)), Type::Integer);
+        assert_eq!(composed.apply(&Type::Placeholder(2)), Type::Integer);
+    }
+
+    #[test]
+    fn test_unify_reports_every_mismatch_instead_of_stopping_at_the_first() {
+        let constraints = [
+            Constraint::equal(
+                Rc::new(Type::Integer),
+                Rc::new(Type::Boolean),
+                ConstraintReason::BooleanLiteral,
+                Span::default(),
+                Span::default(),
+            ),
+            Constraint::equal(
+                Rc::new(Type::Placeholder(1)),
+                Rc::new(Type::Integer),
+                ConstraintReason::LetBinding,
+                Span::default(),
+                Span::default(),
+            ),
+            Constraint::equal(
+                Rc::new(Type::Constructor { name: String::from("list"), arguments: vec![] }),
+                Rc::new(Type::Boolean),
+                ConstraintReason::ApplicationArgument,
+                Span::default(),
+                Span::default(),
+            ),
+        ];
+        let errors = unify(&constraints).unwrap_err();
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn test_mgu_solves_a_variable_against_a_concrete_type() {
+        let substitution = mgu(&Type::Placeholder(1), &Type::Integer).expect("mgu should succeed");
+        assert_eq!(substitution.apply(&Type::Placeholder(1)), Type::Integer);
+    }
+
+    #[test]
+    fn test_mgu_reports_a_type_mismatch_without_a_span() {
+        let error = mgu(&Type::Integer, &Type::Boolean).unwrap_err();
+        assert!(matches!(error, UnifyError::TypeMismatch { .. }));
+    }
+
+    #[test]
+    fn test_mgu_rejects_an_infinite_type() {
+        let error = mgu(
+            &Type::Placeholder(1),
+            &Type::Function {
+                parameter_type: Box::from(Type::Placeholder(1)),
+                return_type: Box::from(Type::Integer),
+                effects: Vec::new(),
+            },
+        )
+        .unwrap_err();
+        assert!(matches!(error, UnifyError::InfiniteType { var: 1, .. }));
+    }
+
+    fn span_at_line(line: usize) -> Span {
+        Span { start: 0, end: 0, line, column: 1 }
+    }
+
+    #[test]
+    fn test_explain_traces_the_constraints_that_forced_each_conflicting_type() {
+        let if_span = span_at_line(1);
+        let plus_span = span_at_line(2);
+        let constraints = [
+            Constraint::equal(
+                Rc::new(Type::Placeholder(1)),
+                Rc::new(Type::Boolean),
+                ConstraintReason::IfConditionBool,
+                if_span,
+                if_span,
+            ),
+            Constraint::equal(
+                Rc::new(Type::Placeholder(1)),
+                Rc::new(Type::Integer),
+                ConstraintReason::BuiltinSignature,
+                plus_span,
+                plus_span,
+            ),
+        ];
+        let explanation = explain(&constraints, plus_span).expect("the second constraint should fail");
+        assert!(explanation.contains("is used as the condition of `if`"));
+        assert!(explanation.contains("line 1"));
+        assert!(explanation.contains("`bool`"));
+        assert!(explanation.contains("is used with a builtin operator"));
+        assert!(explanation.contains("line 2"));
+        assert!(explanation.contains("`int`"));
+    }
+
+    #[test]
+    fn test_explain_returns_none_for_a_constraint_that_did_not_fail() {
+        let span = span_at_line(1);
+        let constraints = [Constraint::equal(
+            Rc::new(Type::Integer),
+            Rc::new(Type::Integer),
+            ConstraintReason::LetBinding,
+            span,
+            span,
+        )];
+        assert_eq!(explain(&constraints, span), None);
+    }
+
+    #[test]
+    fn test_explain_returns_none_for_an_unknown_span() {
+        let span = span_at_line(1);
+        let constraints = [Constraint::equal(
+            Rc::new(Type::Integer),
+            Rc::new(Type::Boolean),
+            ConstraintReason::LetBinding,
+            span,
+            span,
+        )];
+        assert_eq!(explain(&constraints, span_at_line(99)), None);
+    }
+
+    #[test]
+    fn test_substitution_add_constraints_extends_an_already_solved_substitution() {
+        let mut substitution = unify(&[Constraint::equal(
+            Rc::new(Type::Placeholder(1)),
+            Rc::new(Type::Integer),
+            ConstraintReason::LetBinding,
+            Span::default(),
+            Span::default(),
+        )])
+        .expect("unification should succeed");
+        substitution
+            .add_constraints(&[Constraint::equal(
+                Rc::new(Type::Placeholder(2)),
+                Rc::new(Type::Boolean),
+                ConstraintReason::LetBinding,
+                Span::default(),
+                Span::default(),
+            )])
+            .expect("adding the second constraint should succeed");
+        assert_eq!(substitution.apply(&Type::Placeholder(1)), Type::Integer);
+        assert_eq!(substitution.apply(&Type::Placeholder(2)), Type::Boolean);
+    }
+
+    #[test]
+    fn test_substitution_undo_reverts_to_a_checkpoint() {
+        let mut substitution = unify(&[Constraint::equal(
+            Rc::new(Type::Placeholder(1)),
+            Rc::new(Type::Integer),
+            ConstraintReason::LetBinding,
+            Span::default(),
+            Span::default(),
+        )])
+        .expect("unification should succeed");
+        let checkpoint = substitution.checkpoint();
+        substitution
+            .add_constraints(&[Constraint::equal(
+                Rc::new(Type::Placeholder(2)),
+                Rc::new(Type::Boolean),
+                ConstraintReason::LetBinding,
+                Span::default(),
+                Span::default(),
+            )])
+            .expect("adding the second constraint should succeed");
+        assert_eq!(substitution.apply(&Type::Placeholder(2)), Type::Boolean);
+        substitution.undo(checkpoint);
+        assert_eq!(substitution.apply(&Type::Placeholder(1)), Type::Integer);
+        assert_eq!(substitution.apply(&Type::Placeholder(2)), Type::Placeholder(2));
+    }
+
+    #[test]
+    fn test_substitution_add_constraints_reports_errors_without_corrupting_prior_state() {
+        let mut substitution = unify(&[Constraint::equal(
+            Rc::new(Type::Placeholder(1)),
+            Rc::new(Type::Integer),
+            ConstraintReason::LetBinding,
+            Span::default(),
+            Span::default(),
+        )])
+        .expect("unification should succeed");
+        let checkpoint = substitution.checkpoint();
+        let errors = substitution
+            .add_constraints(&[Constraint::equal(
+                Rc::new(Type::Boolean),
+                Rc::new(Type::Integer),
+                ConstraintReason::BooleanLiteral,
+                Span::default(),
+                Span::default(),
+            )])
+            .unwrap_err();
+        assert_eq!(errors.len(), 1);
+        substitution.undo(checkpoint);
+        assert_eq!(substitution.apply(&Type::Placeholder(1)), Type::Integer);
+    }
+
+    #[test]
+    fn test_substitution_add_constraints_instantiates_fresh_variables_past_existing_ones() {
+        let mut substitution = unify(&[Constraint::equal(
+            Rc::new(Type::Placeholder(1)),
+            Rc::new(Type::Integer),
+            ConstraintReason::LetBinding,
+            Span::default(),
+            Span::default(),
+        )])
+        .expect("unification should succeed");
+        let scheme = TypeScheme {
+            bound_vars: vec![(1, None)],
+            ty: Type::Placeholder(1),
+        };
+        substitution
+            .add_constraints(&[Constraint::instance(
+                scheme,
+                Rc::new(Type::Boolean),
+                ConstraintReason::LetInstantiation,
+                Span::default(),
+            )])
+            .expect("instantiating the scheme should succeed");
+        assert_eq!(substitution.apply(&Type::Placeholder(1)), Type::Integer);
+    }
+
+    #[test]
+    fn test_unify_solves_tens_of_thousands_of_chained_constraints() {
+        let count = 20_000;
+        let mut constraints = Vec::with_capacity(count);
+        for id in 0..count as u32 {
+            constraints.push(Constraint::equal(
+                Rc::new(Type::Placeholder(id)),
+                Rc::new(Type::Placeholder(id + 1)),
+                ConstraintReason::LetBinding,
+                Span::default(),
+                Span::default(),
+            ));
+        }
+        constraints.push(Constraint::equal(
+            Rc::new(Type::Placeholder(count as u32)),
+            Rc::new(Type::Integer),
+            ConstraintReason::LetBinding,
+            Span::default(),
+            Span::default(),
+        ));
+        let substitution = unify(&constraints).expect("unification should succeed");
+        assert_eq!(substitution.apply(&Type::Placeholder(0)), Type::Integer);
+    }
+}
@@ -0,0 +1,46 @@
+//! `wasm-bindgen` bindings exposing the checker's pipeline to JavaScript, so
+//! an in-browser playground can call [`check`] directly on whatever the
+//! user types, without running a server to host the checker.
+
+use crate::infer;
+use wasm_bindgen::prelude::*;
+
+/// Runs [`infer`] against `source` and returns the result as a JSON value:
+/// `{"ty": "int => int"}` on success, or `{"diagnostics": ["..."]}` if any
+/// stage reported a problem.
+#[wasm_bindgen]
+pub fn check(source: &str) -> JsValue {
+    JsValue::from_str(&check_to_json(source))
+}
+
+/// The JSON-serialization behind [`check`], split out so it can be tested
+/// without going through [`JsValue`], which only works on a `wasm32`
+/// target. Diagnostics are rendered with [`std::fmt::Display`] rather than
+/// serialized structurally, since [`crate::Diagnostic`] wraps error types
+/// from several modules that don't all implement `serde::Serialize`.
+fn check_to_json(source: &str) -> String {
+    let json = match infer(source) {
+        Ok(ty) => serde_json::json!({ "ty": ty.to_string() }),
+        Err(diagnostics) => serde_json::json!({
+            "diagnostics": diagnostics.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        }),
+    };
+    json.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_to_json;
+
+    #[test]
+    fn test_check_to_json_reports_the_inferred_type_on_success() {
+        let json = check_to_json("fn x => if x then true else false");
+        assert_eq!(json, r#"{"ty":"bool => bool"}"#);
+    }
+
+    #[test]
+    fn test_check_to_json_reports_diagnostics_on_failure() {
+        let json = check_to_json("x");
+        assert!(json.contains(r#""diagnostics":["#));
+    }
+}